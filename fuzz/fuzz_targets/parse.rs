@@ -0,0 +1,56 @@
+//! Feeds arbitrary bytes through the same pipeline the shell protocol runs
+//! (decode, parse, build the tree, serialize) and checks that every span in
+//! the tree stays inside the file and inside its parent.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustsemantic::model::{LocationSpan, Node};
+use rustsemantic::parse::parse_source;
+
+fn check_location(location: &LocationSpan, lines: i64) {
+    assert!(location.start[0] >= 1 && location.start[1] >= 0);
+    assert!(location.start <= location.end, "{:?}", location);
+    assert!(location.end[0] <= lines.max(1), "{:?}", location);
+}
+
+fn check_node(node: &Node, parent: &std::ops::Range<usize>, src: &str, lines: i64) {
+    let range = node.byte_range();
+    assert!(range.start <= range.end, "{} {:?}", node.kind(), range);
+    assert!(
+        parent.start <= range.start && range.end <= parent.end,
+        "{} {:?} outside {:?}",
+        node.kind(),
+        range,
+        parent
+    );
+    assert!(src.is_char_boundary(range.start) && src.is_char_boundary(range.end));
+    check_location(node.location_span(), lines);
+
+    let mut previous_end = range.start;
+    for child in node.children() {
+        let child_range = child.byte_range();
+        assert!(child_range.start >= previous_end, "{} overlaps", child.kind());
+        previous_end = child_range.end;
+        check_node(child, &range, src, lines);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // The shell protocol reads sources as UTF-8 and answers KO otherwise.
+    let Ok(src) = std::str::from_utf8(data) else {
+        return;
+    };
+    let file = parse_source("fuzz.rs", src).expect("valid UTF-8 always parses");
+    // Spans may point one line past the last `\n`.
+    let lines = src.split('\n').count() as i64;
+    check_location(&file.location_span, lines);
+    let mut previous_end = 0;
+    for child in &file.children {
+        let range = child.byte_range();
+        assert!(range.start >= previous_end, "{} overlaps", child.kind());
+        previous_end = range.end;
+        check_node(child, &(0..src.len()), src, lines);
+    }
+    serde_json::to_string(&file).expect("tree serializes");
+});
@@ -0,0 +1,274 @@
+//! The public API of a crate: its `pub` items and their signatures, with
+//! everything private and every position left out, so two snapshots differ
+//! only where the API does.
+
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Args;
+use crate::files::{module_dir, module_file, path_attribute};
+use crate::metadata::{metadata, normalize};
+use crate::model::Signature;
+use crate::outline::{crate_name, crate_root};
+use crate::parse::{impl_name, parse_tree, read_file, ParseOptions};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiItem {
+    /// The tree-sitter kind, like `function_item` or `field_declaration`.
+    #[serde(rename = "type")]
+    pub item_type: String,
+    /// Its name; for an impl, `impl Type` or `impl Trait for Type`, and for
+    /// a `pub use`, what it imports.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+    /// The type of a field, constant, static or type alias.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<String>,
+    /// `const`, `async`, `unsafe` and `extern "abi"`, as they apply.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qualifiers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ApiItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Api {
+    pub name: String,
+    pub items: Vec<ApiItem>,
+}
+
+/// Which of an item's children belong to the API.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Members {
+    /// Those marked `pub`, as in modules, structs and inherent impls.
+    Public,
+    /// All of them, as in traits, trait impls and enums.
+    All,
+}
+
+fn text<'a>(node: tree_sitter::Node, src: &'a str) -> &'a str {
+    src.get(node.byte_range()).unwrap_or("")
+}
+
+/// The attributes right before `node`, outermost first.
+fn attributes<'a>(node: tree_sitter::Node, src: &'a str) -> Vec<&'a str> {
+    let mut out = vec![];
+    let mut sibling = node.prev_named_sibling();
+    while let Some(previous) = sibling {
+        match previous.kind() {
+            "attribute_item" => out.push(text(previous, src)),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = previous.prev_named_sibling();
+    }
+    out.reverse();
+    out
+}
+
+fn is_pub(node: tree_sitter::Node, src: &str) -> bool {
+    let mut cursor = node.walk();
+    let public = node.children(&mut cursor).any(|child| {
+        child.kind() == "visibility_modifier" && text(child, src) == "pub"
+    });
+    public
+}
+
+/// Walks one file's or module's items.
+struct Walk<'a> {
+    options: &'a ParseOptions,
+}
+
+impl Walk<'_> {
+    /// The API among the children of `list`, in `src`, whose `mod foo;`
+    /// files are in `dir` and whose `#[path]`s are relative to `path_dir`.
+    fn items(
+        &self,
+        list: tree_sitter::Node,
+        src: &str,
+        members: Members,
+        dir: &Path,
+        path_dir: &Path,
+    ) -> anyhow::Result<Vec<ApiItem>> {
+        let mut out = vec![];
+        let mut cursor = list.walk();
+        for node in list.named_children(&mut cursor) {
+            if let Some(item) = self.item(node, src, members, dir, path_dir)? {
+                out.push(item);
+            }
+        }
+        Ok(out)
+    }
+
+    fn item(
+        &self,
+        node: tree_sitter::Node,
+        src: &str,
+        members: Members,
+        dir: &Path,
+        path_dir: &Path,
+    ) -> anyhow::Result<Option<ApiItem>> {
+        let kind = node.kind();
+        let exported = match kind {
+            // Impls have no visibility; what is public in them is.
+            "impl_item" => true,
+            "macro_definition" => attributes(node, src)
+                .iter()
+                .any(|attribute| attribute.contains("macro_export")),
+            "function_item"
+            | "function_signature_item"
+            | "struct_item"
+            | "enum_item"
+            | "union_item"
+            | "trait_item"
+            | "mod_item"
+            | "const_item"
+            | "static_item"
+            | "type_item"
+            | "associated_type"
+            | "use_declaration"
+            | "field_declaration"
+            | "enum_variant" => members == Members::All || is_pub(node, src),
+            _ => false,
+        };
+        if !exported {
+            return Ok(None);
+        }
+
+        let field = |name| {
+            node.child_by_field_name(name)
+                .map(|n| normalize(text(n, src)))
+        };
+        let name = match kind {
+            "impl_item" => impl_name(&node, src),
+            "use_declaration" => field("argument").unwrap_or_default(),
+            _ => field("name").unwrap_or_default(),
+        };
+        let metadata = metadata(&node, src, self.options);
+        let mut item = ApiItem {
+            item_type: kind.to_string(),
+            name,
+            signature: None,
+            value_type: match kind {
+                "field_declaration" | "const_item" | "static_item" | "type_item" => {
+                    field("type")
+                }
+                _ => None,
+            },
+            qualifiers: metadata
+                .as_ref()
+                .map(|m| m.qualifiers())
+                .unwrap_or_default(),
+            children: vec![],
+        };
+        item.signature = metadata.and_then(|m| m.signature);
+
+        let body = node.child_by_field_name("body");
+        match kind {
+            "mod_item" => {
+                let module = item.name.as_str();
+                item.children = match body {
+                    Some(body) => {
+                        let dir = dir.join(module);
+                        self.items(body, src, Members::Public, &dir, &dir)?
+                    }
+                    None => {
+                        let attributes = attributes(node, src).join("\n");
+                        let (path, dir) = match path_attribute(&attributes) {
+                            Some(path) => {
+                                let path = path_dir.join(path);
+                                let dir = path
+                                    .parent()
+                                    .unwrap_or(Path::new(""))
+                                    .to_path_buf();
+                                (Some(path).filter(|path| path.is_file()), dir)
+                            }
+                            None => (module_file(dir, module), dir.join(module)),
+                        };
+                        match path {
+                            Some(path) => self.file(&path, &dir)?,
+                            None => vec![],
+                        }
+                    }
+                };
+            }
+            "impl_item" => {
+                let members = if field("trait").is_some() {
+                    Members::All
+                } else {
+                    Members::Public
+                };
+                if let Some(body) = body {
+                    item.children = self.items(body, src, members, dir, path_dir)?;
+                }
+                // An inherent impl with nothing public adds nothing.
+                if members == Members::Public && item.children.is_empty() {
+                    return Ok(None);
+                }
+            }
+            "trait_item" | "enum_item" => {
+                if let Some(body) = body {
+                    item.children =
+                        self.items(body, src, Members::All, dir, path_dir)?;
+                }
+            }
+            "struct_item" | "union_item" => {
+                if let Some(body) =
+                    body.filter(|b| b.kind() == "field_declaration_list")
+                {
+                    item.children =
+                        self.items(body, src, Members::Public, dir, path_dir)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(Some(item))
+    }
+
+    /// The API of the module in `path`, whose own modules are in `dir`.
+    fn file(&self, path: &Path, dir: &Path) -> anyhow::Result<Vec<ApiItem>> {
+        let name = path.to_string_lossy();
+        let src = read_file(&name)?;
+        let tree = parse_tree(&name, &src)?;
+        let path_dir = path.parent().unwrap_or(Path::new(""));
+        self.items(tree.root_node(), &src, Members::Public, dir, path_dir)
+    }
+}
+
+/// The public API of the crate at `path`, a crate directory or its root
+/// file, following its modules into their files. Items only visible within
+/// the crate, like `pub(crate)` ones, are left out; impls are kept whatever
+/// the visibility of their type, which isn't resolved.
+pub fn api(path: &Path) -> anyhow::Result<Api> {
+    let root = crate_root(path)?;
+    let options = ParseOptions {
+        signatures: true,
+        qualifiers: true,
+        ..ParseOptions::default()
+    };
+    let walk = Walk { options: &options };
+    Ok(Api {
+        name: crate_name(&root),
+        items: walk.file(&root, &module_dir(&root))?,
+    })
+}
+
+/// `api <crate dir or root file>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &[], &[])?;
+    let [path] = args.positional() else {
+        anyhow::bail!("usage: api <crate dir or root file>");
+    };
+    let api = api(Path::new(path))?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    serde_json::to_writer_pretty(&mut out, &api)?;
+    writeln!(out)?;
+    Ok(())
+}
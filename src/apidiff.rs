@@ -0,0 +1,286 @@
+//! Comparing two snapshots of a crate's public API, as `api` prints them,
+//! and judging whether the difference breaks the crate's users: what the
+//! semver bump between them has to be, roughly.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::api::{api, Api, ApiItem};
+use crate::cli::Args;
+use crate::parse::read_file;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiChange {
+    pub change: ApiChangeKind,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    /// The names of the item and the items around it, joined with `::`.
+    pub path: String,
+    pub breaking: bool,
+    /// What it was, like `function_item const fn(i32) -> Self`, for changed
+    /// and removed items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// What it is, for changed and added items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// The version bump the changes call for.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// The API is the same.
+    Patch,
+    /// Only additions, or changes no user can notice breaking.
+    Minor,
+    /// Something breaks.
+    Major,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDiff {
+    pub verdict: Verdict,
+    /// By path.
+    pub changes: Vec<ApiChange>,
+}
+
+/// An item and the kind of item it is in.
+struct Entry<'a> {
+    item: &'a ApiItem,
+    parent: &'a str,
+}
+
+/// The namespace of items of kind `item_type`: functions, constants and
+/// statics can share a name with a module or type.
+fn namespace(item_type: &str) -> Namespace {
+    match item_type {
+        "function_item" | "function_signature_item" | "const_item" | "static_item" => {
+            Namespace::Value
+        }
+        _ => Namespace::Type,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Namespace {
+    Type,
+    Value,
+}
+
+/// Every item below `items` by path and namespace. `pub use`s and macros
+/// live apart from the items they share a name with, so they get a path of
+/// their own. Impls of the same type share theirs, so their members are
+/// compared wherever in them they are; any other item with the path of one
+/// before it, like a cfg-gated version of it, gets its path with ` #2`,
+/// ` #3` and so on.
+fn flatten<'a>(
+    items: &'a [ApiItem],
+    prefix: &str,
+    parent: &'a str,
+    out: &mut BTreeMap<(String, Namespace), Entry<'a>>,
+) {
+    for item in items {
+        let name = match item.item_type.as_str() {
+            "use_declaration" => format!("use {}", item.name),
+            "macro_definition" => format!("{}!", item.name),
+            _ => item.name.clone(),
+        };
+        let namespace = namespace(&item.item_type);
+        let mut path = format!("{}{}", prefix, name);
+        if item.item_type != "impl_item" {
+            let base = path.clone();
+            let mut ordinal = 1;
+            while out.contains_key(&(path.clone(), namespace)) {
+                ordinal += 1;
+                path = format!("{} #{}", base, ordinal);
+            }
+        }
+        flatten(&item.children, &format!("{}::", path), &item.item_type, out);
+        out.entry((path, namespace))
+            .or_insert(Entry { item, parent });
+    }
+}
+
+/// What `item` itself is, leaving out its children.
+pub fn describe(item: &ApiItem) -> String {
+    let mut out = item.qualifiers.join(" ");
+    if let Some(signature) = &item.signature {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&format!("fn({})", signature.parameters.join(", ")));
+        if let Some(returns) = &signature.return_type {
+            out.push_str(&format!(" -> {}", returns));
+        }
+    } else if let Some(value_type) = &item.value_type {
+        out.push_str(&format!(": {}", value_type));
+    }
+    if out.is_empty() {
+        item.item_type.clone()
+    } else {
+        format!("{} {}", item.item_type, out)
+    }
+}
+
+/// Whether adding `item` to an item of kind `parent` breaks users: a trait
+/// method without a default or an associated type has to be implemented,
+/// and a variant has to be matched.
+fn addition_breaks(item: &ApiItem, parent: &str) -> bool {
+    matches!(
+        (parent, item.item_type.as_str()),
+        ("trait_item", "function_signature_item" | "associated_type")
+            | ("enum_item", "enum_variant")
+    )
+}
+
+/// Whether changing `old` into `new` breaks users. Making a function
+/// `const` doesn't; any other change to what an item is does.
+fn change_breaks(old: &ApiItem, new: &ApiItem) -> bool {
+    let without_const = |item: &ApiItem| -> Vec<String> {
+        item.qualifiers
+            .iter()
+            .filter(|q| *q != "const")
+            .cloned()
+            .collect()
+    };
+    let gained_const = old.item_type == new.item_type
+        && old.signature == new.signature
+        && old.value_type == new.value_type
+        && without_const(old) == without_const(new)
+        && new.qualifiers.iter().any(|q| q == "const");
+    !gained_const
+}
+
+/// The differences between the `old` and `new` API.
+pub fn api_diff(old: &Api, new: &Api) -> ApiDiff {
+    let mut before = BTreeMap::new();
+    flatten(&old.items, "", "crate", &mut before);
+    let mut after = BTreeMap::new();
+    flatten(&new.items, "", "crate", &mut after);
+
+    let mut changes = vec![];
+    for (key @ (path, _), old) in &before {
+        match after.get(key) {
+            None => changes.push(ApiChange {
+                change: ApiChangeKind::Removed,
+                item_type: old.item.item_type.clone(),
+                path: path.clone(),
+                breaking: true,
+                old: Some(describe(old.item)),
+                new: None,
+            }),
+            Some(new) if describe(old.item) != describe(new.item) => {
+                changes.push(ApiChange {
+                    change: ApiChangeKind::Changed,
+                    item_type: new.item.item_type.clone(),
+                    path: path.clone(),
+                    breaking: change_breaks(old.item, new.item),
+                    old: Some(describe(old.item)),
+                    new: Some(describe(new.item)),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (key @ (path, _), new) in &after {
+        if !before.contains_key(key) {
+            changes.push(ApiChange {
+                change: ApiChangeKind::Added,
+                item_type: new.item.item_type.clone(),
+                path: path.clone(),
+                breaking: addition_breaks(new.item, new.parent),
+                old: None,
+                new: Some(describe(new.item)),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let verdict = if changes.iter().any(|change| change.breaking) {
+        Verdict::Major
+    } else if changes.is_empty() {
+        Verdict::Patch
+    } else {
+        Verdict::Minor
+    };
+    ApiDiff { verdict, changes }
+}
+
+/// The API at `path`: a snapshot `api` wrote, or a crate directory or root
+/// file to take one of.
+pub fn load(path: &str) -> anyhow::Result<Api> {
+    if Path::new(path).is_dir() || path.ends_with(".rs") {
+        return api(Path::new(path));
+    }
+    let contents = read_file(path)?;
+    serde_json::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("{} is not an api snapshot: {}", path, e))
+}
+
+fn write_text(out: &mut dyn Write, diff: &ApiDiff) -> std::io::Result<()> {
+    for change in &diff.changes {
+        let what = match (&change.old, &change.new) {
+            (Some(old), Some(new)) => format!("{} => {}", old, new),
+            (Some(item), None) | (None, Some(item)) => item.clone(),
+            (None, None) => String::new(),
+        };
+        writeln!(
+            out,
+            "{:<8} {:<7} {}: {}",
+            if change.breaking { "breaking" } else { "" },
+            match change.change {
+                ApiChangeKind::Added => "added",
+                ApiChangeKind::Removed => "removed",
+                ApiChangeKind::Changed => "changed",
+            },
+            change.path,
+            what
+        )?;
+    }
+    writeln!(
+        out,
+        "verdict: {}",
+        match diff.verdict {
+            Verdict::Patch => "patch",
+            Verdict::Minor => "minor",
+            Verdict::Major => "major",
+        }
+    )
+}
+
+/// `api-diff [--format text|json] <old> <new>`
+///
+/// Exits 1 when a change breaks the API.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(raw, &[], &["--format"])?;
+    let [old, new] = args.positional() else {
+        anyhow::bail!("usage: api-diff [--format text|json] <old> <new>");
+    };
+    let diff = api_diff(&load(old)?, &load(new)?);
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &diff)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &diff)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown api-diff format {}", other),
+    }
+    Ok(if diff.verdict == Verdict::Major { 1 } else { 0 })
+}
@@ -0,0 +1,256 @@
+//! A checked-in inventory of a repository's declarations, for CI to fail
+//! when one disappears or is renamed without the inventory being updated
+//! along with it: a light structural contract.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Args;
+use crate::diff::{Declaration, ParsedFile};
+use crate::files::expand;
+use crate::hash::content_hash;
+use crate::ignore::Filter;
+use crate::parse::read_file;
+
+/// Where the baseline is kept unless `--baseline` says otherwise.
+pub const DEFAULT_BASELINE: &str = ".rustsemantic-baseline.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub file: String,
+    pub name: String,
+    pub kind: String,
+    /// [`content_hash`] of its own text without its name, the same for a
+    /// declaration and its renamed self.
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Baseline {
+    /// The paths the inventory was taken of, which `baseline check` takes
+    /// again when given none.
+    pub paths: Vec<String>,
+    /// By file, in the order they are declared.
+    pub declarations: Vec<Entry>,
+}
+
+fn entry(file: &str, decl: &Declaration) -> Entry {
+    Entry {
+        file: file.to_string(),
+        name: decl.qualified_name.clone(),
+        kind: decl.kind.clone(),
+        hash: content_hash(decl.own_text.replacen(&decl.name, "", 1)),
+    }
+}
+
+/// The inventory of `files` (name and contents), taken of `paths`.
+pub fn inventory(
+    paths: &[String],
+    files: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<Baseline> {
+    let mut declarations = vec![];
+    for (name, contents) in files {
+        let parsed = ParsedFile::from_source(&name, contents)?;
+        declarations.extend(parsed.declarations.iter().map(|decl| entry(&name, decl)));
+    }
+    Ok(Baseline {
+        paths: paths.to_vec(),
+        declarations,
+    })
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftKind {
+    /// Gone, with nothing like it left.
+    Removed,
+    /// Gone, with the same declaration under another name, in any file.
+    Renamed,
+    /// In another file under the same name. Doesn't fail the check.
+    Moved,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Drift {
+    pub change: DriftKind,
+    pub file: String,
+    pub name: String,
+    pub kind: String,
+    /// Where a renamed or moved declaration is now, as `file:name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub now: Option<String>,
+}
+
+/// The declarations of the `baseline` missing from `current`, outermost
+/// only: what was inside a removed impl goes without saying. Declarations
+/// added since are fine.
+pub fn check(baseline: &Baseline, current: &Baseline) -> Vec<Drift> {
+    /// The entries of `entries` with no counterpart among `others`: same
+    /// file, name and kind, counting repeats like the `impl`s of a file.
+    fn unmatched<'a>(entries: &'a [Entry], others: &[Entry]) -> Vec<&'a Entry> {
+        let key = |e: &Entry| (e.file.clone(), e.name.clone(), e.kind.clone());
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        for e in others {
+            *counts.entry(key(e)).or_default() += 1;
+        }
+        entries
+            .iter()
+            .filter(|e| match counts.get_mut(&key(e)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    // What gone declarations may have become.
+    let mut candidates = unmatched(&current.declarations, &baseline.declarations);
+    let mut out: Vec<Drift> = vec![];
+    for e in unmatched(&baseline.declarations, &current.declarations) {
+        let inside = out
+            .iter()
+            .any(|d| d.file == e.file && e.name.starts_with(&format!("{}::", d.name)));
+        if inside {
+            continue;
+        }
+        let moved = candidates
+            .iter()
+            .position(|c| c.name == e.name && c.kind == e.kind)
+            .map(|i| (DriftKind::Moved, i));
+        // Renamed in its own file first, else in any.
+        let renamed = || {
+            let same = |c: &&Entry| c.kind == e.kind && c.hash == e.hash;
+            candidates
+                .iter()
+                .position(|c| c.file == e.file && same(c))
+                .or_else(|| candidates.iter().position(same))
+                .map(|i| (DriftKind::Renamed, i))
+        };
+        let (change, now) = match moved.or_else(renamed) {
+            Some((change, i)) => {
+                let c = candidates.remove(i);
+                // What is inside it came along and can't stand for anything
+                // else that went.
+                let prefix = format!("{}::", c.name);
+                candidates.retain(|d| d.file != c.file || !d.name.starts_with(&prefix));
+                (change, Some(format!("{}:{}", c.file, c.name)))
+            }
+            None => (DriftKind::Removed, None),
+        };
+        out.push(Drift {
+            change,
+            file: e.file.clone(),
+            name: e.name.clone(),
+            kind: e.kind.clone(),
+            now,
+        });
+    }
+    out
+}
+
+/// The inventory of `paths`.
+fn take(paths: &[String], args: &Args) -> anyhow::Result<Baseline> {
+    let mut files = vec![];
+    for (path, name) in expand(paths, &Filter::from_args(args)?)? {
+        files.push((name, read_file(&path.to_string_lossy())?));
+    }
+    inventory(paths, files)
+}
+
+fn write_text(out: &mut dyn Write, drift: &[Drift]) -> std::io::Result<()> {
+    for d in drift {
+        match d.change {
+            DriftKind::Removed => {
+                writeln!(out, "{}:{}: {} removed", d.file, d.name, d.kind)?
+            }
+            DriftKind::Renamed | DriftKind::Moved => writeln!(
+                out,
+                "{}:{}: {} {} to {}",
+                d.file,
+                d.name,
+                d.kind,
+                if d.change == DriftKind::Renamed {
+                    "renamed"
+                } else {
+                    "moved"
+                },
+                d.now.as_deref().unwrap_or_default()
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// `baseline create [--baseline <file>] [--exclude <glob>] [--include <glob>]
+/// [--no-ignore] [--symlinks follow|skip|error] <paths...>`
+/// `baseline check [--baseline <file>] [--format text|json] [options] [<paths...>]`
+///
+/// `check` exits 1 when a declaration of the baseline was removed or
+/// renamed.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &[
+            "--baseline",
+            "--format",
+            "--exclude",
+            "--include",
+            "--symlinks",
+        ],
+    )?;
+    let file = args.value("--baseline").unwrap_or(DEFAULT_BASELINE);
+    match args.positional() {
+        [command, paths @ ..] if command == "create" && !paths.is_empty() => {
+            let baseline = take(paths, &args)?;
+            let mut json = serde_json::to_string_pretty(&baseline)?;
+            json.push('\n');
+            std::fs::write(file, json)
+                .map_err(|e| anyhow::anyhow!("writing {}: {}", file, e))?;
+            eprintln!(
+                "{}: {} declarations",
+                file,
+                baseline.declarations.len()
+            );
+            Ok(0)
+        }
+        [command, paths @ ..] if command == "check" => {
+            let baseline: Baseline = serde_json::from_str(&read_file(file)?)
+                .map_err(|e| anyhow::anyhow!("{} is not a baseline: {}", file, e))?;
+            let paths = if paths.is_empty() {
+                &baseline.paths[..]
+            } else {
+                paths
+            };
+            let drift = check(&baseline, &take(paths, &args)?);
+
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            match args.value("--format").unwrap_or("text") {
+                "text" => write_text(&mut out, &drift)?,
+                "json" => {
+                    serde_json::to_writer_pretty(&mut out, &drift)?;
+                    writeln!(out)?;
+                }
+                other => anyhow::bail!("unknown baseline format {}", other),
+            }
+            Ok(
+                if drift.iter().any(|d| d.change != DriftKind::Moved) {
+                    1
+                } else {
+                    0
+                },
+            )
+        }
+        _ => anyhow::bail!(
+            "usage: baseline create [--baseline <file>] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>\n       baseline check [--baseline <file>] [--format text|json] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] [<paths...>]"
+        ),
+    }
+}
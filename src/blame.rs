@@ -0,0 +1,261 @@
+//! Who wrote each declaration of a file: `git blame`'s lines, counted per
+//! function, type and impl instead of line by line.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::ParsedFile;
+use crate::git::git;
+use crate::parse::read_file;
+
+/// One line of `git blame --line-porcelain`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Counting from 1, in the blamed version of the file.
+    pub line: i64,
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    /// Seconds since the epoch.
+    pub time: i64,
+    pub text: String,
+}
+
+/// Parses `git blame --line-porcelain` output, which repeats every header
+/// for every line.
+pub fn parse_porcelain(output: &str) -> anyhow::Result<Vec<BlameLine>> {
+    let mut lines = vec![];
+    let mut current: Option<BlameLine> = None;
+    for row in output.lines() {
+        if let Some(text) = row.strip_prefix('\t') {
+            let mut line = current
+                .take()
+                .ok_or_else(|| anyhow::anyhow!("git blame line without a header"))?;
+            line.text = text.to_string();
+            lines.push(line);
+        } else if let Some(line) = current.as_mut() {
+            if let Some(author) = row.strip_prefix("author ") {
+                line.author = author.to_string();
+            } else if let Some(email) = row.strip_prefix("author-mail ") {
+                line.email = email.trim_matches(['<', '>']).to_string();
+            } else if let Some(time) = row.strip_prefix("author-time ") {
+                line.time = time.parse()?;
+            }
+        } else {
+            let mut fields = row.split(' ');
+            let (Some(commit), Some(_), Some(final_line)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                anyhow::bail!("unexpected git blame header {:?}", row);
+            };
+            current = Some(BlameLine {
+                line: final_line.parse()?,
+                commit: commit.to_string(),
+                author: String::new(),
+                email: String::new(),
+                time: 0,
+                text: String::new(),
+            });
+        }
+    }
+    Ok(lines)
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+    pub lines: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub commit: String,
+    pub author: String,
+    pub time: i64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclarationBlame {
+    pub qualified_name: String,
+    pub kind: String,
+    pub start_line: i64,
+    pub end_line: i64,
+    /// The non-blank lines counted for it, which leave out those of the
+    /// declarations nested in it, so an `impl` owns its header and not its
+    /// methods.
+    pub lines: usize,
+    /// Everyone who wrote those lines, most lines first; the first is the
+    /// owner.
+    pub authors: Vec<Author>,
+    /// The most recent commit among them.
+    pub last_change: Option<Change>,
+}
+
+/// Who wrote `lines`, most lines first.
+pub fn authors<'a>(lines: impl IntoIterator<Item = &'a BlameLine>) -> Vec<Author> {
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for line in lines {
+        *counts.entry((&line.author, &line.email)).or_default() += 1;
+    }
+    let mut authors: Vec<Author> = counts
+        .into_iter()
+        .map(|((name, email), lines)| Author {
+            name: name.to_string(),
+            email: email.to_string(),
+            lines,
+        })
+        .collect();
+    authors.sort_by_key(|author| Reverse(author.lines));
+    authors
+}
+
+/// Attributes each non-blank line to the innermost declaration of `parsed`
+/// around it and counts them per author. Declarations keep their order in
+/// the file; lines outside every declaration are left out.
+pub fn blame_declarations(
+    parsed: &ParsedFile,
+    lines: &[BlameLine],
+) -> Vec<DeclarationBlame> {
+    let mut owned: Vec<Vec<&BlameLine>> = vec![vec![]; parsed.declarations.len()];
+    for line in lines {
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        let innermost = parsed
+            .declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, decl)| decl.start[0] <= line.line && line.line <= decl.end[0])
+            .min_by_key(|(_, decl)| decl.range.len());
+        if let Some((i, _)) = innermost {
+            owned[i].push(line);
+        }
+    }
+
+    parsed
+        .declarations
+        .iter()
+        .zip(owned)
+        .map(|(decl, lines)| {
+            let authors = authors(lines.iter().copied());
+            let last_change =
+                lines
+                    .iter()
+                    .max_by_key(|line| line.time)
+                    .map(|line| Change {
+                        commit: line.commit.clone(),
+                        author: line.author.clone(),
+                        time: line.time,
+                    });
+            DeclarationBlame {
+                qualified_name: decl.qualified_name.clone(),
+                kind: decl.kind.clone(),
+                start_line: decl.start[0],
+                end_line: decl.end[0],
+                lines: lines.len(),
+                authors,
+                last_change,
+            }
+        })
+        .collect()
+}
+
+/// The blamed lines of `path`, relative to the current directory, and its
+/// contents: as it is in the working tree, or as of `rev`.
+pub fn blame_file(
+    path: &str,
+    rev: Option<&str>,
+) -> anyhow::Result<(Vec<BlameLine>, String)> {
+    let (blame, contents) = match rev {
+        Some(rev) => (
+            git(&["blame", "--line-porcelain", rev, "--", path])?,
+            git(&["show", &format!("{}:./{}", rev, path)])?,
+        ),
+        None => (
+            git(&["blame", "--line-porcelain", "--", path])?,
+            read_file(path)?,
+        ),
+    };
+    Ok((parse_porcelain(&blame)?, contents))
+}
+
+fn write_table(
+    out: &mut dyn Write,
+    blames: &[DeclarationBlame],
+) -> std::io::Result<()> {
+    let owner = |blame: &DeclarationBlame| {
+        blame
+            .authors
+            .first()
+            .map_or("-", |author| author.name.as_str())
+            .to_string()
+    };
+    let width = blames
+        .iter()
+        .map(|blame| owner(blame).chars().count())
+        .chain(["OWNER".len()])
+        .max()
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "{:<width$}  SHARE  LINES  AUTHORS  CHANGED   DECLARATION",
+        "OWNER"
+    )?;
+    for blame in blames {
+        let share = match blame.authors.first() {
+            Some(author) => format!("{}%", author.lines * 100 / blame.lines),
+            None => "-".to_string(),
+        };
+        let commit = blame
+            .last_change
+            .as_ref()
+            .map_or("-", |change| &change.commit[..change.commit.len().min(8)]);
+        writeln!(
+            out,
+            "{:<width$}  {:>5}  {:>5}  {:>7}  {:<8}  {} {} ({}-{})",
+            owner(blame),
+            share,
+            blame.lines,
+            blame.authors.len(),
+            commit,
+            blame.kind,
+            blame.qualified_name,
+            blame.start_line,
+            blame.end_line
+        )?;
+    }
+    Ok(())
+}
+
+/// `blame [--format table|json] [--rev <commit>] <file>`
+///
+/// Blames the file as it is in the working tree, or as of `--rev`.
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &[], &["--format", "--rev"])?;
+    let [path] = args.positional() else {
+        anyhow::bail!("usage: blame [--format table|json] [--rev <commit>] <file>");
+    };
+    let (lines, contents) = blame_file(path, args.value("--rev"))?;
+    let parsed = ParsedFile::from_source(path, contents)?;
+    let blames = blame_declarations(&parsed, &lines);
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("table") {
+        "table" => write_table(&mut out, &blames)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &blames)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown blame format {}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::ParsedFile;
+use crate::files::expand;
+use crate::ignore::Filter;
+use crate::parse::{
+    build_file, parse_tree, read_file, syntax_errors, LineEndings, ParseOptions,
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One finding, positioned the way CI systems expect: 1-based lines and
+/// 1-based columns.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub file: String,
+    pub line: i64,
+    pub column: i64,
+    pub end_line: i64,
+    pub end_column: i64,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Syntax errors in `contents`, plus a warning for every declaration that
+/// shares its kind and qualified name with an earlier one, since those
+/// can't be told apart when matching revisions, and one for mixed line
+/// endings, which merges can't keep consistent.
+pub fn check_source(file: &str, contents: String) -> anyhow::Result<Vec<Annotation>> {
+    let tree = parse_tree(file, &contents)?;
+    let mut annotations: Vec<Annotation> = syntax_errors(&tree, &contents)
+        .into_iter()
+        .map(|e| Annotation {
+            file: file.to_string(),
+            line: e.start[0],
+            column: e.start[1] + 1,
+            end_line: e.end[0],
+            end_column: e.end[1] + 1,
+            severity: Severity::Error,
+            message: format!("syntax error: {}", e.message),
+        })
+        .collect();
+
+    let endings = LineEndings::of(&contents);
+    if let (true, Some(line)) = (endings.is_mixed(), endings.first_mismatch) {
+        annotations.push(Annotation {
+            file: file.to_string(),
+            line: line as i64,
+            column: 1,
+            end_line: line as i64,
+            end_column: 1,
+            severity: Severity::Warning,
+            message: format!(
+                "mixed line endings ({} LF, {} CRLF); merged declarations may not match \
+                 their neighbours",
+                endings.lf, endings.crlf
+            ),
+        });
+    }
+
+    let semantic = build_file(file, &contents, &tree, &ParseOptions::default())?;
+    let parsed = ParsedFile::from_parts(contents, semantic);
+    let mut first_seen: HashMap<(&str, &str), i64> = HashMap::new();
+    for decl in &parsed.declarations {
+        let key = (decl.kind.as_str(), decl.qualified_name.as_str());
+        match first_seen.get(&key) {
+            Some(line) => annotations.push(Annotation {
+                file: file.to_string(),
+                line: decl.start[0],
+                column: decl.start[1] + 1,
+                end_line: decl.end[0],
+                end_column: decl.end[1] + 1,
+                severity: Severity::Warning,
+                message: format!(
+                    "duplicate {} `{}` (first declared on line {}) can't be matched reliably",
+                    decl.kind, decl.qualified_name, line
+                ),
+            }),
+            None => {
+                first_seen.insert(key, decl.start[0]);
+            }
+        }
+    }
+    Ok(annotations)
+}
+
+/// GitHub Actions workflow commands need `%`, CR and LF escaped in the
+/// message, and additionally `:` and `,` in properties.
+fn github_escape(text: &str, property: bool) -> String {
+    let mut out = text
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    if property {
+        out = out.replace(':', "%3A").replace(',', "%2C");
+    }
+    out
+}
+
+pub fn write_annotations(
+    out: &mut dyn Write,
+    format: &str,
+    annotations: &[Annotation],
+) -> anyhow::Result<()> {
+    match format {
+        "text" => {
+            for a in annotations {
+                let severity = match a.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                writeln!(
+                    out,
+                    "{}:{}:{}: {}: {}",
+                    a.file, a.line, a.column, severity, a.message
+                )?;
+            }
+        }
+        "json" => {
+            serde_json::to_writer_pretty(&mut *out, annotations)?;
+            writeln!(out)?;
+        }
+        "github" => {
+            for a in annotations {
+                let command = match a.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                writeln!(
+                    out,
+                    "::{} file={},line={},col={},endLine={},endColumn={}::{}",
+                    command,
+                    github_escape(&a.file, true),
+                    a.line,
+                    a.column,
+                    a.end_line,
+                    a.end_column,
+                    github_escape(&a.message, false)
+                )?;
+            }
+        }
+        other => anyhow::bail!("unknown annotation format {}", other),
+    }
+    Ok(())
+}
+
+/// `check [--format text|json|github] [--exclude <glob>] [--include <glob>]
+/// [--no-ignore] [--symlinks follow|skip|error] <paths...>`
+///
+/// Exits 1 when any file has syntax errors; warnings alone don't fail.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &["--format", "--exclude", "--include", "--symlinks"],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: check [--format text|json|github] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+    let mut annotations = vec![];
+    for (path, name) in expand(args.positional(), &Filter::from_args(&args)?)? {
+        let contents = read_file(&path.to_string_lossy())?;
+        annotations.extend(check_source(&name, contents)?);
+    }
+    let format = args.value("--format").unwrap_or("text");
+    write_annotations(&mut std::io::stdout().lock(), format, &annotations)?;
+    Ok(
+        if annotations.iter().any(|a| a.severity == Severity::Error) {
+            1
+        } else {
+            0
+        },
+    )
+}
@@ -0,0 +1,214 @@
+//! How often each declaration changed over a stretch of git history: every
+//! commit's Rust files are parsed before and after and diffed like `diff`
+//! does, so a moved function doesn't count as churn the way changed lines
+//! would.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::{file_diff, ChangeKind, ParsedFile};
+use crate::git::{git, show};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Churn {
+    /// Its file as of the last commit that changed it, relative to the
+    /// repository's root.
+    pub file: String,
+    pub name: String,
+    pub kind: String,
+    /// The commits that added, modified, moved, renamed or removed it.
+    pub changes: usize,
+    pub authors: Vec<String>,
+    pub last_commit: String,
+    /// Whether the last of them removed it.
+    pub removed: bool,
+}
+
+/// A commit of the history and who wrote it.
+struct Commit {
+    id: String,
+    author: String,
+}
+
+/// The churn of every declaration below `path` in the commits after
+/// `since` (or all of them) up to `HEAD`, most changed first.
+/// Merge commits are left out, since what they bring in was counted in the
+/// commits merged.
+pub fn churn(path: &str, since: Option<&str>) -> anyhow::Result<Vec<Churn>> {
+    let range = match since {
+        Some(since) => format!("{}..HEAD", since),
+        None => "HEAD".to_string(),
+    };
+    let log = git(&[
+        "log",
+        "--reverse",
+        "--no-merges",
+        "--format=%H%x00%an",
+        &range,
+        "--",
+        path,
+    ])?;
+    let commits = log.lines().filter_map(|line| {
+        let (id, author) = line.split_once('\0')?;
+        Some(Commit {
+            id: id.to_string(),
+            author: author.to_string(),
+        })
+    });
+
+    // Keyed by file and qualified name, which follow renames of either;
+    // methods are qualified with their impl's type and trait, so those of
+    // different impls are kept apart.
+    let mut churn: HashMap<(String, String), Churn> = HashMap::new();
+    for commit in commits {
+        let changed = git(&[
+            "diff-tree",
+            "-r",
+            "-M",
+            "-z",
+            "--root",
+            "--no-commit-id",
+            "--name-status",
+            &commit.id,
+            "--",
+            path,
+        ])?;
+        let mut fields = changed.split('\0').filter(|f| !f.is_empty());
+        while let Some(status) = fields.next() {
+            let (old_path, new_path) = match status.chars().next() {
+                Some('R' | 'C') => (fields.next(), fields.next()),
+                Some('A') => (None, fields.next()),
+                Some('D') => (fields.next(), None),
+                _ => {
+                    let path = fields.next();
+                    (path, path)
+                }
+            };
+            let is_rust = |p: &Option<&str>| p.is_some_and(|p| p.ends_with(".rs"));
+            if !is_rust(&old_path) && !is_rust(&new_path) {
+                continue;
+            }
+            let parse = |rev: &str, path: Option<&str>| -> Option<ParsedFile> {
+                let path = path?;
+                let contents = show(rev, path)?;
+                match ParsedFile::from_source(path, contents) {
+                    Ok(parsed) => Some(parsed),
+                    Err(e) => {
+                        eprintln!("warning: skipping {}:{}: {:#}", rev, path, e);
+                        None
+                    }
+                }
+            };
+            let old = parse(&format!("{}^", commit.id), old_path);
+            let new = parse(&commit.id, new_path);
+            let diff = file_diff(
+                old_path.map(str::to_string),
+                new_path.map(str::to_string),
+                old.as_ref(),
+                new.as_ref(),
+            );
+
+            // Carry what was counted for a renamed file's declarations over
+            // to its new name; those removed before stay where they were.
+            if let (Some(old_path), Some(new_path)) = (old_path, new_path) {
+                if old_path != new_path {
+                    let moved: Vec<_> = churn
+                        .iter()
+                        .filter(|((file, _), entry)| file == old_path && !entry.removed)
+                        .map(|(key, _)| key.clone())
+                        .collect();
+                    for key in moved {
+                        let mut entry = churn.remove(&key).unwrap();
+                        entry.file = new_path.to_string();
+                        churn.insert((new_path.to_string(), key.1), entry);
+                    }
+                }
+            }
+
+            let file = new_path.or(old_path).unwrap_or_default().to_string();
+            for change in diff.changes {
+                let previous = change
+                    .old_name
+                    .as_ref()
+                    .and_then(|old| churn.remove(&(file.clone(), old.clone())));
+                let entry = churn
+                    .entry((file.clone(), change.name.clone()))
+                    .or_insert_with(|| {
+                        previous.unwrap_or_else(|| Churn {
+                            file: file.clone(),
+                            name: String::new(),
+                            kind: String::new(),
+                            changes: 0,
+                            authors: vec![],
+                            last_commit: String::new(),
+                            removed: false,
+                        })
+                    });
+                entry.name = change.name;
+                entry.kind = change.item_type;
+                entry.changes += 1;
+                if !entry.authors.contains(&commit.author) {
+                    entry.authors.push(commit.author.clone());
+                }
+                entry.last_commit = commit.id.clone();
+                entry.removed = change.change == ChangeKind::Removed;
+            }
+        }
+    }
+
+    let mut churn: Vec<Churn> = churn.into_values().collect();
+    churn.sort_by(|a, b| {
+        b.changes
+            .cmp(&a.changes)
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(churn)
+}
+
+fn write_text(out: &mut dyn Write, churn: &[Churn]) -> std::io::Result<()> {
+    for entry in churn {
+        writeln!(
+            out,
+            "{:>5} {}:{} {} ({} authors, last {}){}",
+            entry.changes,
+            entry.file,
+            entry.name,
+            entry.kind,
+            entry.authors.len(),
+            &entry.last_commit[..entry.last_commit.len().min(8)],
+            if entry.removed { ", removed" } else { "" }
+        )?;
+    }
+    Ok(())
+}
+
+/// `churn [--since <rev>] [--top N] [--format text|json] <path>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &[], &["--since", "--top", "--format"])?;
+    let [path] = args.positional() else {
+        anyhow::bail!(
+            "usage: churn [--since <rev>] [--top N] [--format text|json] <path>"
+        );
+    };
+    let mut churn = churn(path, args.value("--since"))?;
+    if let Some(top) = args.value("--top") {
+        churn.truncate(top.parse()?);
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &churn)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &churn)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown churn format {}", other),
+    }
+    Ok(())
+}
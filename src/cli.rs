@@ -0,0 +1,107 @@
+/// Minimal command-line parsing shared by the subcommands: positional
+/// arguments plus `--flag`, `--option value` and `--option=value`.
+pub struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    values: Vec<(String, String)>,
+}
+
+impl Args {
+    pub fn parse(
+        raw: &[String],
+        flags: &[&str],
+        values: &[&str],
+    ) -> anyhow::Result<Args> {
+        let mut args = Args {
+            positional: vec![],
+            flags: vec![],
+            values: vec![],
+        };
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                args.positional.push(arg.clone());
+                continue;
+            }
+            if arg == "--" {
+                args.positional.extend(iter.by_ref().cloned());
+                break;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if values.contains(&name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("{} expects a value", name))?,
+                };
+                args.values.push((name.to_string(), value));
+            } else if flags.contains(&name) && inline_value.is_none() {
+                args.flags.push(name.to_string());
+            } else {
+                anyhow::bail!("unknown option {}", arg);
+            }
+        }
+        Ok(args)
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// Every value given for `name`, for options that can be repeated.
+    pub fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.values
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Last value given for `name`, so later options override earlier ones.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reads a yes-or-no setting given for `name`: `1`, `true`, `yes` or `on`,
+/// or `0`, `false`, `no` or `off`.
+pub fn parse_switch(name: &str, value: &str) -> anyhow::Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => anyhow::bail!("{} must be 1 or 0, got {}", name, value),
+    }
+}
+
+/// Whether `flag` was given, else whether the environment variable `var`
+/// says so, else false. SemanticMerge launches the binary with arguments of
+/// its own, so every option can be set in the environment instead.
+pub fn switch(args: &Args, flag: &str, var: &str) -> anyhow::Result<bool> {
+    if args.flag(flag) {
+        return Ok(true);
+    }
+    match std::env::var(var) {
+        Ok(value) => parse_switch(var, &value),
+        Err(_) => Ok(false),
+    }
+}
+
+/// The last value given for `option`, else that of the environment variable
+/// `var`.
+pub fn setting(args: &Args, option: &str, var: &str) -> Option<String> {
+    args.value(option)
+        .map(str::to_string)
+        .or_else(|| std::env::var(var).ok())
+}
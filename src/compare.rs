@@ -0,0 +1,56 @@
+//! Structural differences between two trees built for the same path, for
+//! diagnosing why SemanticMerge matched a declaration to the wrong one.
+
+use std::collections::HashMap;
+
+use crate::model::{Node, SemanticFile};
+
+/// Every node in `file` as the path of kinds and names leading to it, like
+/// `impl_item impl > declaration_list > function_item new`, in document
+/// order.
+pub fn node_paths(file: &SemanticFile) -> Vec<String> {
+    fn visit(nodes: &[Node], prefix: &str, out: &mut Vec<String>) {
+        for node in nodes {
+            let own = if node.name() == node.kind() {
+                node.kind().to_string()
+            } else {
+                format!("{} {}", node.kind(), node.name())
+            };
+            let path = if prefix.is_empty() {
+                own
+            } else {
+                format!("{} > {}", prefix, own)
+            };
+            out.push(path.clone());
+            visit(node.children(), &path, out);
+        }
+    }
+
+    let mut out = vec![];
+    visit(&file.children, "", &mut out);
+    out
+}
+
+/// The node paths only in `old`, marked `-`, then those only in `new`,
+/// marked `+`. A path that occurs more often on one side counts as that
+/// many extra occurrences.
+pub fn compare(old: &SemanticFile, new: &SemanticFile) -> Vec<String> {
+    fn only_in(a: &[String], b: &[String], mark: char, out: &mut Vec<String>) {
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        for path in b {
+            *remaining.entry(path).or_default() += 1;
+        }
+        for path in a {
+            match remaining.get_mut(path.as_str()) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => out.push(format!("{} {}", mark, path)),
+            }
+        }
+    }
+
+    let (old, new) = (node_paths(old), node_paths(new));
+    let mut out = vec![];
+    only_in(&old, &new, '-', &mut out);
+    only_in(&new, &old, '+', &mut out);
+    out
+}
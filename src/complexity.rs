@@ -0,0 +1,88 @@
+//! Rough measures of how hard a function is to follow, read off its syntax
+//! tree: its cyclomatic complexity, one more than the decisions it makes,
+//! and how deeply its control flow nests.
+
+use crate::model::Complexity;
+
+/// Branching expressions, each a decision. Older grammars have separate
+/// kinds for `if let` and `while let`.
+const BRANCHES: &[&str] = &[
+    "if_expression",
+    "if_let_expression",
+    "while_expression",
+    "while_let_expression",
+    "for_expression",
+];
+
+/// Expressions whose bodies are a level deeper than their own.
+const NESTING: &[&str] = &[
+    "if_expression",
+    "if_let_expression",
+    "while_expression",
+    "while_let_expression",
+    "for_expression",
+    "loop_expression",
+    "match_expression",
+];
+
+/// The complexity of the function `node`, or `None` for anything else or a
+/// function without a body. The functions nested in it are left to be
+/// measured on their own; its closures count as part of it.
+pub fn complexity(node: &tree_sitter::Node) -> Option<Complexity> {
+    fn visit(node: tree_sitter::Node, depth: u32, out: &mut Complexity) {
+        let kind = node.kind();
+        if kind == "function_item" {
+            return;
+        }
+        if BRANCHES.contains(&kind) {
+            out.cyclomatic += 1;
+        }
+        match kind {
+            // A match with n arms makes n - 1 decisions.
+            "match_expression" => {
+                let arms = node.child_by_field_name("body").map_or(0, |body| {
+                    let mut cursor = body.walk();
+                    let arms = body
+                        .named_children(&mut cursor)
+                        .filter(|arm| arm.kind().ends_with("match_arm"))
+                        .count();
+                    arms as u32
+                });
+                out.cyclomatic += arms.saturating_sub(1);
+            }
+            "binary_expression" => {
+                let operator = node.child_by_field_name("operator").map(|op| op.kind());
+                if matches!(operator, Some("&&" | "||")) {
+                    out.cyclomatic += 1;
+                }
+            }
+            _ => {}
+        }
+        // `else if` continues a chain rather than nesting in it.
+        let nested = NESTING.contains(&kind)
+            && node
+                .parent()
+                .map_or(true, |parent| parent.kind() != "else_clause");
+        let depth = if nested { depth + 1 } else { depth };
+        out.nesting = out.nesting.max(depth);
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            visit(child, depth, out);
+        }
+    }
+
+    if node.kind() != "function_item" {
+        return None;
+    }
+    let body = node.child_by_field_name("body")?;
+    let mut complexity = Complexity {
+        cyclomatic: 1,
+        nesting: 0,
+    };
+    let mut cursor = body.walk();
+    for child in body.children(&mut cursor) {
+        visit(child, 0, &mut complexity);
+    }
+    Some(complexity)
+}
@@ -0,0 +1,166 @@
+//! Having trees built by a daemon, `serve --socket`, that outlives the
+//! short-lived shells SemanticMerge and git start during a merge, so one
+//! parse of a file serves them all.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::model::SemanticFile;
+use crate::replay::Environment;
+
+/// How long a request waits on the daemon unless
+/// `SEMANTIC_RUST_DAEMON_TIMEOUT` says otherwise, in milliseconds.
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// A connection to the daemon `SEMANTIC_RUST_DAEMON` names. Each request
+/// connects anew, so a shell that sits idle in a long merge session
+/// doesn't keep other clients waiting.
+pub struct Client {
+    path: PathBuf,
+    /// The settings trees are built with, sent along with each request,
+    /// since the daemon's own environment is not the one SemanticMerge
+    /// gave the shell.
+    environment: Environment,
+    /// How long to wait on the daemon to take a request or answer it.
+    timeout: Duration,
+    /// Cleared when the daemon can't be reached, after which the caller
+    /// parses files itself.
+    available: bool,
+}
+
+impl Client {
+    /// A client for the daemon `SEMANTIC_RUST_DAEMON` names, if set.
+    pub fn from_env() -> Option<Client> {
+        let path = std::env::var_os("SEMANTIC_RUST_DAEMON")?;
+        let timeout = match std::env::var("SEMANTIC_RUST_DAEMON_TIMEOUT") {
+            Ok(value) => value.parse().unwrap_or_else(|_| {
+                tracing::warn!(
+                    "SEMANTIC_RUST_DAEMON_TIMEOUT isn't a number of milliseconds: {:?}",
+                    value
+                );
+                DEFAULT_TIMEOUT_MS
+            }),
+            Err(_) => DEFAULT_TIMEOUT_MS,
+        };
+        Some(Client {
+            path: PathBuf::from(path),
+            environment: Environment::current(),
+            timeout: Duration::from_millis(timeout.max(1)),
+            available: true,
+        })
+    }
+
+    /// The tree for `contents` as `name`, and that tree serialized as the
+    /// shell writes it, or `None` when the daemon didn't answer with one,
+    /// for whatever reason, and the caller should parse the file itself.
+    /// Once the daemon couldn't be reached or didn't answer in time, it
+    /// isn't tried again.
+    pub fn tree(
+        &mut self,
+        name: &str,
+        contents: &str,
+    ) -> Option<(SemanticFile, String)> {
+        if !self.available {
+            return None;
+        }
+        let params = json!({
+            "name": name,
+            "text": contents,
+            "environment": self.environment,
+        });
+        let failed = |e: anyhow::Error| {
+            tracing::warn!(
+                daemon = %self.path.display(),
+                "the daemon didn't build {}, parsing it here: {:#}",
+                name,
+                e
+            );
+        };
+        match self.call("tree", params) {
+            Ok(Ok(result)) => {
+                let tree = result
+                    .get("tree")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("the daemon answered without a tree")
+                    })
+                    .and_then(|tree| {
+                        let file = serde_json::from_str(tree)?;
+                        Ok((file, tree.to_string()))
+                    });
+                tree.map_err(failed).ok()
+            }
+            Ok(Err(message)) => {
+                failed(anyhow::anyhow!("{}", message));
+                None
+            }
+            Err(e) => {
+                tracing::warn!(
+                    daemon = %self.path.display(),
+                    "daemon unavailable, parsing here: {:#}",
+                    e
+                );
+                self.available = false;
+                None
+            }
+        }
+    }
+
+    /// Sends one JSON-RPC request, returning its result or the message of
+    /// the error it was answered with; `Err` when it couldn't be answered.
+    #[cfg(unix)]
+    fn call(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> anyhow::Result<Result<Value, String>> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let request =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        serde_json::to_writer(&mut stream, &request)?;
+        writeln!(stream)?;
+        stream.flush()?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let response: Value = serde_json::from_str(&line)?;
+        Ok(match response.get("error") {
+            Some(error) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string()),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn call(
+        &self,
+        _method: &str,
+        _params: Value,
+    ) -> anyhow::Result<Result<Value, String>> {
+        anyhow::bail!("the daemon needs Unix domain sockets")
+    }
+
+    /// Whether the daemon builds a tree for a one-line sample, for
+    /// `doctor`.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let params = json!({
+            "name": "sample.rs",
+            "text": "fn main() {}\n",
+            "environment": self.environment,
+        });
+        match self.call("tree", params)? {
+            Ok(_) => Ok(()),
+            Err(message) => anyhow::bail!("{}", message),
+        }
+    }
+}
@@ -0,0 +1,714 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::files::{collect_rust_files, module_dir, module_file};
+use crate::model::{base_name, is_declaration_kind, Complexity, Node, SemanticFile};
+use crate::parse::{parse_source_with, read_file, ParseOptions};
+
+/// A declaration found in a parsed file, flattened out of the node tree.
+pub struct Declaration {
+    pub kind: String,
+    pub name: String,
+    /// Names of the enclosing declarations and this one, joined with `::`.
+    pub qualified_name: String,
+    pub parent: Option<usize>,
+    pub start: [i64; 2],
+    pub end: [i64; 2],
+    pub range: std::ops::Range<usize>,
+    /// Source text of the declaration with its nested declarations cut out,
+    /// so a change inside `fn f` isn't also reported against the `impl`.
+    pub own_text: String,
+    /// The file an inlined module's declaration comes from; `start` and
+    /// `end` count in that file.
+    pub file: Option<String>,
+    /// Its `async`, `unsafe`, `const` and `extern` qualifiers, when parsed
+    /// with [`diff_options`].
+    pub qualifiers: Vec<String>,
+    /// How complex a function is, when parsed with [`diff_options`].
+    pub complexity: Option<Complexity>,
+}
+
+/// Parse options that record what diffs report beyond the text: the
+/// qualifiers, whose change is easy to miss in a modified body, and the
+/// complexity of functions, so reviewers see whether a change made one
+/// harder to follow.
+pub fn diff_options() -> ParseOptions {
+    ParseOptions {
+        qualifiers: true,
+        complexity: true,
+        ..ParseOptions::default()
+    }
+}
+
+pub fn declarations(file: &SemanticFile, source: &str) -> Vec<Declaration> {
+    fn collect(
+        nodes: &[Node],
+        source: &str,
+        parent: Option<usize>,
+        prefix: &str,
+        out: &mut Vec<Declaration>,
+    ) {
+        for node in nodes {
+            if !is_declaration_kind(node.kind()) {
+                collect(node.children(), source, parent, prefix, out);
+                continue;
+            }
+            let qualified_name = if prefix.is_empty() {
+                node.name().to_string()
+            } else {
+                format!("{}::{}", prefix, node.name())
+            };
+            let index = out.len();
+            out.push(Declaration {
+                kind: node.kind().to_string(),
+                name: node.name().to_string(),
+                qualified_name: qualified_name.clone(),
+                parent,
+                start: node.location_span().start,
+                end: node.location_span().end,
+                range: node.text_range(source),
+                own_text: String::new(),
+                file: None,
+                qualifiers: node.metadata().map(|m| m.qualifiers()).unwrap_or_default(),
+                complexity: node.metadata().and_then(|m| m.complexity),
+            });
+            collect(node.children(), source, Some(index), &qualified_name, out);
+
+            let range = out[index].range.clone();
+            let mut own_text = String::new();
+            let mut pos = range.start;
+            for nested in out[index + 1..].iter().filter(|d| d.parent == Some(index)) {
+                own_text.push_str(source.get(pos..nested.range.start).unwrap_or(""));
+                pos = nested.range.end;
+            }
+            own_text.push_str(source.get(pos..range.end).unwrap_or(""));
+            out[index].own_text = own_text;
+        }
+    }
+
+    let mut out = vec![];
+    collect(&file.children, source, None, "", &mut out);
+    out
+}
+
+/// Appends the declarations of the files rustc loads for the `mod foo;`s
+/// among `decls`, found in `src` with its modules in `dir`, all the way
+/// down. Each file's declarations go under its `mod`, so they are named
+/// like `foo::bar` and diff as part of the module. Modules whose file can't
+/// be found stay empty.
+pub fn inline_modules(
+    decls: &mut Vec<Declaration>,
+    src: &str,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    for i in 0..decls.len() {
+        if decls[i].kind != "mod_item" {
+            continue;
+        }
+        let text = src.get(decls[i].range.clone()).unwrap_or("");
+        if !text.trim_end().ends_with(';') {
+            continue;
+        }
+        // Modules declared inside inline ones live in their directories.
+        let mut module_dir = dir.to_path_buf();
+        let mut parents = vec![];
+        let mut parent = decls[i].parent;
+        while let Some(p) = parent {
+            if decls[p].kind == "mod_item" {
+                parents.push(base_name(&decls[p].name));
+            }
+            parent = decls[p].parent;
+        }
+        for name in parents.iter().rev() {
+            module_dir.push(name);
+        }
+        let name = base_name(&decls[i].name);
+        let Some(path) = module_file(&module_dir, name) else {
+            continue;
+        };
+        let file = path.to_string_lossy().replace('\\', "/");
+        let contents = read_file(&file)?;
+        let mut module = declarations(
+            &parse_source_with(&file, &contents, &diff_options())?,
+            &contents,
+        );
+        inline_modules(&mut module, &contents, &module_dir.join(name))?;
+
+        let base = decls.len();
+        let prefix = decls[i].qualified_name.clone();
+        for mut decl in module {
+            decl.parent = Some(decl.parent.map_or(i, |p| p + base));
+            decl.qualified_name = format!("{}::{}", prefix, decl.qualified_name);
+            decl.file.get_or_insert_with(|| file.clone());
+            decls.push(decl);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+    Moved,
+    Renamed,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub change: ChangeKind,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_name: Option<String>,
+    pub line: i64,
+    /// The file `line` counts in, when it isn't the diffed file itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Qualifiers a declaration gained (`+async`) or lost (`-unsafe`), which
+    /// change what it means without changing its name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub qualifiers: Vec<String>,
+    /// A function's complexity before and after, when it changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<ComplexityChange>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComplexityChange {
+    pub old: Complexity,
+    pub new: Complexity,
+}
+
+struct Matching {
+    old_status: Vec<Option<ChangeKind>>,
+    new_status: Vec<Option<ChangeKind>>,
+    old_match: Vec<Option<usize>>,
+    new_match: Vec<Option<usize>>,
+}
+
+impl Matching {
+    fn pair(&mut self, old: usize, new: usize, change: Option<ChangeKind>) {
+        self.old_match[old] = Some(new);
+        self.new_match[new] = Some(old);
+        self.old_status[old] = change;
+        self.new_status[new] = change;
+    }
+
+    fn pair_leftovers(
+        &mut self,
+        old: &[Declaration],
+        new: &[Declaration],
+        change: ChangeKind,
+        same: impl Fn(&Declaration, &Declaration) -> bool,
+    ) {
+        for (i, a) in old.iter().enumerate() {
+            if self.old_match[i].is_some() {
+                continue;
+            }
+            let candidate = (0..new.len()).find(|&j| {
+                self.new_match[j].is_none() && a.kind == new[j].kind && same(a, &new[j])
+            });
+            if let Some(j) = candidate {
+                self.pair(i, j, Some(change));
+            }
+        }
+    }
+}
+
+/// Matches declarations between two versions of a file by qualified name
+/// and kind, then pairs up the leftovers as moves (same name, new parent)
+/// or renames (same text apart from the name). Changes nested inside an
+/// added, removed, moved or renamed declaration are implied by it and are
+/// not reported separately.
+pub fn diff_declarations(old: &[Declaration], new: &[Declaration]) -> Vec<Change> {
+    fn keys(decls: &[Declaration]) -> Vec<(&str, &str, usize)> {
+        let mut seen: HashMap<(&str, &str), usize> = HashMap::new();
+        decls
+            .iter()
+            .map(|d| {
+                let count = seen
+                    .entry((d.qualified_name.as_str(), d.kind.as_str()))
+                    .or_default();
+                *count += 1;
+                (d.qualified_name.as_str(), d.kind.as_str(), *count)
+            })
+            .collect()
+    }
+
+    fn hidden(
+        decls: &[Declaration],
+        status: &[Option<ChangeKind>],
+        index: usize,
+    ) -> bool {
+        let mut parent = decls[index].parent;
+        while let Some(p) = parent {
+            if matches!(status[p], Some(c) if c != ChangeKind::Modified) {
+                return true;
+            }
+            parent = decls[p].parent;
+        }
+        false
+    }
+
+    let new_keys = keys(new);
+    let new_by_key: HashMap<_, _> =
+        new_keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+
+    let mut m = Matching {
+        old_status: vec![None; old.len()],
+        new_status: vec![None; new.len()],
+        old_match: vec![None; old.len()],
+        new_match: vec![None; new.len()],
+    };
+    for (i, key) in keys(old).into_iter().enumerate() {
+        if let Some(&j) = new_by_key.get(&key) {
+            let change =
+                (old[i].own_text != new[j].own_text).then_some(ChangeKind::Modified);
+            m.pair(i, j, change);
+        }
+    }
+    m.pair_leftovers(old, new, ChangeKind::Moved, |a, b| a.name == b.name);
+    m.pair_leftovers(old, new, ChangeKind::Renamed, |a, b| {
+        a.own_text.replacen(&a.name, "", 1) == b.own_text.replacen(&b.name, "", 1)
+    });
+
+    let Matching {
+        mut old_status,
+        mut new_status,
+        old_match,
+        new_match,
+    } = m;
+    for (i, status) in old_status.iter_mut().enumerate() {
+        if old_match[i].is_none() {
+            *status = Some(ChangeKind::Removed);
+        }
+    }
+    for (j, status) in new_status.iter_mut().enumerate() {
+        if new_match[j].is_none() {
+            *status = Some(ChangeKind::Added);
+        }
+    }
+
+    let mut changes = vec![];
+    for (j, decl) in new.iter().enumerate() {
+        let change = match new_status[j] {
+            Some(change) if !hidden(new, &new_status, j) => change,
+            _ => continue,
+        };
+        let old_name = match change {
+            ChangeKind::Moved | ChangeKind::Renamed => {
+                new_match[j].map(|i| old[i].qualified_name.clone())
+            }
+            _ => None,
+        };
+        let qualifiers = match new_match[j] {
+            Some(i) => qualifier_changes(&old[i].qualifiers, &decl.qualifiers),
+            None => vec![],
+        };
+        let complexity = match (
+            new_match[j].and_then(|i| old[i].complexity),
+            decl.complexity,
+        ) {
+            (Some(old), Some(new)) if old != new => Some(ComplexityChange { old, new }),
+            _ => None,
+        };
+        changes.push(Change {
+            change,
+            item_type: decl.kind.clone(),
+            name: decl.qualified_name.clone(),
+            old_name,
+            line: decl.start[0],
+            file: decl.file.clone(),
+            qualifiers,
+            complexity,
+        });
+    }
+    for (i, decl) in old.iter().enumerate() {
+        if old_status[i] == Some(ChangeKind::Removed) && !hidden(old, &old_status, i) {
+            changes.push(Change {
+                change: ChangeKind::Removed,
+                item_type: decl.kind.clone(),
+                name: decl.qualified_name.clone(),
+                old_name: None,
+                line: decl.start[0],
+                file: decl.file.clone(),
+                qualifiers: vec![],
+                complexity: None,
+            });
+        }
+    }
+    changes
+}
+
+/// The qualifiers in `new` but not `old`, marked `+`, then those in `old`
+/// but not `new`, marked `-`.
+fn qualifier_changes(old: &[String], new: &[String]) -> Vec<String> {
+    let added = new
+        .iter()
+        .filter(|q| !old.contains(q))
+        .map(|q| format!("+{}", q));
+    let removed = old
+        .iter()
+        .filter(|q| !new.contains(q))
+        .map(|q| format!("-{}", q));
+    added.chain(removed).collect()
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Unchanged,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+    pub changes: Vec<Change>,
+}
+
+/// A file's contents alongside its semantic tree and declarations.
+pub struct ParsedFile {
+    pub contents: String,
+    pub file: SemanticFile,
+    pub declarations: Vec<Declaration>,
+}
+
+impl ParsedFile {
+    pub fn from_source(name: &str, contents: String) -> anyhow::Result<ParsedFile> {
+        let file = parse_source_with(name, &contents, &diff_options())?;
+        Ok(ParsedFile::from_parts(contents, file))
+    }
+
+    pub fn from_parts(contents: String, file: SemanticFile) -> ParsedFile {
+        let declarations = declarations(&file, &contents);
+        ParsedFile {
+            contents,
+            file,
+            declarations,
+        }
+    }
+}
+
+fn parse_path(path: &Path) -> anyhow::Result<ParsedFile> {
+    let name = path.to_string_lossy();
+    let contents = read_file(&name)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", name, e))?;
+    ParsedFile::from_source(&name, contents)
+}
+
+pub fn file_diff(
+    old_path: Option<String>,
+    new_path: Option<String>,
+    old: Option<&ParsedFile>,
+    new: Option<&ParsedFile>,
+) -> FileDiff {
+    let changes = diff_declarations(
+        old.map_or(&[][..], |f| &f.declarations),
+        new.map_or(&[][..], |f| &f.declarations),
+    );
+    let status = match (old, new) {
+        (None, _) => FileStatus::Added,
+        (_, None) => FileStatus::Deleted,
+        // Only the changes count: identical files can still load different modules.
+        (Some(_), Some(_)) if changes.is_empty() => FileStatus::Unchanged,
+        _ => FileStatus::Modified,
+    };
+    FileDiff {
+        status,
+        old_path,
+        new_path,
+        similarity: None,
+        changes,
+    }
+}
+
+/// Diffs two files, with the files their `mod foo;`s load inlined into them
+/// when `inline` is set.
+pub fn diff_file_pair(
+    old: &Path,
+    new: &Path,
+    inline: bool,
+) -> anyhow::Result<FileDiff> {
+    let mut old_file = parse_path(old)?;
+    let mut new_file = parse_path(new)?;
+    if inline {
+        for (file, path) in [(&mut old_file, old), (&mut new_file, new)] {
+            inline_modules(&mut file.declarations, &file.contents, &module_dir(path))?;
+        }
+    }
+    Ok(file_diff(
+        Some(old.to_string_lossy().into_owned()),
+        Some(new.to_string_lossy().into_owned()),
+        Some(&old_file),
+        Some(&new_file),
+    ))
+}
+
+/// How alike two files are by declaration inventory, from 0 to 1.
+fn similarity(a: &ParsedFile, b: &ParsedFile) -> f64 {
+    if a.contents == b.contents {
+        return 1.0;
+    }
+    let total = a.declarations.len() + b.declarations.len();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut counts: HashMap<(&str, &str), i32> = HashMap::new();
+    for d in &a.declarations {
+        *counts.entry((&d.kind, &d.qualified_name)).or_default() += 1;
+    }
+    let mut common = 0;
+    for d in &b.declarations {
+        if let Some(count) =
+            counts.get_mut(&(d.kind.as_str(), d.qualified_name.as_str()))
+        {
+            if *count > 0 {
+                *count -= 1;
+                common += 1;
+            }
+        }
+    }
+    2.0 * common as f64 / total as f64
+}
+
+/// Pairs the files of two directory trees by relative path, treating an
+/// unpaired deletion and addition as a rename when their declarations are
+/// at least `rename_threshold` alike, and diffs every pair.
+pub fn diff_directories(
+    old_root: &Path,
+    new_root: &Path,
+    rename_threshold: f64,
+) -> anyhow::Result<Vec<FileDiff>> {
+    let old_paths = collect_rust_files(old_root)?;
+    let new_paths = collect_rust_files(new_root)?;
+    let old_set: HashSet<&str> = old_paths.iter().map(String::as_str).collect();
+    let new_set: HashSet<&str> = new_paths.iter().map(String::as_str).collect();
+
+    let mut diffs = vec![];
+    for path in old_paths.iter().filter(|p| new_set.contains(p.as_str())) {
+        let old = parse_path(&old_root.join(path))?;
+        let new = parse_path(&new_root.join(path))?;
+        diffs.push(file_diff(
+            Some(path.clone()),
+            Some(path.clone()),
+            Some(&old),
+            Some(&new),
+        ));
+    }
+
+    let deleted = old_paths
+        .iter()
+        .filter(|p| !new_set.contains(p.as_str()))
+        .map(|p| Ok((p.clone(), parse_path(&old_root.join(p))?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let added = new_paths
+        .iter()
+        .filter(|p| !old_set.contains(p.as_str()))
+        .map(|p| Ok((p.clone(), parse_path(&new_root.join(p))?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut candidates = vec![];
+    for (i, (_, old)) in deleted.iter().enumerate() {
+        for (j, (_, new)) in added.iter().enumerate() {
+            let score = similarity(old, new);
+            if score >= rename_threshold && score > 0.0 {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    candidates
+        .sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut deleted_paired = vec![false; deleted.len()];
+    let mut added_paired = vec![false; added.len()];
+    for (score, i, j) in candidates {
+        if deleted_paired[i] || added_paired[j] {
+            continue;
+        }
+        deleted_paired[i] = true;
+        added_paired[j] = true;
+        let mut diff = file_diff(
+            Some(deleted[i].0.clone()),
+            Some(added[j].0.clone()),
+            Some(&deleted[i].1),
+            Some(&added[j].1),
+        );
+        diff.status = FileStatus::Renamed;
+        diff.similarity = Some(score);
+        diffs.push(diff);
+    }
+    for (i, (path, old)) in deleted.iter().enumerate() {
+        if !deleted_paired[i] {
+            diffs.push(file_diff(Some(path.clone()), None, Some(old), None));
+        }
+    }
+    for (j, (path, new)) in added.iter().enumerate() {
+        if !added_paired[j] {
+            diffs.push(file_diff(None, Some(path.clone()), None, Some(new)));
+        }
+    }
+
+    diffs.sort_by(|a, b| {
+        let key = |d: &FileDiff| d.new_path.clone().or_else(|| d.old_path.clone());
+        key(a).cmp(&key(b))
+    });
+    Ok(diffs)
+}
+
+fn change_label(change: ChangeKind) -> &'static str {
+    match change {
+        ChangeKind::Added => "added",
+        ChangeKind::Removed => "removed",
+        ChangeKind::Modified => "modified",
+        ChangeKind::Moved => "moved",
+        ChangeKind::Renamed => "renamed",
+    }
+}
+
+pub fn write_text(out: &mut dyn Write, diffs: &[FileDiff]) -> std::io::Result<()> {
+    let mut counts: HashMap<ChangeKind, usize> = HashMap::new();
+    let mut files: HashMap<FileStatus, usize> = HashMap::new();
+    for diff in diffs {
+        let old_path = diff.old_path.as_deref().unwrap_or("");
+        let new_path = diff.new_path.as_deref().unwrap_or("");
+        match diff.status {
+            FileStatus::Unchanged => continue,
+            FileStatus::Added => writeln!(out, "A {}", new_path)?,
+            FileStatus::Deleted => writeln!(out, "D {}", old_path)?,
+            FileStatus::Modified => writeln!(out, "M {}", new_path)?,
+            FileStatus::Renamed => match diff.similarity {
+                Some(score) => writeln!(
+                    out,
+                    "R {} -> {} ({:.0}% similar)",
+                    old_path,
+                    new_path,
+                    score * 100.0
+                )?,
+                None => writeln!(out, "R {} -> {}", old_path, new_path)?,
+            },
+        }
+        *files.entry(diff.status).or_default() += 1;
+        for change in &diff.changes {
+            *counts.entry(change.change).or_default() += 1;
+            write!(
+                out,
+                "    {:<9} {} {}",
+                change_label(change.change),
+                change.item_type,
+                change.name
+            )?;
+            if let Some(old_name) = &change.old_name {
+                write!(out, " (was {})", old_name)?;
+            }
+            if !change.qualifiers.is_empty() {
+                write!(out, " [{}]", change.qualifiers.join(" "))?;
+            }
+            if let Some(ComplexityChange { old, new }) = change.complexity {
+                write!(
+                    out,
+                    " (complexity {} -> {}, nesting {} -> {})",
+                    old.cyclomatic, new.cyclomatic, old.nesting, new.nesting
+                )?;
+            }
+            writeln!(out)?;
+        }
+    }
+
+    let file_count = |s| files.get(&s).copied().unwrap_or(0);
+    let count = |c| counts.get(&c).copied().unwrap_or(0);
+    writeln!(
+        out,
+        "{} files changed ({} modified, {} added, {} deleted, {} renamed); \
+         declarations: {} added, {} removed, {} modified, {} moved, {} renamed",
+        files.values().sum::<usize>(),
+        file_count(FileStatus::Modified),
+        file_count(FileStatus::Added),
+        file_count(FileStatus::Deleted),
+        file_count(FileStatus::Renamed),
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Modified),
+        count(ChangeKind::Moved),
+        count(ChangeKind::Renamed),
+    )
+}
+
+/// `diff [--recursive] [--inline-modules] [--format text|json]
+/// [--rename-threshold N] [--label-old <label>] [--label-new <label>]
+/// <old> <new>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--recursive", "-r", "--inline-modules"],
+        &[
+            "--format",
+            "--rename-threshold",
+            "--label-old",
+            "--label-new",
+        ],
+    )?;
+    let [old, new] = args.positional() else {
+        anyhow::bail!(
+            "usage: diff [--recursive|-r] [--inline-modules] [--format text|json] \
+             [--rename-threshold N] [--label-old <label>] [--label-new <label>] \
+             <old> <new>"
+        );
+    };
+    let (old, new) = (Path::new(old), Path::new(new));
+    let rename_threshold = match args.value("--rename-threshold") {
+        Some(value) => value.parse::<f64>()?,
+        None => 0.5,
+    };
+
+    // Mercurial's extdiff hands over two snapshot directories when more
+    // than one file changed, so directories always diff recursively.
+    let recursive = args.flag("--recursive") || args.flag("-r");
+    let diffs = if recursive || (old.is_dir() && new.is_dir()) {
+        if args.flag("--inline-modules") {
+            anyhow::bail!("--inline-modules diffs two files, not directories");
+        }
+        diff_directories(old, new, rename_threshold)?
+    } else {
+        let mut diff = diff_file_pair(old, new, args.flag("--inline-modules"))?;
+        if let Some(label) = args.value("--label-old") {
+            diff.old_path = Some(label.to_string());
+        }
+        if let Some(label) = args.value("--label-new") {
+            diff.new_path = Some(label.to_string());
+        }
+        vec![diff]
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &diffs)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &diffs)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown diff format {}", other),
+    }
+    Ok(())
+}
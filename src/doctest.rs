@@ -0,0 +1,137 @@
+//! Parsing the Rust examples in doc comments, so that an edit to a doctest
+//! merges as code rather than as comment text.
+
+use tree_sitter::{Point, Range};
+
+use crate::parse::rust_parser;
+
+/// Whether a fence's info string, like `no_run` or `rust,ignore`, marks a
+/// block rustdoc reads as Rust. A fence without one is Rust too.
+fn is_rust(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .all(|token| {
+            matches!(
+                token,
+                "rust"
+                    | "ignore"
+                    | "no_run"
+                    | "should_panic"
+                    | "compile_fail"
+                    | "test_harness"
+                    | "standalone_crate"
+            ) || token.starts_with("edition")
+        })
+}
+
+/// Where the lines of a doc comment are.
+enum Fence {
+    Prose,
+    /// In a Rust block opened with `marker`, with the ranges of its lines.
+    Rust(&'static str, Vec<Range>),
+    /// In a block of another language, left as text.
+    Other(&'static str),
+}
+
+/// Parses each fenced Rust block in the `///` comments from `leading` up to
+/// `node`. Each tree covers only its block's code, without the comment
+/// markers or the `# ` that hides a line from the rendered docs, and its
+/// positions are those in `src`. A block that doesn't parse is left out.
+pub fn parse(
+    leading: Option<tree_sitter::Node>,
+    node: &tree_sitter::Node,
+    src: &str,
+) -> Vec<tree_sitter::Tree> {
+    let mut blocks = vec![];
+    let mut fence = Fence::Prose;
+    let mut sibling = leading;
+    while let Some(comment) = sibling.filter(|sibling| sibling != node) {
+        sibling = comment.next_sibling();
+        let text = src.get(comment.byte_range()).unwrap_or("");
+        if comment.kind() != "line_comment"
+            || !text.starts_with("///")
+            || text.starts_with("////")
+        {
+            continue;
+        }
+        let line = text[3..].trim_end_matches(['\r', '\n']);
+        let trimmed = line.trim_start();
+        let opening = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        fence = match fence {
+            Fence::Prose => match opening {
+                Some(marker) if is_rust(&trimmed[3..]) => Fence::Rust(marker, vec![]),
+                Some(marker) => Fence::Other(marker),
+                None => Fence::Prose,
+            },
+            Fence::Other(marker) if opening == Some(marker) => Fence::Prose,
+            Fence::Rust(marker, ranges) if opening == Some(marker) => {
+                blocks.push(ranges);
+                Fence::Prose
+            }
+            Fence::Rust(marker, mut ranges) => {
+                ranges.push(code_range(&comment, line, src));
+                Fence::Rust(marker, ranges)
+            }
+            other => other,
+        };
+    }
+
+    let Ok(mut parser) = rust_parser() else {
+        return vec![];
+    };
+    blocks
+        .into_iter()
+        .filter(|ranges| !ranges.is_empty())
+        .filter_map(|ranges| {
+            parser.set_included_ranges(&ranges).ok()?;
+            parser.parse(src, None)
+        })
+        .filter(|tree| {
+            let root = tree.root_node();
+            root.named_child_count() > 0 && !root.has_error()
+        })
+        .collect()
+}
+
+/// The range of the code in `comment`, a `///` comment whose text after the
+/// marker is `line`: from after the marker, its space and any `# `, through
+/// the line ending, so the lines of a block stay apart when parsed together.
+fn code_range(comment: &tree_sitter::Node, line: &str, src: &str) -> Range {
+    let position = comment.start_position();
+    let line_start = comment.start_byte() - position.column;
+    let code = line.strip_prefix(' ').unwrap_or(line);
+    let hidden = code.trim_start();
+    let code = match hidden.strip_prefix("# ") {
+        Some(rest) => rest,
+        None if hidden == "#" => "",
+        None => code,
+    };
+    let start_byte = comment.start_byte() + 3 + (line.len() - code.len());
+    let (end_byte, end_point) = match src[comment.start_byte()..].find('\n') {
+        Some(newline) => (
+            comment.start_byte() + newline + 1,
+            Point {
+                row: position.row + 1,
+                column: 0,
+            },
+        ),
+        None => (
+            src.len(),
+            Point {
+                row: position.row,
+                column: src.len() - line_start,
+            },
+        ),
+    };
+    Range {
+        start_byte,
+        end_byte,
+        start_point: Point {
+            row: position.row,
+            column: start_byte - line_start,
+        },
+        end_point,
+    }
+}
@@ -0,0 +1,358 @@
+//! `doctor`: checks that this build and its configuration work, for when a
+//! user says "it doesn't work on my machine".
+
+use std::path::Path;
+
+use crate::cli::{parse_switch, Args};
+use crate::daemon::Client;
+use crate::files::{display_names, path_encoding};
+use crate::inject::{injections, Injections, Language};
+use crate::model::{CharSpan, Node, SemanticFile};
+use crate::parse::{
+    parse_depth, parse_size, parse_source_with, rust_parser, Binary, Comments, Nested,
+    ParseOptions,
+};
+use crate::replay::SWITCHES;
+use crate::templates::name_templates;
+use crate::units::{column_unit, tab_width, Base, Ends, Unit};
+
+const RUST_SAMPLE: &str = r#"//! A sample with one of most things.
+use std::fmt::{self, Display};
+
+/// A point.
+///
+/// ```
+/// let p = sample::Point { x: 1, y: 2 };
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub enum Shape {
+    Circle(Point, u32),
+    Square { corner: Point, side: u32 },
+}
+
+pub trait Area {
+    const SIDES: u32;
+    fn area(&self) -> f64;
+}
+
+impl Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+pub async unsafe fn scaled(points: &[Point], by: i32) -> Vec<Point> {
+    let scale = |p: &Point| Point { x: p.x * by, y: square!(p.y) };
+    points.iter().map(scale).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn works() {}
+}
+"#;
+
+const HTML_SAMPLE: &str =
+    "fn view() {\n    html! { <div class=\"a\"><p>{ \"hi\" }</p></div> }\n}\n";
+
+const SQL_SAMPLE: &str =
+    "fn load() {\n    sqlx::query!(\"SELECT id FROM users WHERE id = 1\");\n}\n";
+
+/// How a check came out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail,
+    /// Not applicable to this build or configuration.
+    Skip,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, result: Result<String, String>) -> Check {
+        let (status, detail) = match result {
+            Ok(detail) => (Status::Pass, detail),
+            Err(detail) => (Status::Fail, detail),
+        };
+        Check {
+            name: name.into(),
+            status,
+            detail,
+        }
+    }
+
+    fn skip(name: impl Into<String>, detail: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            status: Status::Skip,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Checks that the spans of `file` cover `src` exactly once, in order, and
+/// that every node's span stays inside its parent's.
+fn check_spans(file: &SemanticFile, src: &str) -> Result<(), String> {
+    fn push(span: &CharSpan, pos: &mut i64) -> Result<(), String> {
+        if span.is_empty() {
+            return Ok(());
+        }
+        let [start, end] = span.span;
+        if start != *pos || end < start {
+            return Err(format!("span [{}, {}] should start at {}", start, end, pos));
+        }
+        *pos = end;
+        Ok(())
+    }
+    fn visit(node: &Node, pos: &mut i64) -> Result<(), String> {
+        match node {
+            Node::Container(c) => {
+                push(&c.header_span, pos)?;
+                for child in &c.children {
+                    visit(child, pos)?;
+                }
+                push(&c.footer_span, pos)
+            }
+            Node::Terminal(t) => push(&t.span, pos),
+        }
+    }
+
+    let mut pos = 0;
+    for child in &file.children {
+        visit(child, &mut pos)?;
+    }
+    push(&file.footer_span, &mut pos)?;
+    if pos as usize != src.len() {
+        return Err(format!("spans end at {} of {} bytes", pos, src.len()));
+    }
+    Ok(())
+}
+
+/// Parses `src` with `options` and checks the tree it makes, including that
+/// some node is of kind `expect`.
+fn check_sample(
+    src: &str,
+    options: &ParseOptions,
+    expect: &str,
+) -> Result<String, String> {
+    fn count(nodes: &[Node], kind: &str, found: &mut bool) -> usize {
+        nodes
+            .iter()
+            .map(|node| {
+                *found |= node.kind() == kind;
+                1 + count(node.children(), kind, found)
+            })
+            .sum()
+    }
+
+    let file =
+        parse_source_with("sample.rs", src, options).map_err(|e| format!("{:#}", e))?;
+    if file.parsing_errors_detected {
+        return Err("the sample has syntax errors".to_string());
+    }
+    check_spans(&file, src)?;
+    let mut found = false;
+    let nodes = count(&file.children, expect, &mut found);
+    if !found {
+        return Err(format!("no {} node in the tree", expect));
+    }
+    Ok(format!("{} nodes, spans tile the sample", nodes))
+}
+
+/// Runs every check.
+pub fn checks() -> Vec<Check> {
+    let mut checks = vec![];
+
+    let grammar = if cfg!(feature = "grammar-current") {
+        "current"
+    } else {
+        "0.19"
+    };
+    checks.push(Check::new(
+        "rust grammar",
+        rust_parser()
+            .map(|_| format!("tree-sitter-rust ({})", grammar))
+            .map_err(|e| format!("{:#}", e)),
+    ));
+    let options = ParseOptions {
+        signatures: true,
+        qualifiers: true,
+        docs: true,
+        tests: true,
+        qualify_methods: true,
+        nested: Nested::Nest,
+        doctests: true,
+        ordinals: true,
+        ..ParseOptions::default()
+    };
+    checks.push(Check::new(
+        "rust sample",
+        check_sample(RUST_SAMPLE, &options, "doctest"),
+    ));
+
+    for (language, spec, sample, expect) in [
+        ("html", "html=html", HTML_SAMPLE, "element"),
+        ("sql", "query=sql", SQL_SAMPLE, "statement"),
+    ] {
+        let name = format!("{} grammar", language);
+        if Language::parse(language).is_err() {
+            checks.push(Check::skip(name, "not built in"));
+            continue;
+        }
+        let options = ParseOptions {
+            injections: Injections::parse(spec).unwrap_or_default(),
+            ..ParseOptions::default()
+        };
+        checks.push(Check::new(name, check_sample(sample, &options, expect)));
+    }
+
+    checks.extend(configuration());
+    checks
+}
+
+/// Checks the environment variables that configure the tool, where set.
+fn configuration() -> Vec<Check> {
+    let mut checks = vec![];
+    let mut check =
+        |name: &str, verify: &dyn Fn(&str) -> Result<(), String>| match std::env::var(
+            name,
+        ) {
+            Ok(value) => checks.push(Check::new(
+                name,
+                verify(&value).map(|()| format!("{:?}", value)),
+            )),
+            Err(_) => checks.push(Check::skip(name, "not set")),
+        };
+    let error = |e: anyhow::Error| format!("{:#}", e);
+    check("SEMANTIC_RUST_COLUMNS", &|_| {
+        column_unit(None).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_TAB_WIDTH", &|_| {
+        tab_width(None).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_INJECT", &|_| {
+        injections(None).map(|_| ()).map_err(error)
+    });
+    for name in SWITCHES.iter().chain(&["CLEANUP"]) {
+        let var = format!("SEMANTIC_RUST_{}", name);
+        check(&var, &|value| {
+            parse_switch(&var, value).map(|_| ()).map_err(error)
+        });
+    }
+    check("SEMANTIC_RUST_NESTED", &|value| {
+        Nested::parse(value).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_BINARY", &|value| {
+        Binary::parse(value).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_COMMENTS", &|value| {
+        Comments::parse(value).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_MAX_DEPTH", &|value| {
+        parse_depth("SEMANTIC_RUST_MAX_DEPTH", value)
+            .map(|_| ())
+            .map_err(error)
+    });
+    for name in ["SEMANTIC_RUST_MIN_BYTES", "SEMANTIC_RUST_MIN_LINES"] {
+        check(name, &|value| {
+            parse_size(name, value).map(|_| ()).map_err(error)
+        });
+    }
+    check("SEMANTIC_RUST_NAME_TEMPLATES", &|_| {
+        name_templates(None).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_NAMES", &|_| {
+        display_names().map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_PATH_ENCODING", &|_| {
+        path_encoding().map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_OFFSETS", &|value| {
+        Unit::parse(value).map(|_| ()).map_err(error)
+    });
+    check("SEMANTIC_RUST_ENDS", &|value| {
+        Ends::parse(value).map(|_| ()).map_err(error)
+    });
+    for name in ["SEMANTIC_RUST_LINE_BASE", "SEMANTIC_RUST_COLUMN_BASE"] {
+        check(name, &|value| {
+            Base::parse_one(name, value).map(|_| ()).map_err(error)
+        });
+    }
+    check("SEMANTIC_RUST_DAEMON", &|_| match Client::from_env() {
+        Some(client) => client.check().map_err(error),
+        None => Ok(()),
+    });
+    check("SEMANTIC_RUST_FORMAT", &|value| match value {
+        "json" | "msgpack" | "ndjson" => Ok(()),
+        other => Err(format!("unknown format {}", other)),
+    });
+    check("SEMANTIC_RUST_LOG_FILTER", &|value| {
+        tracing_subscriber::EnvFilter::try_new(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    });
+    for name in [
+        "SEMANTIC_RUST_LOG",
+        "SEMANTIC_RUST_RECORD",
+        "SEMANTIC_RUST_REPORT",
+    ] {
+        check(name, &|value| {
+            let dir = Path::new(value)
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            if dir.is_dir() {
+                Ok(())
+            } else {
+                Err(format!("{} is not a directory", dir.display()))
+            }
+        });
+    }
+    checks
+}
+
+/// `doctor`
+///
+/// Exits 1 when any check fails.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(raw, &[], &[])?;
+    if !args.positional().is_empty() {
+        anyhow::bail!("usage: doctor");
+    }
+    let checks = checks();
+    for check in &checks {
+        let status = match check.status {
+            Status::Pass => "ok",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        println!("{:<4} {}: {}", status, check.name, check.detail);
+    }
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let failed = count(Status::Fail);
+    println!(
+        "{} passed, {} failed, {} skipped",
+        count(Status::Pass),
+        failed,
+        count(Status::Skip)
+    );
+    Ok(if failed == 0 { 0 } else { 1 })
+}
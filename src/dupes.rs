@@ -0,0 +1,279 @@
+//! Finding functions that were copied and pasted: two functions whose
+//! tokens are the same once names and literals are set aside are
+//! structurally identical, and ones that share most of their token runs
+//! are near-identical.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::{diff_options, ParsedFile};
+use crate::files::expand;
+use crate::hash::fnv1a;
+use crate::ignore::Filter;
+use crate::parse::{build_file, parse_tree, read_file};
+
+/// The length of the token runs compared for near-identical functions.
+const SHINGLE: usize = 4;
+
+/// The most a pair that isn't structurally identical can score.
+const NEARLY_ONE: f64 = 0.99;
+
+/// Tokens standing for whatever name or value they have, so that renaming
+/// a variable or changing a constant keeps a copy a copy.
+fn normalized(kind: &str) -> bool {
+    matches!(
+        kind,
+        "identifier"
+            | "type_identifier"
+            | "field_identifier"
+            | "shorthand_field_identifier"
+            | "lifetime"
+    ) || kind.ends_with("_literal")
+}
+
+/// The tokens of `tree`, with the byte each starts at, leaving out
+/// comments and attributes.
+fn tokens(tree: &tree_sitter::Tree, src: &str) -> Vec<(usize, String)> {
+    fn visit(node: tree_sitter::Node, src: &str, out: &mut Vec<(usize, String)>) {
+        let kind = node.kind();
+        if kind.ends_with("comment") || kind == "attribute_item" {
+            return;
+        }
+        if normalized(kind) {
+            out.push((node.start_byte(), kind.to_string()));
+            return;
+        }
+        if node.child_count() == 0 {
+            if let Some(text) = src.get(node.byte_range()) {
+                out.push((node.start_byte(), text.to_string()));
+            }
+            return;
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            visit(child, src, out);
+        }
+    }
+
+    let mut out = vec![];
+    visit(tree.root_node(), src, &mut out);
+    out
+}
+
+/// A function, as compared.
+struct Function {
+    file: String,
+    name: String,
+    line: i64,
+    tokens: usize,
+    structure: u64,
+    /// How many times each token run occurs.
+    shingles: HashMap<u64, usize>,
+    /// All token runs, repeats included.
+    runs: usize,
+}
+
+/// The functions of `src` with at least `min_tokens` tokens.
+fn functions(
+    name: &str,
+    src: String,
+    min_tokens: usize,
+) -> anyhow::Result<Vec<Function>> {
+    let tree = parse_tree(name, &src)?;
+    let file = build_file(name, &src, &tree, &diff_options())?;
+    let tokens = tokens(&tree, &src);
+    let parsed = ParsedFile::from_parts(src, file);
+
+    let mut out = vec![];
+    for decl in &parsed.declarations {
+        if decl.kind != "function_item" {
+            continue;
+        }
+        let first = tokens.partition_point(|(start, _)| *start < decl.range.start);
+        let last = tokens.partition_point(|(start, _)| *start < decl.range.end);
+        let tokens: Vec<&str> = tokens[first..last]
+            .iter()
+            .map(|(_, t)| t.as_str())
+            .collect();
+        if tokens.len() < min_tokens.max(SHINGLE) {
+            continue;
+        }
+        let mut shingles = HashMap::new();
+        for run in tokens.windows(SHINGLE) {
+            *shingles
+                .entry(fnv1a(run.join("\0").as_bytes()))
+                .or_default() += 1;
+        }
+        out.push(Function {
+            file: name.to_string(),
+            name: decl.qualified_name.clone(),
+            line: decl.start[0],
+            tokens: tokens.len(),
+            structure: fnv1a(tokens.join("\0").as_bytes()),
+            shingles,
+            runs: tokens.len() + 1 - SHINGLE,
+        });
+    }
+    Ok(out)
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub file: String,
+    pub name: String,
+    pub line: i64,
+    pub tokens: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Duplicate {
+    /// 1 for structurally identical functions, else the share of their
+    /// token runs they have in common, a run repeated counting as often as
+    /// it occurs in both.
+    pub similarity: f64,
+    pub first: Location,
+    pub second: Location,
+}
+
+/// The pairs of functions in `files` (name and contents) at least
+/// `threshold` similar, most similar first.
+pub fn duplicates(
+    files: impl IntoIterator<Item = (String, String)>,
+    threshold: f64,
+    min_tokens: usize,
+) -> anyhow::Result<Vec<Duplicate>> {
+    let mut all = vec![];
+    for (name, src) in files {
+        all.extend(functions(&name, src, min_tokens)?);
+    }
+    // Shortest first, so each function is compared with the ones long
+    // enough to reach the threshold and stops at the first that is too long:
+    // two sets can't have more in common than the smaller one's share of
+    // the larger.
+    all.sort_by_key(|f| f.runs);
+
+    let location = |f: &Function| Location {
+        file: f.file.clone(),
+        name: f.name.clone(),
+        line: f.line,
+        tokens: f.tokens,
+    };
+    let mut out = vec![];
+    for (i, a) in all.iter().enumerate() {
+        for b in &all[i + 1..] {
+            if (a.runs as f64) < threshold * b.runs as f64 {
+                break;
+            }
+            let similarity = if a.structure == b.structure {
+                1.0
+            } else {
+                let common: usize = a
+                    .shingles
+                    .iter()
+                    .map(|(run, n)| (*n).min(b.shingles.get(run).copied().unwrap_or(0)))
+                    .sum();
+                // The same runs in another order are still not a copy.
+                (common as f64 / (a.runs + b.runs - common) as f64).min(NEARLY_ONE)
+            };
+            if similarity >= threshold {
+                let (first, second) = if (&a.file, a.line) <= (&b.file, b.line) {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                out.push(Duplicate {
+                    similarity,
+                    first: location(first),
+                    second: location(second),
+                });
+            }
+        }
+    }
+    out.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| {
+                (&a.first.file, a.first.line).cmp(&(&b.first.file, b.first.line))
+            })
+            .then_with(|| {
+                (&a.second.file, a.second.line).cmp(&(&b.second.file, b.second.line))
+            })
+    });
+    Ok(out)
+}
+
+fn write_text(out: &mut dyn Write, duplicates: &[Duplicate]) -> std::io::Result<()> {
+    for duplicate in duplicates {
+        writeln!(
+            out,
+            "{:>3.0}% {}:{}: {} ({} tokens) ~ {}:{}: {} ({} tokens)",
+            duplicate.similarity * 100.0,
+            duplicate.first.file,
+            duplicate.first.line,
+            duplicate.first.name,
+            duplicate.first.tokens,
+            duplicate.second.file,
+            duplicate.second.line,
+            duplicate.second.name,
+            duplicate.second.tokens
+        )?;
+    }
+    Ok(())
+}
+
+/// `dupes [--threshold 0.9] [--min-tokens 30] [--format text|json]
+/// [--exclude <glob>] [--include <glob>] [--no-ignore]
+/// [--symlinks follow|skip|error] <paths...>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &[
+            "--threshold",
+            "--min-tokens",
+            "--format",
+            "--exclude",
+            "--include",
+            "--symlinks",
+        ],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: dupes [--threshold 0.9] [--min-tokens 30] [--format text|json] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+    let threshold: f64 = match args.value("--threshold") {
+        Some(threshold) => threshold.parse()?,
+        None => 0.9,
+    };
+    if !(0.0..=1.0).contains(&threshold) {
+        anyhow::bail!("--threshold must be between 0 and 1");
+    }
+    let min_tokens = match args.value("--min-tokens") {
+        Some(n) => n.parse()?,
+        None => 30,
+    };
+
+    let mut files = vec![];
+    for (path, name) in expand(args.positional(), &Filter::from_args(&args)?)? {
+        files.push((name, read_file(&path.to_string_lossy())?));
+    }
+    let duplicates = duplicates(files, threshold, min_tokens)?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &duplicates)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &duplicates)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown dupes format {}", other),
+    }
+    Ok(())
+}
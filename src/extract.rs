@@ -0,0 +1,107 @@
+//! Printing one declaration's source, found by name: its exact text from
+//! the span model, docs and attributes included, for scripts and review
+//! bots that want a function without parsing Rust themselves.
+
+use std::io::Write;
+
+use crate::cli::Args;
+use crate::diff::{Declaration, ParsedFile};
+use crate::model::base_name;
+use crate::parse::{build_file, parse_tree, read_file, ParseOptions};
+
+/// The names `parsed.declarations[index]` answers to: its qualified name as
+/// `parse` and `outline` print it, and its path as written in Rust, with
+/// impls left out since their methods are named after them, like
+/// `Point::new` and `<Point as Draw>::draw`. An impl itself is `impl Point`
+/// or `impl Draw for Point`, as written. Each also without the
+/// `#[cfg(...)]`s that tell cfg-gated versions apart.
+fn names(parsed: &ParsedFile, index: usize) -> Vec<String> {
+    let decls = &parsed.declarations;
+    let mut segments = vec![];
+    let mut current = Some(index);
+    while let Some(i) = current {
+        let decl = &decls[i];
+        if decl.kind != "impl_item" || i == index {
+            segments.push(decl.name.clone());
+        }
+        current = decl.parent;
+    }
+    segments.reverse();
+
+    let without_cfgs = |name: &str| {
+        name.split("::")
+            .map(base_name)
+            .collect::<Vec<_>>()
+            .join("::")
+    };
+    let qualified = decls[index].qualified_name.clone();
+    let path = segments.join("::");
+    vec![
+        without_cfgs(&qualified),
+        without_cfgs(&path),
+        qualified,
+        path,
+    ]
+}
+
+/// The declarations of `parsed` named `name`, in any of the ways [`names`]
+/// allows.
+pub fn find<'a>(parsed: &'a ParsedFile, name: &str) -> Vec<&'a Declaration> {
+    (0..parsed.declarations.len())
+        .filter(|&i| names(parsed, i).iter().any(|n| n == name))
+        .map(|i| &parsed.declarations[i])
+        .collect()
+}
+
+/// The source text of the one declaration in `file`, with `contents`,
+/// named `name`, from the start of its first line when only indentation
+/// comes before it there, so its lines stay lined up.
+pub fn extract(file: &str, contents: String, name: &str) -> anyhow::Result<String> {
+    let options = ParseOptions {
+        qualify_methods: true,
+        ..ParseOptions::default()
+    };
+    let tree = parse_tree(file, &contents)?;
+    let semantic = build_file(file, &contents, &tree, &options)?;
+    let parsed = ParsedFile::from_parts(contents, semantic);
+    match find(&parsed, name)[..] {
+        [decl] => {
+            let before = &parsed.contents[..decl.range.start];
+            let indent = before.len() - before.trim_end_matches([' ', '\t']).len();
+            let start = match before[..before.len() - indent].chars().next_back() {
+                None | Some('\n') => decl.range.start - indent,
+                Some(_) => decl.range.start,
+            };
+            Ok(parsed.contents[start..decl.range.end].to_string())
+        }
+        [] => anyhow::bail!("{} has no declaration named {}", file, name),
+        ref several => {
+            let found: Vec<String> = several
+                .iter()
+                .map(|decl| {
+                    format!("{} at line {}", decl.qualified_name, decl.start[0])
+                })
+                .collect();
+            anyhow::bail!(
+                "{} has {} declarations named {}: {}",
+                file,
+                several.len(),
+                name,
+                found.join(", ")
+            )
+        }
+    }
+}
+
+/// `extract <file> --name <path::to::item>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &[], &["--name"])?;
+    let ([file], Some(name)) = (args.positional(), args.value("--name")) else {
+        anyhow::bail!("usage: extract <file> --name <path::to::item>");
+    };
+    let text = extract(file, read_file(file)?, name)?;
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{}", text)?;
+    Ok(())
+}
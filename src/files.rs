@@ -0,0 +1,528 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::ignore::{Filter, Gitignore, Symlinks};
+use crate::parse::BOM;
+
+/// Relative paths (with `/` separators) of every Rust file below `root`,
+/// sorted so output is stable between runs.
+pub fn collect_rust_files(root: &Path) -> anyhow::Result<Vec<String>> {
+    collect_filtered(root, &Filter::everything())
+}
+
+/// Like [`collect_rust_files`], but only the files `filter` reads; with
+/// `filter.gitignore`, the `.git` directory and whatever the `.gitignore`
+/// files at and below `root` ignore are left out too. Symbolic links are
+/// handled as `filter.symlinks` says.
+pub fn collect_filtered(root: &Path, filter: &Filter) -> anyhow::Result<Vec<String>> {
+    /// `within` holds the canonical paths of `dir` and the directories
+    /// around it, for telling links that loop back from the rest.
+    fn visit(
+        dir: &Path,
+        prefix: &str,
+        filter: &Filter,
+        ignore: &Gitignore,
+        within: &mut Vec<PathBuf>,
+        out: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let mut ignore = ignore.clone();
+        if filter.gitignore {
+            ignore.read(dir, prefix)?;
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let file_type = entry.file_type()?;
+            let (is_dir, canonical) = if file_type.is_symlink() {
+                match filter.symlinks {
+                    Symlinks::Skip => continue,
+                    Symlinks::Error => anyhow::bail!(
+                        "{} is a symbolic link, and symlinks are errors",
+                        entry.path().display()
+                    ),
+                    Symlinks::Follow => match entry.path().canonicalize() {
+                        Ok(target) => (target.is_dir(), target),
+                        Err(e) => {
+                            eprintln!(
+                                "warning: skipping {}, a link to nothing: {}",
+                                entry.path().display(),
+                                e
+                            );
+                            continue;
+                        }
+                    },
+                }
+            } else {
+                let parent = within.last().map_or(dir, |p| p.as_path());
+                (file_type.is_dir(), parent.join(&name))
+            };
+            if filter.gitignore
+                && ((is_dir && name == ".git") || ignore.is_ignored(&relative, is_dir))
+            {
+                continue;
+            }
+            if is_dir {
+                if within.contains(&canonical) {
+                    eprintln!(
+                        "warning: skipping {}, a link back to {}",
+                        entry.path().display(),
+                        canonical.display()
+                    );
+                } else if filter.enters(&relative) {
+                    within.push(canonical);
+                    let visited =
+                        visit(&entry.path(), &relative, filter, &ignore, within, out);
+                    within.pop();
+                    visited?;
+                }
+            } else if name.ends_with(".rs") && filter.reads(&relative) {
+                out.push(relative);
+            }
+        }
+        Ok(())
+    }
+
+    let mut within = vec![root.canonicalize()?];
+    let mut out = vec![];
+    visit(
+        root,
+        "",
+        filter,
+        &Gitignore::default(),
+        &mut within,
+        &mut out,
+    )?;
+    out.sort();
+    Ok(out)
+}
+
+/// `path`, a relative one, with `/` separators and without `.`
+/// components, so the same path is written the same on every platform.
+fn portable(path: &Path) -> String {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .map(|c| c.as_os_str().to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every Rust file named by `paths` that `filter` reads, expanding
+/// directories, with the name to show it by. Files named outright are read
+/// whatever the `.gitignore`s say, but not when `--exclude` or `--include`
+/// leaves them out.
+///
+/// Names are portable between machines: relative paths keep the path
+/// given, with `/` separators and without `.` components, while below an
+/// absolute path they are relative to it, so a file given outright is named
+/// by its file name.
+pub fn expand(
+    paths: &[String],
+    filter: &Filter,
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut out = vec![];
+    for path in paths {
+        let path = Path::new(path);
+        let root = if path.is_absolute() {
+            String::new()
+        } else {
+            portable(path)
+        };
+        let name = |relative: &str| match (root.as_str(), relative) {
+            ("", relative) => relative.to_string(),
+            (root, "") => root.to_string(),
+            (root, relative) => format!("{}/{}", root, relative),
+        };
+        if path.is_dir() {
+            out.extend(
+                collect_filtered(path, filter)?
+                    .into_iter()
+                    .map(|p| (path.join(&p), name(&p))),
+            );
+        } else if filter.reads(&path.to_string_lossy().replace('\\', "/")) {
+            let file_name = if path.is_absolute() {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            } else {
+                String::new()
+            };
+            out.push((path.to_path_buf(), name(&file_name)));
+        }
+    }
+    Ok(out)
+}
+
+/// `path` relative to `root`, with `/` separators, for naming trees the
+/// same wherever the checkout is. Both are canonicalized first, so `.`
+/// and links in either don't matter; `path` must be below `root`.
+pub fn relative_name(path: &Path, root: &Path) -> anyhow::Result<String> {
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .map_err(|e| anyhow::anyhow!("failed to resolve {}: {}", path.display(), e))
+    };
+    let (path, root) = (canonical(path)?, canonical(root)?);
+    let relative = path.strip_prefix(&root).map_err(|_| {
+        anyhow::anyhow!("{} isn't below {}", path.display(), root.display())
+    })?;
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// The directory the out-of-line modules declared in `file` live in: its
+/// own for a `mod.rs`, `lib.rs` or `main.rs`, else the one named after it.
+pub fn module_dir(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    match file.file_stem().and_then(|stem| stem.to_str()) {
+        Some("mod" | "lib" | "main") | None => dir.to_path_buf(),
+        Some(stem) => dir.join(stem),
+    }
+}
+
+/// The file rustc loads for `mod name;` declared with its modules in `dir`:
+/// `name.rs`, else `name/mod.rs`. `#[path]` attributes aren't followed.
+pub fn module_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    [
+        dir.join(format!("{}.rs", name)),
+        dir.join(name).join("mod.rs"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// The path a `#[path = "..."]` attribute in `text`, the source of a `mod`
+/// declaration, gives for the module's file, as written.
+pub fn path_attribute(text: &str) -> Option<&str> {
+    let mut rest = text;
+    while let Some(i) = rest.find("#[") {
+        rest = &rest[i + 2..];
+        let Some(value) = rest.trim_start().strip_prefix("path") else {
+            continue;
+        };
+        let Some(value) = value.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let Some(value) = value.trim_start().strip_prefix('"') else {
+            continue;
+        };
+        return value.split_once('"').map(|(path, _)| path);
+    }
+    None
+}
+
+/// A path as SemanticMerge sent it, without the double quotes some
+/// launchers wrap paths in and the trailing separators some leave on, which
+/// make a file path name a directory that doesn't exist. A root, like `/`
+/// or `C:\`, keeps its separator.
+pub fn clean_path(raw: &str) -> &str {
+    let path = raw
+        .strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(raw);
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        &path[..(trimmed.len() + 1).min(path.len())]
+    } else {
+        trimmed
+    }
+}
+
+/// The names to give trees of files SemanticMerge sends, like the path in
+/// the repository of each temporary copy, so trees, logs and diffs show
+/// the file meant rather than `tmp1234.rs`.
+#[derive(Default, Clone, Debug)]
+pub struct DisplayNames(Vec<(String, String)>);
+
+impl DisplayNames {
+    /// Reads `source = name` lines, where `source` is a path as sent or
+    /// only its file name. Blank lines and those starting with `#` are
+    /// skipped.
+    pub fn parse(text: &str) -> anyhow::Result<DisplayNames> {
+        let mut names = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (source, name) = line.rsplit_once(" = ").ok_or_else(|| {
+                anyhow::anyhow!("line {}: expected source = name", i + 1)
+            })?;
+            names.push((
+                clean_path(source.trim()).to_string(),
+                name.trim().to_string(),
+            ));
+        }
+        Ok(DisplayNames(names))
+    }
+
+    pub fn read(path: &str) -> anyhow::Result<DisplayNames> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path, e))?;
+        DisplayNames::parse(&text).map_err(|e| anyhow::anyhow!("{}: {:#}", path, e))
+    }
+
+    /// The name for the tree of `input`, a path as sent: the one given for
+    /// the path, else for its file name, else the file name itself, since
+    /// the temp directories around it change from merge to merge.
+    pub fn name(&self, input: &str) -> String {
+        let path = clean_path(input);
+        let file_name = Path::new(path)
+            .file_name()
+            .map_or(input.to_string(), |n| n.to_string_lossy().into_owned());
+        let given = |key: &str| {
+            self.0
+                .iter()
+                .rev()
+                .find(|(source, _)| source == key)
+                .map(|(_, name)| name.clone())
+        };
+        given(path)
+            .or_else(|| given(&file_name))
+            .unwrap_or(file_name)
+    }
+}
+
+/// The names in the file `SEMANTIC_RUST_NAMES` names, else none.
+pub fn display_names() -> anyhow::Result<DisplayNames> {
+    match std::env::var("SEMANTIC_RUST_NAMES") {
+        Ok(path) => DisplayNames::read(&path),
+        Err(_) => Ok(DisplayNames::default()),
+    }
+}
+
+/// The Windows long-path form of `path`: `C:\a\b` becomes `\\?\C:\a\b` and
+/// `\\server\share\a` becomes `\\?\UNC\server\share\a`, which aren't
+/// limited to 260 characters. Windows takes these literally, so `/`s become
+/// `\`s and `.` and `..` are resolved first. Relative paths and those
+/// already in this form are returned as they are.
+pub fn verbatim_path(path: &str) -> String {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return path.to_string();
+    }
+    let path = path.replace('/', "\\");
+    let (prefix, rest) = if let Some(rest) = path.strip_prefix(r"\\") {
+        (r"\\?\UNC\", rest)
+    } else if path.get(1..3) == Some(r":\") {
+        (r"\\?\", path.as_str())
+    } else {
+        return path.to_string();
+    };
+    // `..` never climbs above the drive, or the server and share.
+    let root = if prefix.ends_with(r"UNC\") { 2 } else { 1 };
+    let mut segments: Vec<&str> = vec![];
+    for segment in rest.split('\\') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.len() > root {
+                    segments.pop();
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut out = format!("{}{}", prefix, segments.join("\\"));
+    if segments.len() == root {
+        out.push('\\');
+    }
+    out
+}
+
+/// The path to open or create for `raw`, a path SemanticMerge sent:
+/// cleaned up, with its directory canonicalized (network drives mapped to
+/// their shares included), and on Windows in long-path form.
+pub fn resolve_path(raw: &str) -> PathBuf {
+    let path = Path::new(clean_path(raw));
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => {
+            std::fs::canonicalize(dir)
+                .map_or_else(|_| path.to_path_buf(), |dir| dir.join(name))
+        }
+        _ => path.to_path_buf(),
+    };
+    if cfg!(windows) {
+        PathBuf::from(verbatim_path(&resolved.to_string_lossy()))
+    } else {
+        resolved
+    }
+}
+
+/// How the bytes of the paths in protocol requests are read, since the host
+/// tool writes them in whatever encoding its console uses.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathEncoding {
+    /// UTF-8 when the bytes are valid UTF-8, else the system's legacy
+    /// encoding: Windows-1252 on Windows, Latin-1 elsewhere.
+    #[default]
+    Auto,
+    Utf8,
+    Latin1,
+    Windows1252,
+}
+
+/// The characters Windows-1252 has at 0x80 to 0x9F, where Latin-1 has
+/// control characters; the five it leaves undefined map to those.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}',
+    '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}',
+    '\u{17d}', '\u{8f}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}',
+    '\u{2022}', '\u{2013}', '\u{2014}', '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}',
+    '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+impl PathEncoding {
+    pub fn parse(name: &str) -> anyhow::Result<PathEncoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(PathEncoding::Auto),
+            "utf-8" | "utf8" => Ok(PathEncoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(PathEncoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(PathEncoding::Windows1252),
+            other => anyhow::bail!("unknown path encoding {}", other),
+        }
+    }
+
+    /// Reads `bytes`, a line of a request without its line ending. A byte
+    /// order mark in front, which .NET writes at the start of a stream, is
+    /// dropped.
+    pub fn decode(self, bytes: &[u8]) -> anyhow::Result<String> {
+        let bytes = bytes.strip_prefix(BOM.as_bytes()).unwrap_or(bytes);
+        let single_byte = |table: Option<&[char; 32]>| {
+            bytes
+                .iter()
+                .map(|&b| match (b, table) {
+                    (0x80..=0x9f, Some(table)) => table[b as usize - 0x80],
+                    _ => b as char,
+                })
+                .collect()
+        };
+        match self {
+            PathEncoding::Utf8 => Ok(std::str::from_utf8(bytes)
+                .map_err(|e| anyhow::anyhow!("path is not valid UTF-8: {}", e))?
+                .to_string()),
+            PathEncoding::Latin1 => Ok(single_byte(None)),
+            PathEncoding::Windows1252 => Ok(single_byte(Some(&WINDOWS_1252))),
+            PathEncoding::Auto => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(text.to_string()),
+                Err(_) if cfg!(windows) => PathEncoding::Windows1252.decode(bytes),
+                Err(_) => PathEncoding::Latin1.decode(bytes),
+            },
+        }
+    }
+}
+
+/// The path encoding in the `SEMANTIC_RUST_PATH_ENCODING` environment
+/// variable, else [`PathEncoding::Auto`].
+pub fn path_encoding() -> anyhow::Result<PathEncoding> {
+    match std::env::var("SEMANTIC_RUST_PATH_ENCODING") {
+        Ok(name) => PathEncoding::parse(&name),
+        Err(_) => Ok(PathEncoding::Auto),
+    }
+}
+
+/// Why a file the shell was handed couldn't be read or written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFailure {
+    /// There is no such file, or it was removed, like a temp copy cleaned
+    /// up before it was read; or the directory to write into is missing.
+    Missing,
+    PermissionDenied,
+    /// Another process has it open without sharing it, as Windows editors
+    /// and virus scanners do.
+    Locked,
+    IsADirectory,
+    /// It isn't UTF-8 text.
+    NotText,
+    Other,
+}
+
+impl FileFailure {
+    pub fn of(error: &std::io::Error) -> FileFailure {
+        use std::io::ErrorKind;
+
+        // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION.
+        if cfg!(windows) && matches!(error.raw_os_error(), Some(32 | 33)) {
+            return FileFailure::Locked;
+        }
+        match error.kind() {
+            ErrorKind::NotFound => FileFailure::Missing,
+            ErrorKind::PermissionDenied => FileFailure::PermissionDenied,
+            ErrorKind::IsADirectory => FileFailure::IsADirectory,
+            ErrorKind::InvalidData => FileFailure::NotText,
+            _ => FileFailure::Other,
+        }
+    }
+
+    /// A short name for logs and reports, like `permission-denied`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileFailure::Missing => "missing",
+            FileFailure::PermissionDenied => "permission-denied",
+            FileFailure::Locked => "locked",
+            FileFailure::IsADirectory => "is-a-directory",
+            FileFailure::NotText => "not-text",
+            FileFailure::Other => "other",
+        }
+    }
+}
+
+/// A file that couldn't be read or written, and why.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    /// Whether it was being written rather than read.
+    pub writing: bool,
+    pub failure: FileFailure,
+    pub source: std::io::Error,
+}
+
+impl FileError {
+    /// `error`, from reading (or with `writing`, writing) `path`, as a
+    /// [`FileError`] when it is an I/O error.
+    pub fn wrap(path: &Path, writing: bool, error: anyhow::Error) -> anyhow::Error {
+        match error.downcast::<std::io::Error>() {
+            Ok(source) => FileError {
+                path: path.to_path_buf(),
+                writing,
+                failure: FileFailure::of(&source),
+                source,
+            }
+            .into(),
+            Err(error) => error,
+        }
+    }
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let what = match (self.failure, self.writing) {
+            (FileFailure::Missing, false) => "it doesn't exist, or was removed",
+            (FileFailure::Missing, true) => "its directory doesn't exist",
+            (FileFailure::PermissionDenied, _) => "permission denied",
+            (FileFailure::Locked, _) => "another process has it locked",
+            (FileFailure::IsADirectory, _) => "it is a directory",
+            (FileFailure::NotText, _) => "it isn't UTF-8 text",
+            (FileFailure::Other, _) => "I/O error",
+        };
+        write!(
+            f,
+            "failed to {} {}: {} ({})",
+            if self.writing { "write" } else { "read" },
+            self.path.display(),
+            what,
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -0,0 +1,24 @@
+use std::process::Command;
+
+/// Runs `git` with `args` and returns its stdout, failing with git's own
+/// message when it exits unsuccessfully.
+pub fn git(args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Contents of `path` at `rev` (`""` for the index), or `None` when it
+/// doesn't exist there, as for newly added files or an unborn `HEAD`.
+pub fn show(rev: &str, path: &str) -> Option<String> {
+    git(&["show", &format!("{}:{}", rev, path)]).ok()
+}
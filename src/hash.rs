@@ -0,0 +1,15 @@
+/// 64-bit FNV-1a. Unlike `DefaultHasher` its output is fixed, so it can be
+/// stored in artifacts and compared between runs and toolchains.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// [`fnv1a`] of `text` as 16 lowercase hex digits.
+pub fn content_hash(text: impl AsRef<[u8]>) -> String {
+    format!("{:016x}", fnv1a(text.as_ref()))
+}
@@ -0,0 +1,92 @@
+use crate::cli::Args;
+use crate::diff::{diff_options, file_diff, ParsedFile};
+use crate::git::{git, show};
+use crate::parse::{build_file, parse_tree, syntax_errors};
+
+/// Staged Rust files that the commit adds, copies, modifies or renames.
+fn staged_rust_files() -> anyhow::Result<Vec<String>> {
+    let output = git(&[
+        "diff",
+        "--cached",
+        "--name-only",
+        "-z",
+        "--diff-filter=ACMR",
+    ])?;
+    Ok(output
+        .split('\0')
+        .filter(|path| path.ends_with(".rs"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Checks the staged version of every Rust file in the commit, printing
+/// syntax errors as `path:line:column: message`. Returns whether the
+/// commit may go ahead.
+fn pre_commit(max_declarations: Option<usize>) -> anyhow::Result<bool> {
+    let mut ok = true;
+    let mut touched = 0;
+    for path in staged_rust_files()? {
+        let contents = show("", &path)
+            .ok_or_else(|| anyhow::anyhow!("{} is not in the index", path))?;
+        let tree = parse_tree(&path, &contents)?;
+        let errors = syntax_errors(&tree, &contents);
+        for error in &errors {
+            eprintln!(
+                "{}:{}:{}: syntax error: {}",
+                path,
+                error.start[0],
+                error.start[1] + 1,
+                error.message
+            );
+        }
+        if !errors.is_empty() {
+            ok = false;
+            continue;
+        }
+
+        if max_declarations.is_some() {
+            let file = build_file(&path, &contents, &tree, &diff_options())?;
+            let new = ParsedFile::from_parts(contents, file);
+            let old = match show("HEAD", &path) {
+                Some(old) => Some(ParsedFile::from_source(&path, old)?),
+                None => None,
+            };
+            let diff = file_diff(
+                Some(path.clone()),
+                Some(path.clone()),
+                old.as_ref(),
+                Some(&new),
+            );
+            touched += diff.changes.len();
+        }
+    }
+
+    if let Some(max) = max_declarations {
+        if touched > max {
+            eprintln!(
+                "warning: this commit touches {} declarations (more than {})",
+                touched, max
+            );
+        }
+    }
+    Ok(ok)
+}
+
+/// `hook pre-commit [--max-declarations N]`
+///
+/// Meant to be run from `.git/hooks/pre-commit`; exits 1 to block commits
+/// whose staged Rust files don't parse. Touching more than N declarations
+/// only warns.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(raw, &[], &["--max-declarations"])?;
+    match args.positional() {
+        [hook] if hook == "pre-commit" => {
+            let max = match args.value("--max-declarations") {
+                Some(max) => Some(max.parse()?),
+                None => None,
+            };
+            Ok(if pre_commit(max)? { 0 } else { 1 })
+        }
+        _ => anyhow::bail!("usage: hook pre-commit [--max-declarations N]"),
+    }
+}
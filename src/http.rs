@@ -0,0 +1,239 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::parse::BOM;
+use crate::server::{
+    RpcError, Server, CONNECTION_TIMEOUT, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+
+/// Requests larger than this are refused rather than buffered.
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Endpoints exposed over HTTP, each a `POST /<method>` taking the same JSON
+/// params as the JSON-RPC method of that name.
+const ENDPOINTS: &[&str] = &["parse", "outline", "diff", "merge"];
+
+/// Endpoints that also take the source itself as the body, named by a
+/// `name` query parameter, so it needn't be escaped into JSON.
+const SOURCE_ENDPOINTS: &[&str] = &["parse", "outline"];
+
+struct Request {
+    method: String,
+    path: String,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Whether the body is a source rather than JSON params: it is sent as
+    /// `text/*` or `application/octet-stream`.
+    fn is_source(&self) -> bool {
+        let content_type = self.content_type.to_ascii_lowercase();
+        content_type.starts_with("text/")
+            || content_type.starts_with("application/octet-stream")
+    }
+
+    /// The value of the query parameter `name`, percent-decoded.
+    fn query(&self, name: &str) -> Option<String> {
+        let (_, query) = self.path.split_once('?')?;
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| percent_decode(value))
+        })
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (escaped, bytes[i]) {
+            (Some(byte), _) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (None, b'+') => out.push(b' '),
+            (None, byte) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn read_request(stream: &mut BufReader<&TcpStream>) -> anyhow::Result<Request> {
+    let mut line = String::new();
+    stream.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    let mut content_type = String::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                content_type = value.trim().to_string();
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        anyhow::bail!("request body of {} bytes is too large", content_length);
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        content_type,
+        body,
+    })
+}
+
+/// A response body and its content type.
+enum Body {
+    Json(Value),
+    Text(String),
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    body: &Body,
+) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let (content_type, body) = match body {
+        Body::Json(value) => ("application/json", serde_json::to_vec(value)?),
+        Body::Text(text) => ("text/plain; version=0.0.4", text.clone().into_bytes()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn error_body(code: i64, message: &str) -> Value {
+    json!({ "error": { "code": code, "message": message } })
+}
+
+fn respond(server: &mut Server, request: &Request) -> (u16, Body) {
+    let path = request.path.split('?').next().unwrap_or("");
+    if path == "/metrics" && request.method == "GET" {
+        return (200, Body::Text(server.metrics.render()));
+    }
+    let (status, body) = call(server, request, path);
+    (status, Body::Json(body))
+}
+
+fn call(server: &mut Server, request: &Request, path: &str) -> (u16, Value) {
+    let endpoint = path.trim_start_matches('/');
+    if !ENDPOINTS.contains(&endpoint) {
+        let message = format!("no endpoint {}", path);
+        return (404, error_body(METHOD_NOT_FOUND, &message));
+    }
+    if request.method != "POST" {
+        let message = format!("{} expects POST", path);
+        return (405, error_body(METHOD_NOT_FOUND, &message));
+    }
+    let params: Value = if request.is_source() {
+        if !SOURCE_ENDPOINTS.contains(&endpoint) {
+            let message = format!("{} expects JSON", path);
+            return (400, error_body(INVALID_PARAMS, &message));
+        }
+        match std::str::from_utf8(&request.body) {
+            Ok(text) => json!({
+                "name": request.query("name").unwrap_or_default(),
+                "text": text.strip_prefix(BOM).unwrap_or(text),
+            }),
+            Err(e) => {
+                let message = format!("the source isn't UTF-8 text: {}", e);
+                return (400, error_body(INVALID_PARAMS, &message));
+            }
+        }
+    } else {
+        match serde_json::from_slice(&request.body) {
+            Ok(params) => params,
+            Err(e) => return (400, error_body(PARSE_ERROR, &e.to_string())),
+        }
+    };
+    match server.call(endpoint, &params) {
+        Ok(result) => (200, result),
+        Err(RpcError { code, message }) if code == INVALID_PARAMS => {
+            (400, error_body(code, &message))
+        }
+        Err(RpcError { code, message }) => (500, error_body(code, &message)),
+    }
+}
+
+/// Answers the one request on `stream`. The request is read before the
+/// server is locked, so a slow client only holds up itself.
+fn handle(server: &Mutex<Server>, mut stream: TcpStream) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let (status, body) = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => {
+            // A request that panicked leaves the caches as they were.
+            let mut server = server.lock().unwrap_or_else(|e| e.into_inner());
+            respond(&mut server, &request)
+        }
+        Err(e) => (
+            400,
+            Body::Json(error_body(PARSE_ERROR, &format!("{:#}", e))),
+        ),
+    };
+    write_response(&mut stream, status, &body)
+}
+
+/// Serves `POST /parse`, `/outline`, `/diff` and `/merge` on `addr`, and
+/// `GET /metrics` for Prometheus to scrape. Each connection gets a thread,
+/// and they take turns answering so every request shares the same parse
+/// cache; one that sends or takes nothing for [`CONNECTION_TIMEOUT`] is
+/// dropped.
+pub fn serve(addr: &str, server: Server) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    let server = Arc::new(Mutex::new(server));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            if let Err(e) = handle(&server, stream) {
+                eprintln!("error: {:#}", e);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Leaving files out of batch runs: the ones `.gitignore` files ignore and
+//! those `--exclude` and `--include` globs select against, so build output,
+//! vendored crates and generated code don't dominate them.
+
+use std::path::Path;
+
+use crate::cli::Args;
+
+/// Whether `text` matches one path segment of a glob: `*` matches any run
+/// of characters, `?` any one, and `[a-z]` or `[!a-z]` one in (or not in)
+/// the set.
+fn segment_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => {
+            (0..=text.len()).any(|i| segment_matches(rest, &text[i..]))
+        }
+        Some(('?', rest)) => !text.is_empty() && segment_matches(rest, &text[1..]),
+        Some(('[', rest)) => {
+            let Some(close) = rest.iter().skip(1).position(|&c| c == ']') else {
+                return text.first() == Some(&'[') && segment_matches(rest, &text[1..]);
+            };
+            let (class, rest) = (&rest[..close + 1], &rest[close + 2..]);
+            let (negated, class) = match class.split_first() {
+                Some(('!' | '^', class)) => (true, class),
+                _ => (false, class),
+            };
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let mut in_class = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    in_class |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    in_class |= class[i] == c;
+                    i += 1;
+                }
+            }
+            in_class != negated && segment_matches(rest, &text[1..])
+        }
+        Some((&p, rest)) => {
+            text.first() == Some(&p) && segment_matches(rest, &text[1..])
+        }
+    }
+}
+
+/// A glob over `/`-separated paths, where `**` as a whole segment matches
+/// any number of segments.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    segments: Vec<Vec<char>>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        Glob {
+            segments: pattern
+                .trim_matches('/')
+                .split('/')
+                .map(|segment| segment.chars().collect())
+                .collect(),
+        }
+    }
+
+    /// Whether the glob is a single segment, which matches a name anywhere
+    /// rather than a path.
+    fn is_name(&self) -> bool {
+        self.segments.len() == 1 && self.segments[0] != ['*', '*']
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        fn matches(pattern: &[Vec<char>], path: &[Vec<char>]) -> bool {
+            match pattern.split_first() {
+                None => path.is_empty(),
+                Some((segment, rest)) if segment == &['*', '*'] => {
+                    (0..=path.len()).any(|i| matches(rest, &path[i..]))
+                }
+                Some((segment, rest)) => {
+                    path.first()
+                        .is_some_and(|name| segment_matches(segment, name))
+                        && matches(rest, &path[1..])
+                }
+            }
+        }
+
+        let path: Vec<Vec<char>> = path
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .map(|s| s.chars().collect())
+            .collect();
+        matches(&self.segments, &path)
+    }
+
+    /// Whether the glob selects `path`: for a name like `target` or
+    /// `*.generated.rs`, any of its segments matching; else the whole
+    /// path, or (for a directory's contents) any leading part of it.
+    fn selects(&self, path: &str) -> bool {
+        if self.is_name() {
+            return path.split('/').any(|name| {
+                segment_matches(&self.segments[0], &name.chars().collect::<Vec<_>>())
+            });
+        }
+        let segments: Vec<&str> = path.split('/').collect();
+        (1..=segments.len()).any(|n| self.matches(&segments[..n].join("/")))
+    }
+}
+
+/// One line of a `.gitignore`.
+#[derive(Clone, Debug)]
+struct Rule {
+    /// The directory of the `.gitignore`, relative to the walk's root, with
+    /// a trailing `/` unless it is the root itself.
+    base: String,
+    glob: Glob,
+    negated: bool,
+    /// Only directories match, as for `target/`.
+    dir_only: bool,
+    /// Matched against the path from `base` rather than a name anywhere
+    /// below it, as for `/build` or `src/gen`.
+    anchored: bool,
+}
+
+/// The rules of every `.gitignore` between the walk's root and the
+/// directory being read, in the order git applies them.
+#[derive(Default, Clone, Debug)]
+pub struct Gitignore {
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    /// Adds the rules in `contents`, a `.gitignore` in the directory
+    /// `base`, relative to the walk's root.
+    pub fn add(&mut self, base: &str, contents: &str) {
+        let base = if base.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", base.trim_end_matches('/'))
+        };
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            self.rules.push(Rule {
+                base: base.clone(),
+                glob: Glob::new(line),
+                negated,
+                dir_only,
+                anchored: line.contains('/'),
+            });
+        }
+    }
+
+    /// Adds the `.gitignore` in `dir`, if there is one; `base` is `dir`
+    /// relative to the walk's root.
+    pub fn read(&mut self, dir: &Path, base: &str) -> anyhow::Result<()> {
+        match std::fs::read_to_string(dir.join(".gitignore")) {
+            Ok(contents) => self.add(base, &contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    /// Whether `path`, relative to the walk's root, is ignored. The last
+    /// rule that matches decides, so `!` lines can take files back.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Some(relative) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let matched = if rule.anchored {
+                rule.glob.matches(relative)
+            } else {
+                rule.glob.matches(name)
+            };
+            if matched {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// What walks do with the symbolic links they find, and on Windows with
+/// junctions, which Rust reports as links too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Read the files they point to and walk the directories, except for a
+    /// link back to a directory the walk is already in, which would never
+    /// end; that one is skipped with a warning.
+    #[default]
+    Follow,
+    Skip,
+    /// Fail at the first one.
+    Error,
+}
+
+impl Symlinks {
+    pub fn parse(name: &str) -> anyhow::Result<Symlinks> {
+        match name {
+            "follow" => Ok(Symlinks::Follow),
+            "skip" => Ok(Symlinks::Skip),
+            "error" => Ok(Symlinks::Error),
+            other => anyhow::bail!("unknown symlink policy {}", other),
+        }
+    }
+}
+
+/// Which files a batch run reads.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub include: Vec<Glob>,
+    pub exclude: Vec<Glob>,
+    /// Whether `.gitignore` files are read while walking directories.
+    pub gitignore: bool,
+    pub symlinks: Symlinks,
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter {
+            include: vec![],
+            exclude: vec![],
+            gitignore: true,
+            symlinks: Symlinks::default(),
+        }
+    }
+}
+
+impl Filter {
+    /// Reads every file, ignoring `.gitignore`s.
+    pub fn everything() -> Filter {
+        Filter {
+            gitignore: false,
+            ..Filter::default()
+        }
+    }
+
+    /// The filter `--include`, `--exclude` (both repeatable),
+    /// `--no-ignore` and `--symlinks follow|skip|error` give.
+    pub fn from_args(args: &Args) -> anyhow::Result<Filter> {
+        Ok(Filter {
+            include: args.values("--include").map(Glob::new).collect(),
+            exclude: args.values("--exclude").map(Glob::new).collect(),
+            gitignore: !args.flag("--no-ignore"),
+            symlinks: args
+                .value("--symlinks")
+                .map_or(Ok(Symlinks::default()), Symlinks::parse)?,
+        })
+    }
+
+    /// Whether a directory at `path` could hold files to read, so walks
+    /// can skip those excluded outright.
+    pub fn enters(&self, path: &str) -> bool {
+        !self.exclude.iter().any(|glob| glob.selects(path))
+    }
+
+    /// Whether the file at `path` is read: it matches no `--exclude` and,
+    /// when there are any, some `--include`.
+    pub fn reads(&self, path: &str) -> bool {
+        self.enters(path)
+            && (self.include.is_empty()
+                || self.include.iter().any(|glob| glob.selects(path)))
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::cli::Args;
+use crate::diff::{diff_options, ParsedFile};
+use crate::files::collect_filtered;
+use crate::hash::content_hash;
+use crate::ignore::{Filter, Symlinks};
+use crate::parse::{build_file, parse_tree, read_file};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+    path TEXT PRIMARY KEY,
+    hash TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS declarations (
+    id INTEGER PRIMARY KEY,
+    file TEXT NOT NULL REFERENCES files(path) ON DELETE CASCADE,
+    qualified_name TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    start_column INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    end_column INTEGER NOT NULL,
+    start_byte INTEGER NOT NULL,
+    end_byte INTEGER NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS declarations_qualified_name ON declarations(qualified_name);
+CREATE INDEX IF NOT EXISTS declarations_file ON declarations(file);
+CREATE TABLE IF NOT EXISTS usages (
+    declaration INTEGER NOT NULL REFERENCES declarations(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    column INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS usages_name ON usages(name);
+CREATE INDEX IF NOT EXISTS usages_declaration ON usages(declaration);
+";
+
+/// The identifier kinds recorded as usages: names of values, types,
+/// fields and macros. Which item a name means isn't resolved, so a usage
+/// of `new` is one of every `new`.
+const USAGE_KINDS: &[&str] = &[
+    "identifier",
+    "type_identifier",
+    "field_identifier",
+    "shorthand_field_identifier",
+];
+
+/// Talks to the database through the `sqlite3` command-line shell, which
+/// keeps this crate free of a native SQLite build.
+pub struct Database<'a> {
+    pub sqlite: &'a str,
+    pub path: &'a Path,
+}
+
+impl Database<'_> {
+    fn command(&self) -> Command {
+        let mut command = Command::new(self.sqlite);
+        command.arg("-batch").arg(self.path);
+        command
+    }
+
+    /// Runs `sql` and returns its rows of columns. They come back as CSV,
+    /// which quotes values like paths with commas or line breaks in them.
+    pub fn query(&self, sql: &str) -> anyhow::Result<Vec<Vec<String>>> {
+        let output = self
+            .command()
+            .args(["-csv", "-noheader", sql])
+            .output()
+            .map_err(|e| anyhow::anyhow!("failed to run {}: {}", self.sqlite, e))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{}: {}",
+                self.sqlite,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(csv_rows(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Feeds a whole script to the shell on stdin.
+    pub fn execute(&self, script: &str) -> anyhow::Result<()> {
+        let mut child = self
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("failed to run {}: {}", self.sqlite, e))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(script.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{}: {}",
+                self.sqlite,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The rows of `text`, CSV as the `sqlite3` shell writes it: a value with a
+/// comma, quote or line break in it is quoted, with quotes inside doubled,
+/// and rows end in `\r\n`.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut value)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut value));
+                rows.push(std::mem::take(&mut row));
+            }
+            (_, c) => value.push(c),
+        }
+    }
+    if !value.is_empty() || !row.is_empty() {
+        row.push(value);
+        rows.push(row);
+    }
+    rows
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// The identifiers in `tree` used inside each of `parsed`'s declarations,
+/// by index of the innermost one they are in, with their 1-based line and
+/// 0-based byte column. Those naming the declaration they are in, and
+/// those outside every declaration, are left out.
+fn usages(
+    tree: &tree_sitter::Tree,
+    parsed: &ParsedFile,
+) -> Vec<Vec<(String, [usize; 2])>> {
+    fn visit(
+        node: tree_sitter::Node,
+        parsed: &ParsedFile,
+        out: &mut Vec<Vec<(String, [usize; 2])>>,
+    ) {
+        if USAGE_KINDS.contains(&node.kind()) {
+            // The name a declaration introduces, as opposed to the type
+            // named by `Circle { r: 1.0 }`, which is a `name` field too.
+            let is_definition = node.parent().is_some_and(|parent| {
+                let kind = parent.kind();
+                (kind.ends_with("_item")
+                    || kind.ends_with("_declaration")
+                    || kind == "enum_variant"
+                    || kind == "macro_definition")
+                    && parent
+                        .child_by_field_name("name")
+                        .is_some_and(|name| name.id() == node.id())
+            });
+            let start = node.start_byte();
+            let innermost = parsed
+                .declarations
+                .iter()
+                .enumerate()
+                .filter(|(_, decl)| decl.range.contains(&start))
+                .min_by_key(|(_, decl)| decl.range.len());
+            if let (false, Some((i, _)), Some(name)) = (
+                is_definition,
+                innermost,
+                parsed.contents.get(node.byte_range()),
+            ) {
+                let position = node.start_position();
+                out[i].push((name.to_string(), [position.row + 1, position.column]));
+            }
+            return;
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            visit(child, parsed, out);
+        }
+    }
+
+    let mut out = vec![vec![]; parsed.declarations.len()];
+    visit(tree.root_node(), parsed, &mut out);
+    out
+}
+
+fn insert_declarations(
+    script: &mut String,
+    file: &str,
+    parsed: &ParsedFile,
+    usages: &[Vec<(String, [usize; 2])>],
+) {
+    for (decl, usages) in parsed.declarations.iter().zip(usages) {
+        let text = parsed.contents.get(decl.range.clone()).unwrap_or("");
+        script.push_str(&format!(
+            "INSERT INTO declarations (file, qualified_name, name, kind, start_line, start_column, \
+             end_line, end_column, start_byte, end_byte, hash) \
+             VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {});\n",
+            quote(file),
+            quote(&decl.qualified_name),
+            quote(&decl.name),
+            quote(&decl.kind),
+            decl.start[0],
+            decl.start[1],
+            decl.end[0],
+            decl.end[1],
+            decl.range.start,
+            decl.range.end,
+            quote(&content_hash(text)),
+        ));
+        for (name, [line, column]) in usages {
+            script.push_str(&format!(
+                "INSERT INTO usages (declaration, name, line, column) \
+                 VALUES ((SELECT max(id) FROM declarations), {}, {}, {});\n",
+                quote(name),
+                line,
+                column,
+            ));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct IndexSummary {
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub declarations: usize,
+}
+
+/// Brings the index at `db` up to date with the Rust files under `root`,
+/// re-parsing only files whose content hash differs from the stored one.
+/// Files that can't be read, aren't UTF-8 or fail to parse are skipped with
+/// a warning, and what the index had for them is kept.
+pub fn update_index(
+    db: &Database,
+    root: &Path,
+    symlinks: Symlinks,
+) -> anyhow::Result<IndexSummary> {
+    // Indexes made before usages were recorded have none for the files
+    // that haven't changed since, so every file is read again.
+    let has_usages = !db
+        .query("SELECT name FROM sqlite_master WHERE name = 'usages'")?
+        .is_empty();
+    let has_files = !db
+        .query("SELECT name FROM sqlite_master WHERE name = 'files'")?
+        .is_empty();
+    let outdated = has_files && !has_usages;
+    db.execute(SCHEMA)?;
+    let stored: HashMap<String, String> = db
+        .query("SELECT path, hash FROM files")?
+        .into_iter()
+        .filter_map(|row| match row.as_slice() {
+            [path, hash] => Some((path.clone(), hash.clone())),
+            _ => None,
+        })
+        .collect();
+
+    let mut summary = IndexSummary::default();
+    let mut script = String::from("PRAGMA foreign_keys = ON;\nBEGIN;\n");
+    let filter = Filter {
+        symlinks,
+        ..Filter::everything()
+    };
+    let paths = collect_filtered(root, &filter)?;
+    for path in &paths {
+        let full_path = root.join(path);
+        let contents = match read_file(&full_path.to_string_lossy()) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("warning: skipping {}: {:#}", path, e);
+                continue;
+            }
+        };
+        let hash = content_hash(&contents);
+        if !outdated && stored.get(path) == Some(&hash) {
+            summary.unchanged += 1;
+            continue;
+        }
+        let parsed = parse_tree(path, &contents).and_then(|tree| {
+            let file = build_file(path, &contents, &tree, &diff_options())?;
+            Ok((tree, file))
+        });
+        let (tree, file) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("warning: skipping {}: {:#}", path, e);
+                continue;
+            }
+        };
+        let parsed = ParsedFile::from_parts(contents, file);
+        script.push_str(&format!(
+            "DELETE FROM files WHERE path = {};\n",
+            quote(path)
+        ));
+        script.push_str(&format!(
+            "INSERT INTO files (path, hash) VALUES ({}, {});\n",
+            quote(path),
+            quote(&hash)
+        ));
+        insert_declarations(&mut script, path, &parsed, &usages(&tree, &parsed));
+        summary.updated += 1;
+        summary.declarations += parsed.declarations.len();
+    }
+    let paths: HashSet<&String> = paths.iter().collect();
+    for path in stored.keys().filter(|p| !paths.contains(p)) {
+        script.push_str(&format!(
+            "DELETE FROM files WHERE path = {};\n",
+            quote(path)
+        ));
+        summary.removed += 1;
+    }
+    script.push_str("COMMIT;\n");
+    db.execute(&script)?;
+    Ok(summary)
+}
+
+/// The declarations that use an identifier named `name`, as rows of their
+/// qualified name, kind, file and the line of the first use, ordered by
+/// file and line.
+pub fn referencing(db: &Database, name: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    db.query(&format!(
+        "SELECT d.qualified_name, d.kind, d.file, min(u.line) AS line \
+         FROM usages u JOIN declarations d ON d.id = u.declaration \
+         WHERE u.name = {} GROUP BY d.id ORDER BY d.file, line;",
+        quote(name)
+    ))
+}
+
+/// `index [--db <file>] [--sqlite <program>] [--symlinks follow|skip|error]
+/// <dir>`, or `index [--db <file>] [--sqlite <program>] --references
+/// <name>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &[],
+        &["--db", "--sqlite", "--symlinks", "--references"],
+    )?;
+    let db = Database {
+        sqlite: args.value("--sqlite").unwrap_or("sqlite3"),
+        path: Path::new(args.value("--db").unwrap_or("symbols.sqlite")),
+    };
+    let root = match (args.positional(), args.value("--references")) {
+        ([], Some(name)) => {
+            for row in referencing(&db, name)? {
+                if let [qualified_name, kind, file, line] = row.as_slice() {
+                    println!("{}:{}: {} {}", file, line, kind, qualified_name);
+                }
+            }
+            return Ok(());
+        }
+        ([root], None) => root,
+        _ => anyhow::bail!(
+            "usage: index [--db <file>] [--sqlite <program>] [--symlinks follow|skip|error] <dir>\n       index [--db <file>] [--sqlite <program>] --references <name>"
+        ),
+    };
+    let symlinks = args
+        .value("--symlinks")
+        .map_or(Ok(Symlinks::default()), Symlinks::parse)?;
+    let summary = update_index(&db, Path::new(root), symlinks)?;
+    println!(
+        "indexed {} files ({} unchanged, {} removed), {} declarations",
+        summary.updated, summary.unchanged, summary.removed, summary.declarations
+    );
+    Ok(())
+}
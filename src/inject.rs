@@ -0,0 +1,158 @@
+//! Parsing the contents of macros like `html!` and `sqlx::query!` with the
+//! grammar of the language embedded in them, so the markup or SQL gets a
+//! structure of its own instead of being one token tree.
+
+/// An embedded language there is a grammar for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    #[cfg(feature = "inject-html")]
+    Html,
+    #[cfg(feature = "inject-sql")]
+    Sql,
+}
+
+impl Language {
+    pub fn parse(name: &str) -> anyhow::Result<Language> {
+        match name {
+            #[cfg(feature = "inject-html")]
+            "html" => Ok(Language::Html),
+            #[cfg(feature = "inject-sql")]
+            "sql" => Ok(Language::Sql),
+            other => {
+                anyhow::bail!("unknown or not built-in embedded language {}", other)
+            }
+        }
+    }
+
+    #[cfg(any(feature = "inject-html", feature = "inject-sql"))]
+    fn grammar(self) -> tree_sitter::Language {
+        match self {
+            #[cfg(feature = "inject-html")]
+            Language::Html => tree_sitter_html::LANGUAGE.into(),
+            #[cfg(feature = "inject-sql")]
+            Language::Sql => tree_sitter_sequel::LANGUAGE.into(),
+        }
+    }
+}
+
+/// Which macros' contents are parsed as which language.
+#[derive(Default, Clone, Debug)]
+pub struct Injections(Vec<(String, Language)>);
+
+impl Injections {
+    /// Reads comma-separated `macro=language` pairs, like
+    /// `html=html,sqlx::query=sql`.
+    pub fn parse(spec: &str) -> anyhow::Result<Injections> {
+        let mut injections = vec![];
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((path, language)) = pair.split_once('=') else {
+                anyhow::bail!("expected macro=language, got {}", pair);
+            };
+            injections
+                .push((path.trim().to_string(), Language::parse(language.trim())?));
+        }
+        Ok(Injections(injections))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The language for a macro invoked as `path`: the one given for the
+    /// path as written, else for its last segment, so `query` also covers
+    /// `sqlx::query`.
+    pub fn language(&self, path: &str) -> Option<Language> {
+        let last = path.rsplit("::").next().unwrap_or(path);
+        let find = |wanted: &str| {
+            self.0
+                .iter()
+                .find(|(path, _)| path == wanted)
+                .map(|&(_, language)| language)
+        };
+        find(path).or_else(|| find(last))
+    }
+}
+
+/// The injections given on the command line, else in the
+/// `SEMANTIC_RUST_INJECT` environment variable (which also reaches the shell
+/// protocol), else none.
+pub fn injections(flag: Option<&str>) -> anyhow::Result<Injections> {
+    match flag {
+        Some(spec) => Injections::parse(spec),
+        None => match std::env::var("SEMANTIC_RUST_INJECT") {
+            Ok(spec) => Injections::parse(&spec),
+            Err(_) => Ok(Injections::default()),
+        },
+    }
+}
+
+/// The part of a macro's token tree `argument` in the embedded language: the
+/// contents of a string literal passed first, as in `query!("SELECT 1")`,
+/// else everything between the delimiters, as in `html! { <p></p> }`.
+#[cfg(any(feature = "inject-html", feature = "inject-sql"))]
+fn embedded_range(argument: &tree_sitter::Node) -> Option<tree_sitter::Range> {
+    let inner =
+        |start: tree_sitter::Point, end: tree_sitter::Point| tree_sitter::Range {
+            start_byte: argument.start_byte() + 1,
+            end_byte: argument.end_byte() - 1,
+            start_point: tree_sitter::Point {
+                column: start.column + 1,
+                ..start
+            },
+            end_point: tree_sitter::Point {
+                column: end.column - 1,
+                ..end
+            },
+        };
+    let first = argument.named_child(0)?;
+    if !matches!(first.kind(), "string_literal" | "raw_string_literal") {
+        return Some(inner(argument.start_position(), argument.end_position()));
+    }
+    let mut cursor = first.walk();
+    let contents: Vec<_> = first
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "string_content")
+        .collect();
+    let (start, end) = (contents.first()?, contents.last()?);
+    Some(tree_sitter::Range {
+        start_byte: start.start_byte(),
+        end_byte: end.end_byte(),
+        start_point: start.start_position(),
+        end_point: end.end_position(),
+    })
+}
+
+/// Parses the arguments of `invocation`, a `macro_invocation` in `src`, in
+/// the language `injections` gives for the macro. The tree's positions are
+/// those in `src`.
+#[cfg(any(feature = "inject-html", feature = "inject-sql"))]
+pub fn parse(
+    invocation: &tree_sitter::Node,
+    src: &str,
+    injections: &Injections,
+) -> Option<tree_sitter::Tree> {
+    let path = invocation.child_by_field_name("macro")?;
+    let language = injections.language(src.get(path.byte_range())?)?;
+    let mut cursor = invocation.walk();
+    let argument = invocation
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "token_tree")?;
+    let range = embedded_range(&argument)?;
+    if range.start_byte >= range.end_byte {
+        return None;
+    }
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&language.grammar()).ok()?;
+    parser.set_included_ranges(&[range]).ok()?;
+    parser.parse(src, None)
+}
+
+/// Without an embedded grammar built in there is nothing to inject.
+#[cfg(not(any(feature = "inject-html", feature = "inject-sql")))]
+pub fn parse(
+    _invocation: &tree_sitter::Node,
+    _src: &str,
+    _injections: &Injections,
+) -> Option<tree_sitter::Tree> {
+    None
+}
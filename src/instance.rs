@@ -0,0 +1,162 @@
+//! Keeping copies of the tool that run at the same time, like the ones
+//! SemanticMerge launches for two merges at once, out of each other's
+//! files.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `path` with every `{pid}` in it replaced by this process's id, so one
+/// setting inherited by several processes gives each a file of its own.
+pub fn per_process(path: &str) -> String {
+    path.replace("{pid}", &std::process::id().to_string())
+}
+
+/// `path` with `-<pid>` before its extension, like `session-4242.jsonl`.
+pub fn with_pid(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!(
+            "{}-{}.{}",
+            stem,
+            std::process::id(),
+            extension.to_string_lossy()
+        ),
+        None => format!("{}-{}", stem, std::process::id()),
+    };
+    path.with_file_name(name)
+}
+
+/// Creates or truncates `path` and locks it for as long as the file stays
+/// open. When another process holds the lock, writes [`with_pid`] of it
+/// instead of truncating what that process is writing. Returns the file and
+/// the path it has.
+pub fn create_locked(path: &Path) -> anyhow::Result<(File, PathBuf)> {
+    let open = |path: &Path| {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("failed to create {}: {}", path.display(), e))
+    };
+    let (file, path) = {
+        let file = open(path)?;
+        if lock(&file, false)? {
+            (file, path.to_path_buf())
+        } else {
+            let path = with_pid(path);
+            let file = open(&path)?;
+            lock(&file, true)?;
+            (file, path)
+        }
+    };
+    file.set_len(0)?;
+    Ok((file, path))
+}
+
+/// Appends `bytes` to `path` while holding an exclusive lock on it, so what
+/// several processes append at once never interleaves.
+pub fn append_locked(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    lock(&file, true)?;
+    file.write_all(bytes)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Takes an exclusive lock on `file`, released when it is closed, waiting
+/// for it when `wait` is set; `false` when another process holds it and
+/// `wait` isn't. `File::lock` does this from Rust 1.89 on, newer than the
+/// toolchains the 0.19 grammar is there for.
+#[cfg(unix)]
+fn lock(file: &File, wait: bool) -> std::io::Result<bool> {
+    use std::os::raw::c_int;
+    use std::os::unix::io::AsRawFd;
+
+    const LOCK_EX: c_int = 2;
+    const LOCK_NB: c_int = 4;
+
+    extern "C" {
+        fn flock(fd: c_int, operation: c_int) -> c_int;
+    }
+
+    let operation = if wait { LOCK_EX } else { LOCK_EX | LOCK_NB };
+    loop {
+        if unsafe { flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(true);
+        }
+        let e = std::io::Error::last_os_error();
+        match e.kind() {
+            std::io::ErrorKind::Interrupted => continue,
+            std::io::ErrorKind::WouldBlock => return Ok(false),
+            _ => return Err(e),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn lock(file: &File, wait: bool) -> std::io::Result<bool> {
+    use std::ffi::c_void;
+    use std::os::windows::io::AsRawHandle;
+
+    const LOCKFILE_FAIL_IMMEDIATELY: u32 = 1;
+    const LOCKFILE_EXCLUSIVE_LOCK: u32 = 2;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    #[repr(C)]
+    struct Overlapped {
+        internal: usize,
+        internal_high: usize,
+        offset: u32,
+        offset_high: u32,
+        event: *mut c_void,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LockFileEx(
+            file: *mut c_void,
+            flags: u32,
+            reserved: u32,
+            bytes_low: u32,
+            bytes_high: u32,
+            overlapped: *mut Overlapped,
+        ) -> i32;
+    }
+
+    let mut flags = LOCKFILE_EXCLUSIVE_LOCK;
+    if !wait {
+        flags |= LOCKFILE_FAIL_IMMEDIATELY;
+    }
+    let mut overlapped = Overlapped {
+        internal: 0,
+        internal_high: 0,
+        offset: 0,
+        offset_high: 0,
+        event: std::ptr::null_mut(),
+    };
+    let locked = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as *mut c_void,
+            flags,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked != 0 {
+        return Ok(true);
+    }
+    let e = std::io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(ERROR_LOCK_VIOLATION) => Ok(false),
+        _ => Err(e),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock(_file: &File, _wait: bool) -> std::io::Result<bool> {
+    Ok(true)
+}
@@ -0,0 +1,167 @@
+//! `report large`: the functions and impls too long to review comfortably,
+//! grouped by the module they are in, for picking what to split up next.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::{Declaration, ParsedFile};
+use crate::files::expand;
+use crate::ignore::Filter;
+use crate::parse::read_file;
+
+/// The kinds of declaration measured.
+const KINDS: &[&str] = &["function_item", "impl_item"];
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Large {
+    pub name: String,
+    pub kind: String,
+    pub line: i64,
+    pub lines: i64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Module {
+    /// The file, followed by the inline modules around the declarations,
+    /// like `src/parse.rs::tests`.
+    pub module: String,
+    /// Longest first.
+    pub declarations: Vec<Large>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeReport {
+    pub threshold: i64,
+    /// By name.
+    pub modules: Vec<Module>,
+}
+
+/// The module `decl` is in: `file`, and the `mod`s around it.
+fn module_of(file: &str, decls: &[Declaration], decl: &Declaration) -> String {
+    let mut modules = vec![];
+    let mut parent = decl.parent;
+    while let Some(p) = parent {
+        if decls[p].kind == "mod_item" {
+            modules.push(decls[p].name.as_str());
+        }
+        parent = decls[p].parent;
+    }
+    modules.push(file);
+    modules.reverse();
+    modules.join("::")
+}
+
+/// The functions and impls of `files` (name and contents) longer than
+/// `threshold` lines.
+pub fn large_declarations(
+    files: impl IntoIterator<Item = (String, String)>,
+    threshold: i64,
+) -> anyhow::Result<LargeReport> {
+    let mut modules: BTreeMap<String, Vec<Large>> = BTreeMap::new();
+    for (name, contents) in files {
+        let parsed = ParsedFile::from_source(&name, contents)?;
+        for decl in &parsed.declarations {
+            let lines = decl.end[0] - decl.start[0] + 1;
+            if !KINDS.contains(&decl.kind.as_str()) || lines <= threshold {
+                continue;
+            }
+            modules
+                .entry(module_of(&name, &parsed.declarations, decl))
+                .or_default()
+                .push(Large {
+                    name: decl.qualified_name.clone(),
+                    kind: decl.kind.clone(),
+                    line: decl.start[0],
+                    lines,
+                });
+        }
+    }
+    Ok(LargeReport {
+        threshold,
+        modules: modules
+            .into_iter()
+            .map(|(module, mut declarations)| {
+                declarations
+                    .sort_by(|a, b| b.lines.cmp(&a.lines).then(a.line.cmp(&b.line)));
+                Module {
+                    module,
+                    declarations,
+                }
+            })
+            .collect(),
+    })
+}
+
+fn write_markdown(out: &mut dyn Write, report: &LargeReport) -> std::io::Result<()> {
+    writeln!(out, "# Declarations over {} lines", report.threshold)?;
+    if report.modules.is_empty() {
+        writeln!(out, "\nNone.")?;
+    }
+    for module in &report.modules {
+        writeln!(out, "\n## `{}`\n", module.module)?;
+        writeln!(out, "| Lines | Kind | Declaration | Line |")?;
+        writeln!(out, "| ----: | ---- | ----------- | ---: |")?;
+        for decl in &module.declarations {
+            writeln!(
+                out,
+                "| {} | {} | `{}` | {} |",
+                decl.lines,
+                decl.kind.trim_end_matches("_item"),
+                decl.name.replace('|', "\\|"),
+                decl.line
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `report large [--threshold <lines>] [--format markdown|json]
+/// [--exclude <glob>] [--include <glob>] [--no-ignore]
+/// [--symlinks follow|skip|error] <paths...>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &[
+            "--threshold",
+            "--format",
+            "--exclude",
+            "--include",
+            "--symlinks",
+        ],
+    )?;
+    let paths = match args.positional() {
+        [report, paths @ ..] if report == "large" && !paths.is_empty() => paths,
+        _ => anyhow::bail!(
+            "usage: report large [--threshold <lines>] [--format markdown|json] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        ),
+    };
+    let threshold = match args.value("--threshold") {
+        Some(lines) => lines.parse()?,
+        None => 50,
+    };
+
+    let mut files = vec![];
+    for (path, name) in expand(paths, &Filter::from_args(&args)?)? {
+        files.push((name, read_file(&path.to_string_lossy())?));
+    }
+    let report = large_declarations(files, threshold)?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("markdown") {
+        "markdown" => write_markdown(&mut out, &report)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown report format {}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+#[cfg(all(feature = "grammar-current", feature = "grammar-0-19"))]
+compile_error!("the grammar-current and grammar-0-19 features are exclusive");
+#[cfg(not(any(feature = "grammar-current", feature = "grammar-0-19")))]
+compile_error!("enable one of the grammar-current or grammar-0-19 features");
+
+// Both grammars' runtimes go by `tree_sitter`, so the rest of the crate
+// doesn't care which one it was built with.
+#[cfg(all(feature = "grammar-0-19", not(feature = "grammar-current")))]
+extern crate tree_sitter_0_19 as tree_sitter;
+
+pub mod api;
+pub mod apidiff;
+pub mod baseline;
+pub mod blame;
+pub mod check;
+pub mod churn;
+pub mod cli;
+pub mod compare;
+pub mod complexity;
+pub mod daemon;
+pub mod diff;
+pub mod doctest;
+pub mod doctor;
+pub mod dupes;
+pub mod extract;
+pub mod files;
+pub mod git;
+pub mod hash;
+pub mod hook;
+pub mod http;
+pub mod ignore;
+pub mod index;
+pub mod inject;
+pub mod instance;
+pub mod large;
+pub mod logging;
+pub mod merge;
+pub mod mergetool;
+pub mod metadata;
+pub mod metrics;
+pub mod model;
+pub mod msgpack;
+pub mod ndjson;
+pub mod outline;
+pub mod owners;
+pub mod parse;
+pub mod query;
+pub mod replay;
+pub mod report;
+pub mod schema;
+pub mod server;
+pub mod shell;
+pub mod signals;
+pub mod stats;
+pub mod tags;
+pub mod templates;
+pub mod units;
+pub mod watch;
+pub mod workspace;
@@ -0,0 +1,35 @@
+//! Logging for the commands whose output can't carry diagnostics, like the
+//! shell protocol, where SemanticMerge owns stdin and stdout.
+
+use std::sync::Mutex;
+
+use tracing_subscriber::EnvFilter;
+
+use crate::instance::per_process;
+
+/// Appends log events to the file named by `SEMANTIC_RUST_LOG` (with `{pid}`
+/// in it replaced by the process id, for a log per process), at the
+/// levels `SEMANTIC_RUST_LOG_FILTER` lets through (directives as in
+/// `RUST_LOG`, `info` when unset). Without `SEMANTIC_RUST_LOG` nothing is
+/// logged.
+pub fn init() -> anyhow::Result<()> {
+    let path = match std::env::var("SEMANTIC_RUST_LOG") {
+        Ok(path) if !path.is_empty() => per_process(&path),
+        _ => return Ok(()),
+    };
+    let filter = match std::env::var("SEMANTIC_RUST_LOG_FILTER") {
+        Ok(directives) => EnvFilter::try_new(directives)?,
+        Err(_) => EnvFilter::new("info"),
+    };
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| anyhow::anyhow!("failed to open log {}: {}", path, e))?;
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .try_init()
+        .map_err(anyhow::Error::msg)
+}
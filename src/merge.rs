@@ -0,0 +1,500 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::{declarations, Declaration};
+use crate::parse::{has_bom, parse_source, read_file, LineEndings, BOM};
+
+/// Exit code for a merge that resolved cleanly.
+pub const EXIT_CLEAN: i32 = 0;
+/// Exit code for a merge that left conflict markers in the output.
+pub const EXIT_CONFLICTS: i32 = 1;
+/// Exit code when an input couldn't be read or contains syntax errors.
+pub const EXIT_PARSE_FAILURE: i32 = 2;
+
+type Key = (String, String, usize);
+
+/// A declaration together with the gap in front of it, so the chunks of a
+/// body concatenate back to the original text.
+struct Chunk<'a> {
+    text: &'a str,
+    body: Option<Body<'a>>,
+}
+
+/// The text of a file or container split into the part before its first
+/// nested declaration, one chunk per declaration, and the part after.
+struct Body<'a> {
+    header: &'a str,
+    keys: Vec<Key>,
+    chunks: HashMap<Key, Chunk<'a>>,
+    footer: &'a str,
+}
+
+/// The body of `parent` in `source`, spanning `range`, with its chunks
+/// keyed by kind, name and an ordinal telling apart siblings that share
+/// both, like two `impl Foo` blocks. Given the `base` body it is merged
+/// against, each such sibling takes the ordinal of the base one it is: the
+/// one with the same text, else the one with the most declarations in
+/// common, else the next left in order; any others get ordinals of their own.
+fn body<'a>(
+    source: &'a str,
+    decls: &[Declaration],
+    parent: Option<usize>,
+    range: std::ops::Range<usize>,
+    base: Option<&Body>,
+) -> Option<Body<'a>> {
+    let children: Vec<usize> = (0..decls.len())
+        .filter(|&i| decls[i].parent == parent)
+        .collect();
+    let first = children.first()?;
+    let last = children.last()?;
+
+    let mut starts = vec![decls[*first].range.start];
+    starts.extend(children.iter().map(|&i| decls[i].range.end));
+    let text = |j: usize| {
+        source
+            .get(starts[j]..decls[children[j]].range.end)
+            .unwrap_or("")
+    };
+    let same = |j: usize, key: &Key| {
+        let decl = &decls[children[j]];
+        key.0 == decl.kind && key.1 == decl.name
+    };
+    let members = |j: usize| -> HashSet<(&str, &str)> {
+        decls
+            .iter()
+            .filter(|d| d.parent == Some(children[j]))
+            .map(|d| (d.kind.as_str(), d.name.as_str()))
+            .collect()
+    };
+
+    let mut ordinals: Vec<Option<usize>> = vec![None; children.len()];
+    if let Some(base) = base {
+        let mut used: HashSet<&Key> = HashSet::new();
+        let mut claim = |j: usize, pick: &dyn Fn(&Key) -> Option<usize>| {
+            let best = base
+                .keys
+                .iter()
+                .filter(|key| same(j, key) && !used.contains(key))
+                .filter_map(|key| pick(key).map(|score| (score, key)))
+                .max_by_key(|&(score, key)| (score, std::cmp::Reverse(key.2)));
+            best.map(|(_, key)| {
+                used.insert(key);
+                key.2
+            })
+        };
+        for (j, ordinal) in ordinals.iter_mut().enumerate() {
+            let own = text(j).trim();
+            *ordinal =
+                claim(j, &|key| (base.chunks[key].text.trim() == own).then_some(0));
+        }
+        for (j, ordinal) in ordinals.iter_mut().enumerate() {
+            if ordinal.is_none() {
+                let own = members(j);
+                *ordinal = claim(j, &|key| {
+                    let shared = base.chunks[key].body.as_ref().map_or(0, |b| {
+                        b.keys
+                            .iter()
+                            .filter(|k| own.contains(&(k.0.as_str(), k.1.as_str())))
+                            .count()
+                    });
+                    (shared > 0).then_some(shared)
+                });
+            }
+        }
+        for (j, ordinal) in ordinals.iter_mut().enumerate() {
+            if ordinal.is_none() {
+                *ordinal = claim(j, &|_| Some(0));
+            }
+        }
+    }
+    let mut next: HashMap<(&str, &str), usize> = HashMap::new();
+    for key in base.iter().flat_map(|base| &base.keys) {
+        let count = next.entry((&key.0, &key.1)).or_default();
+        *count = (*count).max(key.2);
+    }
+    for (j, ordinal) in ordinals.iter().enumerate() {
+        if let Some(ordinal) = ordinal {
+            let decl = &decls[children[j]];
+            let count = next.entry((&decl.kind, &decl.name)).or_default();
+            *count = (*count).max(*ordinal);
+        }
+    }
+
+    let mut keys = vec![];
+    let mut chunks = HashMap::new();
+    for (j, &i) in children.iter().enumerate() {
+        let decl = &decls[i];
+        let ordinal = ordinals[j].unwrap_or_else(|| {
+            let count = next.entry((&decl.kind, &decl.name)).or_default();
+            *count += 1;
+            *count
+        });
+        let key = (decl.kind.clone(), decl.name.clone(), ordinal);
+        let base_body = base
+            .and_then(|base| base.chunks.get(&key))
+            .and_then(|chunk| chunk.body.as_ref());
+        let chunk = Chunk {
+            text: text(j),
+            body: body(source, decls, Some(i), starts[j]..decl.range.end, base_body),
+        };
+        keys.push(key.clone());
+        chunks.insert(key, chunk);
+    }
+    Some(Body {
+        header: source
+            .get(range.start..decls[*first].range.start)
+            .unwrap_or(""),
+        keys,
+        chunks,
+        footer: source.get(decls[*last].range.end..range.end).unwrap_or(""),
+    })
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSummary {
+    pub auto_merged: usize,
+    pub conflicts: usize,
+    pub moves: usize,
+}
+
+/// Keys of `side` that changed position relative to `base`: everything
+/// outside the longest run of shared keys that kept their base order.
+fn moved_keys<'k>(base: &[Key], side: &'k [Key]) -> HashSet<&'k Key> {
+    let base_index: HashMap<&Key, usize> =
+        base.iter().enumerate().map(|(i, k)| (k, i)).collect();
+    let shared: Vec<(&Key, usize)> = side
+        .iter()
+        .filter_map(|k| base_index.get(k).map(|&i| (k, i)))
+        .collect();
+
+    // Longest increasing subsequence of base positions, O(n^2) is plenty for
+    // the number of declarations in one body.
+    let mut length = vec![1; shared.len()];
+    let mut previous = vec![None; shared.len()];
+    for i in 0..shared.len() {
+        for j in 0..i {
+            if shared[j].1 < shared[i].1 && length[j] + 1 > length[i] {
+                length[i] = length[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut kept = HashSet::new();
+    let mut cursor =
+        (0..shared.len()).max_by_key(|&i| (length[i], std::cmp::Reverse(i)));
+    while let Some(i) = cursor {
+        kept.insert(i);
+        cursor = previous[i];
+    }
+    shared
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !kept.contains(i))
+        .map(|(_, (k, _))| *k)
+        .collect()
+}
+
+/// Places `key` right after its predecessor in `side` (or first, if it has
+/// none that survived into `order`).
+fn insert_after_predecessor(order: &mut Vec<Key>, side: &[Key], key: &Key) {
+    order.retain(|k| k != key);
+    let position = side.iter().position(|k| k == key).unwrap_or(0);
+    let at = side[..position]
+        .iter()
+        .rev()
+        .find_map(|p| order.iter().position(|k| k == p))
+        .map_or(0, |i| i + 1);
+    order.insert(at, key.clone());
+}
+
+fn text<'a>(chunk: Option<&Chunk<'a>>) -> Option<&'a str> {
+    chunk.map(|c| c.text)
+}
+
+fn merge_atom(
+    base: Option<&str>,
+    local: Option<&str>,
+    remote: Option<&str>,
+) -> Option<Option<String>> {
+    if local == remote || remote == base {
+        Some(local.map(str::to_string))
+    } else if local == base {
+        Some(remote.map(str::to_string))
+    } else {
+        None
+    }
+}
+
+/// Splits the whitespace in front of a chunk into the line breaks before
+/// it and the indentation of its first line.
+fn split_leading(text: &str) -> (&str, &str, &str) {
+    let trimmed = text.trim_start();
+    let leading = &text[..text.len() - trimmed.len()];
+    match leading.rfind('\n') {
+        Some(i) => (&leading[..=i], &leading[i + 1..], trimmed),
+        None => ("", leading, trimmed),
+    }
+}
+
+fn push_side(out: &mut String, indent: &str, text: &str) {
+    if !text.is_empty() {
+        out.push_str(indent);
+        out.push_str(text);
+        if !text.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+}
+
+/// Names written after the `<<<<<<<` and `>>>>>>>` conflict markers.
+pub struct Labels<'a> {
+    pub local: &'a str,
+    pub remote: &'a str,
+}
+
+impl Default for Labels<'_> {
+    fn default() -> Self {
+        Labels {
+            local: "local",
+            remote: "remote",
+        }
+    }
+}
+
+struct Merger<'l> {
+    out: String,
+    summary: MergeSummary,
+    labels: &'l Labels<'l>,
+    /// Set after a conflict, whose closing marker already ends the line the
+    /// next text would otherwise break.
+    after_conflict: bool,
+}
+
+impl Merger<'_> {
+    fn push(&mut self, mut text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if std::mem::take(&mut self.after_conflict) {
+            text = text
+                .strip_prefix("\r\n")
+                .or_else(|| text.strip_prefix('\n'))
+                .unwrap_or(text);
+        }
+        self.out.push_str(text);
+    }
+
+    fn conflict(&mut self, local: &str, remote: &str) {
+        let (breaks, local_indent, local) = split_leading(local);
+        let (_, remote_indent, remote) = split_leading(remote);
+        self.push(breaks);
+        let out = &mut self.out;
+        // The first child of a container has its indentation at the end of
+        // the header rather than in front of it; lift it onto the sides.
+        let line = out.rfind('\n').map_or(0, |i| i + 1);
+        let indent = if out[line..].trim().is_empty() {
+            out.split_off(line)
+        } else {
+            out.push('\n');
+            String::new()
+        };
+        let pick = |own: &'_ str| {
+            if own.is_empty() {
+                indent.clone()
+            } else {
+                own.to_string()
+            }
+        };
+        out.push_str(&format!("<<<<<<< {}\n", self.labels.local));
+        push_side(out, &pick(local_indent), local);
+        out.push_str("=======\n");
+        push_side(out, &pick(remote_indent), remote);
+        out.push_str(&format!(">>>>>>> {}\n", self.labels.remote));
+        self.after_conflict = true;
+    }
+
+    fn merge_text(
+        &mut self,
+        base: Option<&str>,
+        local: Option<&str>,
+        remote: Option<&str>,
+    ) -> bool {
+        match merge_atom(base, local, remote) {
+            Some(merged) => {
+                self.push(merged.as_deref().unwrap_or(""));
+                true
+            }
+            None => {
+                self.summary.conflicts += 1;
+                self.conflict(local.unwrap_or(""), remote.unwrap_or(""));
+                false
+            }
+        }
+    }
+
+    fn merge_bodies(&mut self, base: &Body, local: &Body, remote: &Body) {
+        self.merge_text(Some(base.header), Some(local.header), Some(remote.header));
+
+        let moved_local = moved_keys(&base.keys, &local.keys);
+        let moved_remote = moved_keys(&base.keys, &remote.keys);
+        self.summary.moves += moved_local.union(&moved_remote).count();
+
+        let in_base: HashSet<&Key> = base.keys.iter().collect();
+        let in_local: HashSet<&Key> = local.keys.iter().collect();
+        let mut order = local.keys.clone();
+        for key in &remote.keys {
+            let added = !in_base.contains(key) && !in_local.contains(key);
+            let deleted_locally = in_base.contains(key) && !in_local.contains(key);
+            let moved = moved_remote.contains(key) && !moved_local.contains(key);
+            if added || deleted_locally || moved {
+                insert_after_predecessor(&mut order, &remote.keys, key);
+            }
+        }
+
+        for key in &order {
+            let b = base.chunks.get(key);
+            let l = local.chunks.get(key);
+            let r = remote.chunks.get(key);
+            if text(l) == text(b) && text(r) == text(b) {
+                self.push(text(l).unwrap_or(""));
+                continue;
+            }
+            if let (
+                Some(Chunk { body: Some(bb), .. }),
+                Some(Chunk { body: Some(lb), .. }),
+                Some(Chunk { body: Some(rb), .. }),
+            ) = (b, l, r)
+            {
+                self.merge_bodies(bb, lb, rb);
+                continue;
+            }
+            if self.merge_text(text(b), text(l), text(r)) {
+                self.summary.auto_merged += 1;
+            }
+        }
+
+        self.merge_text(Some(base.footer), Some(local.footer), Some(remote.footer));
+    }
+}
+
+/// Three-way merges `local` and `remote` against their common `base`
+/// declaration by declaration, recursing into containers that changed on
+/// both sides. Declarations changed incompatibly are written out between
+/// conflict markers.
+pub fn merge_sources(
+    base: &str,
+    local: &str,
+    remote: &str,
+    labels: &Labels,
+) -> anyhow::Result<(String, MergeSummary)> {
+    fn whole(source: &str) -> anyhow::Result<(Vec<Declaration>, &str)> {
+        let file = parse_source("", source)?;
+        Ok((declarations(&file, source), source))
+    }
+    let (base_decls, base) = whole(base)?;
+    let (local_decls, local) = whole(local)?;
+    let (remote_decls, remote) = whole(remote)?;
+
+    let mut merger = Merger {
+        out: String::new(),
+        summary: MergeSummary::default(),
+        labels,
+        after_conflict: false,
+    };
+    let base_body = body(base, &base_decls, None, 0..base.len(), None);
+    match (
+        &base_body,
+        body(
+            local,
+            &local_decls,
+            None,
+            0..local.len(),
+            base_body.as_ref(),
+        ),
+        body(
+            remote,
+            &remote_decls,
+            None,
+            0..remote.len(),
+            base_body.as_ref(),
+        ),
+    ) {
+        (Some(b), Some(l), Some(r)) => merger.merge_bodies(b, &l, &r),
+        _ => {
+            if merger.merge_text(Some(base), Some(local), Some(remote))
+                && (local != base || remote != base)
+            {
+                merger.summary.auto_merged += 1;
+            }
+        }
+    }
+    Ok((merger.out, merger.summary))
+}
+
+/// Reads a merge input, which must parse cleanly. Mixed line endings only
+/// warn: the merge works, but can't keep the result's endings consistent.
+pub fn read_input(path: &str) -> Result<String, String> {
+    let contents =
+        read_file(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let endings = LineEndings::of(&contents);
+    if endings.is_mixed() {
+        eprintln!(
+            "warning: {} mixes line endings ({} LF, {} CRLF)",
+            path, endings.lf, endings.crlf
+        );
+    }
+    let file = parse_source(path, &contents).map_err(|e| format!("{}: {}", path, e))?;
+    if file.parsing_errors_detected {
+        return Err(format!("{}: syntax errors", path));
+    }
+    Ok(contents)
+}
+
+/// `merge [--output <file>] [--summary] <base> <local> <remote>`
+///
+/// Exits with [`EXIT_CLEAN`], [`EXIT_CONFLICTS`] or [`EXIT_PARSE_FAILURE`].
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(raw, &["--summary"], &["--output", "-o"])?;
+    let [base, local, remote] = args.positional() else {
+        anyhow::bail!(
+            "usage: merge [--output <file>] [--summary] <base> <local> <remote>"
+        );
+    };
+
+    let mut inputs = vec![];
+    for path in [base, local, remote] {
+        match read_input(path) {
+            Ok(contents) => inputs.push(contents),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                return Ok(EXIT_PARSE_FAILURE);
+            }
+        }
+    }
+    let (mut merged, summary) =
+        merge_sources(&inputs[0], &inputs[1], &inputs[2], &Labels::default())?;
+    if has_bom(local) {
+        merged.insert_str(0, BOM);
+    }
+
+    match args.value("--output").or_else(|| args.value("-o")) {
+        Some(output) => std::fs::write(Path::new(output), &merged)?,
+        None => std::io::stdout().lock().write_all(merged.as_bytes())?,
+    }
+    if args.flag("--summary") {
+        eprintln!(
+            "auto-merged {} declarations, {} conflicts, {} moves",
+            summary.auto_merged, summary.conflicts, summary.moves
+        );
+    }
+    Ok(if summary.conflicts > 0 {
+        EXIT_CONFLICTS
+    } else {
+        EXIT_CLEAN
+    })
+}
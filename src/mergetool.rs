@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::Args;
+use crate::merge::{merge_sources, read_input, Labels, EXIT_CLEAN, EXIT_CONFLICTS};
+use crate::parse::{has_bom, BOM};
+
+/// The files git hands a merge tool, from `$BASE $LOCAL $REMOTE $MERGED`.
+pub struct MergeFiles {
+    pub base: String,
+    pub local: String,
+    pub remote: String,
+    pub merged: String,
+}
+
+impl MergeFiles {
+    /// Takes the paths from the arguments when given, otherwise from the
+    /// `BASE`, `LOCAL`, `REMOTE` and `MERGED` environment variables. With
+    /// three arguments the result goes into `local`, like Mercurial's
+    /// default `$base $local $other` convention.
+    fn from_args_or_env(positional: &[String]) -> anyhow::Result<MergeFiles> {
+        match positional {
+            [base, local, remote, merged] => {
+                return Ok(MergeFiles {
+                    base: base.clone(),
+                    local: local.clone(),
+                    remote: remote.clone(),
+                    merged: merged.clone(),
+                })
+            }
+            [base, local, remote] => {
+                return Ok(MergeFiles {
+                    base: base.clone(),
+                    local: local.clone(),
+                    remote: remote.clone(),
+                    merged: local.clone(),
+                })
+            }
+            [] => {}
+            _ => anyhow::bail!("usage: mergetool [<base> <local> <remote> [<merged>]]"),
+        }
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| anyhow::anyhow!("{} is not set", name))
+        };
+        Ok(MergeFiles {
+            base: var("BASE").unwrap_or_default(),
+            local: var("LOCAL")?,
+            remote: var("REMOTE")?,
+            merged: var("MERGED")?,
+        })
+    }
+
+    fn substitute(&self, template: &str) -> String {
+        template
+            .replace("$BASE", &self.base)
+            .replace("$LOCAL", &self.local)
+            .replace("$REMOTE", &self.remote)
+            .replace("$MERGED", &self.merged)
+    }
+}
+
+/// The interactive resolver: `--resolver`, `SEMANTIC_RUST_RESOLVER`, or the
+/// user's editor opened on the merged file.
+fn resolver_command(args: &Args) -> String {
+    args.value("--resolver")
+        .map(str::to_string)
+        .or_else(|| std::env::var("SEMANTIC_RUST_RESOLVER").ok())
+        .or_else(|| std::env::var("GIT_EDITOR").ok())
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Runs `template` with `$BASE`/`$LOCAL`/`$REMOTE`/`$MERGED` filled in; a
+/// template that mentions none of them gets the merged path appended.
+fn run_resolver(template: &str, files: &MergeFiles) -> anyhow::Result<bool> {
+    let mentions_files = ["$BASE", "$LOCAL", "$REMOTE", "$MERGED"]
+        .iter()
+        .any(|v| template.contains(v));
+    let mut words = template.split_whitespace().map(|w| files.substitute(w));
+    let program = words
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("empty resolver command"))?;
+    let mut command = Command::new(&program);
+    command.args(words);
+    if !mentions_files {
+        command.arg(&files.merged);
+    }
+    let status = command
+        .status()
+        .map_err(|e| anyhow::anyhow!("failed to run resolver {}: {}", program, e))?;
+    Ok(status.success())
+}
+
+fn has_conflict_markers(path: &str) -> anyhow::Result<bool> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> ")))
+}
+
+fn install() -> anyhow::Result<()> {
+    let exe = std::env::current_exe()?;
+    let cmd = format!(
+        "'{}' mergetool \"$BASE\" \"$LOCAL\" \"$REMOTE\" \"$MERGED\"",
+        exe.to_string_lossy()
+    );
+    for (key, value) in [
+        ("mergetool.rustsemantic.cmd", cmd.as_str()),
+        ("mergetool.rustsemantic.trustExitCode", "true"),
+    ] {
+        let status = Command::new("git")
+            .args(["config", "--global", key, value])
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("git config {} failed", key);
+        }
+    }
+    println!("installed; run `git mergetool --tool=rustsemantic` or set merge.tool = rustsemantic");
+    Ok(())
+}
+
+/// `mergetool [--install] [--resolver <command>] [--label-local <label>]
+/// [--label-remote <label>] [<base> <local> <remote> [<merged>]]`
+///
+/// Semantically merges the conflicted file into `$MERGED`, and only opens
+/// the interactive resolver when conflicts remain (or the inputs don't
+/// parse). Exits 0 once the file is resolved, so git can trust the code.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(
+        raw,
+        &["--install"],
+        &[
+            "--resolver",
+            "--label-local",
+            "--label-remote",
+            "--label-other",
+            "--label-base",
+        ],
+    )?;
+    if args.flag("--install") {
+        install()?;
+        return Ok(EXIT_CLEAN);
+    }
+    let files = MergeFiles::from_args_or_env(args.positional())?;
+
+    // git passes a missing or empty base for add/add conflicts.
+    let base = if Path::new(&files.base).is_file() {
+        read_input(&files.base)
+    } else {
+        Ok(String::new())
+    };
+    let inputs = (base, read_input(&files.local), read_input(&files.remote));
+    if let (Ok(base), Ok(local), Ok(remote)) = inputs {
+        // `--label-other` and `--label-base` match Mercurial's `$labelother`
+        // and `$labelbase`; the base label is accepted but has no marker.
+        let labels = Labels {
+            local: args.value("--label-local").unwrap_or("local"),
+            remote: args
+                .value("--label-remote")
+                .or_else(|| args.value("--label-other"))
+                .unwrap_or("remote"),
+        };
+        let (mut merged, summary) = merge_sources(&base, &local, &remote, &labels)?;
+        if has_bom(&files.local) {
+            merged.insert_str(0, BOM);
+        }
+        std::fs::write(&files.merged, merged)?;
+        if summary.conflicts == 0 {
+            eprintln!("auto-merged {} declarations", summary.auto_merged);
+            return Ok(EXIT_CLEAN);
+        }
+        eprintln!(
+            "{} conflicts remain after auto-merging {} declarations",
+            summary.conflicts, summary.auto_merged
+        );
+    } else {
+        eprintln!("inputs don't parse, falling back to the resolver");
+    }
+
+    if !run_resolver(&resolver_command(&args), &files)?
+        || has_conflict_markers(&files.merged)?
+    {
+        return Ok(EXIT_CONFLICTS);
+    }
+    Ok(EXIT_CLEAN)
+}
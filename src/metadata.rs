@@ -0,0 +1,229 @@
+//! Optional per-node metadata read off the tree-sitter tree, for consumers
+//! that want more than the SemanticMerge format carries.
+
+use crate::complexity::complexity;
+use crate::model::{is_declaration_kind, Metadata, Signature};
+use crate::parse::ParseOptions;
+
+/// Collapses whitespace inside a type to single spaces, and drops it next to
+/// brackets, `&` and commas, so `Vec< &'a  T >` and `Vec<&'a T>` compare equal.
+pub fn normalize(text: &str) -> String {
+    let mut out = String::new();
+    for word in text.split_whitespace() {
+        let glued = out.ends_with(['<', '(', '[', '&'])
+            || word.starts_with(['>', ')', ']', ',']);
+        if !out.is_empty() && !glued {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+fn node_text<'a>(node: tree_sitter::Node, src: &'a str) -> &'a str {
+    node.utf8_text(src.as_bytes()).unwrap_or("")
+}
+
+/// The parameter and return types of a function or function signature.
+fn signature(node: &tree_sitter::Node, src: &str) -> Option<Signature> {
+    if !matches!(node.kind(), "function_item" | "function_signature_item") {
+        return None;
+    }
+    let mut parameters = vec![];
+    if let Some(list) = node.child_by_field_name("parameters") {
+        for i in 0..list.named_child_count() {
+            let parameter = list.named_child(i).unwrap();
+            match parameter.kind() {
+                "parameter" => {
+                    let ty = parameter.child_by_field_name("type");
+                    parameters.push(normalize(ty.map_or("", |ty| node_text(ty, src))));
+                }
+                "self_parameter" => {
+                    parameters.push(normalize(node_text(parameter, src)))
+                }
+                "variadic_parameter" => parameters.push("...".to_string()),
+                _ => {}
+            }
+        }
+    }
+    let return_type = node
+        .child_by_field_name("return_type")
+        .map(|ty| normalize(node_text(ty, src)));
+    Some(Signature {
+        parameters,
+        return_type,
+    })
+}
+
+/// Records the visibility and `async`/`unsafe`/`const`/`extern` qualifiers
+/// among the direct children of `node`, including those grouped under
+/// `function_modifiers`.
+fn qualifiers(node: &tree_sitter::Node, src: &str, metadata: &mut Metadata) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "visibility_modifier" => {
+                metadata.visibility = Some(normalize(node_text(child, src)));
+            }
+            "function_modifiers" => qualifiers(&child, src, metadata),
+            "async" => metadata.is_async = true,
+            "unsafe" => metadata.is_unsafe = true,
+            "const" if node.kind() == "function_modifiers" => metadata.is_const = true,
+            "extern_modifier" => {
+                let abi = node_text(child, src)
+                    .trim_start_matches("extern")
+                    .trim()
+                    .trim_matches('"');
+                metadata.abi = Some(if abi.is_empty() { "C" } else { abi }.to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The text of an outer doc comment (`///`, `/** */` or `#[doc = "..."]`),
+/// or `None` for anything else.
+fn doc_line(node: tree_sitter::Node, src: &str) -> Option<String> {
+    let text = node_text(node, src);
+    match node.kind() {
+        "line_comment" if text.starts_with("///") && !text.starts_with("////") => {
+            let line = &text[3..];
+            Some(
+                line.strip_prefix(' ')
+                    .unwrap_or(line)
+                    .trim_end()
+                    .to_string(),
+            )
+        }
+        "block_comment" if text.starts_with("/**") && !text.starts_with("/***") => {
+            let body = text[3..].strip_suffix("*/")?;
+            let lines: Vec<&str> = body
+                .lines()
+                .map(|line| {
+                    let line = line.trim();
+                    let line = line.strip_prefix('*').unwrap_or(line);
+                    line.strip_prefix(' ').unwrap_or(line)
+                })
+                .collect();
+            Some(lines.join("\n").trim().to_string())
+        }
+        "attribute_item" => {
+            let inner = text.strip_prefix("#[")?.strip_suffix(']')?.trim();
+            let value = inner.strip_prefix("doc")?.trim_start().strip_prefix('=')?;
+            let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+            Some(value.strip_prefix(' ').unwrap_or(value).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Whether `node` is an outer attribute or doc comment, which belongs to
+/// the item after it.
+pub fn is_outer_attribute(node: tree_sitter::Node, src: &str) -> bool {
+    node.kind() == "attribute_item" || doc_line(node, src).is_some()
+}
+
+/// The doc comment on `node`: the doc comments and `doc` attributes among
+/// the siblings right before it, skipping over other attributes.
+fn doc(node: &tree_sitter::Node, src: &str) -> Option<String> {
+    if matches!(
+        node.kind(),
+        "line_comment" | "block_comment" | "attribute_item"
+    ) {
+        return None;
+    }
+    let mut lines = vec![];
+    let mut sibling = node.prev_named_sibling();
+    while let Some(previous) = sibling {
+        match doc_line(previous, src) {
+            Some(line) => lines.push(line),
+            None if previous.kind() == "attribute_item" => {}
+            None => break,
+        }
+        sibling = previous.prev_named_sibling();
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+/// Longest excerpt kept, in characters, before it is cut off with `…`.
+pub const EXCERPT_LENGTH: usize = 80;
+
+/// The first line of a declaration, trimmed and cut to [`EXCERPT_LENGTH`].
+fn excerpt(node: &tree_sitter::Node, src: &str) -> Option<String> {
+    // `meta_item` ends in `_item` but is the inside of an attribute.
+    if !is_declaration_kind(node.kind()) || node.kind() == "meta_item" {
+        return None;
+    }
+    let line = node_text(*node, src).lines().next()?.trim();
+    if line.chars().count() <= EXCERPT_LENGTH {
+        return Some(line.to_string());
+    }
+    let mut cut: String = line.chars().take(EXCERPT_LENGTH - 1).collect();
+    cut.push('…');
+    Some(cut)
+}
+
+/// Whether `text` is an attribute that marks test code: `#[test]`, or one
+/// from a test framework like `#[tokio::test]`, or `#[cfg(test)]`.
+fn is_test_attribute(text: &str) -> bool {
+    let Some(inner) = text.strip_prefix("#[").and_then(|t| t.strip_suffix(']')) else {
+        return false;
+    };
+    let path = inner.split('(').next().unwrap_or("").trim();
+    path == "test" || path.ends_with("::test") || normalize(inner) == "cfg(test)"
+}
+
+/// Whether declaration `node`, or one it is inside, carries a test
+/// attribute among the attributes and comments right before it.
+fn is_test(node: &tree_sitter::Node, src: &str) -> bool {
+    if !is_declaration_kind(node.kind()) || node.kind() == "meta_item" {
+        return false;
+    }
+    std::iter::successors(Some(*node), |n| n.parent()).any(|item| {
+        let mut sibling = item.prev_named_sibling();
+        while let Some(previous) = sibling {
+            match previous.kind() {
+                "attribute_item" if is_test_attribute(node_text(previous, src)) => {
+                    return true
+                }
+                "attribute_item" | "line_comment" | "block_comment" => {}
+                _ => break,
+            }
+            sibling = previous.prev_named_sibling();
+        }
+        false
+    })
+}
+
+/// The metadata `options` asks for on `node`, or `None` when there is none.
+pub fn metadata(
+    node: &tree_sitter::Node,
+    src: &str,
+    options: &ParseOptions,
+) -> Option<Metadata> {
+    let mut metadata = Metadata {
+        signature: options.signatures.then(|| signature(node, src)).flatten(),
+        ..Metadata::default()
+    };
+    // The modifiers are reported on their function, not on their own node.
+    if options.qualifiers && node.kind() != "function_modifiers" {
+        qualifiers(node, src, &mut metadata);
+    }
+    if options.docs {
+        metadata.doc = doc(node, src);
+    }
+    if options.excerpts {
+        metadata.excerpt = excerpt(node, src);
+    }
+    if options.tests {
+        metadata.is_test = is_test(node, src);
+    }
+    if options.complexity {
+        metadata.complexity = complexity(node);
+    }
+    (!metadata.is_empty()).then_some(metadata)
+}
@@ -0,0 +1,179 @@
+//! Counters and histograms for the server, rendered in the Prometheus text
+//! format for `GET /metrics`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Upper bounds of the duration buckets, in seconds.
+const BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// The durations observed for one set of labels.
+#[derive(Default, Clone, Debug)]
+pub struct Histogram {
+    /// How many observations fell in each of [`BUCKETS`] (not cumulative).
+    counts: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.counts[i] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// What the server has done since it started. Methods are labelled by name,
+/// and documents by language, which is always `rust` for now.
+#[derive(Default, Debug)]
+pub struct Metrics {
+    pub requests: BTreeMap<String, u64>,
+    pub errors: BTreeMap<String, u64>,
+    pub request_durations: BTreeMap<String, Histogram>,
+    pub parse_durations: BTreeMap<String, Histogram>,
+    /// Documents parsed with syntax errors in them.
+    pub syntax_errors: BTreeMap<String, u64>,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+impl Metrics {
+    /// Counts a request to `method` that took `duration`, and whether it
+    /// failed.
+    pub fn request(&mut self, method: &str, duration: Duration, failed: bool) {
+        *self.requests.entry(method.to_string()).or_default() += 1;
+        if failed {
+            *self.errors.entry(method.to_string()).or_default() += 1;
+        }
+        self.request_durations
+            .entry(method.to_string())
+            .or_default()
+            .observe(duration);
+    }
+
+    /// Counts a document of `language` parsed in `duration`.
+    pub fn parse(&mut self, language: &str, duration: Duration, syntax_errors: bool) {
+        self.parse_durations
+            .entry(language.to_string())
+            .or_default()
+            .observe(duration);
+        if syntax_errors {
+            *self.syntax_errors.entry(language.to_string()).or_default() += 1;
+        }
+    }
+
+    /// The Prometheus text exposition of every metric.
+    pub fn render(&self) -> String {
+        fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        }
+        fn counters(
+            out: &mut String,
+            name: &str,
+            help: &str,
+            label: &str,
+            values: &BTreeMap<String, u64>,
+        ) {
+            header(out, name, "counter", help);
+            for (value, count) in values {
+                let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, value, count);
+            }
+        }
+        fn histograms(
+            out: &mut String,
+            name: &str,
+            help: &str,
+            label: &str,
+            values: &BTreeMap<String, Histogram>,
+        ) {
+            header(out, name, "histogram", help);
+            for (value, histogram) in values {
+                histogram.render(out, name, &format!("{}=\"{}\"", label, value));
+            }
+        }
+
+        let mut out = String::new();
+        counters(
+            &mut out,
+            "rustsemantic_requests_total",
+            "Requests handled, by method.",
+            "method",
+            &self.requests,
+        );
+        counters(
+            &mut out,
+            "rustsemantic_request_errors_total",
+            "Requests answered with an error, by method.",
+            "method",
+            &self.errors,
+        );
+        histograms(
+            &mut out,
+            "rustsemantic_request_duration_seconds",
+            "Time spent answering requests, by method.",
+            "method",
+            &self.request_durations,
+        );
+        histograms(
+            &mut out,
+            "rustsemantic_parse_duration_seconds",
+            "Time spent parsing documents, by language.",
+            "language",
+            &self.parse_durations,
+        );
+        counters(
+            &mut out,
+            "rustsemantic_syntax_errors_total",
+            "Documents parsed with syntax errors, by language.",
+            "language",
+            &self.syntax_errors,
+        );
+        for (name, help, value) in [
+            (
+                "rustsemantic_cache_hits_total",
+                "Documents found already parsed.",
+                self.cache_hits,
+            ),
+            (
+                "rustsemantic_cache_misses_total",
+                "Documents that had to be parsed.",
+                self.cache_misses,
+            ),
+        ] {
+            header(&mut out, name, "counter", help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
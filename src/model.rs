@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+
+/// Version of the output document format, bumped whenever consumers would
+/// need to change how they read it. `schema/semantic-file.schema.json`
+/// describes the current version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticFile {
+    pub schema_version: u32,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    pub location_span: LocationSpan,
+    pub footer_span: CharSpan,
+    pub parsing_errors_detected: bool,
+    pub children: Vec<Node>,
+    pub parsing_error: Option<()>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", untagged)]
+pub enum Node {
+    Container(Container),
+    Terminal(Terminal),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Container {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    pub location_span: LocationSpan,
+    pub header_span: CharSpan,
+    pub footer_span: CharSpan,
+    pub children: Vec<Node>,
+    /// Hash of the node's own source text, when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Terminal {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    pub location_span: LocationSpan,
+    pub span: CharSpan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationSpan {
+    pub start: [i64; 2],
+    pub end: [i64; 2],
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct CharSpan {
+    pub span: [i64; 2],
+}
+
+/// Extra facts about a node beyond the SemanticMerge format, each only
+/// present when asked for and applicable.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+    /// The visibility as written, e.g. `pub` or `pub(crate)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    #[serde(rename = "async", default, skip_serializing_if = "is_false")]
+    pub is_async: bool,
+    #[serde(rename = "unsafe", default, skip_serializing_if = "is_false")]
+    pub is_unsafe: bool,
+    #[serde(rename = "const", default, skip_serializing_if = "is_false")]
+    pub is_const: bool,
+    /// The ABI of an `extern` function, `C` when none is given.
+    #[serde(rename = "extern", skip_serializing_if = "Option::is_none")]
+    pub abi: Option<String>,
+    /// The outer doc comment text, without comment markers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// The first line of a declaration's source, truncated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// Whether the declaration is test code: a `#[test]` function, a
+    /// `#[cfg(test)]` item or anything inside one.
+    #[serde(rename = "tests", default, skip_serializing_if = "is_false")]
+    pub is_test: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<Complexity>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.signature.is_none()
+            && self.visibility.is_none()
+            && !self.is_async
+            && !self.is_unsafe
+            && !self.is_const
+            && self.abi.is_none()
+            && self.doc.is_none()
+            && self.excerpt.is_none()
+            && !self.is_test
+            && self.complexity.is_none()
+    }
+
+    /// The `async`, `unsafe`, `const` and `extern` qualifiers recorded, as
+    /// written, like `extern "C"`.
+    pub fn qualifiers(&self) -> Vec<String> {
+        let mut qualifiers = vec![];
+        if self.is_const {
+            qualifiers.push("const".to_string());
+        }
+        if self.is_async {
+            qualifiers.push("async".to_string());
+        }
+        if self.is_unsafe {
+            qualifiers.push("unsafe".to_string());
+        }
+        if let Some(abi) = &self.abi {
+            qualifiers.push(format!("extern \"{}\"", abi));
+        }
+        qualifiers
+    }
+}
+
+/// A function's parameter and return types, with whitespace normalized.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    /// Types of the parameters in order; `self` parameters as written.
+    pub parameters: Vec<String>,
+    /// `None` for functions returning `()` implicitly.
+    pub return_type: Option<String>,
+}
+
+/// How hard a function is to follow, roughly.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Complexity {
+    /// One more than the branches, loops, extra match arms and `&&`/`||`
+    /// operators in its body.
+    pub cyclomatic: u32,
+    /// How many `if`s, loops and `match`es deep its body goes.
+    pub nesting: u32,
+}
+
+impl SemanticFile {
+    /// Fails if an offset, line or column is beyond `i32::MAX`. Positions
+    /// are 64-bit here, but consumers with 32-bit integers, like
+    /// SemanticMerge, would wrap them around and read a corrupt tree.
+    pub fn check_fits_i32(&self) -> anyhow::Result<()> {
+        fn fits(values: &[i64]) -> bool {
+            values.iter().all(|&v| v <= i64::from(i32::MAX))
+        }
+        fn check(node: &Node) -> bool {
+            let location = node.location_span();
+            let spans_fit = match node {
+                Node::Container(c) => {
+                    fits(&c.header_span.span) && fits(&c.footer_span.span)
+                }
+                Node::Terminal(t) => fits(&t.span.span),
+            };
+            spans_fit
+                && fits(&location.start)
+                && fits(&location.end)
+                && node.children().iter().all(check)
+        }
+
+        let fit = fits(&self.footer_span.span)
+            && fits(&self.location_span.start)
+            && fits(&self.location_span.end)
+            && self.children.iter().all(check);
+        if !fit {
+            anyhow::bail!("{} has positions beyond 32-bit integers", self.name);
+        }
+        Ok(())
+    }
+}
+
+impl Node {
+    pub fn kind(&self) -> &str {
+        match self {
+            Node::Container(c) => &c.item_type,
+            Node::Terminal(t) => &t.item_type,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Container(c) => &c.name,
+            Node::Terminal(t) => &t.name,
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Container(c) => &c.children,
+            Node::Terminal(_) => &[],
+        }
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        match self {
+            Node::Container(c) => c.metadata.as_ref(),
+            Node::Terminal(t) => t.metadata.as_ref(),
+        }
+    }
+
+    pub fn location_span(&self) -> &LocationSpan {
+        match self {
+            Node::Container(c) => &c.location_span,
+            Node::Terminal(t) => &t.location_span,
+        }
+    }
+
+    /// Byte range covered by the whole node, header through footer. Spans
+    /// tile the file, so this includes the whitespace leading up to the node.
+    /// Assumes byte offsets with exclusive ends, as trees are built by
+    /// default.
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
+        match self {
+            Node::Container(c) => {
+                let end = if !c.footer_span.is_empty() {
+                    c.footer_span.span[1]
+                } else if let Some(last) = c.children.last() {
+                    last.byte_range().end as i64
+                } else {
+                    c.header_span.span[1]
+                };
+                c.header_span.span[0] as usize..end as usize
+            }
+            Node::Terminal(t) => t.span.span[0] as usize..t.span.span[1] as usize,
+        }
+    }
+
+    /// The node's own source text: [`Node::byte_range`] without the gap
+    /// leading up to it, found by walking back from the end of the span to
+    /// the line and column the node starts at.
+    pub fn text_range(&self, src: &str) -> std::ops::Range<usize> {
+        let range = self.byte_range();
+        let location = self.location_span();
+        let bytes = src.as_bytes();
+        let mut line_start = range.end.saturating_sub(location.end[1] as usize);
+        for _ in location.start[0]..location.end[0] {
+            line_start = bytes[..line_start.saturating_sub(1)]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+        }
+        let start = line_start + location.start[1] as usize;
+        start.clamp(range.start, range.end)..range.end
+    }
+}
+
+impl CharSpan {
+    pub fn is_empty(&self) -> bool {
+        self.span[1] < self.span[0]
+    }
+}
+
+/// A node's name without the `#[cfg(...)]` attributes that tell cfg-gated
+/// versions of a declaration apart, as written in the source.
+pub fn base_name(name: &str) -> &str {
+    name.split(" #[").next().unwrap_or(name)
+}
+
+/// Whether a tree-sitter kind is a declaration in its own right (as opposed
+/// to the expressions, types and punctuation that make one up).
+pub fn is_declaration_kind(kind: &str) -> bool {
+    match kind {
+        "attribute_item" | "inner_attribute_item" => false,
+        "use_declaration"
+        | "extern_crate_declaration"
+        | "macro_definition"
+        | "associated_type" => true,
+        _ => kind.ends_with("_item"),
+    }
+}
@@ -0,0 +1,96 @@
+//! A MessagePack encoder for JSON values, enough to give consumers that
+//! decode millions of nodes a faster format than JSON text.
+
+use serde_json::Value;
+
+/// Appends the MessagePack encoding of `value` to `out`.
+pub fn encode(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                encode_unsigned(n, out);
+            } else if let Some(n) = n.as_i64() {
+                encode_signed(n, out);
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&n.as_f64().unwrap_or(0.0).to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            header(s.len(), 0xa0, 31, [0xd9, 0xda, 0xdb], out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            header(items.len(), 0x90, 15, [0, 0xdc, 0xdd], out);
+            for item in items {
+                encode(item, out);
+            }
+        }
+        Value::Object(map) => {
+            header(map.len(), 0x80, 15, [0, 0xde, 0xdf], out);
+            for (key, value) in map {
+                encode(&Value::String(key.clone()), out);
+                encode(value, out);
+            }
+        }
+    }
+}
+
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    let mut out = vec![];
+    encode(value, &mut out);
+    out
+}
+
+/// Writes a length header: the `fix` form for lengths up to `fix_max`, then
+/// the 8, 16 or 32-bit form (a zero marker means there is no 8-bit form).
+fn header(len: usize, fix: u8, fix_max: usize, markers: [u8; 3], out: &mut Vec<u8>) {
+    if len <= fix_max {
+        out.push(fix | len as u8);
+    } else if len <= u8::MAX as usize && markers[0] != 0 {
+        out.extend_from_slice(&[markers[0], len as u8]);
+    } else if len <= u16::MAX as usize {
+        out.push(markers[1]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn encode_unsigned(n: u64, out: &mut Vec<u8>) {
+    if n <= 0x7f {
+        out.push(n as u8);
+    } else if n <= u8::MAX as u64 {
+        out.extend_from_slice(&[0xcc, n as u8]);
+    } else if n <= u16::MAX as u64 {
+        out.push(0xcd);
+        out.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        out.push(0xce);
+        out.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn encode_signed(n: i64, out: &mut Vec<u8>) {
+    if n >= -32 {
+        out.push(n as i8 as u8);
+    } else if n >= i8::MIN as i64 {
+        out.extend_from_slice(&[0xd0, n as i8 as u8]);
+    } else if n >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(n as i16).to_be_bytes());
+    } else if n >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(n as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
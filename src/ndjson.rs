@@ -0,0 +1,107 @@
+//! Flattened, one-node-per-line output for trees too big to load whole.
+
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::model::{CharSpan, LocationSpan, Metadata, Node, SemanticFile};
+
+/// One node of the tree, pointing at its parent by id instead of nesting.
+/// Ids number the nodes in document order, with the file itself as 0.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FlatNode<'a> {
+    id: usize,
+    parent: Option<usize>,
+    depth: usize,
+    #[serde(rename = "type")]
+    item_type: &'a str,
+    name: &'a str,
+    location_span: &'a LocationSpan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header_span: Option<&'a CharSpan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footer_span: Option<&'a CharSpan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span: Option<&'a CharSpan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parsing_errors_detected: Option<bool>,
+}
+
+fn write_line(out: &mut dyn Write, node: &FlatNode) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *out, node)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Writes `file` as newline-delimited JSON, parents before their children.
+pub fn write_nodes(out: &mut dyn Write, file: &SemanticFile) -> anyhow::Result<()> {
+    fn visit(
+        out: &mut dyn Write,
+        node: &Node,
+        parent: usize,
+        depth: usize,
+        next_id: &mut usize,
+    ) -> anyhow::Result<()> {
+        let id = *next_id;
+        *next_id += 1;
+        let (header_span, footer_span, span, hash, metadata) = match node {
+            Node::Container(c) => (
+                Some(&c.header_span),
+                Some(&c.footer_span),
+                None,
+                &c.hash,
+                &c.metadata,
+            ),
+            Node::Terminal(t) => (None, None, Some(&t.span), &t.hash, &t.metadata),
+        };
+        let flat = FlatNode {
+            id,
+            parent: Some(parent),
+            depth,
+            item_type: node.kind(),
+            name: node.name(),
+            location_span: node.location_span(),
+            header_span,
+            footer_span,
+            span,
+            hash: hash.as_deref(),
+            metadata: metadata.as_ref(),
+            schema_version: None,
+            parsing_errors_detected: None,
+        };
+        write_line(out, &flat)?;
+        for child in node.children() {
+            visit(out, child, id, depth + 1, next_id)?;
+        }
+        Ok(())
+    }
+
+    let root = FlatNode {
+        id: 0,
+        parent: None,
+        depth: 0,
+        item_type: &file.item_type,
+        name: &file.name,
+        location_span: &file.location_span,
+        header_span: None,
+        footer_span: Some(&file.footer_span),
+        span: None,
+        hash: None,
+        metadata: None,
+        schema_version: Some(file.schema_version),
+        parsing_errors_detected: Some(file.parsing_errors_detected),
+    };
+    write_line(out, &root)?;
+    let mut next_id = 1;
+    for child in &file.children {
+        visit(out, child, 0, 1, &mut next_id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use std::path::{Path, PathBuf};
+
+use crate::cli::Args;
+use crate::files::{module_dir, module_file, path_attribute};
+use crate::model::{base_name, is_declaration_kind, Node, SemanticFile};
+use crate::parse::{parse_source, parse_tree, read_file};
+
+/// One declaration in a file's outline, with the declarations nested in it.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineItem {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    pub start: [i64; 2],
+    pub end: [i64; 2],
+    #[serde(skip)]
+    pub range: std::ops::Range<usize>,
+    /// The file the children come from, for a `mod foo;` whose file was
+    /// inlined; their lines count in that file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub children: Vec<OutlineItem>,
+}
+
+/// The declaration hierarchy of a file, skipping all the nodes in between.
+pub fn outline(file: &SemanticFile, src: &str) -> Vec<OutlineItem> {
+    fn collect(nodes: &[Node], src: &str, out: &mut Vec<OutlineItem>) {
+        for node in nodes {
+            if is_declaration_kind(node.kind()) {
+                let mut children = vec![];
+                collect(node.children(), src, &mut children);
+                let location = node.location_span();
+                out.push(OutlineItem {
+                    item_type: node.kind().to_string(),
+                    name: node.name().to_string(),
+                    start: location.start,
+                    end: location.end,
+                    range: node.text_range(src),
+                    file: None,
+                    children,
+                });
+            } else {
+                collect(node.children(), src, out);
+            }
+        }
+    }
+
+    let mut out = vec![];
+    collect(&file.children, src, &mut out);
+    out
+}
+
+/// The root file of the crate at `path`: the file itself, or for a
+/// directory its `src/lib.rs`, else its `src/main.rs`.
+pub fn crate_root(path: &Path) -> anyhow::Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    ["src/lib.rs", "src/main.rs"]
+        .iter()
+        .map(|root| path.join(root))
+        .find(|root| root.is_file())
+        .ok_or_else(|| {
+            anyhow::anyhow!("{} has no src/lib.rs or src/main.rs", path.display())
+        })
+}
+
+/// The package name in the `Cargo.toml` at `dir`, if there is one.
+fn package_name(dir: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if let Some(value) = line.strip_prefix("name").filter(|_| in_package) {
+            let value = value.trim_start().strip_prefix('=')?.trim();
+            return Some(value.trim_matches(['"', '\'']).to_string());
+        }
+    }
+    None
+}
+
+/// The name of the crate whose root file is `root`: its package's, or
+/// failing that its directory's.
+pub fn crate_name(root: &Path) -> String {
+    // The directory above `src`, for a root file in one.
+    let dir = match root.parent() {
+        Some(src) if src.file_name().is_some_and(|name| name == "src") => src.parent(),
+        parent => parent,
+    }
+    .unwrap_or(Path::new(""));
+    package_name(dir).unwrap_or_else(|| {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        dir.file_name()
+            .map_or("crate".to_string(), |n| n.to_string_lossy().into_owned())
+    })
+}
+
+/// The whole crate at `path`, a crate directory or its root file, as one
+/// `crate` item: the declarations of its root file, with every module's
+/// file inlined under its `mod`. The crate is named after its package, or
+/// failing that its directory.
+pub fn crate_outline(path: &Path) -> anyhow::Result<OutlineItem> {
+    let root = crate_root(path)?;
+    let file = root.to_string_lossy().replace('\\', "/");
+    let contents = read_file(&file)?;
+    let tree = parse_source(&file, &contents)?;
+    let mut children = outline(&tree, &contents);
+    inline_modules(&mut children, &contents, &root)?;
+    Ok(OutlineItem {
+        item_type: "crate".to_string(),
+        name: crate_name(&root),
+        start: tree.location_span.start,
+        end: tree.location_span.end,
+        range: 0..contents.len(),
+        file: Some(file),
+        children,
+    })
+}
+
+/// Replaces the children of every `mod foo;` among `items`, declared in
+/// `src`, the source of `file`, with the outline of the file rustc loads
+/// for it, all the way down, including those `#[path]` points to. Modules
+/// whose file can't be found stay empty.
+pub fn inline_modules(
+    items: &mut [OutlineItem],
+    src: &str,
+    file: &Path,
+) -> anyhow::Result<()> {
+    let file_dir = file.parent().unwrap_or(Path::new(""));
+    inline(items, src, &module_dir(file), file_dir)
+}
+
+/// [`inline_modules`] for `items` whose modules live in `dir`, and whose
+/// `#[path]`s are relative to `path_dir`: the directory of the file for
+/// those at its top level, `dir` inside inline modules.
+fn inline(
+    items: &mut [OutlineItem],
+    src: &str,
+    dir: &Path,
+    path_dir: &Path,
+) -> anyhow::Result<()> {
+    for item in items {
+        if item.item_type != "mod_item" {
+            inline(&mut item.children, src, dir, path_dir)?;
+            continue;
+        }
+        let name = base_name(&item.name);
+        let text = src.get(item.range.clone()).unwrap_or("");
+        if !text.trim_end().ends_with(';') {
+            let dir = dir.join(name);
+            inline(&mut item.children, src, &dir, &dir)?;
+            continue;
+        }
+        // A file `#[path]` points to keeps its modules next to it, like a
+        // `mod.rs`.
+        let (path, dir) = match path_attribute(text) {
+            Some(path) => {
+                let path = path_dir.join(path);
+                let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                (Some(path).filter(|path| path.is_file()), dir)
+            }
+            None => (module_file(dir, name), dir.join(name)),
+        };
+        let Some(path) = path else {
+            continue;
+        };
+        let file = path.to_string_lossy().replace('\\', "/");
+        let contents = read_file(&file)?;
+        let mut children = outline(&parse_source(&file, &contents)?, &contents);
+        let file_dir = path.parent().unwrap_or(Path::new(""));
+        inline(&mut children, &contents, &dir, file_dir)?;
+        item.children = children;
+        item.file = Some(file);
+    }
+    Ok(())
+}
+
+/// An LSP `Position`: zero-based line and UTF-16 code unit offset.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// The LSP `DocumentSymbol` shape.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub detail: String,
+    pub kind: u8,
+    pub range: Range,
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// LSP `SymbolKind` values, picked to match what rust-analyzer reports.
+fn symbol_kind(item_type: &str, in_impl: bool) -> Option<u8> {
+    Some(match item_type {
+        "function_item" | "function_signature_item" if in_impl => 6,
+        "function_item" | "function_signature_item" | "macro_definition" => 12,
+        "mod_item" => 2,
+        "foreign_mod_item" => 3,
+        "struct_item" | "union_item" => 23,
+        "enum_item" => 10,
+        "trait_item" => 11,
+        "impl_item" => 19,
+        "const_item" => 14,
+        "static_item" => 13,
+        "type_item" | "associated_type" => 26,
+        _ => return None,
+    })
+}
+
+fn position(source: &str, offset: usize) -> Position {
+    let offset = offset.min(source.len());
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+/// The byte range of the identifier naming `item`, parsed into `root`: the
+/// `name` of most declarations and the type of an impl.
+fn name_range(
+    root: tree_sitter::Node,
+    item: &OutlineItem,
+) -> Option<std::ops::Range<usize>> {
+    let end = item.range.end;
+    let mut node = root.descendant_for_byte_range(end.saturating_sub(1), end)?;
+    while node.kind() != item.item_type || node.end_byte() != end {
+        node = node.parent()?;
+    }
+    let field = match node.kind() {
+        "impl_item" => "type",
+        _ => "name",
+    };
+    Some(node.child_by_field_name(field)?.byte_range())
+}
+
+/// Converts an outline of `source`, parsed into `root`, to
+/// `DocumentSymbol[]`. Declarations LSP has no symbol kind for, like `use`,
+/// are left out along with their children.
+pub fn document_symbols(
+    items: &[OutlineItem],
+    source: &str,
+    root: tree_sitter::Node,
+    in_impl: bool,
+) -> Vec<DocumentSymbol> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let kind = symbol_kind(&item.item_type, in_impl)?;
+            let range = Range {
+                start: position(source, item.range.start),
+                end: position(source, item.range.end),
+            };
+            let selection_range = match name_range(root, item) {
+                Some(name) => Range {
+                    start: position(source, name.start),
+                    end: position(source, name.end),
+                },
+                None => range,
+            };
+            let nested_in_impl =
+                matches!(item.item_type.as_str(), "impl_item" | "trait_item");
+            Some(DocumentSymbol {
+                name: item.name.clone(),
+                detail: item.item_type.clone(),
+                kind,
+                range,
+                selection_range,
+                children: document_symbols(
+                    &item.children,
+                    source,
+                    root,
+                    nested_in_impl,
+                ),
+            })
+        })
+        .collect()
+}
+
+pub fn write_text(
+    out: &mut dyn Write,
+    items: &[OutlineItem],
+    depth: usize,
+) -> std::io::Result<()> {
+    for item in items {
+        write!(
+            out,
+            "{:indent$}{} {} ({}-{})",
+            "",
+            item.item_type,
+            item.name,
+            item.start[0],
+            item.end[0],
+            indent = depth * 4
+        )?;
+        match &item.file {
+            Some(file) => writeln!(out, " from {}", file)?,
+            None => writeln!(out)?,
+        }
+        write_text(out, &item.children, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// `outline [--format text|json|lsp] [--inline-modules] <file>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &["--inline-modules"], &["--format", "--crate"])?;
+    let (items, contents) = match (args.positional(), args.value("--crate")) {
+        ([], Some(path)) => {
+            if args.value("--format") == Some("lsp") {
+                anyhow::bail!("LSP symbols describe one document; drop --crate");
+            }
+            let root = crate_outline(Path::new(path))?;
+            (vec![root], String::new())
+        }
+        ([path], None) => {
+            let contents = read_file(path)?;
+            let mut items = outline(&parse_source(path, &contents)?, &contents);
+            if args.flag("--inline-modules") {
+                if args.value("--format") == Some("lsp") {
+                    anyhow::bail!(
+                        "LSP symbols describe one document; drop --inline-modules"
+                    );
+                }
+                inline_modules(&mut items, &contents, Path::new(path))?;
+            }
+            (items, contents)
+        }
+        _ => anyhow::bail!(
+            "usage: outline [--format text|json|lsp] [--inline-modules] <file>\n       outline [--format text|json] --crate <dir or root file>"
+        ),
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &items, 0)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &items)?;
+            writeln!(out)?;
+        }
+        "lsp" => {
+            let tree = parse_tree(&args.positional()[0], &contents)?;
+            let symbols = document_symbols(&items, &contents, tree.root_node(), false);
+            serde_json::to_writer_pretty(&mut out, &symbols)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown outline format {}", other),
+    }
+    Ok(())
+}
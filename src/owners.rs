@@ -0,0 +1,187 @@
+//! Who owns each file, module, trait and impl, from `git blame`: the share
+//! of its lines each author wrote, for keeping `CODEOWNERS` in step with
+//! who actually works on the code.
+
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::blame::{authors, blame_file, BlameLine};
+use crate::cli::Args;
+use crate::diff::ParsedFile;
+use crate::files::expand;
+use crate::ignore::Filter;
+
+/// The declarations authorship is rolled up to, besides files.
+const CONTAINER_KINDS: &[&str] = &["mod_item", "trait_item", "impl_item"];
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+    pub name: String,
+    pub email: String,
+    pub lines: usize,
+    /// Of the container's lines, rounded to one decimal.
+    pub percent: f64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Ownership {
+    pub file: String,
+    /// The container's qualified name, empty for the file itself.
+    pub container: String,
+    /// `file`, or the kind of the declaration.
+    pub kind: String,
+    /// Its non-blank lines, including those of everything inside it.
+    pub lines: usize,
+    /// Most lines first.
+    pub authors: Vec<Share>,
+}
+
+fn ownership<'a>(
+    file: &str,
+    container: &str,
+    kind: &str,
+    lines: impl IntoIterator<Item = &'a BlameLine>,
+) -> Ownership {
+    let authors = authors(lines);
+    let total: usize = authors.iter().map(|author| author.lines).sum();
+    Ownership {
+        file: file.to_string(),
+        container: container.to_string(),
+        kind: kind.to_string(),
+        lines: total,
+        authors: authors
+            .into_iter()
+            .map(|author| Share {
+                percent: ((author.lines as f64) * 1000.0 / total.max(1) as f64).round()
+                    / 10.0,
+                name: author.name,
+                email: author.email,
+                lines: author.lines,
+            })
+            .collect(),
+    }
+}
+
+/// The ownership of `file` and of each module, trait and impl in it, in
+/// the order they appear. Blank lines aren't counted.
+pub fn owners(file: &str, parsed: &ParsedFile, lines: &[BlameLine]) -> Vec<Ownership> {
+    let lines: Vec<&BlameLine> = lines
+        .iter()
+        .filter(|line| !line.text.trim().is_empty())
+        .collect();
+    let mut out = vec![ownership(file, "", "file", lines.iter().copied())];
+    for decl in &parsed.declarations {
+        if !CONTAINER_KINDS.contains(&decl.kind.as_str()) {
+            continue;
+        }
+        let inside = lines
+            .iter()
+            .copied()
+            .filter(|line| decl.start[0] <= line.line && line.line <= decl.end[0]);
+        out.push(ownership(file, &decl.qualified_name, &decl.kind, inside));
+    }
+    out
+}
+
+fn write_text(out: &mut dyn Write, owners: &[Ownership]) -> std::io::Result<()> {
+    for ownership in owners {
+        let shares: Vec<String> = ownership
+            .authors
+            .iter()
+            .map(|share| format!("{} {}%", share.name, share.percent))
+            .collect();
+        let name = if ownership.container.is_empty() {
+            ownership.file.clone()
+        } else {
+            format!("{} {}", ownership.file, ownership.container)
+        };
+        writeln!(
+            out,
+            "{} ({} lines): {}",
+            name,
+            ownership.lines,
+            shares.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+/// `field` quoted for CSV when it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One row per container and author.
+fn write_csv(out: &mut dyn Write, owners: &[Ownership]) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "file,container,kind,lines,author,email,author_lines,percent"
+    )?;
+    for ownership in owners {
+        for share in &ownership.authors {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                csv_field(&ownership.file),
+                csv_field(&ownership.container),
+                ownership.kind,
+                ownership.lines,
+                csv_field(&share.name),
+                csv_field(&share.email),
+                share.lines,
+                share.percent
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `owners [--format text|json|csv] [--exclude <glob>] [--include <glob>]
+/// [--no-ignore] [--symlinks follow|skip|error] <paths...>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &["--format", "--exclude", "--include", "--symlinks"],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: owners [--format text|json|csv] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+
+    let mut all = vec![];
+    for (path, name) in expand(args.positional(), &Filter::from_args(&args)?)? {
+        let path = path.to_string_lossy();
+        // Files git doesn't track yet have no history to blame.
+        let (lines, contents) = match blame_file(&path, None) {
+            Ok(blamed) => blamed,
+            Err(e) => {
+                eprintln!("warning: skipping {}: {:#}", name, e);
+                continue;
+            }
+        };
+        let parsed = ParsedFile::from_source(&name, contents)?;
+        all.extend(owners(&name, &parsed, &lines));
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &all)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &all)?;
+            writeln!(out)?;
+        }
+        "csv" => write_csv(&mut out, &all)?,
+        other => anyhow::bail!("unknown owners format {}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,1365 @@
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use tree_sitter::Point;
+
+use crate::cli::{setting, switch, Args};
+use crate::doctest;
+use crate::files::relative_name;
+use crate::hash::content_hash;
+use crate::inject::{self, injections, Injections};
+use crate::metadata::{is_outer_attribute, metadata, normalize};
+use crate::model::{
+    is_declaration_kind, CharSpan, Container, LocationSpan, Node, SemanticFile,
+    Terminal, SCHEMA_VERSION,
+};
+use crate::templates::{self, name_templates, NameTemplates};
+use crate::units::{
+    column_unit, convert_columns, convert_ends, convert_spans, rebase, tab_width, Base,
+    Ends, Unit,
+};
+use crate::{msgpack, ndjson};
+
+/// The UTF-8 byte order mark some Windows editors start files with.
+pub const BOM: &str = "\u{feff}";
+
+/// Reads a source file without its byte order mark, if it has one. The mark
+/// isn't part of the source (SemanticMerge's decoder drops it as well), so
+/// spans and columns count from just after it.
+pub fn read_file(path: &str) -> anyhow::Result<String> {
+    decode(std::fs::read(path)?)
+}
+
+/// `bytes` as source text, without its byte order mark. Files that may be
+/// binary are read as bytes and checked with [`is_binary`] first, since
+/// binary data is rarely valid UTF-8.
+pub fn decode(bytes: Vec<u8>) -> anyhow::Result<String> {
+    let contents = String::from_utf8(bytes).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("stream did not contain valid UTF-8: {}", e.utf8_error()),
+        )
+    })?;
+    Ok(match contents.strip_prefix(BOM) {
+        Some(rest) => rest.to_string(),
+        None => contents,
+    })
+}
+
+/// Whether the file at `path` starts with a byte order mark, which
+/// [`read_file`] leaves out.
+pub fn has_bom(path: &str) -> bool {
+    let mut start = [0; 3];
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut start))
+        .is_ok_and(|()| start == BOM.as_bytes())
+}
+
+/// The line endings in a source.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LineEndings {
+    pub lf: usize,
+    pub crlf: usize,
+    /// The first line (1-based) that ends differently from the first line.
+    pub first_mismatch: Option<usize>,
+}
+
+impl LineEndings {
+    pub fn of(src: &str) -> LineEndings {
+        let mut endings = LineEndings::default();
+        let mut first_crlf = None;
+        for (line, (i, _)) in src.match_indices('\n').enumerate() {
+            let crlf = src[..i].ends_with('\r');
+            if crlf {
+                endings.crlf += 1;
+            } else {
+                endings.lf += 1;
+            }
+            match first_crlf {
+                None => first_crlf = Some(crlf),
+                Some(first) if first != crlf && endings.first_mismatch.is_none() => {
+                    endings.first_mismatch = Some(line + 1);
+                }
+                Some(_) => {}
+            }
+        }
+        endings
+    }
+
+    pub fn is_mixed(&self) -> bool {
+        self.lf > 0 && self.crlf > 0
+    }
+}
+
+pub fn parse_tree(
+    name: &str,
+    file_contents: &str,
+) -> anyhow::Result<tree_sitter::Tree> {
+    // tree-sitter keeps offsets in 32 bits and would wrap around past them.
+    if file_contents.len() > u32::MAX as usize {
+        anyhow::bail!("{} is too large to parse (over 4 GiB)", name);
+    }
+    rust_parser()?
+        .parse(file_contents, None)
+        .ok_or_else(|| anyhow::anyhow!("tree-sitter failed to parse {}", name))
+}
+
+/// A parser for the grammar the crate was built with.
+pub fn rust_parser() -> anyhow::Result<tree_sitter::Parser> {
+    let mut parser = tree_sitter::Parser::new();
+    #[cfg(feature = "grammar-current")]
+    parser.set_language(&tree_sitter_rust::LANGUAGE.into())?;
+    #[cfg(all(feature = "grammar-0-19", not(feature = "grammar-current")))]
+    parser.set_language(tree_sitter_rust_0_19::language())?;
+    Ok(parser)
+}
+
+/// What to make of functions and closures defined inside a function.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nested {
+    /// One terminal each, merged whole as part of the function around them.
+    #[default]
+    Fold,
+    /// Containers like any other function, named with the path of the
+    /// functions around them, like `outer::inner` or `outer::{closure}`.
+    Nest,
+}
+
+impl Nested {
+    pub fn parse(name: &str) -> anyhow::Result<Nested> {
+        match name {
+            "fold" => Ok(Nested::Fold),
+            "nest" => Ok(Nested::Nest),
+            other => anyhow::bail!("unknown nested function policy {}", other),
+        }
+    }
+}
+
+/// What to make of comments that aren't doc comments on an item.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comments {
+    /// One terminal each, named after its kind.
+    #[default]
+    Separate,
+    /// One terminal each, named after its first words, like
+    /// `// TODO: check the bounds…`, so they can be told apart.
+    Named,
+    /// Part of the node after them, like attributes.
+    Following,
+    /// Part of the node before them, like the comment at the end of a line.
+    Preceding,
+}
+
+impl Comments {
+    pub fn parse(name: &str) -> anyhow::Result<Comments> {
+        match name {
+            "separate" => Ok(Comments::Separate),
+            "named" => Ok(Comments::Named),
+            "following" => Ok(Comments::Following),
+            "preceding" => Ok(Comments::Preceding),
+            other => anyhow::bail!("unknown comment policy {}", other),
+        }
+    }
+}
+
+/// What to make of files that look binary.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binary {
+    /// Fail, so the shell answers `KO`.
+    #[default]
+    Refuse,
+    /// A tree with one opaque `binary` terminal covering the whole file.
+    Opaque,
+}
+
+impl Binary {
+    pub fn parse(name: &str) -> anyhow::Result<Binary> {
+        match name {
+            "refuse" => Ok(Binary::Refuse),
+            "opaque" => Ok(Binary::Opaque),
+            other => anyhow::bail!("unknown binary file policy {}", other),
+        }
+    }
+}
+
+/// Optional extras to include in the tree; all off by default, which gives
+/// the plain SemanticMerge format.
+#[derive(Default, Clone, Debug)]
+pub struct ParseOptions {
+    /// Add a content hash of each node's own text.
+    pub hashes: bool,
+    /// Add parameter and return types to functions.
+    pub signatures: bool,
+    /// Add visibility and `async`/`unsafe`/`const`/`extern` qualifiers.
+    pub qualifiers: bool,
+    /// Add the text of each item's doc comment.
+    pub docs: bool,
+    /// Add the first line of each declaration's source.
+    pub excerpts: bool,
+    /// Flag test functions and modules, and the declarations inside them.
+    pub tests: bool,
+    /// Add the cyclomatic complexity and nesting depth of each function.
+    pub complexity: bool,
+    /// What character spans count; bytes unless asked otherwise.
+    pub offsets: Unit,
+    /// What columns count; bytes unless asked otherwise.
+    pub columns: Unit,
+    /// Expand tabs to the next multiple of this many columns.
+    pub tab_width: Option<usize>,
+    /// What lines and columns count from; SemanticMerge's unless asked
+    /// otherwise.
+    pub base: Base,
+    /// Whether span ends are exclusive, as [`Node::byte_range`] expects, or
+    /// inclusive.
+    pub ends: Ends,
+    /// Name methods in impls after their impl, like `<Foo as Display>::fmt`,
+    /// so the `fmt`s of different impls in one file don't match each other.
+    pub qualify_methods: bool,
+    /// Whether functions and closures inside functions are folded into
+    /// terminals or nested as containers.
+    pub nested: Nested,
+    /// Macros whose contents are parsed as another language.
+    pub injections: Injections,
+    /// Parse the Rust blocks in doc comments as children of their item.
+    pub doctests: bool,
+    /// Number the declarations that share a kind and name with an earlier
+    /// sibling, like a second `impl Foo`, so they can't be paired up out of
+    /// order between revisions.
+    pub ordinals: bool,
+    /// Fold the containers this many levels down into terminals covering
+    /// all of their text, the file's children being at level 1.
+    pub max_depth: Option<usize>,
+    /// How declarations of each kind are named, in place of the built-in
+    /// names.
+    pub name_templates: NameTemplates,
+    /// tree-sitter kinds that never become nodes, like `block` or
+    /// `token_tree`; their text goes to the parent's header or footer, or
+    /// to the sibling after them.
+    pub skip_kinds: Vec<String>,
+    /// Leave out nodes shorter than this many bytes, like [`skip_kinds`]
+    /// does, unless they are declarations, fields or variants.
+    ///
+    /// [`skip_kinds`]: ParseOptions::skip_kinds
+    pub min_bytes: usize,
+    /// The same, for nodes spanning fewer than this many lines.
+    pub min_lines: usize,
+    /// Collapse containers whose only child covers the same text, like an
+    /// `expression_statement` around a `match_expression`, into that child.
+    pub flatten: bool,
+    /// Where comments other than doc comments go.
+    pub comments: Comments,
+    /// What to make of files that look binary.
+    pub binary: Binary,
+}
+
+/// Reads a minimum node size given for `option`.
+pub fn parse_size(option: &str, value: &str) -> anyhow::Result<usize> {
+    value
+        .parse()
+        .map_err(|e| anyhow::anyhow!("{} must be a number: {}", option, e))
+}
+
+/// Reads a comma-separated list of node kinds, like `block,token_tree`.
+pub fn parse_kinds(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads a maximum depth given for `option`, which must be at least 1.
+pub fn parse_depth(option: &str, value: &str) -> anyhow::Result<usize> {
+    match value.parse()? {
+        0 => anyhow::bail!("{} must be at least 1", option),
+        depth => Ok(depth),
+    }
+}
+
+pub fn parse_source(name: &str, file_contents: &str) -> anyhow::Result<SemanticFile> {
+    parse_source_with(name, file_contents, &ParseOptions::default())
+}
+
+pub fn parse_source_with(
+    name: &str,
+    file_contents: &str,
+    options: &ParseOptions,
+) -> anyhow::Result<SemanticFile> {
+    if let Some(file) = binary_file(name, file_contents.as_bytes(), options)? {
+        return Ok(file);
+    }
+    let tree = parse_tree(name, file_contents)?;
+    build_file(name, file_contents, &tree, options)
+}
+
+/// Whether `file_contents` look like binary data rather than source: they
+/// have a NUL byte, or control characters make up more than a tenth of
+/// their first 8 KiB, where text has none but tabs, line breaks and form
+/// feeds.
+pub fn is_binary(file_contents: &[u8]) -> bool {
+    if file_contents.contains(&0) {
+        return true;
+    }
+    let start = &file_contents[..file_contents.len().min(8192)];
+    let control = start
+        .iter()
+        .filter(|&&b| {
+            b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c)
+        })
+        .count();
+    control * 10 > start.len()
+}
+
+/// What to make of a file that [`is_binary`], before tree-sitter sees it:
+/// an error, or with [`Binary::Opaque`] a tree holding nothing but one
+/// `binary` terminal. `None` for source. Positions count bytes, which
+/// is all there is to count when the contents aren't text.
+pub fn binary_file(
+    name: &str,
+    file_contents: &[u8],
+    options: &ParseOptions,
+) -> anyhow::Result<Option<SemanticFile>> {
+    if !is_binary(file_contents) {
+        return Ok(None);
+    }
+    if options.binary == Binary::Refuse {
+        anyhow::bail!("{} looks binary rather than Rust source", name);
+    }
+    let location_span = file_location(file_contents);
+    let mut file = SemanticFile {
+        schema_version: SCHEMA_VERSION,
+        item_type: "file".to_string(),
+        name: name.to_string(),
+        location_span: LocationSpan {
+            start: location_span.start,
+            end: location_span.end,
+        },
+        footer_span: CharSpan { span: [0, -1] },
+        parsing_errors_detected: false,
+        children: vec![Node::Terminal(Terminal {
+            item_type: "binary".to_string(),
+            name: "binary".to_string(),
+            location_span,
+            span: CharSpan {
+                span: [0, file_contents.len() as i64],
+            },
+            hash: options.hashes.then(|| content_hash(file_contents)),
+            metadata: None,
+        })],
+        parsing_error: None,
+    };
+    match std::str::from_utf8(file_contents) {
+        Ok(text) => convert(&mut file, text, options),
+        // Characters and UTF-16 units mean nothing for bytes that aren't
+        // text, so columns and offsets stay in bytes.
+        Err(_) => {
+            convert_ends(&mut file, options.ends);
+            rebase(&mut file, options.base);
+        }
+    }
+    Ok(Some(file))
+}
+
+/// Where a file of `file_contents` starts and ends. It ends after its last
+/// byte, so a trailing newline puts the end at the start of the (empty) line
+/// after it. An empty file still has one line.
+fn file_location(file_contents: &[u8]) -> LocationSpan {
+    let line_count = file_contents.iter().filter(|&&b| b == b'\n').count() + 1;
+    let last_line_start = file_contents
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    let last_pos = file_contents.len() - last_line_start;
+    LocationSpan {
+        start: [1, 0],
+        end: [line_count as i64, last_pos as i64],
+    }
+}
+
+pub fn build_file(
+    name: &str,
+    file_contents: &str,
+    tree: &tree_sitter::Tree,
+    options: &ParseOptions,
+) -> anyhow::Result<SemanticFile> {
+    let root = tree.root_node();
+    let (children, end) = walk_children(
+        &semantic_children(&root, file_contents, options),
+        file_contents,
+        options,
+        0,
+    );
+    let footer_span = if end < file_contents.len() {
+        CharSpan {
+            span: [end as i64, file_contents.len() as i64],
+        }
+    } else {
+        CharSpan { span: [0, -1] }
+    };
+    let mut file = SemanticFile {
+        schema_version: SCHEMA_VERSION,
+        item_type: "file".to_string(),
+        name: name.to_string(),
+        location_span: file_location(file_contents.as_bytes()),
+        footer_span,
+        parsing_errors_detected: root.has_error(),
+        children,
+        parsing_error: None,
+    };
+    if let Some(max_depth) = options.max_depth {
+        fold_below(&mut file.children, max_depth);
+    }
+    if options.flatten {
+        flatten_chains(&mut file.children);
+    }
+    if options.ordinals {
+        number_duplicates(&mut file.children);
+    }
+    convert(&mut file, file_contents, options);
+    Ok(file)
+}
+
+/// Converts the byte offsets, exclusive ends and bases the tree is built
+/// with to the ones `options` asks for.
+fn convert(file: &mut SemanticFile, file_contents: &str, options: &ParseOptions) {
+    convert_columns(file, file_contents, options.columns, options.tab_width);
+    convert_spans(file, file_contents, options.offsets);
+    convert_ends(file, options.ends);
+    rebase(file, options.base);
+}
+
+/// Replaces the containers at level `depth` with terminals spanning all of
+/// their text, so nothing deeper is emitted; `nodes` are at level 1.
+fn fold_below(nodes: &mut [Node], depth: usize) {
+    for node in nodes {
+        let range = node.byte_range();
+        let Node::Container(c) = node else {
+            continue;
+        };
+        if depth > 1 {
+            fold_below(&mut c.children, depth - 1);
+            continue;
+        }
+        *node = Node::Terminal(Terminal {
+            item_type: std::mem::take(&mut c.item_type),
+            name: std::mem::take(&mut c.name),
+            location_span: LocationSpan {
+                start: c.location_span.start,
+                end: c.location_span.end,
+            },
+            span: CharSpan {
+                span: [range.start as i64, range.end as i64],
+            },
+            hash: c.hash.take(),
+            metadata: c.metadata.take(),
+        });
+    }
+}
+
+/// Replaces each container among `nodes` (and below them) whose only child
+/// has the same text with that child, the more specific of the two, taking
+/// over the container's span. The `uses` and `doctest` groups are kept, so
+/// one with a single member still lines up with one that has more.
+fn flatten_chains(nodes: &mut [Node]) {
+    fn is_wrapper(c: &Container) -> bool {
+        let [child] = &c.children[..] else {
+            return false;
+        };
+        let (outer, inner) = (&c.location_span, child.location_span());
+        !matches!(c.item_type.as_str(), "uses" | "doctest")
+            && outer.start == inner.start
+            && outer.end == inner.end
+    }
+
+    for node in nodes {
+        while let Node::Container(c) = node {
+            if !is_wrapper(c) {
+                break;
+            }
+            let start = c.header_span.span[0];
+            let mut child = c.children.pop().expect("a wrapper has a child");
+            let (span_start, hash, metadata) = match &mut child {
+                Node::Container(inner) => (
+                    &mut inner.header_span.span[0],
+                    &mut inner.hash,
+                    &mut inner.metadata,
+                ),
+                Node::Terminal(inner) => (
+                    &mut inner.span.span[0],
+                    &mut inner.hash,
+                    &mut inner.metadata,
+                ),
+            };
+            *span_start = start;
+            *hash = hash.take().or(c.hash.take());
+            *metadata = metadata.take().or(c.metadata.take());
+            *node = child;
+        }
+        if let Node::Container(c) = node {
+            flatten_chains(&mut c.children);
+        }
+    }
+}
+
+/// Appends ` (2)`, ` (3)` and so on to the names of the declarations (and
+/// `uses` and `doctest` groups) among `nodes` that share their kind and name
+/// with an earlier sibling, and does the same among the children of each.
+/// The first keeps its name, so adding a duplicate after it renames nothing.
+fn number_duplicates(nodes: &mut [Node]) {
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for node in nodes {
+        let (kind, name, children) = match node {
+            Node::Container(c) => (&c.item_type, &mut c.name, &mut c.children[..]),
+            Node::Terminal(t) => (&t.item_type, &mut t.name, &mut [][..]),
+        };
+        if is_declaration_kind(kind) || matches!(kind.as_str(), "uses" | "doctest") {
+            let count = seen.entry((kind.clone(), name.clone())).or_default();
+            *count += 1;
+            if *count > 1 {
+                *name = format!("{} ({})", name, count);
+            }
+        }
+        number_duplicates(children);
+    }
+}
+
+/// Builds the nodes for `nodes`, siblings in source order, the first starting at `start`, each
+/// later one where its predecessor ended, so that the whitespace between
+/// siblings belongs to the node after it. Outer attributes and doc comments
+/// are part of the node they precede, so a merge can't split an item from
+/// its `#[derive]`s; only a run with nothing after it stands on its own.
+/// Returns the children and where the last one ended. tree-sitter yields
+/// children in source order, so children always come out sorted by offset.
+fn walk_children(
+    nodes: &[tree_sitter::Node],
+    file_contents: &str,
+    options: &ParseOptions,
+    start: usize,
+) -> (Vec<Node>, usize) {
+    let attaches = |child: tree_sitter::Node| {
+        is_outer_attribute(child, file_contents)
+            || (options.comments == Comments::Following && is_comment(&child))
+    };
+    let last_item = nodes.iter().rposition(|child| !attaches(*child));
+    let mut children: Vec<Node> = vec![];
+    let mut pos = start;
+    let mut leading = None;
+    for (i, child_node) in nodes.iter().enumerate() {
+        if last_item.is_some_and(|last| i < last) && attaches(*child_node) {
+            leading = leading.or(Some(*child_node));
+            continue;
+        }
+        if options.comments == Comments::Preceding
+            && is_comment(child_node)
+            && leading.is_none()
+        {
+            if let Some(previous) = children.last_mut() {
+                let (end, end_position) = node_end(child_node, file_contents);
+                extend_to(previous, end, end_position);
+                pos = end;
+                continue;
+            }
+        }
+        children.push(walk_tree(child_node, leading, file_contents, options, pos));
+        pos = node_end(child_node, file_contents).0;
+        leading = None;
+    }
+    (group_uses(children, file_contents, options), pos)
+}
+
+fn is_comment(node: &tree_sitter::Node) -> bool {
+    matches!(node.kind(), "line_comment" | "block_comment")
+}
+
+/// Makes `node` end at `end`, the text up to there joining its footer if
+/// it is a container.
+fn extend_to(node: &mut Node, end: usize, end_position: Point) {
+    let range = node.byte_range();
+    match node {
+        Node::Container(c) => {
+            let start = if c.footer_span.is_empty() {
+                range.end as i64
+            } else {
+                c.footer_span.span[0]
+            };
+            c.footer_span = CharSpan {
+                span: [start, end as i64],
+            };
+            c.location_span.end = convert_point(end_position);
+        }
+        Node::Terminal(t) => {
+            t.span.span[1] = end as i64;
+            t.location_span.end = convert_point(end_position);
+        }
+    }
+}
+
+/// The name of a comment under [`Comments::Named`]: its marker and first
+/// few words, with `…` when there are more.
+fn comment_name(text: &str) -> String {
+    const WORDS: usize = 6;
+    let marker = if text.starts_with("/*") { "/*" } else { "//" };
+    let words: Vec<&str> = text
+        .trim_start_matches(['/', '*', '!'])
+        .trim_end_matches(['*', '/'])
+        .split_whitespace()
+        .collect();
+    if words.is_empty() {
+        return marker.to_string();
+    }
+    let mut name = format!("{} {}", marker, words[..words.len().min(WORDS)].join(" "));
+    if words.len() > WORDS {
+        name.push('…');
+    }
+    name
+}
+
+/// Wraps `run`, nodes built from one source in order, in a container of
+/// `kind` with no text of its own: its header is empty and its children
+/// keep their spans.
+fn group(
+    kind: &str,
+    run: Vec<Node>,
+    file_contents: &str,
+    options: &ParseOptions,
+) -> Node {
+    let (first, last) = (&run[0], &run[run.len() - 1]);
+    let start = first.byte_range().start as i64;
+    let text = file_contents
+        .get(first.text_range(file_contents).start..last.byte_range().end)
+        .unwrap_or("");
+    Node::Container(Container {
+        item_type: kind.to_string(),
+        name: kind.to_string(),
+        location_span: LocationSpan {
+            start: first.location_span().start,
+            end: last.location_span().end,
+        },
+        header_span: CharSpan {
+            span: [start, start],
+        },
+        footer_span: CharSpan { span: [0, -1] },
+        hash: options.hashes.then(|| content_hash(text)),
+        metadata: None,
+        children: run,
+    })
+}
+
+/// Wraps each run of adjacent `use` declarations among `children` in a
+/// `uses` container, so that imports added on both sides of a merge line up
+/// one by one instead of conflicting as a block.
+fn group_uses(
+    children: Vec<Node>,
+    file_contents: &str,
+    options: &ParseOptions,
+) -> Vec<Node> {
+    let mut grouped = vec![];
+    let mut run = vec![];
+    for child in children {
+        if child.kind() == "use_declaration" {
+            run.push(child);
+            continue;
+        }
+        if !run.is_empty() {
+            grouped.push(group(
+                "uses",
+                std::mem::take(&mut run),
+                file_contents,
+                options,
+            ));
+        }
+        grouped.push(child);
+    }
+    if !run.is_empty() {
+        grouped.push(group("uses", run, file_contents, options));
+    }
+    grouped
+}
+
+/// The named children of `node` that become nodes of their own. A macro's
+/// name already names its container, so it stays in the header, and the
+/// kinds `options` skips and nodes smaller than it asks for stay in the
+/// text around them. So do nodes with no text: tree-sitter makes up empty
+/// ones for what is missing near syntax errors, like the name after
+/// `x.`, which would come out as spans SemanticMerge can't handle.
+fn semantic_children<'t>(
+    node: &tree_sitter::Node<'t>,
+    file_contents: &str,
+    options: &ParseOptions,
+) -> Vec<tree_sitter::Node<'t>> {
+    let name = match node.kind() {
+        "macro_definition" => node.child_by_field_name("name"),
+        _ => None,
+    };
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|child| Some(*child) != name)
+        .filter(|child| !options.skip_kinds.iter().any(|kind| kind == child.kind()))
+        .filter(|child| is_big_enough(child, options))
+        .filter(|child| has_text(child, file_contents))
+        .collect()
+}
+
+fn has_text(node: &tree_sitter::Node, file_contents: &str) -> bool {
+    !node.is_missing()
+        && file_contents
+            .get(node.start_byte()..node_end(node, file_contents).0)
+            .is_some_and(|text| !text.trim().is_empty())
+}
+
+/// Whether `node` is at least as big as `options` asks nodes to be. What
+/// merges match up, declarations and the fields and variants of types, is
+/// kept whatever its size.
+fn is_big_enough(node: &tree_sitter::Node, options: &ParseOptions) -> bool {
+    let kind = node.kind();
+    is_declaration_kind(kind)
+        || matches!(kind, "field_declaration" | "enum_variant")
+        || (node.byte_range().len() >= options.min_bytes
+            && node.end_position().row - node.start_position().row + 1
+                >= options.min_lines)
+}
+
+/// Where `node` ends, leaving out a line ending at its end. Newer grammars
+/// end doc comments after their `\n`, and tree-sitter only breaks lines at
+/// `\n`, so a line comment on a CRLF line would otherwise take the `\r` with
+/// it and end a column later than the same comment with LF endings. Trimmed,
+/// the whole line ending goes to the gap before the next node.
+fn node_end(node: &tree_sitter::Node, file_contents: &str) -> (usize, Point) {
+    let bytes = file_contents.as_bytes();
+    let (mut end, mut position) = (node.end_byte(), node.end_position());
+    if end > node.start_byte() && bytes[end - 1] == b'\n' {
+        end -= 1;
+        let line_start = file_contents[..end].rfind('\n').map_or(0, |i| i + 1);
+        position = Point {
+            row: position.row - 1,
+            column: end - line_start,
+        };
+    }
+    if end > node.start_byte()
+        && bytes.get(end - 1) == Some(&b'\r')
+        && bytes.get(end) == Some(&b'\n')
+    {
+        end -= 1;
+        position.column -= 1;
+    }
+    (end, position)
+}
+
+/// Builds the node for `node`, with its span starting at `start` and its
+/// location at `leading`, the first of the attributes before it, if any.
+fn walk_tree(
+    node: &tree_sitter::Node,
+    leading: Option<tree_sitter::Node>,
+    file_contents: &str,
+    options: &ParseOptions,
+    start: usize,
+) -> Node {
+    let kind = node.kind();
+    let (end, end_position) = node_end(node, file_contents);
+    let first = leading.unwrap_or(*node);
+    let text = file_contents.get(first.start_byte()..end).unwrap_or("");
+    let hash = options.hashes.then(|| content_hash(text));
+    let metadata = metadata(node, file_contents, options);
+    let mut contents = file_contents
+        .get(node.start_byte()..end)
+        .unwrap_or("")
+        .to_string();
+    let name = match kind {
+        "macro_definition" | "mod_item" | "struct_item" | "enum_item"
+        | "union_item" | "trait_item" | "field_declaration" | "enum_variant"
+        | "const_item" | "static_item" | "type_item" | "associated_type" => node
+            .child_by_field_name("name")
+            .and_then(|name| file_contents.get(name.byte_range()))
+            .unwrap_or(kind),
+        // An element of embedded HTML is known by its tag.
+        "element" => node
+            .named_child(0)
+            .and_then(|tag| tag.named_child(0))
+            .and_then(|name| file_contents.get(name.byte_range()))
+            .unwrap_or(kind),
+        // An import is known by its path, like `std::io::{self, Write}`.
+        "use_declaration" => {
+            contents = node
+                .child_by_field_name("argument")
+                .and_then(|argument| file_contents.get(argument.byte_range()))
+                .map(|argument| {
+                    // The same import wrapped or not, or with a trailing
+                    // comma in its list, has the same name.
+                    normalize(argument)
+                        .replace("{ ", "{")
+                        .replace(" }", "}")
+                        .replace(",}", "}")
+                })
+                .unwrap_or_default();
+            &contents
+        }
+        // An impl is known by what it implements, so impls of different
+        // types never pair up, however they are ordered.
+        "impl_item" => {
+            contents = impl_name(node, file_contents);
+            &contents
+        }
+        // An extern block is known by its ABI, `extern "C"` when unwritten.
+        "foreign_mod_item" => {
+            let mut cursor = node.walk();
+            let abi = node
+                .children(&mut cursor)
+                .find(|child| child.kind() == "extern_modifier")
+                .and_then(|modifier| modifier.named_child(0))
+                .and_then(|abi| file_contents.get(abi.byte_range()))
+                .unwrap_or("\"C\"");
+            contents = format!("extern {}", abi);
+            &contents
+        }
+        "line_comment" | "block_comment" if options.comments == Comments::Named => {
+            contents = comment_name(&contents);
+            &contents
+        }
+        _ if options.qualify_methods && impl_of(node).is_some() => {
+            contents = qualified_method(node, file_contents).unwrap_or_default();
+            &contents
+        }
+        _ if options.nested == Nested::Nest && is_nested_function(node) => {
+            contents = nested_path(node, file_contents);
+            &contents
+        }
+        // A function is known by its name alone, so gaining or losing
+        // `async`, `unsafe`, `const` or `extern` still matches it up.
+        _ if is_foreign_item(node)
+            || matches!(kind, "function_item" | "function_signature_item") =>
+        {
+            node.child_by_field_name("name")
+                .and_then(|name| file_contents.get(name.byte_range()))
+                .unwrap_or(kind)
+        }
+        // A rule arm is known by its matcher, like `($x:expr)`.
+        "macro_rule" => {
+            contents = node
+                .child_by_field_name("left")
+                .and_then(|left| file_contents.get(left.byte_range()))
+                .map(|left| left.split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default();
+            &contents
+        }
+        // Attributes were `meta_item`s before tree-sitter-rust 0.20.
+        _ if kind.contains("identifier")
+            || kind.contains("item")
+            || kind == "attribute" =>
+        {
+            contents = contents
+                .replace(['{', '}', '(', ')', ':', '#', '[', ']'], " ")
+                .replace("fn", " ")
+                .replace("struct", " ")
+                .replace("enum", " ")
+                .replace("pub", " ");
+            contents.split_whitespace().next().unwrap_or(kind)
+        }
+        _ => kind,
+    };
+    let name = match options.name_templates.get(kind) {
+        Some(template) => templates::render(template, node, file_contents, name),
+        None => name.to_string(),
+    };
+    // Items gated on different cfgs, like a `#[cfg(unix)] fn open` and a
+    // `#[cfg(windows)] fn open`, are different declarations.
+    let cfgs = cfg_attributes(leading, node, file_contents);
+    let name = if cfgs.is_empty() {
+        name
+    } else {
+        format!("{} {}", name, cfgs.join(" "))
+    };
+    let location_span = LocationSpan {
+        start: convert_point(first.start_position()),
+        end: convert_point(end_position),
+    };
+
+    // Newer grammars split doc comments into marker and text nodes; a
+    // comment is still one terminal, whichever grammar parsed it. A macro
+    // rule's token trees mean nothing until the macro is expanded, so each
+    // rule arm is one terminal too, as is each import. Struct fields and enum
+    // variants are the smallest parts of a type worth merging on their own,
+    // and constants, statics and type aliases are merged whole, as are the
+    // functions and closures inside a function unless asked to nest them.
+    let semantic_children = semantic_children(node, file_contents, options);
+    if semantic_children.is_empty()
+        || (options.nested == Nested::Fold && is_nested_function(node))
+        || matches!(
+            kind,
+            "line_comment"
+                | "block_comment"
+                | "macro_rule"
+                | "use_declaration"
+                | "field_declaration"
+                | "enum_variant"
+                | "const_item"
+                | "static_item"
+                | "type_item"
+                | "associated_type"
+        )
+    {
+        return Node::Terminal(Terminal {
+            item_type: kind.to_string(),
+            name,
+            location_span,
+            span: CharSpan {
+                span: [start as i64, end as i64],
+            },
+            hash,
+            metadata,
+        });
+    }
+
+    // A macro whose contents are in another language has their structure
+    // in place of its token tree.
+    let injected = match kind {
+        "macro_invocation" if !options.injections.is_empty() => {
+            inject::parse(node, file_contents, &options.injections)
+                .filter(|tree| tree.root_node().named_child_count() > 0)
+        }
+        _ => None,
+    };
+    let child_nodes = match &injected {
+        Some(tree) => {
+            let mut nodes: Vec<_> = semantic_children
+                .into_iter()
+                .filter(|child| child.kind() != "token_tree")
+                .collect();
+            nodes.extend(self::semantic_children(
+                &tree.root_node(),
+                file_contents,
+                options,
+            ));
+            nodes
+        }
+        None => semantic_children,
+    };
+    // The examples in an item's doc comment come before its own children,
+    // each block in a `doctest` container.
+    let doctests = if options.doctests {
+        doctest::parse(leading, node, file_contents)
+    } else {
+        vec![]
+    };
+    let first_child_start = doctests
+        .first()
+        .map_or(child_nodes[0].start_byte(), |tree| {
+            tree.root_node().start_byte()
+        });
+    let mut children = vec![];
+    let mut pos = first_child_start;
+    for tree in &doctests {
+        let (block, block_end) = walk_children(
+            &self::semantic_children(&tree.root_node(), file_contents, options),
+            file_contents,
+            options,
+            pos,
+        );
+        children.push(group("doctest", block, file_contents, options));
+        pos = block_end;
+    }
+    let (own, children_end) = walk_children(&child_nodes, file_contents, options, pos);
+    children.extend(own);
+    let footer_span = if children_end < end {
+        CharSpan {
+            span: [children_end as i64, end as i64],
+        }
+    } else {
+        CharSpan { span: [0, -1] }
+    };
+    Node::Container(Container {
+        item_type: kind.to_string(),
+        name,
+        location_span,
+        header_span: CharSpan {
+            span: [start as i64, first_child_start as i64],
+        },
+        footer_span,
+        children,
+        hash,
+        metadata,
+    })
+}
+
+/// Whether `node` is a function, static or type declared in an extern
+/// block.
+fn is_foreign_item(node: &tree_sitter::Node) -> bool {
+    node.parent()
+        .filter(|list| list.kind() == "declaration_list")
+        .and_then(|list| list.parent())
+        .is_some_and(|block| block.kind() == "foreign_mod_item")
+}
+
+/// Whether `node` is a function or closure inside another one.
+fn is_nested_function(node: &tree_sitter::Node) -> bool {
+    matches!(node.kind(), "function_item" | "closure_expression")
+        && std::iter::successors(node.parent(), |n| n.parent())
+            .any(|n| matches!(n.kind(), "function_item" | "closure_expression"))
+}
+
+/// `node`'s name prefixed with those of the functions around it, with
+/// `{closure}` standing for closures, as in `outer::{closure}::inner`.
+fn nested_path(node: &tree_sitter::Node, src: &str) -> String {
+    let mut path: Vec<&str> = std::iter::successors(Some(*node), |n| n.parent())
+        .filter_map(|n| match n.kind() {
+            "function_item" => n
+                .child_by_field_name("name")
+                .and_then(|name| src.get(name.byte_range())),
+            "closure_expression" => Some("{closure}"),
+            _ => None,
+        })
+        .collect();
+    path.reverse();
+    path.join("::")
+}
+
+/// The impl `node` is a method of, if it is one.
+fn impl_of<'t>(node: &tree_sitter::Node<'t>) -> Option<tree_sitter::Node<'t>> {
+    if node.kind() != "function_item" {
+        return None;
+    }
+    node.parent()
+        .filter(|list| list.kind() == "declaration_list")
+        .and_then(|list| list.parent())
+        .filter(|parent| parent.kind() == "impl_item")
+}
+
+/// The name of an impl: `impl Display for Foo<T>` for a trait impl, `impl
+/// Foo<T>` for an inherent one, with whitespace normalized.
+pub fn impl_name(node: &tree_sitter::Node, src: &str) -> String {
+    let text = |field| {
+        node.child_by_field_name(field)
+            .and_then(|n| src.get(n.byte_range()))
+            .map(normalize)
+    };
+    match (text("trait"), text("type")) {
+        (Some(tr), Some(ty)) => format!("impl {} for {}", tr, ty),
+        (None, Some(ty)) => format!("impl {}", ty),
+        _ => "impl".to_string(),
+    }
+}
+
+/// A method's name qualified with its impl: `<Foo as Display>::fmt` in a
+/// trait impl, `Foo::new` in an inherent one.
+fn qualified_method(node: &tree_sitter::Node, src: &str) -> Option<String> {
+    let impl_item = impl_of(node)?;
+    let text = |field| {
+        impl_item
+            .child_by_field_name(field)
+            .and_then(|n| src.get(n.byte_range()))
+            .map(normalize)
+    };
+    let name = src.get(node.child_by_field_name("name")?.byte_range())?;
+    let ty = text("type")?;
+    Some(match text("trait") {
+        Some(trait_name) => format!("<{} as {}>::{}", ty, trait_name, name),
+        None => format!("{}::{}", ty, name),
+    })
+}
+
+/// The `#[cfg(...)]` attributes from `leading` up to `node`, with their
+/// whitespace normalized.
+fn cfg_attributes(
+    leading: Option<tree_sitter::Node>,
+    node: &tree_sitter::Node,
+    src: &str,
+) -> Vec<String> {
+    let mut cfgs = vec![];
+    let mut attribute = leading;
+    while let Some(current) = attribute.filter(|a| a != node) {
+        let text = normalize(src.get(current.byte_range()).unwrap_or(""));
+        let is_cfg = current.kind() == "attribute_item"
+            && text
+                .strip_prefix("#[")
+                .and_then(|inner| inner.trim_start().strip_prefix("cfg"))
+                .is_some_and(|rest| rest.trim_start().starts_with('('));
+        if is_cfg {
+            cfgs.push(text);
+        }
+        attribute = current.next_named_sibling();
+    }
+    cfgs
+}
+
+fn convert_point(p: Point) -> [i64; 2] {
+    [p.row as i64 + 1, p.column as i64]
+}
+
+/// Where tree-sitter had to recover from invalid syntax.
+#[derive(Serialize, Debug)]
+pub struct SyntaxError {
+    /// 1-based line and 0-based column, like node locations.
+    pub start: [i64; 2],
+    pub end: [i64; 2],
+    pub message: String,
+}
+
+/// The outermost `ERROR` and `MISSING` nodes in `tree`, in source order.
+pub fn syntax_errors(
+    tree: &tree_sitter::Tree,
+    file_contents: &str,
+) -> Vec<SyntaxError> {
+    fn visit(node: tree_sitter::Node, file_contents: &str, out: &mut Vec<SyntaxError>) {
+        if !node.has_error() {
+            return;
+        }
+        let message = if node.is_missing() {
+            Some(format!("missing `{}`", node.kind()))
+        } else if node.is_error() {
+            let text = node.utf8_text(file_contents.as_bytes()).unwrap_or("");
+            let first_line = text.lines().next().unwrap_or("").trim();
+            let excerpt: String = first_line.chars().take(40).collect();
+            Some(format!("unexpected `{}`", excerpt))
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            out.push(SyntaxError {
+                start: convert_point(node.start_position()),
+                end: convert_point(node.end_position()),
+                message,
+            });
+            return;
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            visit(child, file_contents, out);
+        }
+    }
+
+    let mut out = vec![];
+    visit(tree.root_node(), file_contents, &mut out);
+    out
+}
+
+/// tree-sitter's concrete syntax tree for `tree` as an S-expression, one
+/// named node per line, indented by depth and prefixed with its field name.
+/// Positions use the same 1-based lines and 0-based columns as node
+/// locations, so they can be compared directly with the emitted tree.
+pub fn dump_cst(tree: &tree_sitter::Tree) -> String {
+    let mut out = String::new();
+    let mut cursor = tree.walk();
+    let mut depth = 0;
+    let shown = |node: &tree_sitter::Node| node.is_named() || node.is_missing();
+    loop {
+        let node = cursor.node();
+        if shown(&node) {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&"  ".repeat(depth));
+            if let Some(field) = cursor.field_name() {
+                out.push_str(field);
+                out.push_str(": ");
+            }
+            let kind = if node.is_missing() {
+                format!("MISSING {}", node.kind())
+            } else {
+                node.kind().to_string()
+            };
+            let [start_line, start_col] = convert_point(node.start_position());
+            let [end_line, end_col] = convert_point(node.end_position());
+            out.push_str(&format!(
+                "({} [{}, {}] - [{}, {}]",
+                kind, start_line, start_col, end_line, end_col
+            ));
+        }
+        if cursor.goto_first_child() {
+            depth += 1;
+            continue;
+        }
+        if shown(&node) {
+            out.push(')');
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                out.push('\n');
+                return out;
+            }
+            depth -= 1;
+            if shown(&cursor.node()) {
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// `parse [--format json|msgpack|ndjson] [--hashes] [--dump-cst] [--output <file>] <file>`
+///
+/// Writes the same tree the shell protocol would, to stdout by default. With
+/// `--dump-cst` tree-sitter's own syntax tree goes to stderr as well. Every
+/// option not given falls back to its `SEMANTIC_RUST_*` environment
+/// variable, like `SEMANTIC_RUST_NESTED` for `--nested`.
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &[
+            "--hashes",
+            "--signatures",
+            "--qualifiers",
+            "--docs",
+            "--excerpts",
+            "--tests",
+            "--complexity",
+            "--qualify-methods",
+            "--doctests",
+            "--ordinals",
+            "--flatten",
+            "--dump-cst",
+        ],
+        &[
+            "--format",
+            "--offsets",
+            "--columns",
+            "--tab-width",
+            "--line-base",
+            "--column-base",
+            "--ends",
+            "--nested",
+            "--inject",
+            "--max-depth",
+            "--name-templates",
+            "--skip-kinds",
+            "--min-bytes",
+            "--min-lines",
+            "--comments",
+            "--binary",
+            "--name",
+            "--relative-to",
+            "--output",
+            "-o",
+        ],
+    )?;
+    let [path] = args.positional() else {
+        anyhow::bail!(
+            "usage: parse [--format json|msgpack|ndjson] [--hashes] [--signatures] \
+             [--qualifiers] [--docs] [--excerpts] [--tests] [--complexity] \
+             [--offsets bytes|chars|utf16] \
+             [--columns bytes|chars|utf16] [--tab-width N] \
+             [--line-base 0|1] [--column-base 0|1] \
+             [--ends exclusive|inclusive] [--qualify-methods] [--nested fold|nest] \
+             [--inject macro=language,...] [--doctests] [--ordinals] \
+             [--max-depth N] [--name-templates <file>] [--skip-kinds kind,...] \
+             [--min-bytes N] [--min-lines N] [--flatten] \
+             [--comments separate|named|following|preceding] \
+             [--binary refuse|opaque] [--name <name>] [--relative-to <dir>] [--dump-cst] [--output <file>] <file>"
+        );
+    };
+    let bytes = std::fs::read(path)?;
+    let base = |option, var, default| match setting(&args, option, var) {
+        Some(value) => Base::parse_one(option, &value),
+        None => Ok(default),
+    };
+    let options = ParseOptions {
+        hashes: switch(&args, "--hashes", "SEMANTIC_RUST_HASHES")?,
+        signatures: switch(&args, "--signatures", "SEMANTIC_RUST_SIGNATURES")?,
+        qualifiers: switch(&args, "--qualifiers", "SEMANTIC_RUST_QUALIFIERS")?,
+        docs: switch(&args, "--docs", "SEMANTIC_RUST_DOCS")?,
+        excerpts: switch(&args, "--excerpts", "SEMANTIC_RUST_EXCERPTS")?,
+        tests: switch(&args, "--tests", "SEMANTIC_RUST_TESTS")?,
+        complexity: switch(&args, "--complexity", "SEMANTIC_RUST_COMPLEXITY")?,
+        offsets: Unit::parse(
+            &setting(&args, "--offsets", "SEMANTIC_RUST_OFFSETS")
+                .unwrap_or_else(|| "bytes".to_string()),
+        )?,
+        columns: column_unit(args.value("--columns"))?,
+        tab_width: tab_width(args.value("--tab-width"))?,
+        base: Base {
+            line: base(
+                "--line-base",
+                "SEMANTIC_RUST_LINE_BASE",
+                Base::SEMANTIC_MERGE.line,
+            )?,
+            column: base(
+                "--column-base",
+                "SEMANTIC_RUST_COLUMN_BASE",
+                Base::SEMANTIC_MERGE.column,
+            )?,
+        },
+        ends: Ends::parse(
+            &setting(&args, "--ends", "SEMANTIC_RUST_ENDS")
+                .unwrap_or_else(|| "exclusive".to_string()),
+        )?,
+        qualify_methods: switch(
+            &args,
+            "--qualify-methods",
+            "SEMANTIC_RUST_QUALIFY_METHODS",
+        )?,
+        nested: Nested::parse(
+            &setting(&args, "--nested", "SEMANTIC_RUST_NESTED")
+                .unwrap_or_else(|| "fold".to_string()),
+        )?,
+        injections: injections(args.value("--inject"))?,
+        doctests: switch(&args, "--doctests", "SEMANTIC_RUST_DOCTESTS")?,
+        ordinals: switch(&args, "--ordinals", "SEMANTIC_RUST_ORDINALS")?,
+        max_depth: match setting(&args, "--max-depth", "SEMANTIC_RUST_MAX_DEPTH") {
+            Some(value) => Some(parse_depth("--max-depth", &value)?),
+            None => None,
+        },
+        name_templates: name_templates(args.value("--name-templates"))?,
+        skip_kinds: parse_kinds(
+            &setting(&args, "--skip-kinds", "SEMANTIC_RUST_SKIP_KINDS")
+                .unwrap_or_default(),
+        ),
+        min_bytes: match setting(&args, "--min-bytes", "SEMANTIC_RUST_MIN_BYTES") {
+            Some(value) => parse_size("--min-bytes", &value)?,
+            None => 0,
+        },
+        min_lines: match setting(&args, "--min-lines", "SEMANTIC_RUST_MIN_LINES") {
+            Some(value) => parse_size("--min-lines", &value)?,
+            None => 0,
+        },
+        flatten: switch(&args, "--flatten", "SEMANTIC_RUST_FLATTEN")?,
+        comments: Comments::parse(
+            &setting(&args, "--comments", "SEMANTIC_RUST_COMMENTS")
+                .unwrap_or_else(|| "separate".to_string()),
+        )?,
+        binary: Binary::parse(
+            &setting(&args, "--binary", "SEMANTIC_RUST_BINARY")
+                .unwrap_or_else(|| "refuse".to_string()),
+        )?,
+    };
+    // The tree is named by the path given unless asked otherwise, so it
+    // can leave the machine without the user's directories in it.
+    let name = match (args.value("--name"), args.value("--relative-to")) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(root)) => relative_name(Path::new(path), Path::new(root))?,
+        (None, None) => path.to_string(),
+    };
+    let file_node = match binary_file(&name, &bytes, &options)? {
+        Some(file) => file,
+        None => {
+            let file_contents = decode(bytes)?;
+            let tree = parse_tree(&name, &file_contents)?;
+            if args.flag("--dump-cst") {
+                eprint!("{}", dump_cst(&tree));
+            }
+            build_file(&name, &file_contents, &tree, &options)?
+        }
+    };
+
+    let stdout = std::io::stdout();
+    let mut out: Box<dyn Write> =
+        match args.value("--output").or_else(|| args.value("-o")) {
+            Some(output) => Box::new(BufWriter::new(std::fs::File::create(output)?)),
+            None => Box::new(BufWriter::new(stdout.lock())),
+        };
+    match setting(&args, "--format", "SEMANTIC_RUST_FORMAT")
+        .as_deref()
+        .unwrap_or("json")
+    {
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &file_node)?;
+            writeln!(out)?;
+        }
+        "msgpack" => {
+            out.write_all(&msgpack::to_vec(&serde_json::to_value(&file_node)?))?
+        }
+        "ndjson" => ndjson::write_nodes(&mut out, &file_node)?,
+        other => anyhow::bail!("unknown format {}", other),
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,213 @@
+//! Selecting declarations with a small selector syntax, like
+//! `mod net > impl * > fn connect*`, so scripts can pick out parts of a
+//! file without walking the tree themselves.
+//!
+//! A selector is a list of steps, each a kind and optionally a glob for the
+//! name, joined by `>` for a declaration directly inside the previous one
+//! or `>>` for one anywhere inside it. A leading `>` anchors the first step
+//! at the top of the file; otherwise it matches at any depth.
+
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::{Declaration, ParsedFile};
+use crate::ignore::Glob;
+use crate::model::base_name;
+use crate::parse::{build_file, parse_tree, read_file, ParseOptions};
+
+/// How a step relates to the one before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    /// `>`: directly inside it, or for the first step, at the top level.
+    Child,
+    /// `>>`, or nothing before the first step: anywhere inside it.
+    Descendant,
+}
+
+#[derive(Debug)]
+struct Step {
+    combinator: Combinator,
+    /// The tree-sitter kinds it matches, or none for `*`.
+    kinds: Vec<String>,
+    name: Option<Glob>,
+}
+
+/// The kinds a step's kind stands for: the short names of the common ones,
+/// any tree-sitter kind as is, and the `_item` kinds without the suffix.
+fn kinds(kind: &str) -> anyhow::Result<Vec<String>> {
+    let kinds: &[&str] = match kind {
+        "*" => &[],
+        "fn" => &["function_item", "function_signature_item"],
+        "mod" => &["mod_item"],
+        "type" => &["type_item", "associated_type"],
+        "use" => &["use_declaration"],
+        "macro" => &["macro_definition"],
+        "field" => &["field_declaration"],
+        "variant" => &["enum_variant"],
+        "extern" => &["foreign_mod_item"],
+        "struct" | "enum" | "union" | "trait" | "impl" | "const" | "static" => {
+            return Ok(vec![format!("{}_item", kind)])
+        }
+        _ if kind.contains('_') => return Ok(vec![kind.to_string()]),
+        _ => anyhow::bail!("unknown kind {} in selector", kind),
+    };
+    Ok(kinds.iter().map(|k| k.to_string()).collect())
+}
+
+fn parse_selector(selector: &str) -> anyhow::Result<Vec<Step>> {
+    let mut steps = vec![];
+    let mut combinator = Combinator::Descendant;
+    let mut dangling = false;
+    let mut tokens = selector.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            ">" | ">>" if dangling => {
+                anyhow::bail!("two combinators in a row in selector {:?}", selector)
+            }
+            ">" => (combinator, dangling) = (Combinator::Child, true),
+            ">>" => (combinator, dangling) = (Combinator::Descendant, true),
+            kind => {
+                let name = match tokens.peek() {
+                    Some(&next) if next != ">" && next != ">>" => {
+                        tokens.next();
+                        Some(Glob::new(next))
+                    }
+                    _ => None,
+                };
+                steps.push(Step {
+                    combinator,
+                    kinds: kinds(kind)?,
+                    name,
+                });
+                (combinator, dangling) = (Combinator::Descendant, false);
+            }
+        }
+    }
+    if steps.is_empty() || dangling {
+        anyhow::bail!("selector {:?} doesn't end in a step", selector);
+    }
+    Ok(steps)
+}
+
+/// The names a declaration answers to in a selector: its name with and
+/// without the cfgs that tell cfg-gated versions apart, and for an impl,
+/// `impl Trait for Type`, also `Trait for Type` and `Type`.
+fn names(decl: &Declaration) -> Vec<String> {
+    let mut names = vec![decl.name.clone(), base_name(&decl.name).to_string()];
+    if decl.kind == "impl_item" {
+        if let Some(header) = base_name(&decl.name).strip_prefix("impl ") {
+            names.push(header.to_string());
+            if let Some((_, ty)) = header.rsplit_once(" for ") {
+                names.push(ty.to_string());
+            }
+        }
+    }
+    names
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryMatch {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub name: String,
+    pub qualified_name: String,
+    pub start: [i64; 2],
+    pub end: [i64; 2],
+    /// Its byte range in the file, docs and attributes included.
+    pub span: [usize; 2],
+    /// Its source, with `--text`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Whether `steps` match with the last one on `decls[i]`, given whether
+/// a step matches a declaration.
+fn selects(
+    steps: &[Step],
+    i: usize,
+    decls: &[Declaration],
+    step_matches: &dyn Fn(&Step, usize) -> bool,
+) -> bool {
+    let Some((last, rest)) = steps.split_last() else {
+        return true;
+    };
+    if !step_matches(last, i) {
+        return false;
+    }
+    let parent = decls[i].parent;
+    match (last.combinator, rest.is_empty()) {
+        (Combinator::Child, true) => parent.is_none(),
+        (Combinator::Descendant, true) => true,
+        (Combinator::Child, false) => {
+            parent.is_some_and(|p| selects(rest, p, decls, step_matches))
+        }
+        (Combinator::Descendant, false) => {
+            let mut ancestor = parent;
+            while let Some(a) = ancestor {
+                if selects(rest, a, decls, step_matches) {
+                    return true;
+                }
+                ancestor = decls[a].parent;
+            }
+            false
+        }
+    }
+}
+
+/// The declarations of `contents`, the source of `file`, that `selector`
+/// matches, in the order they appear.
+pub fn query(
+    file: &str,
+    contents: String,
+    selector: &str,
+    with_text: bool,
+) -> anyhow::Result<Vec<QueryMatch>> {
+    let steps = parse_selector(selector)?;
+    let tree = parse_tree(file, &contents)?;
+    let semantic = build_file(file, &contents, &tree, &ParseOptions::default())?;
+    let parsed = ParsedFile::from_parts(contents, semantic);
+    let decls = &parsed.declarations;
+    let names: Vec<Vec<String>> = decls.iter().map(names).collect();
+
+    let step_matches = |step: &Step, i: usize| {
+        (step.kinds.is_empty() || step.kinds.contains(&decls[i].kind))
+            && step
+                .name
+                .as_ref()
+                .map_or(true, |glob| names[i].iter().any(|name| glob.matches(name)))
+    };
+    Ok((0..decls.len())
+        .filter(|&i| selects(&steps, i, decls, &step_matches))
+        .map(|i| {
+            let decl = &decls[i];
+            QueryMatch {
+                item_type: decl.kind.clone(),
+                name: decl.name.clone(),
+                qualified_name: decl.qualified_name.clone(),
+                start: decl.start,
+                end: decl.end,
+                span: [decl.range.start, decl.range.end],
+                text: with_text
+                    .then(|| parsed.contents[decl.range.clone()].to_string()),
+            }
+        })
+        .collect())
+}
+
+/// `query [--text] <file> <selector>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &["--text"], &[])?;
+    let [file, selector] = args.positional() else {
+        anyhow::bail!("usage: query [--text] <file> <selector>");
+    };
+    let matches = query(file, read_file(file)?, selector, args.flag("--text"))?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    serde_json::to_writer_pretty(&mut out, &matches)?;
+    writeln!(out)?;
+    Ok(())
+}
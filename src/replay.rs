@@ -0,0 +1,327 @@
+//! Recording shell sessions and replaying them, so a session that went wrong
+//! on a user's machine can be reproduced exactly from one file.
+//!
+//! A recording is newline-delimited JSON: a [`Header`] with the environment
+//! the shell read its options from and the files it names, then one
+//! [`Exchange`] per request.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Args;
+use crate::files::DisplayNames;
+use crate::hash::content_hash;
+use crate::instance::{create_locked, per_process};
+use crate::shell::{answer, contain_panics, inline_source, shell_options};
+
+/// The environment variables the shell protocol takes its options from.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab_width: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nested: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_templates: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_kinds: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_bytes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_lines: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub names: Option<String>,
+    /// The yes-or-no options set, by name without the `SEMANTIC_RUST_`
+    /// prefix, like `HASHES`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub switches: BTreeMap<String, String>,
+}
+
+/// The yes-or-no options the shell protocol can be given in the
+/// environment, as `SEMANTIC_RUST_<name>`.
+pub const SWITCHES: &[&str] = &[
+    "HASHES",
+    "SIGNATURES",
+    "QUALIFIERS",
+    "DOCS",
+    "EXCERPTS",
+    "TESTS",
+    "COMPLEXITY",
+    "QUALIFY_METHODS",
+    "DOCTESTS",
+    "ORDINALS",
+    "FLATTEN",
+];
+
+impl Environment {
+    pub fn current() -> Environment {
+        let var = |name| std::env::var(name).ok();
+        Environment {
+            columns: var("SEMANTIC_RUST_COLUMNS"),
+            tab_width: var("SEMANTIC_RUST_TAB_WIDTH"),
+            inject: var("SEMANTIC_RUST_INJECT"),
+            nested: var("SEMANTIC_RUST_NESTED"),
+            max_depth: var("SEMANTIC_RUST_MAX_DEPTH"),
+            name_templates: var("SEMANTIC_RUST_NAME_TEMPLATES"),
+            skip_kinds: var("SEMANTIC_RUST_SKIP_KINDS"),
+            min_bytes: var("SEMANTIC_RUST_MIN_BYTES"),
+            min_lines: var("SEMANTIC_RUST_MIN_LINES"),
+            comments: var("SEMANTIC_RUST_COMMENTS"),
+            binary: var("SEMANTIC_RUST_BINARY"),
+            names: var("SEMANTIC_RUST_NAMES"),
+            switches: SWITCHES
+                .iter()
+                .filter_map(|name| {
+                    let value =
+                        std::env::var(format!("SEMANTIC_RUST_{}", name)).ok()?;
+                    Some((name.to_string(), value))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    pub version: String,
+    pub environment: Environment,
+    /// The contents of the files the environment names, by variable without
+    /// the `SEMANTIC_RUST_` prefix, like `NAMES`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, String>,
+}
+
+/// One request as SemanticMerge sent it and how it was answered.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    /// The protocol lines of the request: source, encoding and output paths.
+    pub lines: Vec<String>,
+    /// The source as read, unless it couldn't be or isn't UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+    /// The source in hex, when it isn't UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents_hash: Option<String>,
+    /// `OK` or `KO`.
+    pub response: String,
+    /// A hash of the tree written, when the answer was `OK`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+}
+
+/// Writes a recording as the shell session goes.
+pub struct Recorder {
+    out: Box<dyn Write>,
+}
+
+impl Recorder {
+    /// Starts a recording at `path`, with `{pid}` in it replaced by the
+    /// process id. Another shell already recording there keeps the file,
+    /// and this one records next to it, with its process id in the name.
+    pub fn create(path: &str, environment: &Environment) -> anyhow::Result<Recorder> {
+        let path = PathBuf::from(per_process(path));
+        let (file, created) = create_locked(&path)
+            .map_err(|e| anyhow::anyhow!("failed to create recording: {:#}", e))?;
+        if created != path {
+            tracing::info!(recording = %created.display(), "recording in use, recording elsewhere");
+        }
+        let mut recorder = Recorder {
+            out: Box::new(std::io::BufWriter::new(file)),
+        };
+        let named = [
+            ("NAMES", &environment.names),
+            ("NAME_TEMPLATES", &environment.name_templates),
+        ];
+        let files = named
+            .into_iter()
+            .filter_map(|(variable, path)| {
+                let contents = std::fs::read_to_string(path.as_deref()?).ok()?;
+                Some((variable.to_string(), contents))
+            })
+            .collect();
+        recorder.write(&Header {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            environment: environment.clone(),
+            files,
+        })?;
+        Ok(recorder)
+    }
+
+    /// Records a request for `contents`, the source unless it couldn't be
+    /// read, answered with `output`, the tree written, or nothing for `KO`.
+    pub fn record(
+        &mut self,
+        lines: Vec<String>,
+        contents: Option<Vec<u8>>,
+        output: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let contents_hash = contents.as_deref().map(content_hash);
+        let (contents, binary) = match contents.map(String::from_utf8) {
+            Some(Ok(text)) => (Some(text), None),
+            Some(Err(e)) => (None, Some(hex(e.as_bytes()))),
+            None => (None, None),
+        };
+        self.write(&Exchange {
+            lines,
+            contents_hash,
+            contents,
+            binary,
+            response: if output.is_some() { "OK" } else { "KO" }.to_string(),
+            output_hash: output.map(content_hash),
+        })
+    }
+
+    fn write(&mut self, entry: &impl Serialize) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.out, entry)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> anyhow::Result<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("malformed hex in recording"))
+        })
+        .collect()
+}
+
+/// How a replayed request compared with its recording.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub name: String,
+    pub response: String,
+    pub matches: bool,
+}
+
+/// Re-answers every request in the recording at `path` against copies of
+/// the recorded sources in `dir`, with the recorded environment pointing at
+/// copies of the recorded files it names.
+pub fn replay(path: &str, dir: &Path) -> anyhow::Result<Vec<Outcome>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("failed to open recording {}: {}", path, e))?;
+    let mut lines = BufReader::new(file).lines();
+    let header: Header = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => anyhow::bail!("{} is an empty recording", path),
+    };
+    let mut environment = header.environment;
+    std::fs::create_dir_all(dir)?;
+    for (variable, contents) in &header.files {
+        let copy = dir.join(variable.to_lowercase());
+        std::fs::write(&copy, contents)?;
+        let copy = Some(copy.to_string_lossy().into_owned());
+        match variable.as_str() {
+            "NAMES" => environment.names = copy,
+            "NAME_TEMPLATES" => environment.name_templates = copy,
+            _ => {}
+        }
+    }
+    let options = shell_options(&environment)?;
+    let names = match environment.names.as_deref() {
+        Some(path) => DisplayNames::read(path)?,
+        None => DisplayNames::default(),
+    };
+
+    let mut outcomes = vec![];
+    for (i, line) in lines.enumerate() {
+        let exchange: Exchange = serde_json::from_str(&line?)?;
+        let name = exchange
+            .lines
+            .first()
+            .and_then(|input| match inline_source(input) {
+                Some((_, name)) => Some(name.to_string()).filter(|n| !n.is_empty()),
+                None => Path::new(input)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned()),
+            })
+            .unwrap_or_else(|| "source.rs".to_string());
+        // The tree is named as the shell named it; the copy only needs the
+        // same file name.
+        let tree_name = match exchange.lines.first() {
+            Some(input) if inline_source(input).is_none() => names.name(input),
+            _ => name.clone(),
+        };
+        let request_dir = dir.join(i.to_string());
+        std::fs::create_dir_all(&request_dir)?;
+        let input = request_dir.join(&name);
+        if let Some(contents) = &exchange.contents {
+            std::fs::write(&input, contents)?;
+        } else if let Some(binary) = &exchange.binary {
+            std::fs::write(&input, unhex(binary)?)?;
+        }
+        let output = request_dir.join("output.json");
+        let result = contain_panics(|| {
+            answer(
+                &input.to_string_lossy(),
+                &tree_name,
+                &output.to_string_lossy(),
+                &options,
+                None,
+            )
+        });
+        let (response, output_hash) = match &result {
+            Ok((_, tree)) => ("OK", Some(content_hash(tree))),
+            Err(_) => ("KO", None),
+        };
+        outcomes.push(Outcome {
+            matches: response == exchange.response
+                && output_hash == exchange.output_hash,
+            name: tree_name,
+            response: response.to_string(),
+        });
+    }
+    Ok(outcomes)
+}
+
+/// `replay <recording>`
+///
+/// Exits 1 when any answer differs from the recorded one.
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(raw, &[], &[])?;
+    let [path] = args.positional() else {
+        anyhow::bail!("usage: replay <recording>");
+    };
+    let dir = std::env::temp_dir()
+        .join(format!("rustsemantic-replay-{}", std::process::id()));
+    let outcomes = replay(path, &dir);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut differences = 0;
+    for (i, outcome) in outcomes?.iter().enumerate() {
+        let note = if outcome.matches {
+            ""
+        } else {
+            differences += 1;
+            " (differs from the recording)"
+        };
+        println!("{}: {} {}{}", i + 1, outcome.name, outcome.response, note);
+    }
+    Ok(if differences == 0 { 0 } else { 1 })
+}
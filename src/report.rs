@@ -0,0 +1,76 @@
+//! Reports of the requests the shell answered `KO`, with enough detail to
+//! act on a support ticket.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::files::FileError;
+use crate::instance::{append_locked, per_process};
+use crate::parse::{parse_tree, read_file, syntax_errors, SyntaxError};
+
+/// Why a request failed.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureReport {
+    pub input: String,
+    pub language: &'static str,
+    /// The error and its causes, outermost first.
+    pub errors: Vec<String>,
+    /// Why the file couldn't be read or written, like `permission-denied`,
+    /// when that is what failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    /// Where the source has invalid syntax, if it could be read at all.
+    pub syntax_errors: Vec<SyntaxError>,
+    pub version: &'static str,
+}
+
+/// Builds the report for `input_path` failing with `error`.
+pub fn failure_report(input_path: &str, error: &anyhow::Error) -> FailureReport {
+    let syntax = read_file(input_path)
+        .ok()
+        .and_then(|contents| {
+            let tree = parse_tree(input_path, &contents).ok()?;
+            Some(syntax_errors(&tree, &contents))
+        })
+        .unwrap_or_default();
+    FailureReport {
+        input: input_path.to_string(),
+        language: "rust",
+        errors: error.chain().map(|cause| cause.to_string()).collect(),
+        reason: error
+            .downcast_ref::<FileError>()
+            .map(|e| e.failure.as_str()),
+        syntax_errors: syntax,
+        version: env!("CARGO_PKG_VERSION"),
+    }
+}
+
+/// The file a shell session appends its failure reports to, one JSON object
+/// per line: `SEMANTIC_RUST_REPORT` (with `{pid}` in it replaced by the
+/// process id), else one named after the process in the temp directory. Nothing is written until something fails.
+pub struct Reports {
+    pub path: PathBuf,
+}
+
+impl Reports {
+    pub fn for_session() -> Reports {
+        let path = match std::env::var("SEMANTIC_RUST_REPORT") {
+            Ok(path) if !path.is_empty() => PathBuf::from(per_process(&path)),
+            _ => std::env::temp_dir().join(format!(
+                "rustsemantic-{}-failures.jsonl",
+                std::process::id()
+            )),
+        };
+        Reports { path }
+    }
+
+    /// Appends the report for `input_path`. Sessions running at once can
+    /// share the file: each report is appended whole.
+    pub fn write(&self, input_path: &str, error: &anyhow::Error) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(&failure_report(input_path, error))?;
+        line.push(b'\n');
+        append_locked(&self.path, &line)
+    }
+}
@@ -0,0 +1,13 @@
+use std::io::Write;
+
+/// JSON Schema for the document `parse` and the shell protocol write.
+pub const SCHEMA: &str = include_str!("../schema/semantic-file.schema.json");
+
+/// `schema`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    if !raw.is_empty() {
+        anyhow::bail!("usage: schema");
+    }
+    std::io::stdout().lock().write_all(SCHEMA.as_bytes())?;
+    Ok(())
+}
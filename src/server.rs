@@ -0,0 +1,403 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::cli::Args;
+use crate::diff::{file_diff, ParsedFile};
+use crate::http;
+use crate::merge::{merge_sources, Labels};
+use crate::metrics::Metrics;
+use crate::outline::outline;
+use crate::parse::{parse_source_with, read_file};
+use crate::replay::Environment;
+use crate::shell::shell_options;
+use crate::templates::NameTemplates;
+
+/// How many parsed files to keep between requests before the cache is flushed.
+const CACHE_CAPACITY: usize = 256;
+
+/// How long a connection may go without sending or taking anything before
+/// it is dropped.
+pub(crate) const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> RpcError {
+        RpcError {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(e: serde_json::Error) -> RpcError {
+        RpcError {
+            code: SERVER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> RpcError {
+        RpcError {
+            code: SERVER_ERROR,
+            message: format!("{:#}", e),
+        }
+    }
+}
+
+/// The method implementations shared by every server transport.
+#[derive(Default)]
+pub struct Server {
+    cache: HashMap<(String, u64), Arc<ParsedFile>>,
+    /// Trees as the shell protocol writes them, by name and a hash of the
+    /// source and the settings they were built with.
+    trees: HashMap<(String, u64), Arc<String>>,
+    shutdown_requested: bool,
+    /// Whether documents may be given as paths the server opens itself.
+    /// Network transports only accept inline text.
+    pub allow_paths: bool,
+    pub metrics: Metrics,
+}
+
+/// The methods requests are counted under; any other is counted as
+/// `unknown`, so made-up names can't grow the metrics without bound.
+const METHODS: &[&str] = &["parse", "outline", "diff", "merge", "tree", "shutdown"];
+
+impl Server {
+    /// Parses a `{"path": ...}` or `{"name": ..., "text": ...}` document
+    /// argument, reusing an earlier parse of the same contents.
+    fn document(&mut self, params: &Value) -> Result<Arc<ParsedFile>, RpcError> {
+        let (name, contents) = match (params.get("text"), params.get("path")) {
+            (Some(Value::String(text)), _) => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                (name.to_string(), text.clone())
+            }
+            (_, Some(Value::String(path))) if self.allow_paths => {
+                (path.clone(), read_file(path)?)
+            }
+            _ => return Err(RpcError::invalid_params("expected a path or text")),
+        };
+
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let key = (name, hasher.finish());
+        if let Some(parsed) = self.cache.get(&key) {
+            self.metrics.cache_hits += 1;
+            return Ok(parsed.clone());
+        }
+        self.metrics.cache_misses += 1;
+        let started = Instant::now();
+        let parsed = Arc::new(ParsedFile::from_source(&key.0, contents)?);
+        self.metrics.parse(
+            "rust",
+            started.elapsed(),
+            parsed.file.parsing_errors_detected,
+        );
+        if self.cache.len() >= CACHE_CAPACITY {
+            self.cache.clear();
+        }
+        self.cache.insert(key, parsed.clone());
+        Ok(parsed)
+    }
+
+    /// The tree the shell protocol gives `text` as `name`, with the options
+    /// `environment` sets, serialized as the shell writes it, reusing an
+    /// earlier one for the same source and settings. The name templates file
+    /// is read once per request and its contents are part of the settings,
+    /// so an edit to it is picked up.
+    fn tree(
+        &mut self,
+        name: &str,
+        text: &str,
+        environment: &Environment,
+    ) -> Result<Arc<String>, RpcError> {
+        let mut settings = environment.clone();
+        let templates = match settings.name_templates.take() {
+            Some(path) => std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path, e))
+                .map(|text| Some((path, text)))?,
+            None => None,
+        };
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        serde_json::to_string(&settings)?.hash(&mut hasher);
+        templates.hash(&mut hasher);
+        let key = (name.to_string(), hasher.finish());
+        if let Some(tree) = self.trees.get(&key) {
+            self.metrics.cache_hits += 1;
+            return Ok(tree.clone());
+        }
+        self.metrics.cache_misses += 1;
+        let started = Instant::now();
+        let mut options = shell_options(&settings)?;
+        if let Some((path, text)) = &templates {
+            options.name_templates = NameTemplates::parse(text)
+                .map_err(|e| anyhow::anyhow!("{}: {:#}", path, e))?;
+        }
+        let file = parse_source_with(name, text, &options)?;
+        file.check_fits_i32()?;
+        self.metrics
+            .parse("rust", started.elapsed(), file.parsing_errors_detected);
+        let tree = Arc::new(serde_json::to_string_pretty(&file)?);
+        if self.trees.len() >= CACHE_CAPACITY {
+            self.trees.clear();
+        }
+        self.trees.insert(key, tree.clone());
+        Ok(tree)
+    }
+
+    pub fn call(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let started = Instant::now();
+        let result = self.dispatch(method, params);
+        let label = if METHODS.contains(&method) {
+            method
+        } else {
+            "unknown"
+        };
+        self.metrics
+            .request(label, started.elapsed(), result.is_err());
+        result
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "parse" => {
+                let parsed = self.document(params)?;
+                Ok(serde_json::to_value(&parsed.file)?)
+            }
+            "outline" => {
+                let parsed = self.document(params)?;
+                Ok(serde_json::to_value(outline(
+                    &parsed.file,
+                    &parsed.contents,
+                ))?)
+            }
+            "diff" => {
+                let old = params
+                    .get("old")
+                    .ok_or_else(|| RpcError::invalid_params("missing old"))?;
+                let new = params
+                    .get("new")
+                    .ok_or_else(|| RpcError::invalid_params("missing new"))?;
+                let old = self.document(old)?;
+                let new = self.document(new)?;
+                let diff = file_diff(
+                    Some(old.file.name.clone()),
+                    Some(new.file.name.clone()),
+                    Some(&old),
+                    Some(&new),
+                );
+                Ok(serde_json::to_value(diff)?)
+            }
+            "merge" => {
+                let mut sides = vec![];
+                for side in ["base", "local", "remote"] {
+                    let document = params.get(side).ok_or_else(|| {
+                        RpcError::invalid_params(format!("missing {}", side))
+                    })?;
+                    sides.push(self.document(document)?);
+                }
+                let (merged, summary) = merge_sources(
+                    &sides[0].contents,
+                    &sides[1].contents,
+                    &sides[2].contents,
+                    &Labels::default(),
+                )?;
+                Ok(json!({ "merged": merged, "summary": summary }))
+            }
+            "tree" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                let text = params
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::invalid_params("expected text"))?;
+                let environment = match params.get("environment") {
+                    Some(environment) => serde_json::from_value(environment.clone())
+                        .map_err(|e| RpcError::invalid_params(e.to_string()))?,
+                    None => Environment::default(),
+                };
+                let tree = self.tree(name, text, &environment)?;
+                Ok(json!({ "tree": *tree }))
+            }
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method {}", method),
+            }),
+        }
+    }
+
+    /// Answers one JSON-RPC 2.0 message. Notifications get no response.
+    pub fn handle(&mut self, message: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string()))
+            }
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST,
+                    "missing method",
+                ))
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.call(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e.code, &e.message),
+        })
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Answers newline-delimited JSON-RPC read from `input` on `output` until
+/// input ends or a `shutdown` request arrives.
+fn serve_lines(
+    server: &mut Server,
+    input: impl BufRead,
+    output: &mut dyn Write,
+) -> anyhow::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = server.handle(&line);
+        if let Some(response) = response {
+            serde_json::to_writer(&mut *output, &response)?;
+            writeln!(output)?;
+            output.flush()?;
+        }
+        if server.shutdown_requested {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Serves newline-delimited JSON-RPC on stdin/stdout until stdin closes or
+/// a `shutdown` request arrives.
+fn serve_stdio() -> anyhow::Result<()> {
+    let mut server = Server {
+        allow_paths: true,
+        ..Server::default()
+    };
+    serve_lines(
+        &mut server,
+        std::io::stdin().lock(),
+        &mut std::io::stdout().lock(),
+    )
+}
+
+/// Serves newline-delimited JSON-RPC on a Unix domain socket at `path`,
+/// one connection at a time so every client shares the same caches, until
+/// a `shutdown` request arrives. A client that stalls for
+/// [`CONNECTION_TIMEOUT`] is dropped, so it can't hold up the others. A socket left behind by a daemon that died
+/// is replaced; one another daemon is listening on is not.
+#[cfg(unix)]
+fn serve_socket(path: &Path) -> anyhow::Result<()> {
+    use std::io::BufReader;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            anyhow::bail!("a daemon is already listening on {}", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| {
+        anyhow::anyhow!("failed to listen on {}: {}", path.display(), e)
+    })?;
+    eprintln!("listening on {}", path.display());
+    let mut server = Server {
+        allow_paths: true,
+        ..Server::default()
+    };
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let input = stream
+            .set_read_timeout(Some(CONNECTION_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(CONNECTION_TIMEOUT)))
+            .and_then(|()| stream.try_clone());
+        let input = match input {
+            Ok(input) => BufReader::new(input),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = serve_lines(&mut server, input, &mut stream) {
+            eprintln!("error: {:#}", e);
+        }
+        if server.shutdown_requested {
+            break;
+        }
+    }
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_path: &Path) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "--socket needs Unix domain sockets; use --http 127.0.0.1:<port> here"
+    )
+}
+
+/// `serve --stdio | --http <addr> | --socket <path>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &["--stdio"], &["--http", "--socket"])?;
+    match (
+        args.flag("--stdio"),
+        args.value("--http"),
+        args.value("--socket"),
+    ) {
+        (true, None, None) => serve_stdio(),
+        (false, Some(addr), None) => http::serve(addr, Server::default()),
+        (false, None, Some(path)) => serve_socket(Path::new(path)),
+        _ => anyhow::bail!("usage: serve --stdio | --http <addr> | --socket <path>"),
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, Read, Write};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::cli::{parse_switch, switch, Args};
+use crate::compare::compare;
+use crate::daemon::Client;
+use crate::files::{
+    display_names, path_encoding, resolve_path, FileError, PathEncoding,
+};
+use crate::hash::content_hash;
+use crate::inject::Injections;
+use crate::model::{Node, SemanticFile};
+use crate::parse::{
+    binary_file, decode, parse_depth, parse_kinds, parse_size, parse_source_with,
+    Binary, Comments, Nested, ParseOptions,
+};
+use crate::replay::{Environment, Recorder};
+use crate::report::Reports;
+use crate::signals;
+use crate::templates::NameTemplates;
+use crate::units::{tab_width, Base, Ends, Unit};
+
+/// How a session ended, which the shell's exit code tells the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ending {
+    /// The `end` sentinel arrived.
+    End,
+    /// Input ended without it: SemanticMerge exited or closed the pipe.
+    Eof,
+    /// Writing a response failed because nobody reads the responses any
+    /// more.
+    BrokenPipe,
+    /// The shell was asked to stop, by a signal or the console closing.
+    Terminated,
+}
+
+impl Ending {
+    pub fn code(self) -> i32 {
+        match self {
+            Ending::End => 0,
+            Ending::Eof => 3,
+            Ending::BrokenPipe => 4,
+            Ending::Terminated => 5,
+        }
+    }
+}
+
+/// What a session did, logged when it ends so users can tell whether
+/// SemanticMerge used the parser at all and how it fared.
+struct Summary {
+    started: Instant,
+    requests: usize,
+    failed: usize,
+    /// Trees identical to one answered earlier in the session, like the
+    /// base and an unchanged side of a merge, which a cache would have
+    /// answered without parsing.
+    repeated: usize,
+    seen: HashSet<String>,
+    /// Files answered, by the language of their tree.
+    languages: BTreeMap<&'static str, usize>,
+    busy: Duration,
+}
+
+impl Summary {
+    fn new() -> Summary {
+        Summary {
+            started: Instant::now(),
+            requests: 0,
+            failed: 0,
+            repeated: 0,
+            seen: HashSet::new(),
+            languages: BTreeMap::new(),
+            busy: Duration::ZERO,
+        }
+    }
+
+    fn add(&mut self, tree: Option<(&SemanticFile, &str)>, took: Duration) {
+        self.requests += 1;
+        self.busy += took;
+        let Some((tree, serialized)) = tree else {
+            self.failed += 1;
+            return;
+        };
+        if !self.seen.insert(content_hash(serialized)) {
+            self.repeated += 1;
+        }
+        let language = match tree.children.as_slice() {
+            [node] if node.kind() == "binary" => "binary",
+            _ => "rust",
+        };
+        *self.languages.entry(language).or_default() += 1;
+    }
+
+    fn log(&self, ending: Ending) {
+        let languages = self
+            .languages
+            .iter()
+            .map(|(language, count)| format!("{}={}", language, count))
+            .collect::<Vec<_>>()
+            .join(",");
+        tracing::info!(
+            ?ending,
+            requests = self.requests,
+            parsed = self.requests - self.failed,
+            failed = self.failed,
+            repeated = self.repeated,
+            languages = %languages,
+            busy_ms = self.busy.as_millis(),
+            total_ms = self.started.elapsed().as_millis(),
+            "session summary"
+        );
+    }
+}
+
+/// The trees a session wrote, removed when the session ends if asked to, so
+/// long merge sessions don't leave their results behind in the temp
+/// directory. Dropping it removes them, however the session ended.
+struct Outputs {
+    cleanup: bool,
+    written: Vec<PathBuf>,
+}
+
+impl Outputs {
+    fn new(cleanup: bool) -> Outputs {
+        Outputs {
+            cleanup,
+            written: vec![],
+        }
+    }
+
+    fn add(&mut self, path: PathBuf) {
+        if self.cleanup && !self.written.contains(&path) {
+            self.written.push(path);
+        }
+    }
+}
+
+impl Drop for Outputs {
+    fn drop(&mut self) {
+        let removed = self
+            .written
+            .drain(..)
+            .filter(|path| std::fs::remove_file(path).is_ok())
+            .count();
+        if removed > 0 {
+            tracing::info!(removed, "removed the trees written");
+        }
+    }
+}
+
+/// Runs the SemanticMerge external parser protocol: signal readiness through
+/// the flag file, then answer `source / encoding / output` triples on stdin
+/// until the `end` sentinel arrives. Returns the exit code for how the
+/// session ended.
+///
+/// `shell <flag file> [--record <file>] [--cleanup]`
+pub fn run(raw: &[String]) -> anyhow::Result<i32> {
+    tracing::info!(args = ?std::env::args().collect::<Vec<_>>(), "shell started");
+    let args = Args::parse(raw, &["--cleanup"], &["--record"])?;
+    let [flag_file] = args.positional() else {
+        anyhow::bail!("usage: shell <flag file> [--record <file>] [--cleanup]");
+    };
+
+    // Before the flag file, so SemanticMerge can't stop the shell without
+    // it noticing.
+    signals::install(Ending::Terminated.code());
+    save_file(flag_file, "hello")?;
+
+    let environment = Environment::current();
+    let options = shell_options(&environment)?;
+    let encoding = path_encoding()?;
+    let record = args
+        .value("--record")
+        .map(str::to_string)
+        .or_else(|| std::env::var("SEMANTIC_RUST_RECORD").ok());
+    let mut recorder = match record {
+        Some(path) => Some(Recorder::create(&path, &environment)?),
+        None => None,
+    };
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let ending = session(
+        &mut stdin.lock(),
+        &mut stdout.lock(),
+        &options,
+        encoding,
+        recorder.as_mut(),
+        &Reports::for_session(),
+        &mut Outputs::new(switch(&args, "--cleanup", "SEMANTIC_RUST_CLEANUP")?),
+    )?;
+    match ending {
+        Ending::End => tracing::info!("shell done"),
+        Ending::Eof => tracing::warn!("input ended without end, SemanticMerge is gone"),
+        Ending::BrokenPipe => {
+            tracing::warn!("responses can't be written, SemanticMerge is gone")
+        }
+        Ending::Terminated => tracing::warn!("terminated"),
+    }
+    Ok(ending.code())
+}
+
+/// The options trees are built with for SemanticMerge, given the
+/// environment variables it can set.
+pub fn shell_options(environment: &Environment) -> anyhow::Result<ParseOptions> {
+    // SemanticMerge is a .NET application and counts UTF-16 code units. The
+    // base and span ends aren't configurable: it misplaces everything unless
+    // lines are 1-based, columns 0-based and span ends inclusive. It matches
+    // methods by name, so they are qualified with their impl, and pairs up
+    // declarations with the same name at random, so they are numbered.
+    // Everything else, and those two, can be set in the environment.
+    let switch = |name: &str, default| match environment.switches.get(name) {
+        Some(value) => parse_switch(&format!("SEMANTIC_RUST_{}", name), value),
+        None => Ok(default),
+    };
+    Ok(ParseOptions {
+        hashes: switch("HASHES", false)?,
+        signatures: switch("SIGNATURES", false)?,
+        qualifiers: switch("QUALIFIERS", false)?,
+        docs: switch("DOCS", false)?,
+        excerpts: switch("EXCERPTS", false)?,
+        tests: switch("TESTS", false)?,
+        complexity: switch("COMPLEXITY", false)?,
+        offsets: Unit::Utf16,
+        columns: Unit::parse(environment.columns.as_deref().unwrap_or("bytes"))?,
+        injections: Injections::parse(environment.inject.as_deref().unwrap_or(""))?,
+        tab_width: match environment.tab_width.as_deref() {
+            Some(width) => tab_width(Some(width))?,
+            None => None,
+        },
+        base: Base::SEMANTIC_MERGE,
+        ends: Ends::Inclusive,
+        qualify_methods: switch("QUALIFY_METHODS", true)?,
+        nested: Nested::parse(environment.nested.as_deref().unwrap_or("fold"))?,
+        doctests: switch("DOCTESTS", false)?,
+        ordinals: switch("ORDINALS", true)?,
+        max_depth: match environment.max_depth.as_deref() {
+            Some(value) => Some(parse_depth("SEMANTIC_RUST_MAX_DEPTH", value)?),
+            None => None,
+        },
+        name_templates: match environment.name_templates.as_deref() {
+            Some(path) => NameTemplates::read(path)?,
+            None => NameTemplates::default(),
+        },
+        skip_kinds: parse_kinds(environment.skip_kinds.as_deref().unwrap_or("")),
+        min_bytes: match environment.min_bytes.as_deref() {
+            Some(value) => parse_size("SEMANTIC_RUST_MIN_BYTES", value)?,
+            None => 0,
+        },
+        min_lines: match environment.min_lines.as_deref() {
+            Some(value) => parse_size("SEMANTIC_RUST_MIN_LINES", value)?,
+            None => 0,
+        },
+        flatten: switch("FLATTEN", false)?,
+        comments: Comments::parse(
+            environment.comments.as_deref().unwrap_or("separate"),
+        )?,
+        binary: Binary::parse(environment.binary.as_deref().unwrap_or("refuse"))?,
+    })
+}
+
+/// The output path that asks for the tree in the response instead of in a
+/// file: `OK <length>`, then that many bytes of it.
+pub const INLINE_OUTPUT: &str = "-";
+
+/// The length and name in a source line of the form
+/// `inline:<length>:<name>`, which sends the source itself in the
+/// `<length>` bytes after the line instead of naming a file, so editors can
+/// have unsaved buffers parsed without writing them out.
+pub fn inline_source(line: &str) -> Option<(usize, &str)> {
+    let (length, name) = line.strip_prefix("inline:")?.split_once(':')?;
+    Some((length.parse().ok()?, name))
+}
+
+/// Reads the `length` bytes of an inline source, or `None` when input ends
+/// before they all arrive.
+fn read_inline(
+    input: &mut dyn BufRead,
+    length: usize,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut contents = vec![];
+    input.take(length as u64).read_to_end(&mut contents)?;
+    Ok((contents.len() == length).then_some(contents))
+}
+
+/// Reads the next non-blank line without its line ending, in `encoding`,
+/// or `None` at end of input. Paths may contain spaces, so nothing else is
+/// trimmed.
+fn next_line(
+    input: &mut dyn BufRead,
+    encoding: PathEncoding,
+) -> anyhow::Result<Option<String>> {
+    let mut line = vec![];
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = encoding.decode(&line)?;
+        let trimmed = trimmed.trim_end_matches(['\r', '\n']);
+        if !trimmed.trim().is_empty() {
+            return Ok(Some(trimmed.to_string()));
+        }
+    }
+}
+
+fn session(
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    options: &ParseOptions,
+    encoding: PathEncoding,
+    mut recorder: Option<&mut Recorder>,
+    reports: &Reports,
+    outputs: &mut Outputs,
+) -> anyhow::Result<Ending> {
+    // With SEMANTIC_RUST_COMPARE set, each tree is compared with the last
+    // one built for the same file name, which in a merge is the previous
+    // version of the same file.
+    let mut previous: Option<HashMap<String, SemanticFile>> =
+        std::env::var_os("SEMANTIC_RUST_COMPARE").map(|_| HashMap::new());
+    let names = display_names()?;
+    let mut daemon = Client::from_env();
+    let mut summary = Summary::new();
+    // A read cut short by a termination request fails or looks like the
+    // end of input, so the request is checked for first.
+    fn ending<T>(result: anyhow::Result<Option<T>>) -> Result<T, Ending> {
+        match result {
+            _ if signals::terminated() => Err(Ending::Terminated),
+            Ok(Some(read)) => Ok(read),
+            Ok(None) => Err(Ending::Eof),
+            Err(e) => {
+                tracing::warn!("failed to read a request: {:#}", e);
+                Err(Ending::Eof)
+            }
+        }
+    }
+    let ending = loop {
+        let input_path = match ending(next_line(input, encoding)) {
+            Ok(line) => line,
+            Err(ending) => break ending,
+        };
+        if input_path == "end" {
+            break Ending::End;
+        }
+        let inline = match inline_source(&input_path) {
+            Some((length, _)) => match ending(read_inline(input, length)) {
+                Ok(contents) => Some(contents),
+                Err(ending) => {
+                    tracing::warn!(input = %input_path, "incomplete inline source");
+                    break ending;
+                }
+            },
+            None => None,
+        };
+        let request = ending(next_line(input, encoding)).and_then(|file_encoding| {
+            Ok((file_encoding, ending(next_line(input, encoding))?))
+        });
+        let (file_encoding, output_path) = match request {
+            Ok(request) => request,
+            Err(ending) => {
+                tracing::warn!(input = %input_path, "incomplete request");
+                break ending;
+            }
+        };
+        // SemanticMerge hands over copies in fresh temp directories, so the
+        // tree is named by the file name alone, or the name mapped to it, to
+        // keep the output identical for identical sources.
+        let name = match inline_source(&input_path) {
+            Some((_, name)) => name.to_string(),
+            None => names.name(&input_path),
+        };
+        tracing::info!(input = %input_path, name = %name, output = %output_path, "parsing");
+        // A panic on one pathological file answers KO for that file rather
+        // than leave SemanticMerge with a dead parser mid-merge.
+        let started = Instant::now();
+        let result = contain_panics(|| match &inline {
+            Some(contents) => {
+                answer_inline(contents, &name, &output_path, options, daemon.as_mut())
+            }
+            None => answer(&input_path, &name, &output_path, options, daemon.as_mut()),
+        });
+        summary.add(
+            result
+                .as_ref()
+                .ok()
+                .map(|(tree, serialized)| (tree, serialized.as_str())),
+            started.elapsed(),
+        );
+        let serialized = match result {
+            Ok((tree, serialized)) => {
+                if output_path != INLINE_OUTPUT {
+                    outputs.add(resolve_path(&output_path));
+                }
+                if let Some(previous) = previous.as_mut() {
+                    if let Some(old) = previous.get(&name) {
+                        log_comparison(&name, old, &tree);
+                    }
+                    previous.insert(name.clone(), tree);
+                }
+                Some(serialized)
+            }
+            Err(e) => {
+                match e.downcast_ref::<FileError>() {
+                    Some(file) => tracing::warn!(
+                        input = %input_path,
+                        reason = file.failure.as_str(),
+                        "{}",
+                        file
+                    ),
+                    None => {
+                        tracing::warn!(input = %input_path, "failed to parse: {:#}", e)
+                    }
+                }
+                match reports.write(&input_path, &e) {
+                    Ok(()) => {
+                        tracing::info!(report = %reports.path.display(), "failure reported")
+                    }
+                    Err(e) => tracing::warn!("failed to write failure report: {:#}", e),
+                }
+                None
+            }
+        };
+        let response = match &serialized {
+            Some(tree) if output_path == INLINE_OUTPUT => {
+                format!("OK {}\n{}\n", tree.len(), tree)
+            }
+            Some(_) => "OK\n".to_string(),
+            None => "KO\n".to_string(),
+        };
+        match output
+            .write_all(response.as_bytes())
+            .and_then(|()| output.flush())
+        {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                break Ending::BrokenPipe
+            }
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(recorder) = recorder.as_deref_mut() {
+            let contents = inline.or_else(|| std::fs::read(&input_path).ok());
+            let lines = vec![input_path.clone(), file_encoding, output_path];
+            // Losing the recording is no reason to stop answering.
+            if let Err(e) = recorder.record(lines, contents, serialized.as_deref()) {
+                tracing::warn!("failed to record the request: {:#}", e);
+            }
+        }
+    };
+    summary.log(ending);
+    Ok(ending)
+}
+
+/// Logs how `new` differs in structure from `old`, an earlier tree for
+/// `name`.
+fn log_comparison(name: &str, old: &SemanticFile, new: &SemanticFile) {
+    let changes = compare(old, new);
+    tracing::info!(
+        name,
+        changes = changes.len(),
+        "compared with the previous tree"
+    );
+    for change in changes {
+        tracing::info!(name, "{}", change);
+    }
+}
+
+/// Runs `f`, turning a panic in it into an error carrying the panic's
+/// message.
+pub fn contain_panics<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            tracing::error!("panicked: {}", message);
+            anyhow::bail!("panicked: {}", message)
+        }
+    }
+}
+
+/// Parses `input_path` as `name` and writes its tree to `output_path`,
+/// logging at debug level how big the file was and where the time went.
+/// Returns the tree and its serialization as written.
+pub(crate) fn answer(
+    input_path: &str,
+    name: &str,
+    output_path: &str,
+    options: &ParseOptions,
+    daemon: Option<&mut Client>,
+) -> anyhow::Result<(SemanticFile, String)> {
+    // The paths are resolved first, so quirks in how they were written
+    // don't fail the request, and the error names the path tried when it
+    // fails anyway.
+    let input = resolve_path(input_path);
+    let contents = std::fs::read(&input)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Source::of(name, bytes, options))
+        .map_err(|e| FileError::wrap(&input, false, e))?;
+    answer_source(input_path, contents, name, output_path, options, daemon)
+}
+
+/// Like [`answer`], for a source sent inline.
+fn answer_inline(
+    contents: &[u8],
+    name: &str,
+    output_path: &str,
+    options: &ParseOptions,
+    daemon: Option<&mut Client>,
+) -> anyhow::Result<(SemanticFile, String)> {
+    let contents =
+        Source::of(name, contents.to_vec(), options).map_err(|e| match e
+            .downcast_ref::<std::io::Error>()
+        {
+            Some(e) => anyhow::anyhow!("the inline source isn't UTF-8 text: {}", e),
+            None => e,
+        })?;
+    answer_source(name, contents, name, output_path, options, daemon)
+}
+
+/// A file's contents, as answered: source text, or the tree of a file that
+/// looks binary, which neither tree-sitter nor the daemon get to see.
+enum Source {
+    Text(String),
+    /// The opaque tree of a binary file, and its length in bytes.
+    Binary(SemanticFile, usize),
+}
+
+impl Source {
+    /// `bytes`, checked for binary data before they are decoded, since that
+    /// is rarely valid UTF-8.
+    fn of(
+        name: &str,
+        bytes: Vec<u8>,
+        options: &ParseOptions,
+    ) -> anyhow::Result<Source> {
+        Ok(match binary_file(name, &bytes, options)? {
+            Some(file) => Source::Binary(file, bytes.len()),
+            None => Source::Text(decode(bytes)?),
+        })
+    }
+}
+
+/// Parses `contents` as `name` and writes its tree to `output_path`, unless
+/// that is [`INLINE_OUTPUT`]; `input` names the source in the log. With a
+/// `daemon` that can be reached, the daemon builds the tree instead, and
+/// when it fails to, the file is parsed here after all.
+fn answer_source(
+    input: &str,
+    contents: Source,
+    name: &str,
+    output_path: &str,
+    options: &ParseOptions,
+    daemon: Option<&mut Client>,
+) -> anyhow::Result<(SemanticFile, String)> {
+    fn count(nodes: &[Node]) -> usize {
+        nodes.iter().map(|node| 1 + count(node.children())).sum()
+    }
+
+    let started = Instant::now();
+    let bytes = match &contents {
+        Source::Text(text) => text.len(),
+        Source::Binary(_, len) => *len,
+    };
+    let remote = match &contents {
+        Source::Text(text) => daemon.and_then(|daemon| daemon.tree(name, text)),
+        Source::Binary(..) => None,
+    };
+    let from_daemon = remote.is_some();
+    let (file_node, serialized, parse_micros, serialize_micros) = match remote {
+        Some((file_node, serialized)) => {
+            (file_node, serialized, started.elapsed().as_micros(), 0)
+        }
+        None => {
+            let file_node = match contents {
+                Source::Text(text) => parse_source_with(name, &text, options)?,
+                Source::Binary(file, _) => file,
+            };
+            let parse_micros = started.elapsed().as_micros();
+            // SemanticMerge reads positions as 32-bit integers: answer KO
+            // rather than hand it a tree where they have wrapped around.
+            file_node.check_fits_i32()?;
+            let started = Instant::now();
+            let serialized = serde_json::to_string_pretty(&file_node)?;
+            let serialize_micros = started.elapsed().as_micros();
+            (file_node, serialized, parse_micros, serialize_micros)
+        }
+    };
+    if output_path != INLINE_OUTPUT {
+        let output = resolve_path(output_path);
+        std::fs::write(&output, &serialized)
+            .map_err(|e| FileError::wrap(&output, true, e.into()))?;
+    }
+    tracing::debug!(
+        input = %input,
+        bytes,
+        nodes = count(&file_node.children),
+        parse_micros,
+        serialize_micros,
+        daemon = from_daemon,
+        output_bytes = serialized.len(),
+        "parsed"
+    );
+    Ok((file_node, serialized))
+}
+
+fn save_file(path: &str, file: &str) -> anyhow::Result<()> {
+    std::fs::write(resolve_path(path), file)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Noticing that the shell was asked to stop, by SIGTERM or SIGHUP on Unix
+//! or a console close, logoff or shutdown on Windows, so the session can
+//! end like any other instead of dying mid-write.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a termination request has arrived since [`install`].
+pub fn terminated() -> bool {
+    TERMINATED.load(Ordering::SeqCst)
+}
+
+/// Handles termination requests from now on. On Unix the handler closes
+/// stdin, so the read the shell is blocked in returns and it can see
+/// [`terminated`]. On Windows the handler runs on a thread of its own and
+/// the process is gone when it returns, so it logs and exits with `code`
+/// there itself.
+#[cfg(unix)]
+pub fn install(_code: i32) {
+    use std::os::raw::c_int;
+
+    const SIGHUP: c_int = 1;
+    const SIGTERM: c_int = 15;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn close(fd: c_int) -> c_int;
+    }
+
+    extern "C" fn on_signal(_: c_int) {
+        TERMINATED.store(true, Ordering::SeqCst);
+        // Only async-signal-safe calls here: close is one.
+        unsafe {
+            close(0);
+        }
+    }
+
+    unsafe {
+        signal(SIGTERM, on_signal);
+        signal(SIGHUP, on_signal);
+    }
+}
+
+#[cfg(windows)]
+pub fn install(code: i32) {
+    use std::sync::atomic::AtomicI32;
+
+    static CODE: AtomicI32 = AtomicI32::new(0);
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(
+            handler: Option<unsafe extern "system" fn(u32) -> i32>,
+            add: i32,
+        ) -> i32;
+    }
+
+    unsafe extern "system" fn on_event(event: u32) -> i32 {
+        TERMINATED.store(true, Ordering::SeqCst);
+        tracing::warn!(event, "terminated");
+        std::process::exit(CODE.load(Ordering::SeqCst))
+    }
+
+    CODE.store(code, Ordering::SeqCst);
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_event), 1);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn install(_code: i32) {}
@@ -0,0 +1,238 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::cli::Args;
+use crate::diff::declarations;
+use crate::files::expand;
+use crate::ignore::Filter;
+use crate::model::Node;
+use crate::parse::{build_file, parse_tree, read_file, ParseOptions};
+
+/// Size and cost of one parsed file.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStats {
+    pub file: String,
+    pub bytes: usize,
+    pub nodes: usize,
+    /// Nesting depth of the deepest node; the file's children are at 1.
+    pub max_depth: usize,
+    pub parse_micros: u128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionSize {
+    pub file: String,
+    pub name: String,
+    pub line: i64,
+    pub lines: i64,
+}
+
+/// How complex one function is, for `--functions`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionComplexity {
+    pub file: String,
+    pub name: String,
+    pub line: i64,
+    pub lines: i64,
+    pub cyclomatic: u32,
+    pub nesting: u32,
+}
+
+/// Totals over every file added, with only the `top` largest functions and
+/// slowest files kept.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub files: usize,
+    pub nodes: usize,
+    pub nodes_by_kind: BTreeMap<String, usize>,
+    pub max_depth: usize,
+    pub parse_micros: u128,
+    pub largest_functions: Vec<FunctionSize>,
+    pub slowest_files: Vec<FileStats>,
+    /// Every function with a body, in the order added; only collected when
+    /// set to `Some` before files are added.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<FunctionComplexity>>,
+    #[serde(skip)]
+    top: usize,
+}
+
+impl Stats {
+    pub fn new(top: usize) -> Stats {
+        Stats {
+            files: 0,
+            nodes: 0,
+            nodes_by_kind: BTreeMap::new(),
+            max_depth: 0,
+            parse_micros: 0,
+            largest_functions: vec![],
+            slowest_files: vec![],
+            functions: None,
+            top,
+        }
+    }
+
+    /// Parses `contents` and folds its numbers into the totals.
+    pub fn add(&mut self, file: &str, contents: &str) -> anyhow::Result<()> {
+        fn visit(
+            nodes: &[Node],
+            depth: usize,
+            kinds: &mut BTreeMap<String, usize>,
+            stats: &mut FileStats,
+        ) {
+            for node in nodes {
+                *kinds.entry(node.kind().to_string()).or_default() += 1;
+                stats.nodes += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                visit(node.children(), depth + 1, kinds, stats);
+            }
+        }
+
+        let started = Instant::now();
+        let tree = parse_tree(file, contents)?;
+        let options = ParseOptions {
+            complexity: self.functions.is_some(),
+            ..ParseOptions::default()
+        };
+        let semantic = build_file(file, contents, &tree, &options)?;
+        let mut stats = FileStats {
+            file: file.to_string(),
+            bytes: contents.len(),
+            nodes: 0,
+            max_depth: 0,
+            parse_micros: started.elapsed().as_micros(),
+        };
+        visit(&semantic.children, 1, &mut self.nodes_by_kind, &mut stats);
+
+        let functions: Vec<_> = declarations(&semantic, contents)
+            .into_iter()
+            .filter(|d| d.kind == "function_item")
+            .collect();
+        if let Some(complexities) = &mut self.functions {
+            complexities.extend(functions.iter().filter_map(|d| {
+                let complexity = d.complexity?;
+                Some(FunctionComplexity {
+                    file: file.to_string(),
+                    name: d.qualified_name.clone(),
+                    line: d.start[0],
+                    lines: d.end[0] - d.start[0] + 1,
+                    cyclomatic: complexity.cyclomatic,
+                    nesting: complexity.nesting,
+                })
+            }));
+        }
+        self.largest_functions
+            .extend(functions.into_iter().map(|d| FunctionSize {
+                file: file.to_string(),
+                name: d.qualified_name,
+                line: d.start[0],
+                lines: d.end[0] - d.start[0] + 1,
+            }));
+        self.largest_functions.sort_by_key(|f| Reverse(f.lines));
+        self.largest_functions.truncate(self.top);
+
+        self.files += 1;
+        self.nodes += stats.nodes;
+        self.max_depth = self.max_depth.max(stats.max_depth);
+        self.parse_micros += stats.parse_micros;
+        self.slowest_files.push(stats);
+        self.slowest_files.sort_by_key(|f| Reverse(f.parse_micros));
+        self.slowest_files.truncate(self.top);
+        Ok(())
+    }
+}
+
+pub fn write_text(out: &mut dyn Write, stats: &Stats) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{} files, {} nodes, max depth {}, parsed in {:.1} ms",
+        stats.files,
+        stats.nodes,
+        stats.max_depth,
+        stats.parse_micros as f64 / 1000.0
+    )?;
+    writeln!(out, "\nnodes by kind:")?;
+    let mut kinds: Vec<_> = stats.nodes_by_kind.iter().collect();
+    kinds.sort_by(|a, b| b.1.cmp(a.1));
+    for (kind, count) in kinds {
+        writeln!(out, "{:>8} {}", count, kind)?;
+    }
+    writeln!(out, "\nlargest functions:")?;
+    for f in &stats.largest_functions {
+        writeln!(
+            out,
+            "{:>8} lines {} ({}:{})",
+            f.lines, f.name, f.file, f.line
+        )?;
+    }
+    if let Some(functions) = &stats.functions {
+        writeln!(out, "\nfunctions:")?;
+        for f in functions {
+            writeln!(
+                out,
+                "{:>8} complexity, nesting {}, {} lines: {} ({}:{})",
+                f.cyclomatic, f.nesting, f.lines, f.name, f.file, f.line
+            )?;
+        }
+    }
+    writeln!(out, "\nslowest files:")?;
+    for f in &stats.slowest_files {
+        writeln!(
+            out,
+            "{:>8.1} ms {} ({} bytes, {} nodes, depth {})",
+            f.parse_micros as f64 / 1000.0,
+            f.file,
+            f.bytes,
+            f.nodes,
+            f.max_depth
+        )?;
+    }
+    Ok(())
+}
+
+/// `stats [--format text|json] [--top N] [--functions] [--exclude <glob>]
+/// [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore", "--functions"],
+        &["--format", "--top", "--exclude", "--include", "--symlinks"],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: stats [--format text|json] [--top N] [--functions] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+    let top = match args.value("--top") {
+        Some(n) => n.parse()?,
+        None => 10,
+    };
+
+    let mut stats = Stats::new(top);
+    if args.flag("--functions") {
+        stats.functions = Some(vec![]);
+    }
+    for (path, name) in expand(args.positional(), &Filter::from_args(&args)?)? {
+        stats.add(&name, &read_file(&path.to_string_lossy())?)?;
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_text(&mut out, &stats)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &stats)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown stats format {}", other),
+    }
+    Ok(())
+}
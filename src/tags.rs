@@ -0,0 +1,151 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::cli::Args;
+use crate::diff::{Declaration, ParsedFile};
+use crate::files::expand;
+use crate::ignore::Filter;
+use crate::model::base_name;
+use crate::parse::read_file;
+
+/// The universal-ctags Rust kind (letter and long name) for a declaration,
+/// or `None` for declarations editors don't navigate to.
+fn tag_kind(
+    decl: &Declaration,
+    parent: Option<&Declaration>,
+) -> Option<(char, &'static str)> {
+    let in_impl =
+        parent.is_some_and(|p| p.kind == "impl_item" || p.kind == "trait_item");
+    Some(match decl.kind.as_str() {
+        "function_item" | "function_signature_item" if in_impl => ('P', "method"),
+        "function_item" | "function_signature_item" => ('f', "function"),
+        "mod_item" => ('n', "module"),
+        "struct_item" | "union_item" => ('s', "struct"),
+        "enum_item" => ('g', "enum"),
+        "trait_item" => ('i', "interface"),
+        "impl_item" => ('c', "implementation"),
+        "const_item" => ('C', "constant"),
+        "static_item" => ('v', "variable"),
+        "type_item" | "associated_type" => ('t', "typedef"),
+        "macro_definition" => ('M', "macro"),
+        _ => return None,
+    })
+}
+
+/// The identifier editors look `decl` up by: its name without the cfgs, and
+/// for an impl, the name of the type it is for, like `Point` for `impl<T>
+/// Draw for geometry::Point<T>`.
+fn tag_name(decl: &Declaration) -> &str {
+    let name = base_name(&decl.name);
+    if decl.kind != "impl_item" {
+        return name;
+    }
+    let header = name.strip_prefix("impl ").unwrap_or(name);
+    let ty = header.rsplit_once(" for ").map_or(header, |(_, ty)| ty);
+    let ty = ty.split('<').next().unwrap_or(ty);
+    let ty = ty.rsplit([' ', '&']).next().unwrap_or(ty);
+    ty.rsplit("::")
+        .next()
+        .unwrap_or(ty)
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+}
+
+struct Tag {
+    name: String,
+    file: String,
+    line: i64,
+    kind: char,
+    scope: Option<String>,
+}
+
+fn file_tags(file: &str, declarations: &[Declaration], out: &mut Vec<Tag>) {
+    for decl in declarations {
+        let parent = decl.parent.map(|p| &declarations[p]);
+        let Some((kind, _)) = tag_kind(decl, parent) else {
+            continue;
+        };
+        let scope = parent.and_then(|p| {
+            let grandparent = p.parent.map(|g| &declarations[g]);
+            tag_kind(p, grandparent).map(|(_, long)| {
+                let mut path = vec![];
+                let mut ancestor = Some(p);
+                while let Some(a) = ancestor {
+                    path.push(tag_name(a));
+                    ancestor = a.parent.map(|g| &declarations[g]);
+                }
+                path.reverse();
+                format!("{}:{}", long, path.join("::"))
+            })
+        });
+        // Editors look tags up by identifier, without the cfgs.
+        out.push(Tag {
+            name: tag_name(decl).to_string(),
+            file: file.to_string(),
+            line: decl.start[0],
+            kind,
+            scope,
+        });
+    }
+}
+
+/// Writes `tags` in the extended format universal-ctags emits, sorted by
+/// name so editors can binary-search the file.
+fn write_tags(out: &mut dyn Write, tags: &mut [Tag]) -> std::io::Result<()> {
+    tags.sort_by(|a, b| (&a.name, &a.file, a.line).cmp(&(&b.name, &b.file, b.line)));
+    writeln!(out, "!_TAG_FILE_FORMAT\t2\t/extended format/")?;
+    writeln!(
+        out,
+        "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/"
+    )?;
+    writeln!(out, "!_TAG_PROGRAM_NAME\trustsemantic\t//")?;
+    writeln!(
+        out,
+        "!_TAG_PROGRAM_VERSION\t{}\t//",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    for tag in tags.iter() {
+        write!(
+            out,
+            "{}\t{}\t{};\"\t{}\tline:{}",
+            tag.name, tag.file, tag.line, tag.kind, tag.line
+        )?;
+        if let Some(scope) = &tag.scope {
+            write!(out, "\t{}", scope)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// `tags [-f <file>] [--exclude <glob>] [--include <glob>] [--no-ignore]
+/// [--symlinks follow|skip|error] <paths...>`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--no-ignore"],
+        &["-f", "--exclude", "--include", "--symlinks"],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: tags [-f <file>] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+
+    let mut tags = vec![];
+    for (path, name) in expand(args.positional(), &Filter::from_args(&args)?)? {
+        let parsed =
+            ParsedFile::from_source(&name, read_file(&path.to_string_lossy())?)?;
+        file_tags(&name, &parsed.declarations, &mut tags);
+    }
+
+    match args.value("-f").unwrap_or("tags") {
+        "-" => write_tags(&mut std::io::stdout().lock(), &mut tags)?,
+        output => {
+            let mut file =
+                std::io::BufWriter::new(std::fs::File::create(Path::new(output))?);
+            write_tags(&mut file, &mut tags)?;
+            file.flush()?;
+        }
+    }
+    Ok(())
+}
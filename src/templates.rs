@@ -0,0 +1,160 @@
+//! Templates for the names declarations are shown with, per node kind, so
+//! teams can choose how nodes are labeled in the merge tool.
+
+use crate::metadata::normalize;
+
+/// The placeholders a template can use. Each is the text of the part of the
+/// declaration it names, with whitespace normalized, or nothing when the
+/// declaration has no such part.
+const PLACEHOLDERS: &[&str] = &[
+    "name", "kind", "vis", "generics", "params", "return", "type", "trait",
+];
+
+/// A template for each node kind that has one.
+#[derive(Default, Clone, Debug)]
+pub struct NameTemplates(Vec<(String, String)>);
+
+impl NameTemplates {
+    /// Reads `kind = "template"` lines, like
+    /// `function_item = "{vis} fn {name}{generics}"`. Blank lines and
+    /// those starting with `#` are skipped; `{{` and `}}` in a template
+    /// stand for braces.
+    pub fn parse(text: &str) -> anyhow::Result<NameTemplates> {
+        let mut templates = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| anyhow::anyhow!("line {}: {}", i + 1, message);
+            let (kind, template) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected kind = \"template\""))?;
+            let template = template
+                .trim()
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+                .ok_or_else(|| error("the template must be in double quotes"))?
+                .replace("\\\"", "\"")
+                .replace("\\\\", "\\");
+            check(&template).map_err(|e| error(&e))?;
+            templates.push((kind.trim().to_string(), template));
+        }
+        Ok(NameTemplates(templates))
+    }
+
+    pub fn read(path: &str) -> anyhow::Result<NameTemplates> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path, e))?;
+        NameTemplates::parse(&text).map_err(|e| anyhow::anyhow!("{}: {:#}", path, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The template for `kind`; the last one given wins.
+    pub fn get(&self, kind: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(k, _)| k == kind)
+            .map(|(_, template)| template.as_str())
+    }
+}
+
+/// The templates in the file given on the command line, else in the file
+/// `SEMANTIC_RUST_NAME_TEMPLATES` names, else none.
+pub fn name_templates(flag: Option<&str>) -> anyhow::Result<NameTemplates> {
+    match flag {
+        Some(path) => NameTemplates::read(path),
+        None => match std::env::var("SEMANTIC_RUST_NAME_TEMPLATES") {
+            Ok(path) => NameTemplates::read(&path),
+            Err(_) => Ok(NameTemplates::default()),
+        },
+    }
+}
+
+/// Splits `template` into literal text and placeholders, calling `f` with
+/// each: `Err` for a placeholder's name, `Ok` for text.
+fn pieces<'t>(
+    template: &'t str,
+    mut f: impl FnMut(Result<&'t str, &'t str>) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        f(Ok(&rest[..i]))?;
+        let (brace, after) = rest[i..].split_at(1);
+        if after.starts_with(brace) {
+            f(Ok(brace))?;
+            rest = &after[1..];
+        } else if brace == "}" {
+            return Err(format!("unmatched }} in {:?}", template));
+        } else {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unclosed {{ in {:?}", template))?;
+            f(Err(&after[..end]))?;
+            rest = &after[end + 1..];
+        }
+    }
+    f(Ok(rest))
+}
+
+fn check(template: &str) -> Result<(), String> {
+    pieces(template, |piece| match piece {
+        Err(name) if !PLACEHOLDERS.contains(&name) => Err(format!(
+            "unknown placeholder {{{}}}, expected one of {}",
+            name,
+            PLACEHOLDERS.join(", ")
+        )),
+        _ => Ok(()),
+    })
+}
+
+/// Fills in `template` for `node` in `src`, whose name would otherwise be
+/// `name`. Runs of whitespace collapse to one space, so placeholders that
+/// come out empty leave no gaps; a name that comes out empty stays `name`.
+pub fn render(
+    template: &str,
+    node: &tree_sitter::Node,
+    src: &str,
+    name: &str,
+) -> String {
+    let field = |field| {
+        node.child_by_field_name(field)
+            .and_then(|n| src.get(n.byte_range()))
+            .map(normalize)
+            .unwrap_or_default()
+    };
+    let mut out = String::new();
+    // Templates are checked as they are read.
+    let _ = pieces(template, |piece| {
+        match piece {
+            Ok(text) => out.push_str(text),
+            Err("name") => out.push_str(name),
+            Err("kind") => out.push_str(node.kind()),
+            Err("vis") => {
+                let mut cursor = node.walk();
+                let vis = node
+                    .children(&mut cursor)
+                    .find(|child| child.kind() == "visibility_modifier")
+                    .and_then(|vis| src.get(vis.byte_range()))
+                    .map(normalize)
+                    .unwrap_or_default();
+                out.push_str(&vis);
+            }
+            Err("generics") => out.push_str(&field("type_parameters")),
+            Err("params") => out.push_str(&field("parameters")),
+            Err("return") => out.push_str(&field("return_type")),
+            Err(other) => out.push_str(&field(other)),
+        }
+        Ok(())
+    });
+    let out = out.split_whitespace().collect::<Vec<_>>().join(" ");
+    if out.is_empty() {
+        name.to_string()
+    } else {
+        out
+    }
+}
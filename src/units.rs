@@ -0,0 +1,273 @@
+//! Re-expressing the byte offsets tree-sitter reports in the units other
+//! consumers count in.
+
+use crate::model::{CharSpan, LocationSpan, Node, SemanticFile};
+
+/// What character spans and columns count.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    /// UTF-8 bytes, as tree-sitter reports them.
+    #[default]
+    Bytes,
+    /// Unicode scalar values, as Rust's `char`s.
+    Chars,
+    /// UTF-16 code units, as .NET strings (and so SemanticMerge) count them.
+    Utf16,
+}
+
+impl Unit {
+    pub fn parse(name: &str) -> anyhow::Result<Unit> {
+        match name {
+            "bytes" => Ok(Unit::Bytes),
+            "chars" => Ok(Unit::Chars),
+            "utf16" => Ok(Unit::Utf16),
+            other => anyhow::bail!("unknown unit {}", other),
+        }
+    }
+}
+
+/// Whether a character span's end is the offset just past its last
+/// character or that of the last character itself.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ends {
+    /// `[start, end)`, as tree-sitter and Rust ranges have it; spans with
+    /// nothing in them are `[0, -1]`, or `[n, n]` for a header that ends
+    /// where it starts.
+    #[default]
+    Exclusive,
+    /// `[start, end]`, as SemanticMerge reads them; every span with nothing
+    /// in it is `[0, -1]`.
+    Inclusive,
+}
+
+impl Ends {
+    pub fn parse(name: &str) -> anyhow::Result<Ends> {
+        match name {
+            "exclusive" => Ok(Ends::Exclusive),
+            "inclusive" => Ok(Ends::Inclusive),
+            other => anyhow::bail!("unknown span end convention {}", other),
+        }
+    }
+}
+
+/// The numbers the first line and the first column are counted from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Base {
+    pub line: i64,
+    pub column: i64,
+}
+
+impl Base {
+    /// 1-based lines and 0-based columns, which SemanticMerge requires.
+    pub const SEMANTIC_MERGE: Base = Base { line: 1, column: 0 };
+
+    /// Reads a `0` or `1` given for `option`.
+    pub fn parse_one(option: &str, value: &str) -> anyhow::Result<i64> {
+        match value {
+            "0" => Ok(0),
+            "1" => Ok(1),
+            other => anyhow::bail!("{} must be 0 or 1, got {}", option, other),
+        }
+    }
+}
+
+impl Default for Base {
+    fn default() -> Base {
+        Base::SEMANTIC_MERGE
+    }
+}
+
+/// The column unit given on the command line, else in the
+/// `SEMANTIC_RUST_COLUMNS` environment variable (which also reaches the
+/// shell protocol, where SemanticMerge passes no options), else bytes.
+pub fn column_unit(flag: Option<&str>) -> anyhow::Result<Unit> {
+    match flag {
+        Some(name) => Unit::parse(name),
+        None => match std::env::var("SEMANTIC_RUST_COLUMNS") {
+            Ok(name) => Unit::parse(&name),
+            Err(_) => Ok(Unit::Bytes),
+        },
+    }
+}
+
+/// The tab width given on the command line, else in the
+/// `SEMANTIC_RUST_TAB_WIDTH` environment variable, else none: tabs count as
+/// one character.
+pub fn tab_width(flag: Option<&str>) -> anyhow::Result<Option<usize>> {
+    let value = match flag {
+        Some(value) => value.to_string(),
+        None => match std::env::var("SEMANTIC_RUST_TAB_WIDTH") {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        },
+    };
+    match value.parse()? {
+        0 => anyhow::bail!("tab width must be at least 1"),
+        width => Ok(Some(width)),
+    }
+}
+
+/// Maps byte offsets in one source to offsets in `unit`.
+pub struct Offsets {
+    /// The offset in `unit` of every byte offset, or empty for bytes.
+    table: Vec<usize>,
+}
+
+impl Offsets {
+    pub fn new(src: &str, unit: Unit) -> Offsets {
+        let table = match unit {
+            Unit::Bytes => vec![],
+            Unit::Chars | Unit::Utf16 => {
+                let mut table = Vec::with_capacity(src.len() + 1);
+                let mut pos = 0;
+                for c in src.chars() {
+                    // Offsets inside a character round down to its start.
+                    table.extend(std::iter::repeat(pos).take(c.len_utf8()));
+                    pos += if unit == Unit::Chars {
+                        1
+                    } else {
+                        c.len_utf16()
+                    };
+                }
+                table.push(pos);
+                table
+            }
+        };
+        Offsets { table }
+    }
+
+    pub fn convert(&self, byte: usize) -> usize {
+        if self.table.is_empty() {
+            byte
+        } else {
+            self.table[byte.min(self.table.len() - 1)]
+        }
+    }
+
+    fn convert_span(&self, span: &mut CharSpan) {
+        if !span.is_empty() {
+            span.span = span.span.map(|offset| self.convert(offset as usize) as i64);
+        }
+    }
+}
+
+fn each_node(nodes: &mut [Node], f: &mut impl FnMut(&mut Node)) {
+    for node in nodes {
+        f(node);
+        if let Node::Container(c) = node {
+            each_node(&mut c.children, f);
+        }
+    }
+}
+
+/// Rewrites every character span in `file`, built from `src`, in `unit`.
+pub fn convert_spans(file: &mut SemanticFile, src: &str, unit: Unit) {
+    if unit == Unit::Bytes {
+        return;
+    }
+    let offsets = Offsets::new(src, unit);
+    offsets.convert_span(&mut file.footer_span);
+    each_node(&mut file.children, &mut |node| match node {
+        Node::Container(c) => {
+            offsets.convert_span(&mut c.header_span);
+            offsets.convert_span(&mut c.footer_span);
+        }
+        Node::Terminal(t) => offsets.convert_span(&mut t.span),
+    });
+}
+
+/// Rewrites every character span in `file`, built with exclusive ends, to
+/// end the way `ends` says.
+pub fn convert_ends(file: &mut SemanticFile, ends: Ends) {
+    fn inclusive(span: &mut CharSpan) {
+        span.span = match span.span {
+            [start, end] if end > start => [start, end - 1],
+            _ => [0, -1],
+        };
+    }
+
+    if ends == Ends::Exclusive {
+        return;
+    }
+    inclusive(&mut file.footer_span);
+    each_node(&mut file.children, &mut |node| match node {
+        Node::Container(c) => {
+            inclusive(&mut c.header_span);
+            inclusive(&mut c.footer_span);
+        }
+        Node::Terminal(t) => inclusive(&mut t.span),
+    });
+}
+
+/// Rewrites every column in `file`, built from `src`, to count `unit`s from
+/// the start of its line. With a `tab_width`, a tab advances to the next
+/// multiple of it, the way editors and merge tools display it.
+pub fn convert_columns(
+    file: &mut SemanticFile,
+    src: &str,
+    unit: Unit,
+    tab_width: Option<usize>,
+) {
+    if unit == Unit::Bytes && tab_width.is_none() {
+        return;
+    }
+    // The column of every byte offset, rounding down inside a character.
+    let mut columns = Vec::with_capacity(src.len() + 1);
+    let mut column = 0;
+    for c in src.chars() {
+        columns.extend(std::iter::repeat(column).take(c.len_utf8()));
+        column = match (c, tab_width) {
+            ('\n', _) => 0,
+            ('\t', Some(width)) => (column / width + 1) * width,
+            _ => {
+                column
+                    + match unit {
+                        Unit::Bytes => c.len_utf8(),
+                        Unit::Chars => 1,
+                        Unit::Utf16 => c.len_utf16(),
+                    }
+            }
+        };
+    }
+    columns.push(column);
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let convert = |location: &mut LocationSpan| {
+        for point in [&mut location.start, &mut location.end] {
+            if let Some(&start) = line_starts.get(point[0] as usize - 1) {
+                let offset = (start + point[1] as usize).min(src.len());
+                point[1] = columns[offset] as i64;
+            }
+        }
+    };
+    convert(&mut file.location_span);
+    each_node(&mut file.children, &mut |node| match node {
+        Node::Container(c) => convert(&mut c.location_span),
+        Node::Terminal(t) => convert(&mut t.location_span),
+    });
+}
+
+/// Shifts every line and column in `file`, built with
+/// [`Base::SEMANTIC_MERGE`], to count from `base`.
+pub fn rebase(file: &mut SemanticFile, base: Base) {
+    if base == Base::SEMANTIC_MERGE {
+        return;
+    }
+    let shift = [
+        base.line - Base::SEMANTIC_MERGE.line,
+        base.column - Base::SEMANTIC_MERGE.column,
+    ];
+    let convert = |location: &mut LocationSpan| {
+        for point in [&mut location.start, &mut location.end] {
+            point[0] += shift[0];
+            point[1] += shift[1];
+        }
+    };
+    convert(&mut file.location_span);
+    each_node(&mut file.children, &mut |node| match node {
+        Node::Container(c) => convert(&mut c.location_span),
+        Node::Terminal(t) => convert(&mut t.location_span),
+    });
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde_json::{json, Value};
+
+use crate::cli::Args;
+use crate::diff::{file_diff, ParsedFile};
+use crate::files::expand;
+use crate::ignore::Filter;
+use crate::parse::read_file;
+
+/// What a watched file looked like when it was last parsed.
+struct Watched {
+    name: String,
+    modified: SystemTime,
+    len: u64,
+    parsed: ParsedFile,
+}
+
+/// Where the tree for the file named `name` lives inside `--output-dir`,
+/// mirroring the name without its `..` components.
+fn output_path(dir: &Path, name: &str) -> PathBuf {
+    let mut target = dir.to_path_buf();
+    for component in Path::new(name).components() {
+        if let Component::Normal(part) = component {
+            target.push(part);
+        }
+    }
+    target.set_extension("rs.json");
+    target
+}
+
+fn emit(
+    out: &mut dyn Write,
+    output_dir: Option<&Path>,
+    name: &str,
+    event: Value,
+) -> anyhow::Result<()> {
+    match output_dir {
+        Some(dir) => {
+            let target = output_path(dir, name);
+            if event["event"] == "removed" {
+                if target.exists() {
+                    std::fs::remove_file(target)?;
+                }
+                return Ok(());
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(target, serde_json::to_string_pretty(&event)?)?;
+        }
+        None => {
+            serde_json::to_writer(&mut *out, &event)?;
+            writeln!(out)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// `watch [--diff] [--interval <ms>] [--output-dir <dir>] [--exclude <glob>]
+/// [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>`
+///
+/// Polls the watched files and re-parses any whose modification time or
+/// size changed, emitting one JSON event per change: the new tree, or with
+/// `--diff` the declaration changes since the previous parse.
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(
+        raw,
+        &["--diff", "--no-ignore"],
+        &[
+            "--interval",
+            "--output-dir",
+            "--exclude",
+            "--include",
+            "--symlinks",
+        ],
+    )?;
+    if args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: watch [--diff] [--interval <ms>] [--output-dir <dir>] [--exclude <glob>] [--include <glob>] [--no-ignore] [--symlinks follow|skip|error] <paths...>"
+        );
+    }
+    let interval = Duration::from_millis(match args.value("--interval") {
+        Some(ms) => ms.parse()?,
+        None => 500,
+    });
+    let output_dir = args.value("--output-dir").map(Path::new);
+    let diffs = args.flag("--diff");
+    let filter = Filter::from_args(&args)?;
+
+    let stdout = std::io::stdout();
+    let mut watched: BTreeMap<PathBuf, Watched> = BTreeMap::new();
+    loop {
+        let paths = expand(args.positional(), &filter)?;
+        let mut out = stdout.lock();
+
+        for (path, name) in &paths {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                continue;
+            };
+            let modified = metadata.modified()?;
+            let len = metadata.len();
+            let previous = watched.get(path);
+            if previous.is_some_and(|w| w.modified == modified && w.len == len) {
+                continue;
+            }
+
+            let parsed = match read_file(&path.to_string_lossy())
+                .and_then(|c| ParsedFile::from_source(name, c))
+            {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    eprintln!("error: {}: {:#}", name, e);
+                    continue;
+                }
+            };
+            // Touched but not edited files are re-read quietly.
+            if previous.map_or(true, |w| w.parsed.contents != parsed.contents) {
+                let event = if diffs {
+                    let diff = file_diff(
+                        previous.map(|_| name.clone()),
+                        Some(name.clone()),
+                        previous.map(|w| &w.parsed),
+                        Some(&parsed),
+                    );
+                    json!({ "event": "changed", "path": name, "diff": diff })
+                } else {
+                    json!({ "event": "changed", "path": name, "tree": parsed.file })
+                };
+                emit(&mut out, output_dir, name, event)?;
+            }
+            let entry = Watched {
+                name: name.clone(),
+                modified,
+                len,
+                parsed,
+            };
+            watched.insert(path.clone(), entry);
+        }
+
+        let removed: Vec<PathBuf> = watched
+            .keys()
+            .filter(|p| !paths.iter().any(|(path, _)| path == *p) || !p.exists())
+            .cloned()
+            .collect();
+        for path in removed {
+            let Some(Watched { name, .. }) = watched.remove(&path) else {
+                continue;
+            };
+            let event = json!({ "event": "removed", "path": name });
+            emit(&mut out, output_dir, &name, event)?;
+        }
+
+        drop(out);
+        std::thread::sleep(interval);
+    }
+}
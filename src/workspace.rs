@@ -0,0 +1,218 @@
+//! The structure of a whole Cargo workspace: its packages, their targets,
+//! the files each target is built from and the declarations in them, so
+//! tools can reason about crates rather than one file at a time.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cli::Args;
+use crate::files::relative_name;
+use crate::outline::{inline_modules, outline, write_text, OutlineItem};
+use crate::parse::{parse_source, read_file};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub packages: Vec<Package>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// Relative to the workspace root, like every path in the model.
+    pub manifest: String,
+    /// The names of the packages it depends on, sorted.
+    pub dependencies: Vec<String>,
+    pub targets: Vec<Target>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub name: String,
+    /// As Cargo names them: `lib`, `bin`, `test`, `bench`, `example`,
+    /// `custom-build` and so on.
+    pub kinds: Vec<String>,
+    /// The crate root, followed by the file of every module below it that
+    /// could be found.
+    pub files: Vec<String>,
+    /// The declarations in the crate, with those of each `mod foo;` inlined.
+    pub items: Vec<OutlineItem>,
+    /// Why the crate root couldn't be read or parsed, in which case the
+    /// target has no files and items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The output of `cargo metadata --format-version 1 --no-deps`, run with
+/// `cargo`, for the workspace `manifest` belongs to, or the one in the
+/// current directory.
+pub fn cargo_metadata(cargo: &str, manifest: Option<&str>) -> anyhow::Result<Value> {
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(manifest) = manifest {
+        command.args(["--manifest-path", manifest]);
+    }
+    let output = command
+        .output()
+        .map_err(|e| anyhow::anyhow!("failed to run {}: {}", cargo, e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} metadata: {}",
+            cargo,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Builds the model of the workspace `metadata` describes, parsing every
+/// target's files. Packages and targets keep Cargo's order.
+pub fn model(metadata: &Value) -> anyhow::Result<Workspace> {
+    let text = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("cargo metadata without {}", key))
+    };
+    let list = |value: &Value, key: &str| -> Vec<Value> {
+        value
+            .get(key)
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let root = text(metadata, "workspace_root")?;
+    let root = Path::new(&root);
+
+    let mut packages = vec![];
+    for package in list(metadata, "packages") {
+        let mut dependencies: Vec<String> = list(&package, "dependencies")
+            .iter()
+            .filter_map(|dependency| text(dependency, "name").ok())
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+        let mut targets = vec![];
+        for target in list(&package, "targets") {
+            let src_path = text(&target, "src_path")?;
+            let kinds = list(&target, "kind")
+                .iter()
+                .filter_map(|kind| kind.as_str().map(str::to_string))
+                .collect();
+            let mut target = Target {
+                name: text(&target, "name")?,
+                kinds,
+                files: vec![],
+                items: vec![],
+                error: None,
+            };
+            match crate_items(Path::new(&src_path)) {
+                Ok(mut items) => {
+                    target
+                        .files
+                        .push(relative_name(Path::new(&src_path), root)?);
+                    relativize(&mut items, root, &mut target.files)?;
+                    target.items = items;
+                }
+                Err(e) => target.error = Some(format!("{:#}", e)),
+            }
+            targets.push(target);
+        }
+        packages.push(Package {
+            name: text(&package, "name")?,
+            version: text(&package, "version")?,
+            manifest: relative_name(
+                Path::new(&text(&package, "manifest_path")?),
+                root,
+            )?,
+            dependencies,
+            targets,
+        });
+    }
+    Ok(Workspace { packages })
+}
+
+/// The outline of the crate rooted at `root`, with its modules inlined.
+fn crate_items(root: &Path) -> anyhow::Result<Vec<OutlineItem>> {
+    let path = root.to_string_lossy();
+    let contents = read_file(&path)?;
+    let mut items = outline(&parse_source(&path, &contents)?, &contents);
+    inline_modules(&mut items, &contents, root)?;
+    Ok(items)
+}
+
+/// Makes the files modules were inlined from relative to `root`, adding
+/// each to `files`.
+fn relativize(
+    items: &mut [OutlineItem],
+    root: &Path,
+    files: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    for item in items {
+        if let Some(file) = &item.file {
+            let file = relative_name(Path::new(file), root)?;
+            files.push(file.clone());
+            item.file = Some(file);
+        }
+        relativize(&mut item.children, root, files)?;
+    }
+    Ok(())
+}
+
+fn write_model(out: &mut dyn Write, workspace: &Workspace) -> std::io::Result<()> {
+    for package in &workspace.packages {
+        writeln!(
+            out,
+            "{} {} ({})",
+            package.name, package.version, package.manifest
+        )?;
+        for target in &package.targets {
+            writeln!(
+                out,
+                "    {} {} from {}",
+                target.kinds.join(","),
+                target.name,
+                target.files.first().map_or("?", String::as_str)
+            )?;
+            if let Some(error) = &target.error {
+                writeln!(out, "        error: {}", error)?;
+            }
+            write_text(out, &target.items, 2)?;
+        }
+    }
+    Ok(())
+}
+
+/// `workspace [--format text|json] [--manifest-path <Cargo.toml>]
+/// [--cargo <program>]`
+pub fn run(raw: &[String]) -> anyhow::Result<()> {
+    let args = Args::parse(raw, &[], &["--format", "--manifest-path", "--cargo"])?;
+    if !args.positional().is_empty() {
+        anyhow::bail!(
+            "usage: workspace [--format text|json] [--manifest-path <Cargo.toml>] [--cargo <program>]"
+        );
+    }
+    let cargo = args.value("--cargo").unwrap_or("cargo");
+    let metadata = cargo_metadata(cargo, args.value("--manifest-path"))?;
+    let workspace = model(&metadata)?;
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.value("--format").unwrap_or("text") {
+        "text" => write_model(&mut out, &workspace)?,
+        "json" => {
+            serde_json::to_writer_pretty(&mut out, &workspace)?;
+            writeln!(out)?;
+        }
+        other => anyhow::bail!("unknown workspace format {}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! `api` snapshots the public items of a crate, across its module files.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use rustsemantic::api::{api, ApiItem};
+
+use common::scratch;
+
+fn write_crate(root: &Path) {
+    let files = [
+        ("Cargo.toml", "[package]\nname = \"shapes\"\n"),
+        (
+            "src/lib.rs",
+            "pub mod point;\nmod private;\n#[path = \"gen/generated.rs\"]\npub mod generated;\npub use point::Point;\npub(crate) fn hidden() {}\nfn helper() {}\npub const LIMIT: usize = 10;\n#[macro_export]\nmacro_rules! square { ($x:expr) => { $x * $x }; }\nmacro_rules! quiet { () => {}; }\npub trait Draw {\n    fn draw(&self) -> String;\n}\npub enum Shape { Circle(f64), Square { side: f64 } }\npub async fn fetch(url: &str) -> String { url.into() }\n",
+        ),
+        (
+            "src/point.rs",
+            "pub struct Point { pub x: i32, y: i32 }\nimpl Point {\n    pub const fn new(x: i32, y: i32) -> Self { Point { x, y } }\n    fn secret(&self) {}\n}\nimpl Point {\n    fn only_private(&self) {}\n}\nimpl crate::Draw for Point {\n    fn draw(&self) -> String { String::new() }\n}\n",
+        ),
+        ("src/private.rs", "pub fn unreachable() {}\n"),
+        ("src/gen/generated.rs", "pub fn generated() {}\n"),
+    ];
+    for (name, contents) in files {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+/// Every item as `type path`, depth first.
+fn paths(items: &[ApiItem], prefix: &str, out: &mut Vec<String>) {
+    for item in items {
+        let path = format!("{}{}", prefix, item.name);
+        out.push(format!("{} {}", item.item_type, path));
+        paths(&item.children, &format!("{}::", path), out);
+    }
+}
+
+#[test]
+fn only_public_items_are_kept() {
+    let root = scratch("api-public");
+    write_crate(&root);
+    let api = api(&root).unwrap();
+    assert_eq!(api.name, "shapes");
+    let mut all = vec![];
+    paths(&api.items, "", &mut all);
+    assert_eq!(
+        all,
+        [
+            "mod_item point",
+            "struct_item point::Point",
+            "field_declaration point::Point::x",
+            "impl_item point::impl Point",
+            "function_item point::impl Point::new",
+            "impl_item point::impl crate::Draw for Point",
+            "function_item point::impl crate::Draw for Point::draw",
+            "mod_item generated",
+            "function_item generated::generated",
+            "use_declaration point::Point",
+            "const_item LIMIT",
+            "macro_definition square",
+            "trait_item Draw",
+            "function_signature_item Draw::draw",
+            "enum_item Shape",
+            "enum_variant Shape::Circle",
+            "enum_variant Shape::Square",
+            "function_item fetch",
+        ]
+    );
+}
+
+#[test]
+fn items_sharing_a_path_are_all_kept() {
+    let root = scratch("api-shared");
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub mod util {\n    pub fn a() {}\n}\npub fn util() {}\npub struct S;\nimpl S {\n    pub fn a() {}\n}\nimpl S {\n    pub fn b() {}\n}\n",
+    )
+    .unwrap();
+    let mut all = vec![];
+    paths(&api(&root).unwrap().items, "", &mut all);
+    assert_eq!(
+        all,
+        [
+            "mod_item util",
+            "function_item util::a",
+            "function_item util",
+            "struct_item S",
+            "impl_item impl S",
+            "function_item impl S::a",
+            "impl_item impl S",
+            "function_item impl S::b",
+        ]
+    );
+}
+
+#[test]
+fn snapshots_carry_signatures_but_no_positions() {
+    let root = scratch("api-snapshot");
+    write_crate(&root);
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("api")
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let items = json["items"].as_array().unwrap();
+    let fetch = items.iter().find(|item| item["name"] == "fetch").unwrap();
+    assert_eq!(fetch["signature"]["parameters"][0], "&str");
+    assert_eq!(fetch["signature"]["returnType"], "String");
+    assert_eq!(fetch["qualifiers"][0], "async");
+    let limit = items.iter().find(|item| item["name"] == "LIMIT").unwrap();
+    assert_eq!(limit["valueType"], "usize");
+
+    let text = String::from_utf8(output.stdout).unwrap();
+    for position in ["locationSpan", "span", "line"] {
+        assert!(!text.contains(&format!("\"{}\"", position)), "{}", text);
+    }
+
+    // Private changes leave the snapshot alone.
+    std::fs::write(
+        root.join("src/private.rs"),
+        "pub fn unreachable(changed: u8) {}\n",
+    )
+    .unwrap();
+    let again = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("api")
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(again.stdout).unwrap(), text);
+}
@@ -0,0 +1,135 @@
+//! `api-diff` classifies the changes between two API snapshots and says
+//! whether they break users.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rustsemantic::api::api;
+use rustsemantic::apidiff::{api_diff, Verdict};
+
+use common::scratch;
+
+fn write_lib(dir: &Path, lib: &str) -> PathBuf {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+    dir.to_path_buf()
+}
+
+const OLD: &str = "pub trait Draw {\n    fn draw(&self);\n}\npub enum Shape { Circle, Square }\npub struct Point;\nimpl Point {\n    pub const fn new() -> Self { Point }\n    pub fn norm(&self) -> f64 { 0.0 }\n}\npub fn area(side: f64) -> f64 { side * side }\npub fn gone() {}\nfn private() {}\n";
+
+#[test]
+fn changes_are_classified_as_breaking_or_not() {
+    let root = scratch("apidiff-classify");
+    let old = write_lib(&root.join("old"), OLD);
+    let new = write_lib(
+        &root.join("new"),
+        "pub trait Draw {\n    fn draw(&self);\n    fn name(&self) -> String { String::new() }\n}\npub enum Shape { Circle, Square, Dot }\npub struct Point;\nimpl Point {\n    pub const fn new() -> Self { Point }\n    pub const fn norm(&self) -> f64 { 0.0 }\n}\npub fn area(side: f64, scale: f64) -> f64 { side * scale }\npub fn added() {}\nfn private(changed: u8) {}\n",
+    );
+    let diff = api_diff(&api(&old).unwrap(), &api(&new).unwrap());
+    let found: Vec<String> = diff
+        .changes
+        .iter()
+        .map(|change| {
+            format!(
+                "{:?} {} {}",
+                change.change,
+                change.path,
+                if change.breaking { "breaking" } else { "ok" }
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "Added Draw::name ok",
+            "Added Shape::Dot breaking",
+            "Added added ok",
+            "Changed area breaking",
+            "Removed gone breaking",
+            "Changed impl Point::norm ok",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(diff.verdict, Verdict::Major);
+}
+
+#[test]
+fn items_sharing_a_path_are_compared_apart() {
+    let root = scratch("apidiff-shared");
+    let old = "pub mod util {\n    pub fn a() {}\n}\npub fn util() {}\npub struct S;\nimpl S {\n    pub fn a() {}\n}\nimpl S {\n    pub fn b() {}\n}\n#[cfg(unix)]\npub fn sys() {}\n#[cfg(windows)]\npub fn sys() {}\n";
+    let new = old
+        .replace("pub fn util() {}\n", "")
+        .replace("    pub fn a() {}\n}\nimpl S {\n", "    pub fn a() {}\n")
+        .replace(
+            "#[cfg(windows)]\npub fn sys() {}",
+            "#[cfg(windows)]\npub fn sys(x: u8) {}",
+        );
+    let old = api(&write_lib(&root.join("old"), old)).unwrap();
+    let new = api(&write_lib(&root.join("new"), &new)).unwrap();
+    let found: Vec<String> = api_diff(&old, &new)
+        .changes
+        .iter()
+        .map(|change| {
+            format!("{:?} {} {}", change.change, change.item_type, change.path)
+        })
+        .collect();
+    assert_eq!(
+        found,
+        ["Changed function_item sys #2", "Removed function_item util"]
+    );
+}
+
+#[test]
+fn verdicts_follow_the_worst_change() {
+    let root = scratch("apidiff-verdict");
+    let old = api(&write_lib(&root.join("old"), OLD)).unwrap();
+    let same = api(&write_lib(&root.join("same"), &format!("{}\n", OLD))).unwrap();
+    assert_eq!(api_diff(&old, &same).verdict, Verdict::Patch);
+    assert!(api_diff(&old, &same).changes.is_empty());
+    let more = api(&write_lib(
+        &root.join("more"),
+        &format!("{}pub fn more() {{}}\n", OLD),
+    ))
+    .unwrap();
+    assert_eq!(api_diff(&old, &more).verdict, Verdict::Minor);
+    assert_eq!(api_diff(&more, &old).verdict, Verdict::Major);
+}
+
+#[test]
+fn snapshots_compare_against_crates_with_an_exit_code() {
+    let root = scratch("apidiff-cli");
+    let old = write_lib(&root.join("old"), OLD);
+    let snapshot = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("api")
+        .arg(&old)
+        .output()
+        .unwrap();
+    std::fs::write(root.join("old.json"), snapshot.stdout).unwrap();
+    let run = |new: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+            .args(["api-diff", "--format", "json"])
+            .arg(root.join("old.json"))
+            .arg(new)
+            .output()
+            .unwrap()
+    };
+
+    let same = run(&old);
+    assert_eq!(same.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&same.stdout).unwrap();
+    assert_eq!(json["verdict"], "patch");
+
+    let broken =
+        write_lib(&root.join("broken"), &OLD.replace("pub fn gone() {}\n", ""));
+    let output = run(&broken);
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["verdict"], "major");
+    assert_eq!(json["changes"][0]["change"], "removed");
+    assert_eq!(json["changes"][0]["path"], "gone");
+    assert_eq!(json["changes"][0]["old"], "function_item fn()");
+}
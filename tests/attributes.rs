@@ -0,0 +1,39 @@
+use rustsemantic::model::Node;
+use rustsemantic::parse::parse_source;
+
+#[test]
+fn outer_attributes_and_docs_belong_to_their_item() {
+    let src = "//! Crate docs.\n\n/// A pixel.\n#[derive(Debug)]\nstruct Pixel {}\n";
+    let file = parse_source("attributes.rs", src).unwrap();
+    let kinds: Vec<_> = file.children.iter().map(|c| c.kind()).collect();
+    assert_eq!(kinds, ["line_comment", "struct_item"]);
+
+    let pixel = &file.children[1];
+    assert_eq!(pixel.name(), "Pixel");
+    assert_eq!(pixel.location_span().start, [3, 0]);
+    assert!(src[pixel.text_range(src)].starts_with("/// A pixel.\n#[derive(Debug)]"));
+}
+
+#[test]
+fn attributes_with_no_item_after_them_stand_alone() {
+    let src = "fn f() {}\n\n#[cfg(test)]\n";
+    let file = parse_source("attributes.rs", src).unwrap();
+    let kinds: Vec<_> = file.children.iter().map(|c| c.kind()).collect();
+    assert_eq!(kinds, ["function_item", "attribute_item"]);
+    assert!(matches!(file.children[0], Node::Container(_)));
+}
+
+#[test]
+fn cfg_gated_versions_of_an_item_have_distinct_names() {
+    let src = "#[cfg(unix)]\nfn open() {}\n\n#[cfg(windows)]\n#[inline]\nfn open() {}\n\n#[cfg(  test )]\nfn open() {}\n";
+    let file = parse_source("attributes.rs", src).unwrap();
+    let names: Vec<_> = file.children.iter().map(|c| c.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "open #[cfg(unix)]",
+            "open #[cfg(windows)]",
+            "open #[cfg(test)]"
+        ]
+    );
+}
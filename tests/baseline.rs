@@ -0,0 +1,121 @@
+//! `baseline check` fails when declarations of the baseline were removed or
+//! renamed, and lets additions and moves through.
+
+mod common;
+
+use std::process::Command;
+
+use rustsemantic::baseline::{check, inventory, DriftKind};
+
+use common::scratch;
+
+fn files(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, contents)| (name.to_string(), contents.to_string()))
+        .collect()
+}
+
+const LIB: &str = "mod shapes {\n    pub fn area(side: f64) -> f64 {\n        side * side\n    }\n    pub fn perimeter(side: f64) -> f64 {\n        4.0 * side\n    }\n}\nstruct Point;\nimpl Point {\n    fn new() -> Self {\n        Point\n    }\n}\nfn helper() {}\n";
+
+#[test]
+fn removals_renames_and_moves_are_told_apart() {
+    let paths = ["src".to_string()];
+    let baseline = inventory(&paths, files(&[("src/lib.rs", LIB)])).unwrap();
+    let lib = LIB
+        .replace("fn area(", "fn square_area(")
+        .replace(
+            "impl Point {\n    fn new() -> Self {\n        Point\n    }\n}\n",
+            "",
+        )
+        .replace("fn helper() {}\n", "fn added() {}\n");
+    let current = inventory(
+        &paths,
+        files(&[("src/lib.rs", &lib), ("src/util.rs", "fn helper() {}\n")]),
+    )
+    .unwrap();
+
+    let found: Vec<String> = check(&baseline, &current)
+        .iter()
+        .map(|d| format!("{:?} {} {:?}", d.change, d.name, d.now))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "Renamed shapes::area Some(\"src/lib.rs:shapes::square_area\")",
+            "Removed impl Point None",
+            "Moved helper Some(\"src/util.rs:helper\")",
+        ]
+    );
+    assert!(check(&baseline, &baseline).is_empty());
+}
+
+#[test]
+fn renamed_types_traits_and_unions_are_caught() {
+    let paths = ["src".to_string()];
+    let lib = "pub(crate) struct Foo;\npub trait Alpha {}\nunion U {\n    a: u32,\n}\n";
+    let baseline = inventory(&paths, files(&[("src/lib.rs", lib)])).unwrap();
+    let renamed = lib
+        .replace("Foo", "Bar")
+        .replace("Alpha", "Beta")
+        .replace("union U", "union V");
+    let current = inventory(&paths, files(&[("src/lib.rs", &renamed)])).unwrap();
+    let found: Vec<String> = check(&baseline, &current)
+        .iter()
+        .map(|d| format!("{:?} {} {:?}", d.change, d.name, d.now))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "Renamed Foo Some(\"src/lib.rs:Bar\")",
+            "Renamed Alpha Some(\"src/lib.rs:Beta\")",
+            "Renamed U Some(\"src/lib.rs:V\")",
+        ]
+    );
+}
+
+#[test]
+fn a_renamed_module_is_reported_once() {
+    let paths = ["src".to_string()];
+    let baseline = inventory(&paths, files(&[("src/lib.rs", LIB)])).unwrap();
+    let current = inventory(
+        &paths,
+        files(&[("src/lib.rs", &LIB.replace("mod shapes", "mod geometry"))]),
+    )
+    .unwrap();
+    let drift = check(&baseline, &current);
+    assert_eq!(drift.len(), 1, "{:?}", drift);
+    assert_eq!(drift[0].change, DriftKind::Renamed);
+    assert_eq!(drift[0].now.as_deref(), Some("src/lib.rs:geometry"));
+}
+
+#[test]
+fn check_fails_until_the_baseline_is_updated() {
+    let dir = scratch("baseline-cli");
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/lib.rs"), LIB).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+            .current_dir(&dir)
+            .arg("baseline")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert_eq!(run(&["create", "src"]).status.code(), Some(0));
+    assert!(dir.join(".rustsemantic-baseline.json").is_file());
+    assert_eq!(run(&["check"]).status.code(), Some(0));
+
+    std::fs::write(dir.join("src/lib.rs"), LIB.replace("fn helper() {}\n", ""))
+        .unwrap();
+    let output = run(&["check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "src/lib.rs:helper: function_item removed\n"
+    );
+
+    assert_eq!(run(&["create", "src"]).status.code(), Some(0));
+    assert_eq!(run(&["check", "src"]).status.code(), Some(0));
+}
@@ -0,0 +1,40 @@
+//! Files that look binary are refused, or made one opaque node, before
+//! tree-sitter sees them.
+
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{is_binary, parse_source_with, Binary, ParseOptions};
+
+#[test]
+fn binary_looking_contents_are_detected() {
+    assert!(is_binary(b"fn f() {}\0\n"));
+    assert!(is_binary(b"\x01\x02\x03\x04abc"));
+    assert!(!is_binary(b"fn f() {\n\tg();\r\n}\x0c\n"));
+    assert!(!is_binary(b""));
+    assert!(!is_binary(
+        "// \u{1b}[1m bold \u{1b}[0m in a comment, once\nfn f() {}\n".as_bytes()
+    ));
+}
+
+#[test]
+fn binary_files_are_refused_or_opaque() {
+    let src = "\u{7f}ELF\u{2}\u{1}\u{1}\0\0\0\nmore\n";
+    let error = parse_source_with("a.rs", src, &ParseOptions::default()).unwrap_err();
+    assert!(error.to_string().contains("looks binary"), "{}", error);
+
+    let options = ParseOptions {
+        binary: Binary::Opaque,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("a.rs", src, &options).unwrap();
+    common::check_tiling(&file, src).unwrap();
+    common::check_locations(&file, src).unwrap();
+    let [Node::Terminal(binary)] = &file.children[..] else {
+        panic!("expected one terminal, got {:?}", file.children);
+    };
+    assert_eq!(binary.item_type, "binary");
+    assert_eq!(binary.span.span, [0, src.len() as i64]);
+    assert!(!file.parsing_errors_detected);
+    assert!(Binary::parse("maybe").is_err());
+}
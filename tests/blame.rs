@@ -0,0 +1,219 @@
+//! `blame` counts `git blame`'s lines per declaration, so each function has
+//! an owner rather than a list of line authors, and `owners` rolls them up
+//! to files, modules and impls.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use rustsemantic::blame::BlameLine;
+use rustsemantic::diff::ParsedFile;
+use rustsemantic::owners::owners;
+
+use common::{scratch, Scratch};
+
+fn commit_as(repo: &Path, author: &str, date: &str, contents: &str) {
+    std::fs::write(repo.join("lib.rs"), contents).unwrap();
+    let email = format!("{}@example.com", author.to_lowercase());
+    for args in [&["add", "lib.rs"][..], &["commit", "-q", "-m", author]] {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(repo)
+            .env("GIT_AUTHOR_NAME", author)
+            .env("GIT_AUTHOR_EMAIL", &email)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_NAME", author)
+            .env("GIT_COMMITTER_EMAIL", &email)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
+
+fn init(test: &str) -> Scratch {
+    let repo = scratch(&format!("blame-{}", test));
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    repo
+}
+
+fn run(repo: &Path, command: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg(command)
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn declarations_are_owned_by_whoever_wrote_most_of_them() {
+    let repo = init("declarations");
+    commit_as(
+        &repo,
+        "Ada",
+        "2020-01-01T12:00:00Z",
+        "struct Point {\n    x: i32,\n}\n\nimpl Point {\n    fn norm(&self) -> i32 {\n        self.x\n    }\n}\n",
+    );
+    commit_as(
+        &repo,
+        "Grace",
+        "2021-01-01T12:00:00Z",
+        "struct Point {\n    x: i32,\n}\n\nimpl Point {\n    fn norm(&self) -> i32 {\n        let x = self.x;\n        x.abs()\n    }\n\n    fn twice(&self) -> i32 {\n        self.x * 2\n    }\n}\n",
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run(&repo, "blame", &["--format", "json", "lib.rs"]))
+            .unwrap();
+    // Which author git gives a line of only `}` depends on its diff, so
+    // only owners and totals are checked.
+    let owners: Vec<(&str, &str, u64)> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|decl| {
+            (
+                decl["qualifiedName"].as_str().unwrap(),
+                decl["authors"][0]["name"].as_str().unwrap(),
+                decl["lines"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        owners,
+        [
+            ("Point", "Ada", 3),
+            ("impl Point", "Ada", 2),
+            ("impl Point::norm", "Grace", 4),
+            ("impl Point::twice", "Grace", 3),
+        ]
+    );
+    assert_eq!(json[2]["authors"].as_array().unwrap().len(), 2);
+    assert_eq!(json[3]["lastChange"]["author"], "Grace");
+
+    // As of the first commit, Ada wrote everything.
+    let table = run(&repo, "blame", &["--rev", "HEAD~1", "lib.rs"]);
+    assert!(table.starts_with("OWNER"), "{}", table);
+    assert!(
+        table.lines().skip(1).all(|row| row.starts_with("Ada ")),
+        "{}",
+        table
+    );
+    assert_eq!(table.lines().count(), 4, "{}", table);
+}
+
+#[test]
+fn ownership_rolls_up_to_files_modules_and_impls() {
+    let repo = init("containers");
+    commit_as(
+        &repo,
+        "Ada",
+        "2020-01-01T12:00:00Z",
+        "mod shapes {\n    pub struct Square {\n        pub side: u32,\n    }\n}\n\nimpl shapes::Square {\n    fn area(&self) -> u32 {\n        self.side * self.side\n    }\n}\n",
+    );
+    commit_as(
+        &repo,
+        "Grace",
+        "2021-01-01T12:00:00Z",
+        "mod shapes {\n    pub struct Square {\n        pub side: u32,\n    }\n}\n\nimpl shapes::Square {\n    fn area(&self) -> u32 {\n        self.side * self.side\n    }\n\n    fn perimeter(&self) -> u32 {\n        4 * self.side\n    }\n}\n",
+    );
+    // Not committed, so there is nothing to blame.
+    std::fs::write(repo.join("new.rs"), "fn draft() {}\n").unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run(&repo, "owners", &["--format", "json", "."]))
+            .unwrap();
+    let shares: Vec<String> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|ownership| {
+            let authors: Vec<String> = ownership["authors"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|share| format!("{} {}", share["name"], share["percent"]))
+                .collect();
+            format!(
+                "{} {} {}: {}",
+                ownership["file"],
+                ownership["kind"],
+                ownership["lines"],
+                authors.join(", ")
+            )
+        })
+        .collect();
+    assert_eq!(
+        shares,
+        [
+            r#""lib.rs" "file" 13: "Ada" 76.9, "Grace" 23.1"#,
+            r#""lib.rs" "mod_item" 5: "Ada" 100.0"#,
+            r#""lib.rs" "impl_item" 8: "Ada" 62.5, "Grace" 37.5"#,
+        ]
+    );
+
+    let csv = run(&repo, "owners", &["--format", "csv", "lib.rs"]);
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        rows[0],
+        "file,container,kind,lines,author,email,author_lines,percent"
+    );
+    assert_eq!(rows.len(), 6, "{}", csv);
+    assert_eq!(
+        rows[3],
+        "lib.rs,shapes,mod_item,5,Ada,ada@example.com,5,100"
+    );
+}
+
+/// The blame of `contents` with its lines written by `authors`, in order.
+fn blamed(contents: &str, authors: &[&str]) -> Vec<BlameLine> {
+    authors
+        .iter()
+        .zip(contents.lines())
+        .enumerate()
+        .map(|(i, (author, text))| BlameLine {
+            line: i as i64 + 1,
+            commit: format!("{:040}", i),
+            author: author.to_string(),
+            email: format!("{}@example.com", author.to_lowercase()),
+            time: 0,
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn shares_are_rounded_to_a_tenth() {
+    let contents = "fn a() {}\nfn b() {}\nfn c() {}\n";
+    let parsed = ParsedFile::from_source("lib.rs", contents.to_string()).unwrap();
+    let lines = blamed(contents, &["Ada", "Ada", "Grace"]);
+    let percents: Vec<f64> = owners("lib.rs", &parsed, &lines)[0]
+        .authors
+        .iter()
+        .map(|share| share.percent)
+        .collect();
+    assert_eq!(percents, [66.7, 33.3]);
+}
+
+#[test]
+fn impls_are_told_apart_by_type_and_trait() {
+    let contents = "impl A {\n    fn a() {}\n}\nimpl B {\n    fn b() {}\n}\nimpl Draw for A {\n    fn draw() {}\n}\n";
+    let parsed = ParsedFile::from_source("lib.rs", contents.to_string()).unwrap();
+    let lines = blamed(contents, &["Ada"; 9]);
+    let containers: Vec<String> = owners("lib.rs", &parsed, &lines)
+        .into_iter()
+        .map(|ownership| ownership.container)
+        .collect();
+    assert_eq!(containers, ["", "impl A", "impl B", "impl Draw for A"]);
+}
@@ -0,0 +1,50 @@
+//! A leading byte order mark is left out of the tree, and merges keep it.
+
+mod common;
+
+use std::process::Command;
+
+use rustsemantic::parse::{has_bom, parse_source, read_file, BOM};
+
+use common::scratch;
+
+const SOURCE: &str = "fn f() {}\nstruct S;\n";
+
+#[test]
+fn spans_and_columns_start_after_the_bom() {
+    let dir = scratch("bom-parse");
+    let path = dir.join("bom.rs");
+    std::fs::write(&path, format!("{}{}", BOM, SOURCE)).unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    assert!(has_bom(&path));
+    let contents = read_file(&path).unwrap();
+    assert_eq!(contents, SOURCE);
+    let file = parse_source("bom.rs", &contents).unwrap();
+    common::check_tiling(&file, &contents).unwrap();
+    assert_eq!(file.children[0].location_span().start, [1, 0]);
+    assert_eq!(file.children[0].byte_range(), 0..9);
+}
+
+#[test]
+fn merges_keep_the_local_bom() {
+    let dir = scratch("bom-merge");
+    let write = |name: &str, text: String| {
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    };
+    let base = write("base.rs", SOURCE.to_string());
+    let local = write("local.rs", format!("{}{}", BOM, SOURCE));
+    let remote = write("remote.rs", format!("{}fn g() {{}}\n", SOURCE));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("merge")
+        .args([&base, &local, &remote])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let merged = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(merged, format!("{}{}fn g() {{}}\n", BOM, SOURCE));
+    assert!(!has_bom(&base.to_string_lossy()));
+}
@@ -0,0 +1,148 @@
+//! `churn` counts the commits that changed each declaration, following
+//! renames of declarations and of their files.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::scratch;
+
+fn git(repo: &Path, author: &str, args: &[&str]) {
+    let email = format!("{}@example.com", author.to_lowercase());
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .env("GIT_AUTHOR_NAME", author)
+        .env("GIT_AUTHOR_EMAIL", &email)
+        .env("GIT_COMMITTER_NAME", author)
+        .env("GIT_COMMITTER_EMAIL", &email)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn commit(repo: &Path, author: &str, file: &str, contents: &str) {
+    std::fs::write(repo.join(file), contents).unwrap();
+    git(repo, author, &["add", "-A"]);
+    git(repo, author, &["commit", "-q", "-m", "change"]);
+}
+
+fn churn(repo: &Path, args: &[&str]) -> Vec<(String, String, u64, usize, bool)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("churn")
+        .args(["--format", "json"])
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    json.as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["file"].as_str().unwrap().to_string(),
+                entry["name"].as_str().unwrap().to_string(),
+                entry["changes"].as_u64().unwrap(),
+                entry["authors"].as_array().unwrap().len(),
+                entry["removed"].as_bool().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn declarations_are_counted_across_renames() {
+    let repo = scratch("churn-history");
+    git(&repo, "Ada", &["init", "-q"]);
+    commit(
+        &repo,
+        "Ada",
+        "lib.rs",
+        "fn parse() -> u8 {\n    1\n}\n\nfn helper(x: u8) -> u8 {\n    x + 1\n}\n\nfn gone() {}\n",
+    );
+    commit(
+        &repo,
+        "Grace",
+        "lib.rs",
+        "fn parse() -> u8 {\n    2\n}\n\nfn helper(x: u8) -> u8 {\n    x + 1\n}\n",
+    );
+    commit(
+        &repo,
+        "Ada",
+        "lib.rs",
+        "fn parse() -> u8 {\n    3\n}\n\nfn assist(x: u8) -> u8 {\n    x + 1\n}\n",
+    );
+    git(&repo, "Ada", &["mv", "lib.rs", "core.rs"]);
+    commit(
+        &repo,
+        "Ada",
+        "core.rs",
+        "fn parse() -> u8 {\n    3\n}\n\nfn assist(x: u8) -> u8 {\n    x + 2\n}\n",
+    );
+
+    let entry = |file: &str, name: &str, changes, authors, removed| {
+        (
+            file.to_string(),
+            name.to_string(),
+            changes,
+            authors,
+            removed,
+        )
+    };
+    assert_eq!(
+        churn(&repo, &["."]),
+        [
+            entry("core.rs", "assist", 3, 1, false),
+            entry("core.rs", "parse", 3, 2, false),
+            entry("lib.rs", "gone", 2, 2, true),
+        ]
+    );
+    assert_eq!(
+        churn(&repo, &["--since", "HEAD~2", "--top", "1", "."]),
+        [entry("core.rs", "assist", 2, 1, false)]
+    );
+}
+
+#[test]
+fn methods_of_different_impls_are_counted_apart() {
+    let repo = scratch("churn-impls");
+    git(&repo, "Ada", &["init", "-q"]);
+    let imp = |ty: &str, n: u8| {
+        format!(
+            "impl {} {{\n    fn new() -> u8 {{\n        {}\n    }}\n}}\n",
+            ty, n
+        )
+    };
+    commit(
+        &repo,
+        "Ada",
+        "lib.rs",
+        &format!("{}\n{}", imp("A", 1), imp("B", 1)),
+    );
+    // An impl above them doesn't shift their history onto each other.
+    let lib = |a, b| format!("{}\n{}\n{}", imp("C", 1), imp("A", a), imp("B", b));
+    commit(&repo, "Grace", "lib.rs", &lib(1, 1));
+    commit(&repo, "Ada", "lib.rs", &lib(1, 2));
+    commit(&repo, "Grace", "lib.rs", &lib(2, 3));
+
+    let mut methods: Vec<(String, u64, usize)> = churn(&repo, &["."])
+        .into_iter()
+        .filter(|(_, name, ..)| name.ends_with("::new"))
+        .map(|(_, name, changes, authors, _)| (name, changes, authors))
+        .collect();
+    methods.sort();
+    assert_eq!(
+        methods,
+        [
+            ("impl A::new".to_string(), 1, 1),
+            ("impl B::new".to_string(), 2, 2),
+        ]
+    );
+}
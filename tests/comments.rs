@@ -0,0 +1,83 @@
+//! `--comments` decides where comments that aren't doc comments go.
+
+mod common;
+
+use rustsemantic::parse::{parse_source_with, Comments, ParseOptions};
+
+const SOURCE: &str = "// Helpers for working with the points we draw on screen.\nfn a() {} // the first one\n\n/* set up */\nfn b() {}\n// the end\n";
+
+fn children(comments: Comments) -> Vec<(String, String)> {
+    let options = ParseOptions {
+        comments,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+    common::check_tiling(&file, SOURCE).unwrap();
+    common::check_locations(&file, SOURCE).unwrap();
+    file.children
+        .iter()
+        .map(|child| {
+            let range = child.text_range(SOURCE);
+            (child.name().to_string(), SOURCE[range].to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn comments_stand_alone_by_default() {
+    let names: Vec<_> = children(Comments::Separate)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "line_comment",
+            "a",
+            "line_comment",
+            "block_comment",
+            "b",
+            "line_comment"
+        ]
+    );
+}
+
+#[test]
+fn comments_can_be_named_after_their_first_words() {
+    let names: Vec<_> = children(Comments::Named)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "// Helpers for working with the points…",
+            "a",
+            "// the first one",
+            "/* set up",
+            "b",
+            "// the end"
+        ]
+    );
+}
+
+#[test]
+fn comments_can_belong_to_the_item_after_them() {
+    let children = children(Comments::Following);
+    let names: Vec<_> = children.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["a", "b", "line_comment"]);
+    assert!(children[0].1.starts_with("// Helpers"));
+    assert!(children[1]
+        .1
+        .starts_with("// the first one\n\n/* set up */\nfn b"));
+}
+
+#[test]
+fn comments_can_belong_to_the_item_before_them() {
+    let children = children(Comments::Preceding);
+    let names: Vec<_> = children.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["line_comment", "a", "b"]);
+    assert_eq!(children[1].1, "fn a() {} // the first one\n\n/* set up */");
+    assert_eq!(children[2].1, "fn b() {}\n// the end");
+    assert!(Comments::parse("sideways").is_err());
+}
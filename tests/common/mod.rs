@@ -0,0 +1,170 @@
+//! Invariants every emitted tree must satisfy, shared by the test suites.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use rustsemantic::model::{is_declaration_kind, CharSpan, Node, SemanticFile};
+
+/// An empty directory of its own for a test, removed when dropped.
+pub struct Scratch(PathBuf);
+
+impl std::ops::Deref for Scratch {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for Scratch {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<std::ffi::OsStr> for Scratch {
+    fn as_ref(&self) -> &std::ffi::OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A fresh [`Scratch`] directory named after `prefix` and this process, so
+/// test binaries running side by side don't share one.
+pub fn scratch(prefix: &str) -> Scratch {
+    let dir = std::env::temp_dir().join(format!(
+        "rustsemantic-{}-{}",
+        prefix,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    Scratch(dir)
+}
+
+/// The spans of `file` in document order: each container's header, then its
+/// children, then its footer. Empty spans are skipped.
+pub fn spans(file: &SemanticFile) -> Vec<[i64; 2]> {
+    fn visit(node: &Node, out: &mut Vec<[i64; 2]>) {
+        match node {
+            Node::Container(c) => {
+                push(&c.header_span, out);
+                for child in &c.children {
+                    visit(child, out);
+                }
+                push(&c.footer_span, out);
+            }
+            Node::Terminal(t) => push(&t.span, out),
+        }
+    }
+    fn push(span: &CharSpan, out: &mut Vec<[i64; 2]>) {
+        if !span.is_empty() {
+            out.push(span.span);
+        }
+    }
+
+    let mut out = vec![];
+    for child in &file.children {
+        visit(child, &mut out);
+    }
+    push(&file.footer_span, &mut out);
+    out
+}
+
+/// Checks that the spans of `file` cover `src` exactly once, in order.
+pub fn check_tiling(file: &SemanticFile, src: &str) -> Result<(), String> {
+    let mut pos = 0;
+    for [start, end] in spans(file) {
+        if start != pos {
+            return Err(format!("span [{}, {}] should start at {}", start, end, pos));
+        }
+        if end < start || end as usize > src.len() {
+            return Err(format!("span [{}, {}] is out of bounds", start, end));
+        }
+        pos = end;
+    }
+    if pos as usize != src.len() {
+        return Err(format!("spans end at {} of {} bytes", pos, src.len()));
+    }
+    Ok(())
+}
+
+/// Checks that every declaration-level node has a name.
+pub fn check_names(file: &SemanticFile) -> Result<(), String> {
+    fn visit(node: &Node) -> Result<(), String> {
+        if is_declaration_kind(node.kind()) && node.name().trim().is_empty() {
+            let line = node.location_span().start[0];
+            return Err(format!("{} on line {} has no name", node.kind(), line));
+        }
+        node.children().iter().try_for_each(visit)
+    }
+    file.children.iter().try_for_each(visit)
+}
+
+/// Checks that every node has some text of its own other than whitespace.
+pub fn check_text(file: &SemanticFile, src: &str) -> Result<(), String> {
+    fn visit(node: &Node, src: &str) -> Result<(), String> {
+        if src[node.text_range(src)].trim().is_empty() {
+            let [line, column] = node.location_span().start;
+            return Err(format!(
+                "{} at {}:{} has no text",
+                node.kind(),
+                line,
+                column
+            ));
+        }
+        node.children()
+            .iter()
+            .try_for_each(|child| visit(child, src))
+    }
+    file.children.iter().try_for_each(|child| visit(child, src))
+}
+
+/// Byte offsets where each line of `src` starts.
+pub fn line_starts(src: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// The 1-based line and 0-based byte column of `offset`, the way node
+/// locations are reported.
+pub fn point(line_starts: &[usize], offset: usize) -> [i64; 2] {
+    let line = line_starts.partition_point(|&start| start <= offset);
+    [line as i64, (offset - line_starts[line - 1]) as i64]
+}
+
+/// Checks that every node's location agrees with where its text sits in
+/// `src`.
+pub fn check_locations(file: &SemanticFile, src: &str) -> Result<(), String> {
+    fn visit(node: &Node, src: &str, lines: &[usize]) -> Result<(), String> {
+        let range = node.text_range(src);
+        let location = node.location_span();
+        let expected = [point(lines, range.start), point(lines, range.end)];
+        if [location.start, location.end] != expected {
+            return Err(format!(
+                "{} at bytes {:?} is located at {:?}-{:?}, expected {:?}-{:?}",
+                node.kind(),
+                range,
+                location.start,
+                location.end,
+                expected[0],
+                expected[1]
+            ));
+        }
+        let children = node.children();
+        children
+            .iter()
+            .try_for_each(|child| visit(child, src, lines))
+    }
+    let lines = line_starts(src);
+    file.children
+        .iter()
+        .try_for_each(|child| visit(child, src, &lines))
+}
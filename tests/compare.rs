@@ -0,0 +1,31 @@
+//! Structural differences between consecutive trees for one path.
+
+use rustsemantic::compare::{compare, node_paths};
+use rustsemantic::parse::parse_source;
+
+#[test]
+fn nodes_are_listed_by_path_in_document_order() {
+    let file = parse_source("a.rs", "mod m {\n    struct S;\n}\n").unwrap();
+    let paths = node_paths(&file);
+    assert_eq!(paths[0], "mod_item m");
+    assert_eq!(paths[1], "mod_item m > identifier m");
+    assert_eq!(paths[2], "mod_item m > declaration_list");
+    assert!(paths[3].starts_with("mod_item m > declaration_list > struct_item"));
+}
+
+#[test]
+fn only_added_and_removed_paths_are_reported() {
+    let old = parse_source("a.rs", "fn a() {}\nfn b() {}\n").unwrap();
+    let new = parse_source("a.rs", "fn b() {}\nfn a() {}\nfn c() {}\n").unwrap();
+    let changes = compare(&old, &new);
+    assert_eq!(
+        changes,
+        [
+            "+ function_item c",
+            "+ function_item c > identifier c",
+            "+ function_item c > parameters",
+            "+ function_item c > block",
+        ]
+    );
+    assert!(compare(&new, &new).is_empty());
+}
@@ -0,0 +1,89 @@
+//! Function complexity is listed by `stats --functions` and reported by
+//! diffs when a change makes a function simpler or harder to follow.
+
+use rustsemantic::diff::{
+    declarations, diff_declarations, diff_options, ComplexityChange,
+};
+use rustsemantic::model::Complexity;
+use rustsemantic::parse::parse_source_with;
+use rustsemantic::stats::Stats;
+
+const OLD: &str = "fn check(x: u32) -> bool {
+    x > 1
+}
+
+fn render(x: u32) -> String {
+    x.to_string()
+}
+";
+
+const NEW: &str = "fn check(x: u32) -> bool {
+    if x > 1 {
+        for i in 2..x {
+            if x % i == 0 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn render(x: u32) -> String {
+    format!(\"{}\", x)
+}
+";
+
+#[test]
+fn diffs_report_complexity_changes() {
+    let decls = |src| {
+        let file = parse_source_with("complexity.rs", src, &diff_options()).unwrap();
+        declarations(&file, src)
+    };
+    let changes = diff_declarations(&decls(OLD), &decls(NEW));
+    let found: Vec<(&str, Option<ComplexityChange>)> = changes
+        .iter()
+        .map(|c| (c.name.as_str(), c.complexity))
+        .collect();
+    let complexity = |cyclomatic, nesting| Complexity {
+        cyclomatic,
+        nesting,
+    };
+    assert_eq!(
+        found,
+        [
+            (
+                "check",
+                Some(ComplexityChange {
+                    old: complexity(1, 0),
+                    new: complexity(4, 3),
+                })
+            ),
+            // Modified without becoming any more or less complex.
+            ("render", None),
+        ]
+    );
+}
+
+#[test]
+fn stats_list_functions_only_when_asked() {
+    let mut plain = Stats::new(10);
+    plain.add("new.rs", NEW).unwrap();
+    assert!(plain.functions.is_none());
+
+    let mut stats = Stats::new(10);
+    stats.functions = Some(vec![]);
+    stats.add("new.rs", NEW).unwrap();
+    let functions: Vec<_> = stats
+        .functions
+        .unwrap()
+        .iter()
+        .map(|f| (f.name.clone(), f.line, f.cyclomatic, f.nesting))
+        .collect();
+    assert_eq!(
+        functions,
+        [
+            ("check".to_string(), 1, 4, 3),
+            ("render".to_string(), 12, 1, 0)
+        ]
+    );
+}
@@ -0,0 +1,87 @@
+//! Opt-in regression suite over real-world Rust code. Set
+//! `RUSTSEMANTIC_CORPUS` to one or more directories (separated like `PATH`),
+//! for example a few large crates checked out side by side or
+//! `~/.cargo/registry/src`, and every `.rs` file below them is parsed and
+//! checked against the tree invariants.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use rustsemantic::parse::parse_source;
+
+fn rust_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            rust_files(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+fn check_files(mut paths: Vec<PathBuf>) {
+    paths.sort();
+    let mut failures = vec![];
+    let mut skipped = 0;
+    for path in &paths {
+        // Not every file in a crate is UTF-8 (test data, mostly), and the
+        // protocol refuses those up front.
+        let Ok(src) = std::fs::read_to_string(path) else {
+            skipped += 1;
+            continue;
+        };
+        let name = path.to_string_lossy();
+        let checked = std::panic::catch_unwind(|| {
+            let file = parse_source(&name, &src).map_err(|e| format!("{:#}", e))?;
+            common::check_tiling(&file, &src)?;
+            common::check_names(&file)?;
+            common::check_text(&file, &src)?;
+            common::check_locations(&file, &src)
+        });
+        match checked {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => failures.push(format!("{}: {}", name, e)),
+            Err(_) => failures.push(format!("{}: panicked", name)),
+        }
+    }
+    eprintln!(
+        "checked {} files ({} not UTF-8), {} failures",
+        paths.len() - skipped,
+        skipped,
+        failures.len()
+    );
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn fixture_invariants() {
+    let mut paths = vec![];
+    rust_files(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+        &mut paths,
+    );
+    assert!(!paths.is_empty());
+    check_files(paths);
+}
+
+#[test]
+fn corpus_invariants() {
+    let Some(roots) = std::env::var_os("RUSTSEMANTIC_CORPUS") else {
+        eprintln!("RUSTSEMANTIC_CORPUS is not set, skipping the corpus suite");
+        return;
+    };
+    let mut paths = vec![];
+    for root in std::env::split_paths(&roots) {
+        rust_files(&root, &mut paths);
+    }
+    assert!(!paths.is_empty(), "no .rs files under RUSTSEMANTIC_CORPUS");
+    check_files(paths);
+}
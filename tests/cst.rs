@@ -0,0 +1,17 @@
+use rustsemantic::parse::{dump_cst, parse_tree};
+
+#[test]
+fn dumps_named_nodes_with_fields_and_positions() {
+    let src = "fn f(x: u8) {\n    let y = 1\n}\n";
+    let tree = parse_tree("f.rs", src).unwrap();
+    let dump = dump_cst(&tree);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines[0], "(source_file [1, 0] - [4, 0]");
+    assert_eq!(lines[1], "  (function_item [1, 0] - [3, 1]");
+    assert_eq!(lines[2], "    name: (identifier [1, 3] - [1, 4])");
+    assert!(dump.contains("type: (primitive_type [1, 8] - [1, 10])"));
+    assert!(dump.contains("(MISSING ; "));
+    assert!(!dump.contains("\"fn\""));
+    assert_eq!(dump.matches('(').count(), dump.matches(')').count());
+    assert!(dump.ends_with(")))\n"));
+}
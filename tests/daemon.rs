@@ -0,0 +1,183 @@
+//! Shells pointed at a daemon with `SEMANTIC_RUST_DAEMON` have it build
+//! their trees, which come out as the shell would have built them, and
+//! parse files themselves when it isn't there.
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use rustsemantic::server::Server;
+
+use common::scratch;
+
+fn start_daemon(socket: &Path) -> Child {
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .args(["serve", "--socket"])
+        .arg(socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    for _ in 0..200 {
+        if UnixStream::connect(socket).is_ok() {
+            return daemon;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let _ = daemon.kill();
+    let _ = daemon.wait();
+    panic!("the daemon didn't start listening");
+}
+
+fn call(socket: &Path, request: &str) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).unwrap();
+    writeln!(stream, "{}", request).unwrap();
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).unwrap();
+    serde_json::from_str(&line).unwrap()
+}
+
+/// Runs a shell session parsing `source` into `output`, with `env`, and
+/// returns its responses and log.
+fn session(
+    dir: &Path,
+    source: &Path,
+    output: &Path,
+    env: &[(&str, &str)],
+) -> (String, String) {
+    let log = dir.join("shell.log");
+    let _ = std::fs::remove_file(&log);
+    let mut shell = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .env_remove("SEMANTIC_RUST_DAEMON")
+        .env("SEMANTIC_RUST_LOG", &log)
+        .env("SEMANTIC_RUST_LOG_FILTER", "debug")
+        .env("SEMANTIC_RUST_REPORT", dir.join("failures.jsonl"))
+        .envs(env.iter().copied())
+        .arg("shell")
+        .arg(dir.join("flag.txt"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    write!(
+        shell.stdin.take().unwrap(),
+        "{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        output.display()
+    )
+    .unwrap();
+    let output = shell.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        std::fs::read_to_string(&log).unwrap(),
+    )
+}
+
+#[test]
+fn shells_have_the_daemon_build_their_trees() {
+    let dir = scratch("daemon-trees");
+    let socket = dir.join("daemon.sock");
+    let source = dir.join("a.rs");
+    std::fs::write(
+        &source,
+        "struct S;\n\nimpl S {\n    fn new() -> S {\n        S\n    }\n}\n",
+    )
+    .unwrap();
+    let mut daemon = start_daemon(&socket);
+
+    let (local, log) = session(&dir, &source, &dir.join("local.json"), &[]);
+    assert_eq!(local, "OK\n");
+    assert!(log.contains("daemon=false"), "{}", log);
+
+    let env = [
+        ("SEMANTIC_RUST_DAEMON", socket.to_str().unwrap()),
+        ("SEMANTIC_RUST_HASHES", "1"),
+    ];
+    for output in ["first.json", "second.json"] {
+        let (remote, log) = session(&dir, &source, &dir.join(output), &env);
+        assert_eq!(remote, "OK\n");
+        assert!(log.contains("daemon=true"), "{}", log);
+    }
+    session(&dir, &source, &dir.join("hashed.json"), &env[1..]);
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("first.json"), read("hashed.json"));
+    assert_eq!(read("second.json"), read("hashed.json"));
+    assert_ne!(read("first.json"), read("local.json"));
+
+    let error = call(
+        &socket,
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "tree", "params": {"name": "a.rs"}}"#,
+    );
+    assert_eq!(error["error"]["message"], "expected text");
+
+    call(
+        &socket,
+        r#"{"jsonrpc": "2.0", "id": 2, "method": "shutdown"}"#,
+    );
+    assert!(daemon.wait().unwrap().success());
+    assert!(!socket.exists());
+}
+
+#[test]
+fn shells_parse_files_themselves_without_the_daemon() {
+    let dir = scratch("daemon-fallback");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, "fn f() {}\n").unwrap();
+    let socket = dir.join("nobody.sock");
+    let env = [("SEMANTIC_RUST_DAEMON", socket.to_str().unwrap())];
+    let (responses, log) = session(&dir, &source, &dir.join("a.json"), &env);
+    assert_eq!(responses, "OK\n");
+    assert!(log.contains("daemon unavailable"), "{}", log);
+    assert!(log.contains("daemon=false"), "{}", log);
+}
+
+#[test]
+fn shells_parse_files_themselves_when_the_daemon_never_answers() {
+    let dir = scratch("daemon-silent");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, "fn f() {}\n").unwrap();
+    let socket = dir.join("silent.sock");
+    let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let silent = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        std::thread::sleep(Duration::from_secs(2));
+        drop(stream);
+    });
+    let env = [
+        ("SEMANTIC_RUST_DAEMON", socket.to_str().unwrap()),
+        ("SEMANTIC_RUST_DAEMON_TIMEOUT", "200"),
+    ];
+    let started = std::time::Instant::now();
+    let (responses, log) = session(&dir, &source, &dir.join("a.json"), &env);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(responses, "OK\n");
+    assert!(log.contains("daemon unavailable"), "{}", log);
+    assert!(log.contains("daemon=false"), "{}", log);
+    silent.join().unwrap();
+}
+
+#[test]
+fn edited_name_templates_are_picked_up() {
+    let dir = scratch("daemon-templates");
+    let templates = dir.join("templates.txt");
+    let mut server = Server::default();
+    let mut tree = |template: &str| {
+        std::fs::write(&templates, format!("function_item = \"{}\"\n", template))
+            .unwrap();
+        let params = serde_json::json!({
+            "name": "a.rs",
+            "text": "fn f() {}\n",
+            "environment": { "nameTemplates": templates.to_str().unwrap() },
+        });
+        let result = server.call("tree", &params).unwrap();
+        let tree: serde_json::Value =
+            serde_json::from_str(result["tree"].as_str().unwrap()).unwrap();
+        tree["children"][0]["name"].clone()
+    };
+    assert_eq!(tree("fn {name}"), "fn f");
+    assert_eq!(tree("{name}()"), "f()");
+}
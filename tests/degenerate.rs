@@ -0,0 +1,87 @@
+//! Broken sources make tree-sitter invent empty nodes for what is missing;
+//! none of them, nor anything else without text, makes it into the tree.
+
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+use rustsemantic::replay::Environment;
+use rustsemantic::shell::shell_options;
+
+/// Sources cut off or mistyped mid-edit, as they reach a merge.
+const BROKEN: &[&str] = &[
+    "fn f() { x. }\n",
+    "fn f<>() where {}\n",
+    "fn f() { let x = 1 }\n",
+    "fn f( {\n",
+    "struct {}\n",
+    "impl  for X {}\n",
+    "fn f() -> {}\n",
+    "fn f() { g(1,; }\n",
+    "enum E { A( }\n",
+    "use a::{;\n",
+    "fn f() {\r\n    x.\r\n}\r\n",
+    "mod m {\n    fn f() { y. }\n",
+];
+
+fn spans(nodes: &[Node], out: &mut Vec<[i64; 2]>) {
+    for node in nodes {
+        match node {
+            Node::Container(c) => {
+                out.push(c.header_span.span);
+                out.push(c.footer_span.span);
+            }
+            Node::Terminal(t) => out.push(t.span.span),
+        }
+        spans(node.children(), out);
+    }
+}
+
+#[test]
+fn missing_nodes_are_left_out() {
+    for src in BROKEN {
+        let file = parse_source_with("a.rs", src, &ParseOptions::default()).unwrap();
+        assert!(file.parsing_errors_detected, "{:?}", src);
+        common::check_tiling(&file, src)
+            .map_err(|e| format!("{:?}: {}", src, e))
+            .unwrap();
+        common::check_locations(&file, src)
+            .map_err(|e| format!("{:?}: {}", src, e))
+            .unwrap();
+        common::check_text(&file, src)
+            .map_err(|e| format!("{:?}: {}", src, e))
+            .unwrap();
+    }
+}
+
+#[test]
+fn semantic_merge_gets_no_degenerate_spans() {
+    let options = shell_options(&Environment::default()).unwrap();
+    for src in BROKEN {
+        let file = parse_source_with("a.rs", src, &options).unwrap();
+        let mut all = vec![];
+        spans(&file.children, &mut all);
+        for [start, end] in all {
+            // Inclusive ends: `[0, -1]` is the one way to say empty.
+            assert!(
+                end >= start || [start, end] == [0, -1],
+                "{:?}: [{}, {}]",
+                src,
+                start,
+                end
+            );
+        }
+    }
+}
+
+#[test]
+fn the_same_broken_source_gives_the_same_tree() {
+    for src in BROKEN {
+        let first = parse_source_with("a.rs", src, &ParseOptions::default()).unwrap();
+        let second = parse_source_with("a.rs", src, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap()
+        );
+    }
+}
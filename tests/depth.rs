@@ -0,0 +1,51 @@
+//! `--max-depth` folds everything below a level into opaque terminals.
+
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+const SOURCE: &str = "mod outer {\n    mod inner {\n        fn deep() {\n            let x = 1;\n        }\n    }\n\n    fn shallow() {}\n}\n\nfn top() {}\n";
+
+fn depth(nodes: &[Node]) -> usize {
+    nodes
+        .iter()
+        .map(|node| 1 + depth(node.children()))
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn containers_at_the_maximum_depth_become_terminals() {
+    let unlimited =
+        parse_source_with("a.rs", SOURCE, &ParseOptions::default()).unwrap();
+    assert!(depth(&unlimited.children) > 3);
+
+    for max_depth in 1..=3 {
+        let options = ParseOptions {
+            max_depth: Some(max_depth),
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+        assert_eq!(depth(&file.children), max_depth);
+        common::check_tiling(&file, SOURCE).unwrap();
+        common::check_locations(&file, SOURCE).unwrap();
+    }
+
+    let options = ParseOptions {
+        max_depth: Some(1),
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+    let Node::Terminal(outer) = &file.children[0] else {
+        panic!("{:?}", file.children[0]);
+    };
+    assert_eq!(
+        (outer.item_type.as_str(), outer.name.as_str()),
+        ("mod_item", "outer")
+    );
+    assert_eq!(
+        outer.span.span,
+        [0, SOURCE.find("\n\nfn top").unwrap() as i64]
+    );
+}
@@ -0,0 +1,97 @@
+//! The same input must give byte-identical output in every format, whatever
+//! the environment, so that output can be cached and compared.
+
+use std::path::Path;
+use std::process::Command;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+fn parse(format: &str, env: &[(&str, &str)]) -> Vec<u8> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .current_dir(root)
+        .args(["parse", "--format", format, "tests/fixtures/generics.rs"])
+        .envs(env.iter().copied())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn parse_output_is_byte_identical_across_runs() {
+    for format in ["json", "msgpack", "ndjson"] {
+        let first = parse(format, &[]);
+        let second = parse(format, &[("TZ", "Pacific/Kiritimati"), ("LC_ALL", "C")]);
+        assert!(!first.is_empty());
+        assert!(first == second, "{} output differs between runs", format);
+    }
+}
+
+fn function_hash(src: &str) -> String {
+    let options = ParseOptions {
+        hashes: true,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("hash.rs", src, &options).unwrap();
+    let function = file
+        .children
+        .iter()
+        .find(|node| node.kind() == "function_item")
+        .unwrap();
+    match function {
+        Node::Container(c) => c.hash.clone().unwrap(),
+        Node::Terminal(t) => t.hash.clone().unwrap(),
+    }
+}
+
+#[test]
+fn node_hashes_depend_only_on_the_node_text() {
+    let body = "fn f() -> u8 {\n    1\n}";
+    let moved = function_hash(&format!("struct S;\n\n\n{}\n", body));
+    assert_eq!(function_hash(body), moved);
+    assert_ne!(
+        function_hash(body),
+        function_hash("fn f() -> u8 {\n    2\n}")
+    );
+}
+
+fn parse_from(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .current_dir(dir)
+        .arg("parse")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn trees_can_be_named_without_the_checkout_path() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let absolute = root.join("tests/fixtures/generics.rs");
+    let absolute = absolute.to_str().unwrap();
+
+    let leaked = parse_from(root, &[absolute]);
+    assert!(leaked.contains(absolute));
+
+    let from_root = parse_from(root, &["--relative-to", ".", absolute]);
+    let from_tests =
+        parse_from(&root.join("tests"), &["--relative-to", "..", absolute]);
+    assert_eq!(from_root, from_tests);
+    assert!(from_root.contains("\"tests/fixtures/generics.rs\""));
+    assert!(!from_root.contains(absolute));
+
+    let named = parse_from(root, &["--name", "generics.rs", absolute]);
+    assert!(named.contains("\"generics.rs\""));
+    assert!(!named.contains(absolute));
+
+    let outside = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .current_dir(root)
+        .args(["parse", "--relative-to", "src", absolute])
+        .output()
+        .unwrap();
+    assert!(!outside.status.success());
+}
@@ -0,0 +1,70 @@
+//! The Rust blocks in doc comments become children of their item.
+
+mod common;
+
+use rustsemantic::model::{Node, SemanticFile};
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+const SOURCE: &str = r#"/// Adds one.
+///
+/// ```
+/// # use demo::add;
+/// let x = add(1);
+/// ```
+///
+/// ```text
+/// not code
+/// ```
+///
+/// ```rust,no_run
+/// fn main() {}
+/// ```
+///
+/// ```ignore
+/// this doesn't parse (
+/// ```
+pub fn add(x: i32) -> i32 {
+    x + 1
+}
+"#;
+
+fn parse(doctests: bool) -> SemanticFile {
+    let options = ParseOptions {
+        doctests,
+        ..ParseOptions::default()
+    };
+    parse_source_with("doctests.rs", SOURCE, &options).unwrap()
+}
+
+fn kinds(nodes: &[Node]) -> Vec<&str> {
+    nodes.iter().map(|node| node.kind()).collect()
+}
+
+#[test]
+fn rust_blocks_become_doctest_containers() {
+    let file = parse(true);
+    common::check_tiling(&file, SOURCE).unwrap();
+    common::check_locations(&file, SOURCE).unwrap();
+
+    let add = &file.children[0];
+    let children = add.children();
+    assert_eq!(&kinds(children)[..2], ["doctest", "doctest"]);
+    assert_eq!(
+        kinds(children[0].children()),
+        ["uses", "let_declaration"],
+        "a hidden `# ` line is code"
+    );
+    assert_eq!(children[0].children()[0].children()[0].name(), "demo::add");
+    assert_eq!(children[1].children()[0].name(), "main");
+    assert!(
+        !kinds(children).contains(&"ERROR"),
+        "a block that doesn't parse stays comment text"
+    );
+    assert_eq!(kinds(&children[2..])[0], "visibility_modifier");
+}
+
+#[test]
+fn doc_comments_are_text_unless_asked() {
+    let file = parse(false);
+    assert!(!kinds(file.children[0].children()).contains(&"doctest"));
+}
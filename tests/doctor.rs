@@ -0,0 +1,26 @@
+//! `doctor` passes on a working build.
+
+use rustsemantic::doctor::{checks, Status};
+
+#[test]
+fn grammars_and_samples_check_out() {
+    let checks = checks();
+    let grammars: Vec<_> = checks
+        .iter()
+        .filter(|c| !c.name.starts_with("SEMANTIC_RUST_"))
+        .collect();
+    assert_eq!(grammars[0].name, "rust grammar");
+    assert_eq!(grammars[1].name, "rust sample");
+    for check in grammars {
+        let expected = if check.name == "rust grammar"
+            || check.name == "rust sample"
+            || cfg!(feature = "inject-html") && check.name == "html grammar"
+            || cfg!(feature = "inject-sql") && check.name == "sql grammar"
+        {
+            Status::Pass
+        } else {
+            Status::Skip
+        };
+        assert_eq!(check.status, expected, "{}: {}", check.name, check.detail);
+    }
+}
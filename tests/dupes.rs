@@ -0,0 +1,130 @@
+//! `dupes` pairs functions that are copies of each other, whatever their
+//! names, variables and comments, and ones that are nearly so.
+
+use rustsemantic::dupes::duplicates;
+
+const ORIGINAL: &str = r#"
+fn total(items: &[Item]) -> u64 {
+    let mut sum = 0;
+    for item in items {
+        if item.active {
+            sum += item.price * item.count;
+        }
+    }
+    sum
+}
+"#;
+
+/// `total`, renamed throughout and commented.
+const RENAMED: &str = r#"
+impl Cart {
+    /// Adds up the lines.
+    #[inline]
+    fn subtotal(lines: &[Line]) -> u64 {
+        let mut acc = 10;
+        for line in lines {
+            // Skip removed lines.
+            if line.active {
+                acc += line.price * line.count;
+            }
+        }
+        acc
+    }
+}
+"#;
+
+/// `total` with a discount.
+const EDITED: &str = r#"
+fn discounted(items: &[Item]) -> u64 {
+    let mut sum = 0;
+    for item in items {
+        if item.active {
+            sum += item.price * item.count;
+        }
+    }
+    sum - sum / 10
+}
+"#;
+
+const UNRELATED: &str = r#"
+fn parse(text: &str) -> Option<(String, String)> {
+    let (key, value) = text.split_once('=')?;
+    Some((key.trim().to_string(), value.trim().to_string()))
+}
+"#;
+
+fn files() -> Vec<(String, String)> {
+    [
+        ("a.rs", ORIGINAL),
+        ("b.rs", RENAMED),
+        ("c.rs", EDITED),
+        ("d.rs", UNRELATED),
+    ]
+    .iter()
+    .map(|(name, src)| (name.to_string(), src.to_string()))
+    .collect()
+}
+
+fn pairs(threshold: f64, min_tokens: usize) -> Vec<(String, String, f64)> {
+    duplicates(files(), threshold, min_tokens)
+        .unwrap()
+        .into_iter()
+        .map(|d| (d.first.name, d.second.name, d.similarity))
+        .collect()
+}
+
+#[test]
+fn copies_are_found_whatever_their_names() {
+    let pairs = pairs(0.6, 10);
+    assert_eq!(
+        pairs[0],
+        ("total".into(), "impl Cart::subtotal".into(), 1.0)
+    );
+    let near: Vec<_> = pairs[1..]
+        .iter()
+        .map(|(a, b, _)| (a.as_str(), b.as_str()))
+        .collect();
+    assert_eq!(
+        near,
+        [
+            ("total", "discounted"),
+            ("impl Cart::subtotal", "discounted")
+        ]
+    );
+    assert!(pairs[1].2 < 1.0 && pairs[1].2 >= 0.6, "{:?}", pairs);
+    assert_eq!(pairs[1].2, pairs[2].2);
+}
+
+#[test]
+fn thresholds_and_sizes_limit_what_is_reported() {
+    assert_eq!(pairs(1.0, 10).len(), 1);
+    assert!(pairs(0.6, 1000).is_empty());
+}
+
+/// `total` with its loop run twice.
+const REPEATED: &str = r#"
+fn twice(items: &[Item]) -> u64 {
+    let mut sum = 0;
+    for item in items {
+        if item.active {
+            sum += item.price * item.count;
+        }
+    }
+    for item in items {
+        if item.active {
+            sum += item.price * item.count;
+        }
+    }
+    sum
+}
+"#;
+
+#[test]
+fn repeated_runs_count_as_often_as_they_occur() {
+    let files = [("a.rs", ORIGINAL), ("e.rs", REPEATED)]
+        .iter()
+        .map(|(name, src)| (name.to_string(), src.to_string()));
+    let found = duplicates(files, 0.0, 10).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].similarity < 0.7, "{}", found[0].similarity);
+}
@@ -0,0 +1,121 @@
+//! `extract` prints a declaration's exact source, found by its path.
+
+mod common;
+
+use std::process::Command;
+
+use rustsemantic::extract::extract;
+
+use common::scratch;
+
+const SRC: &str = "use std::fmt;
+
+/// A point.
+#[derive(Debug)]
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    /// The origin.
+    #[inline]
+    pub fn new() -> Self {
+        Point { x: 0 }
+    }
+}
+
+pub trait Draw {
+    fn draw(&self);
+}
+
+impl Draw for Point {
+    fn draw(&self) {}
+}
+
+mod platform {
+    #[cfg(unix)]
+    fn path() {}
+    #[cfg(windows)]
+    fn path() {}
+}
+";
+
+fn get(name: &str) -> anyhow::Result<String> {
+    extract("lib.rs", SRC.to_string(), name)
+}
+
+#[test]
+fn declarations_come_with_their_docs_and_attributes() {
+    assert_eq!(
+        get("Point").unwrap(),
+        "/// A point.\n#[derive(Debug)]\npub struct Point {\n    x: i32,\n}"
+    );
+    let new = "    /// The origin.\n    #[inline]\n    pub fn new() -> Self {\n        Point { x: 0 }\n    }";
+    assert_eq!(get("Point::new").unwrap(), new);
+    assert_eq!(get("impl Point::Point::new").unwrap(), new);
+}
+
+#[test]
+fn traits_impls_and_cfgs_are_named_as_in_rust() {
+    assert_eq!(get("Draw::draw").unwrap(), "    fn draw(&self);");
+    assert_eq!(
+        get("<Point as Draw>::draw").unwrap(),
+        "    fn draw(&self) {}"
+    );
+    assert_eq!(
+        get("platform::path #[cfg(windows)]").unwrap(),
+        "    #[cfg(windows)]\n    fn path() {}"
+    );
+    let ambiguous = get("platform::path").unwrap_err().to_string();
+    assert!(ambiguous.contains("2 declarations"), "{}", ambiguous);
+    assert!(get("Missing").is_err());
+}
+
+#[test]
+fn modules_holding_impls_are_found_by_their_own_path() {
+    let src = "mod net {\n    pub struct Conn;\n    impl Conn {\n        fn open() {}\n    }\n    impl Drop for Conn {\n        fn drop(&mut self) {}\n    }\n}\n";
+    let get = |name| extract("net.rs", src.to_string(), name);
+    assert_eq!(get("net").unwrap(), src.trim_end());
+    assert_eq!(
+        get("net::impl Conn").unwrap(),
+        "    impl Conn {\n        fn open() {}\n    }"
+    );
+    assert_eq!(
+        get("net::impl Drop for Conn").unwrap(),
+        "    impl Drop for Conn {\n        fn drop(&mut self) {}\n    }"
+    );
+    assert_eq!(
+        get("net::<Conn as Drop>::drop").unwrap(),
+        "        fn drop(&mut self) {}"
+    );
+}
+
+#[test]
+fn types_are_found_by_their_identifier() {
+    let src = "struct Buffer;\npub(crate) struct Foo {\n    x: u8,\n}\nunion U {\n    a: u32,\n}\n";
+    let get = |name| extract("types.rs", src.to_string(), name);
+    assert_eq!(get("Buffer").unwrap(), "struct Buffer;");
+    assert_eq!(
+        get("Foo").unwrap(),
+        "pub(crate) struct Foo {\n    x: u8,\n}"
+    );
+    assert_eq!(get("U").unwrap(), "union U {\n    a: u32,\n}");
+}
+
+#[test]
+fn the_command_prints_the_text() {
+    let dir = scratch("extract");
+    let file = dir.join("lib.rs");
+    std::fs::write(&file, SRC).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("extract")
+        .arg(&file)
+        .args(["--name", "Draw"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "pub trait Draw {\n    fn draw(&self);\n}\n"
+    );
+}
@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+//! Module docs.
+
+/// A documented, derived struct.
+#[derive(Debug, Clone, PartialEq)]
+#[repr(C)]
+pub struct Pixel {
+    #[doc = "red"]
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_works() {
+        assert_eq!(2 + 2, 4);
+    }
+}
+
+#[inline(always)]
+#[must_use]
+fn double(x: u32) -> u32 {
+    x * 2
+}
+
+#[non_exhaustive]
+pub enum Color {
+    #[default]
+    Red,
+    Green,
+    Blue,
+}
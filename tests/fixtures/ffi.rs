@@ -0,0 +1,16 @@
+use std::os::raw::c_char;
+
+#[link(name = "m")]
+extern "C" {
+    fn abs(input: i32) -> i32;
+    pub static errno: i32;
+    static mut environ: *const *const c_char;
+}
+
+extern {
+    fn strlen(s: *const c_char) -> usize;
+}
+
+extern "system" {
+    pub fn GetLastError() -> u32;
+}
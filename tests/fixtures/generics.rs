@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+pub struct Cache<K, V = String>
+where
+    K: std::hash::Hash + Eq,
+{
+    entries: HashMap<K, V>,
+}
+
+impl<K: std::hash::Hash + Eq, V: Clone> Cache<K, V> {
+    pub fn get<'a>(&'a self, key: &K) -> Option<&'a V> {
+        self.entries.get(key)
+    }
+}
+
+pub trait Visitor<T: ?Sized> {
+    type Output;
+
+    fn visit(&mut self, value: &T) -> Self::Output;
+}
+
+pub fn largest<T: PartialOrd + Copy, const N: usize>(items: [T; N]) -> T {
+    let mut best = items[0];
+    for item in items {
+        if item > best {
+            best = item;
+        }
+    }
+    best
+}
+
+pub fn debug_all(items: &[impl Debug]) -> Vec<String> {
+    items.iter().map(|i| format!("{:?}", i)).collect()
+}
@@ -0,0 +1,31 @@
+macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+macro_rules! make_struct {
+    ($name:ident { $($field:ident: $ty:ty),* }) => {
+        struct $name {
+            $($field: $ty),*
+        }
+    };
+}
+
+macro_rules! max {
+    ($x:expr) => { $x };
+    ($x:expr, $($rest:expr),+) => {
+        std::cmp::max($x, max!($($rest),+))
+    };
+}
+
+make_struct!(Point { x: i32, y: i32 });
+
+thread_local! {
+    static COUNTER: std::cell::Cell<u32> = std::cell::Cell::new(0);
+}
+
+fn area(side: i32) -> i32 {
+    println!("computing {}", side);
+    square!(side)
+}
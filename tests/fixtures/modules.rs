@@ -0,0 +1,27 @@
+pub mod outer {
+    pub mod inner {
+        pub fn deep() {}
+
+        pub struct Nested;
+    }
+
+    pub use self::inner::deep;
+
+    fn helper() {}
+}
+
+mod declared;
+
+extern crate alloc;
+
+pub(crate) type Alias = outer::inner::Nested;
+
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+
+impl Shape for outer::inner::Nested {
+    fn area(&self) -> f64 {
+        0.0
+    }
+}
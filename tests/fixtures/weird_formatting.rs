@@ -0,0 +1,16 @@
+fn   spaced  (  a :i32,b:i32 )->i32{a+b}
+struct Tight{a:u8,b:u8}fn after_struct(){}
+
+    pub     fn
+indented
+(
+)
+{
+}
+
+enum E { A, B,
+    C }
+
+impl Tight { fn new() -> Self { Tight { a: 0, b: 0 } } fn zero(&self) -> u8 { 0 } }
+
+const X: i32 = 1; static Y: &str = "y";
@@ -0,0 +1,61 @@
+//! `--flatten` collapses wrappers around a single child with the same text.
+
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+const SOURCE: &str = "use std::io;\n\nfn f(x: Option<u32>) {\n    match x {\n        Some(y) => {}\n        None => {}\n    }\n    if true {}\n}\n";
+
+fn kinds(nodes: &[Node], out: &mut Vec<String>) {
+    for node in nodes {
+        out.push(node.kind().to_string());
+        kinds(node.children(), out);
+    }
+}
+
+/// The containers below `nodes` whose only child has the same location.
+fn wrappers(nodes: &[Node], out: &mut Vec<String>) {
+    for node in nodes {
+        if let Node::Container(c) = node {
+            if let [child] = &c.children[..] {
+                if child.location_span().start == c.location_span.start
+                    && child.location_span().end == c.location_span.end
+                {
+                    out.push(format!("{} > {}", c.item_type, child.kind()));
+                }
+            }
+            wrappers(&c.children, out);
+        }
+    }
+}
+
+#[test]
+fn single_child_wrappers_collapse_into_the_child() {
+    let plain = parse_source_with("a.rs", SOURCE, &ParseOptions::default()).unwrap();
+    let mut before = vec![];
+    wrappers(&plain.children, &mut before);
+    // Which wrappers there are depends on the grammar.
+    assert!(before.len() > 1, "{:?}", before);
+
+    let options = ParseOptions {
+        flatten: true,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+    common::check_tiling(&file, SOURCE).unwrap();
+    common::check_locations(&file, SOURCE).unwrap();
+    let mut after = vec![];
+    wrappers(&file.children, &mut after);
+    // A group of one import is still a group.
+    assert_eq!(after, ["uses > use_declaration"]);
+
+    let count = |file: &rustsemantic::model::SemanticFile| {
+        let mut kinds = vec![];
+        self::kinds(&file.children, &mut kinds);
+        kinds
+    };
+    let (plain, flat) = (count(&plain), count(&file));
+    assert_eq!(flat.len() + before.len() - 1, plain.len());
+    assert!(flat.iter().any(|kind| kind == "match_expression"));
+}
@@ -0,0 +1,108 @@
+//! `serve --http` answers requests over a real socket.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+/// Starts the server on a free port and returns it with its address.
+fn start() -> (Child, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .args(["serve", "--http", "127.0.0.1:0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn rustsemantic serve");
+    let mut line = String::new();
+    BufReader::new(child.stderr.as_mut().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("listening on http://")
+        .unwrap_or_else(|| panic!("unexpected {:?}", line))
+        .to_string();
+    (child, addr)
+}
+
+/// Sends one request and returns the status code and body.
+fn post(
+    addr: &str,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+) -> (u16, serde_json::Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        path,
+        content_type,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn sources_can_be_posted_as_they_are() {
+    let (mut child, addr) = start();
+    let source = "fn f() {}\n";
+
+    let (status, tree) = post(
+        &addr,
+        "/parse?name=my%20lib.rs",
+        "text/plain",
+        source.as_bytes(),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(tree["name"], "my lib.rs");
+    let document = serde_json::json!({ "name": "my lib.rs", "text": source });
+    let (status, json_tree) = post(
+        &addr,
+        "/parse",
+        "application/json",
+        document.to_string().as_bytes(),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(tree, json_tree);
+
+    let (status, outline) = post(
+        &addr,
+        "/outline?name=a.rs",
+        "text/x-rust",
+        source.as_bytes(),
+    );
+    assert_eq!(status, 200, "{}", outline);
+    assert_eq!(post(&addr, "/diff", "text/plain", b"").0, 400);
+    assert_eq!(
+        post(&addr, "/parse", "application/octet-stream", b"\xff").0,
+        400
+    );
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn a_stalled_client_holds_up_nobody() {
+    let (mut child, addr) = start();
+    let mut stalled = TcpStream::connect(&addr).unwrap();
+    write!(
+        stalled,
+        "POST /parse HTTP/1.1\r\nContent-Length: 100\r\n\r\n"
+    )
+    .unwrap();
+
+    let started = std::time::Instant::now();
+    let (status, _) = post(&addr, "/parse?name=a.rs", "text/plain", b"fn f() {}\n");
+    assert_eq!(status, 200);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    drop(stalled);
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
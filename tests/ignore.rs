@@ -0,0 +1,156 @@
+//! Batch runs skip what `.gitignore` ignores and what `--exclude` and
+//! `--include` leave out, and treat symbolic links as `--symlinks` says.
+//! The files they find are named the same on every machine.
+
+mod common;
+
+use rustsemantic::files::{collect_filtered, collect_rust_files, expand};
+use rustsemantic::ignore::{Filter, Gitignore, Glob};
+
+use common::scratch;
+
+fn write(root: &std::path::Path, files: &[(&str, &str)]) {
+    for (name, contents) in files {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+#[test]
+fn globs_match_names_and_paths() {
+    assert!(Glob::new("*.rs").matches("lib.rs"));
+    assert!(!Glob::new("*.rs").matches("src/lib.rs"));
+    assert!(Glob::new("src/**/*.rs").matches("src/lib.rs"));
+    assert!(Glob::new("src/**/*.rs").matches("src/a/b/c.rs"));
+    assert!(Glob::new("**/gen_?.rs").matches("a/gen_1.rs"));
+    assert!(Glob::new("[a-c]*.rs").matches("b.rs"));
+    assert!(!Glob::new("[!a-c]*.rs").matches("b.rs"));
+}
+
+#[test]
+fn gitignore_rules_apply_in_order_below_their_directory() {
+    let mut ignore = Gitignore::default();
+    ignore.add("", "# build output\ntarget/\n*.bak.rs\n/top.rs\n");
+    ignore.add("src", "gen/*.rs\n!gen/keep.rs\n");
+
+    assert!(ignore.is_ignored("target", true));
+    assert!(!ignore.is_ignored("target", false));
+    assert!(ignore.is_ignored("src/old.bak.rs", false));
+    assert!(ignore.is_ignored("top.rs", false));
+    assert!(!ignore.is_ignored("src/top.rs", false));
+    assert!(ignore.is_ignored("src/gen/a.rs", false));
+    assert!(!ignore.is_ignored("src/gen/keep.rs", false));
+    assert!(!ignore.is_ignored("gen/a.rs", false));
+}
+
+#[test]
+fn walks_skip_ignored_and_excluded_files() {
+    let root = scratch("ignore-walk");
+    write(
+        &root,
+        &[
+            (".gitignore", "target/\n"),
+            ("src/lib.rs", ""),
+            ("src/schema.generated.rs", ""),
+            ("src/gen/.gitignore", "*.rs\n!keep.rs\n"),
+            ("src/gen/out.rs", ""),
+            ("src/gen/keep.rs", ""),
+            ("target/debug/build.rs", ""),
+            ("vendor/dep/lib.rs", ""),
+            (".git/hooks/hook.rs", ""),
+        ],
+    );
+
+    assert_eq!(collect_rust_files(&root).unwrap().len(), 7);
+    assert_eq!(
+        collect_filtered(&root, &Filter::default()).unwrap(),
+        [
+            "src/gen/keep.rs",
+            "src/lib.rs",
+            "src/schema.generated.rs",
+            "vendor/dep/lib.rs"
+        ]
+    );
+
+    let filter = Filter {
+        exclude: vec![Glob::new("vendor"), Glob::new("*.generated.rs")],
+        ..Filter::default()
+    };
+    assert_eq!(
+        collect_filtered(&root, &filter).unwrap(),
+        ["src/gen/keep.rs", "src/lib.rs"]
+    );
+
+    let filter = Filter {
+        include: vec![Glob::new("src/gen")],
+        gitignore: false,
+        ..Filter::default()
+    };
+    assert_eq!(
+        collect_filtered(&root, &filter).unwrap(),
+        ["src/gen/keep.rs", "src/gen/out.rs"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_followed_skipped_or_refused() {
+    use rustsemantic::ignore::Symlinks;
+    use std::os::unix::fs::symlink;
+
+    let root = scratch("ignore-symlinks");
+    let shared = scratch("ignore-symlinks-shared");
+    write(&root, &[("src/lib.rs", ""), ("src/a/b.rs", "")]);
+    write(&shared, &[("common.rs", "")]);
+    symlink(&shared, root.join("src/shared")).unwrap();
+    symlink(root.join("src"), root.join("src/a/loop")).unwrap();
+    symlink(root.join("src/lib.rs"), root.join("alias.rs")).unwrap();
+    symlink(root.join("gone.rs"), root.join("dangling.rs")).unwrap();
+
+    let walk = |symlinks| {
+        let filter = Filter {
+            symlinks,
+            ..Filter::everything()
+        };
+        collect_filtered(&root, &filter)
+    };
+    assert_eq!(
+        walk(Symlinks::Follow).unwrap(),
+        [
+            "alias.rs",
+            "src/a/b.rs",
+            "src/lib.rs",
+            "src/shared/common.rs"
+        ]
+    );
+    assert_eq!(walk(Symlinks::Skip).unwrap(), ["src/a/b.rs", "src/lib.rs"]);
+    let error = walk(Symlinks::Error).unwrap_err().to_string();
+    assert!(error.contains("symbolic link"), "{}", error);
+    assert!(Symlinks::parse("sideways").is_err());
+}
+
+#[test]
+fn expanded_files_have_portable_names() {
+    let root = scratch("ignore-expand");
+    write(&root, &[("src/lib.rs", ""), ("src/a/b.rs", "")]);
+    let absolute = root.to_str().unwrap().to_string();
+    let lib = root.join("src/lib.rs").to_str().unwrap().to_string();
+    let names = |paths: &[String]| -> Vec<String> {
+        expand(paths, &Filter::everything())
+            .unwrap()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect()
+    };
+    assert_eq!(names(&[absolute]), ["src/a/b.rs", "src/lib.rs"]);
+    assert_eq!(names(&[lib]), ["lib.rs"]);
+
+    // Tests run in the package's directory.
+    let generics = names(&["./tests/./fixtures/".to_string()]);
+    assert!(generics.contains(&"tests/fixtures/generics.rs".to_string()));
+    assert_eq!(
+        names(&["./tests/fixtures/generics.rs".to_string()]),
+        ["tests/fixtures/generics.rs"]
+    );
+}
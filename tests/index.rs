@@ -0,0 +1,143 @@
+//! The index records which declarations use which names, for "what uses X"
+//! queries without a compiler. It runs through the `sqlite3` shell, so
+//! these tests do nothing where there is none.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use rustsemantic::ignore::Symlinks;
+use rustsemantic::index::{referencing, update_index, Database};
+
+use common::scratch;
+
+fn has_sqlite() -> bool {
+    Command::new("sqlite3").arg("-version").output().is_ok()
+}
+
+#[test]
+fn declarations_using_a_name_are_found() {
+    if !has_sqlite() {
+        return;
+    }
+    let root = scratch("index-usages");
+    let src = root.join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(
+        src.join("shapes.rs"),
+        "pub struct Circle {\n    pub r: f64,\n}\n\nimpl Circle {\n    pub fn area(&self) -> f64 {\n        3.14 * self.r * self.r\n    }\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("main.rs"),
+        "fn main() {\n    let c = Circle { r: 1.0 };\n    println!(\"{}\", c.area());\n}\n\nfn unrelated() {}\n",
+    )
+    .unwrap();
+    let db_path = root.join("symbols.sqlite");
+    let db = Database {
+        sqlite: "sqlite3",
+        path: &db_path,
+    };
+    update_index(&db, &root, Symlinks::Follow).unwrap();
+
+    let users = |name: &str| -> Vec<String> {
+        referencing(&db, name)
+            .unwrap()
+            .into_iter()
+            .map(|row| format!("{} {}:{}", row[0], row[2], row[3]))
+            .collect()
+    };
+    assert_eq!(
+        users("Circle"),
+        ["main src/main.rs:2", "impl Circle src/shapes.rs:5"]
+    );
+    assert_eq!(users("area"), ["main src/main.rs:3"]);
+    // Field uses are recorded in the method, but not the field's own name.
+    assert_eq!(
+        users("r"),
+        ["main src/main.rs:2", "impl Circle::area src/shapes.rs:7"]
+    );
+    assert!(users("unrelated").is_empty());
+
+    // Removing a file removes its usages with its declarations.
+    std::fs::remove_file(src.join("main.rs")).unwrap();
+    update_index(&db, &root, Symlinks::Follow).unwrap();
+    assert_eq!(users("area"), Vec::<String>::new());
+    assert_eq!(users("Circle"), ["impl Circle src/shapes.rs:5"]);
+}
+
+#[test]
+fn indexes_from_before_usages_are_rebuilt() {
+    if !has_sqlite() {
+        return;
+    }
+    let root = scratch("index-upgrade");
+    std::fs::write(root.join("lib.rs"), "fn a() {\n    b();\n}\n\nfn b() {}\n")
+        .unwrap();
+    let db_path = root.join("symbols.sqlite");
+    let db = Database {
+        sqlite: "sqlite3",
+        path: &db_path,
+    };
+    update_index(&db, Path::new(&root), Symlinks::Follow).unwrap();
+    db.execute("DROP TABLE usages;").unwrap();
+
+    let summary = update_index(&db, &root, Symlinks::Follow).unwrap();
+    assert_eq!(summary.updated, 1);
+    assert_eq!(referencing(&db, "b").unwrap().len(), 1);
+    let summary = update_index(&db, &root, Symlinks::Follow).unwrap();
+    assert_eq!(summary.unchanged, 1);
+}
+
+#[test]
+fn files_that_cant_be_read_are_skipped() {
+    if !has_sqlite() {
+        return;
+    }
+    let root = scratch("index-unreadable");
+    std::fs::write(root.join("lib.rs"), "fn a() {\n    b();\n}\n\nfn b() {}\n")
+        .unwrap();
+    std::fs::write(root.join("latin1.rs"), b"// caf\xe9\nfn c() {}\n").unwrap();
+    let db_path = root.join("symbols.sqlite");
+    let db = Database {
+        sqlite: "sqlite3",
+        path: &db_path,
+    };
+    let summary = update_index(&db, &root, Symlinks::Follow).unwrap();
+    assert_eq!(summary.updated, 1);
+    assert_eq!(referencing(&db, "b").unwrap().len(), 1);
+}
+
+#[test]
+fn paths_with_separators_in_them_survive_the_round_trip() {
+    if !has_sqlite() {
+        return;
+    }
+    let root = scratch("index-separators");
+    let names = ["tab\there.rs", "line\nbreak.rs", "comma, \"quoted\".rs"];
+    for name in names {
+        std::fs::write(root.join(name), "fn a() {\n    b();\n}\n").unwrap();
+    }
+    let db_path = root.join("symbols.sqlite");
+    let db = Database {
+        sqlite: "sqlite3",
+        path: &db_path,
+    };
+    assert_eq!(
+        update_index(&db, &root, Symlinks::Follow).unwrap().updated,
+        3
+    );
+    let summary = update_index(&db, &root, Symlinks::Follow).unwrap();
+    assert_eq!((summary.updated, summary.unchanged), (0, 3));
+
+    let mut files: Vec<String> = referencing(&db, "b")
+        .unwrap()
+        .into_iter()
+        .map(|row| row[2].clone())
+        .collect();
+    files.sort();
+    let mut expected = names.map(str::to_string);
+    expected.sort();
+    assert_eq!(files, expected);
+}
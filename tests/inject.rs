@@ -0,0 +1,92 @@
+//! Macros mapped to an embedded language get its structure as children.
+#![cfg(all(feature = "inject-html", feature = "inject-sql"))]
+
+mod common;
+
+use rustsemantic::inject::{Injections, Language};
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+const SOURCE: &str = r#"fn view() -> Html {
+    html! {
+        <div class="card">
+            <p>{ "hi" }</p>
+        </div>
+    }
+}
+
+fn load() {
+    let rows = sqlx::query!("SELECT id FROM users").fetch_all();
+    let other = other!("SELECT 1");
+}
+"#;
+
+fn find<'a>(node: &'a Node, kind: &str, out: &mut Vec<&'a Node>) {
+    if node.kind() == kind {
+        out.push(node);
+    }
+    for child in node.children() {
+        find(child, kind, out);
+    }
+}
+
+fn parse(spec: &str) -> rustsemantic::model::SemanticFile {
+    let options = ParseOptions {
+        injections: Injections::parse(spec).unwrap(),
+        ..ParseOptions::default()
+    };
+    parse_source_with("inject.rs", SOURCE, &options).unwrap()
+}
+
+#[test]
+fn macros_are_looked_up_by_path_then_last_segment() {
+    let injections = Injections::parse("html=html, sqlx::query=sql").unwrap();
+    assert_eq!(injections.language("html"), Some(Language::Html));
+    assert_eq!(injections.language("sqlx::query"), Some(Language::Sql));
+    assert_eq!(injections.language("query"), None);
+    assert_eq!(
+        Injections::parse("query=sql")
+            .unwrap()
+            .language("sqlx::query"),
+        Some(Language::Sql)
+    );
+    assert!(Injections::parse("html").is_err());
+    assert!(Injections::parse("html=cobol").is_err());
+}
+
+#[test]
+fn embedded_html_and_sql_get_their_own_children() {
+    let file = parse("html=html,query=sql");
+    common::check_tiling(&file, SOURCE).unwrap();
+    common::check_locations(&file, SOURCE).unwrap();
+
+    let mut macros = vec![];
+    for child in &file.children {
+        find(child, "macro_invocation", &mut macros);
+    }
+    let kinds: Vec<Vec<&str>> = macros
+        .iter()
+        .map(|m| m.children().iter().map(|c| c.kind()).collect())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            vec!["identifier", "element"],
+            vec!["scoped_identifier", "statement"],
+            vec!["identifier", "token_tree"],
+        ]
+    );
+    assert_eq!(macros[0].children()[1].name(), "div");
+}
+
+#[test]
+fn nothing_is_injected_unless_asked() {
+    let file = parse("");
+    let mut macros = vec![];
+    for child in &file.children {
+        find(child, "macro_invocation", &mut macros);
+    }
+    assert!(macros
+        .iter()
+        .all(|m| m.children().last().unwrap().kind() == "token_tree"));
+}
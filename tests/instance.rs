@@ -0,0 +1,62 @@
+//! Copies of the tool running at once keep to their own files.
+
+mod common;
+
+use std::path::Path;
+
+use rustsemantic::instance::{append_locked, create_locked, per_process, with_pid};
+
+use common::scratch;
+
+#[test]
+fn paths_are_namespaced_by_process() {
+    let pid = std::process::id();
+    assert_eq!(
+        per_process("/tmp/shell-{pid}.log"),
+        format!("/tmp/shell-{}.log", pid)
+    );
+    assert_eq!(
+        with_pid(Path::new("/tmp/session.jsonl")),
+        Path::new(&format!("/tmp/session-{}.jsonl", pid))
+    );
+    assert_eq!(
+        with_pid(Path::new("session")),
+        Path::new(&format!("session-{}", pid))
+    );
+}
+
+#[test]
+fn a_locked_file_is_left_to_its_holder() {
+    let dir = scratch("instance");
+    let path = dir.join("recording.jsonl");
+
+    let (mut first, first_path) = create_locked(&path).unwrap();
+    assert_eq!(first_path, path);
+    std::io::Write::write_all(&mut first, b"first\n").unwrap();
+    let (_second, second_path) = create_locked(&path).unwrap();
+    assert_eq!(second_path, with_pid(&path));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+
+    drop(first);
+    let (_third, third_path) = create_locked(&path).unwrap();
+    assert_eq!(third_path, path);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    let log = dir.join("failures.jsonl");
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let log = log.clone();
+            std::thread::spawn(move || {
+                let line = format!("{}\n", i.to_string().repeat(4096));
+                append_locked(&log, line.as_bytes()).unwrap();
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let text = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(text.lines().count(), 8);
+    assert!(text.lines().all(|line| line.len() == 4096
+        && line.chars().all(|c| c == line.chars().next().unwrap())));
+}
@@ -0,0 +1,108 @@
+//! `report large` lists the functions and impls over a length, by module.
+
+mod common;
+
+use std::process::Command;
+
+use rustsemantic::large::large_declarations;
+
+use common::scratch;
+
+/// A function `name` of `lines` lines.
+fn function(name: &str, lines: usize, indent: &str) -> String {
+    let body = format!("{}    let x = 1;\n", indent).repeat(lines - 2);
+    format!("{0}fn {1}() {{\n{2}{0}}}\n", indent, name, body)
+}
+
+fn sources() -> Vec<(String, String)> {
+    let lib = format!(
+        "{}{}\nmod inner {{\n{}}}\n",
+        function("small", 5, ""),
+        function("big", 12, ""),
+        function("nested", 20, "    ")
+    );
+    let shapes = format!(
+        "struct Square;\n\nimpl Square {{\n{}{}}}\n",
+        function("area", 8, "    "),
+        function("draw", 3, "    ")
+    );
+    vec![
+        ("src/lib.rs".to_string(), lib),
+        ("src/shapes.rs".to_string(), shapes),
+    ]
+}
+
+#[test]
+fn long_declarations_are_grouped_by_module() {
+    let report = large_declarations(sources(), 7).unwrap();
+    let found: Vec<(&str, Vec<(&str, i64)>)> = report
+        .modules
+        .iter()
+        .map(|module| {
+            (
+                module.module.as_str(),
+                module
+                    .declarations
+                    .iter()
+                    .map(|d| (d.name.as_str(), d.lines))
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("src/lib.rs", vec![("big", 12)]),
+            ("src/lib.rs::inner", vec![("inner::nested", 20)]),
+            (
+                "src/shapes.rs",
+                vec![("impl Square", 13), ("impl Square::area", 8)]
+            ),
+        ]
+    );
+    assert!(large_declarations(sources(), 20)
+        .unwrap()
+        .modules
+        .is_empty());
+}
+
+#[test]
+fn reports_are_markdown_or_json() {
+    let dir = scratch("large");
+    for (name, contents) in sources() {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    let report = |format: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+            .args(["report", "large", "--threshold", "10", "--format", format])
+            .arg(dir.join("src"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let markdown = report("markdown");
+    assert!(
+        markdown.starts_with("# Declarations over 10 lines\n"),
+        "{}",
+        markdown
+    );
+    assert!(markdown.contains("\n## `lib.rs::inner`\n"), "{}", markdown);
+    assert!(
+        markdown.contains("\n| 20 | function | `inner::nested` | "),
+        "{}",
+        markdown
+    );
+    assert!(
+        markdown.contains("\n| 13 | impl | `impl Square` | 3 |\n"),
+        "{}",
+        markdown
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&report("json")).unwrap();
+    assert_eq!(json["threshold"], 10);
+    assert_eq!(json["modules"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,120 @@
+//! The same source with LF, CRLF and mixed line endings must tile its file
+//! and come out at the same lines and columns.
+
+mod common;
+
+use rustsemantic::check::{check_source, Severity};
+use rustsemantic::model::{Node, SemanticFile};
+use rustsemantic::parse::{parse_source, LineEndings};
+
+const SOURCE: &str = "// Header comment\n\
+use std::io; // trailing\n\
+\n\
+/// Docs.\n\
+pub fn f(x: u8) -> u8 {\n\
+    // inside\n\
+    x + 1\n\
+}\n\
+\n\
+mod m {\n\
+    struct S; // last\n\
+}\n";
+
+fn locations(file: &SemanticFile) -> Vec<(String, [i64; 2], [i64; 2])> {
+    fn visit(nodes: &[Node], out: &mut Vec<(String, [i64; 2], [i64; 2])>) {
+        for node in nodes {
+            let location = node.location_span();
+            out.push((node.kind().to_string(), location.start, location.end));
+            visit(node.children(), out);
+        }
+    }
+    let mut out = vec![];
+    visit(&file.children, &mut out);
+    out
+}
+
+fn check(src: &str) -> SemanticFile {
+    let file = parse_source("endings.rs", src).unwrap();
+    common::check_tiling(&file, src)
+        .and_then(|()| common::check_locations(&file, src))
+        .unwrap_or_else(|e| panic!("{}\nsource: {:?}", e, src));
+    file
+}
+
+#[test]
+fn crlf_and_mixed_endings_locate_nodes_like_lf() {
+    let crlf = SOURCE.replace('\n', "\r\n");
+    let mixed: String = SOURCE
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            if i % 2 == 0 {
+                line.replace('\n', "\r\n")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    let expected = locations(&check(SOURCE));
+    for src in [&crlf, &mixed] {
+        assert_eq!(locations(&check(src)), expected, "source: {:?}", src);
+    }
+}
+
+#[test]
+fn line_comments_leave_the_carriage_return_to_the_line_ending() {
+    let src = "// a\r\nfn f() {}\r\n";
+    let file = check(src);
+    let Node::Terminal(comment) = &file.children[0] else {
+        panic!("expected a terminal comment");
+    };
+    assert_eq!(comment.span.span, [0, 4]);
+    assert_eq!(comment.location_span.end, [1, 4]);
+    assert_eq!(file.children[1].byte_range().start, 4);
+}
+
+#[test]
+fn the_file_ends_after_its_last_byte() {
+    let cases: &[(&str, [i64; 2])] = &[
+        ("", [1, 0]),
+        ("struct S;", [1, 9]),
+        ("struct S;\n", [2, 0]),
+        ("struct S;\n\n", [3, 0]),
+        ("struct S;\r\n", [2, 0]),
+        ("struct S;\n  ", [2, 2]),
+    ];
+    for (src, end) in cases {
+        let file = check(src);
+        assert_eq!(file.location_span.start, [1, 0]);
+        assert_eq!(&file.location_span.end, end, "source: {:?}", src);
+    }
+}
+
+#[test]
+fn the_file_footer_covers_trailing_whitespace() {
+    let src = "struct S;\n\n  \n";
+    let file = check(src);
+    assert_eq!(file.footer_span.span, [9, src.len() as i64]);
+    assert!(check("struct S;").footer_span.is_empty());
+    assert_eq!(check("\n\n").footer_span.span, [0, 2]);
+}
+
+#[test]
+fn detects_mixed_line_endings() {
+    assert!(!LineEndings::of(SOURCE).is_mixed());
+    assert!(!LineEndings::of(&SOURCE.replace('\n', "\r\n")).is_mixed());
+
+    let mixed = "struct A;\r\nstruct B;\r\nstruct C;\nstruct D;\r\n";
+    let endings = LineEndings::of(mixed);
+    assert_eq!((endings.lf, endings.crlf), (1, 3));
+    assert_eq!(endings.first_mismatch, Some(3));
+
+    let annotations = check_source("mixed.rs", mixed.to_string()).unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].severity, Severity::Warning);
+    assert_eq!(annotations[0].line, 3);
+    assert!(annotations[0]
+        .message
+        .starts_with("mixed line endings (1 LF, 3 CRLF)"));
+}
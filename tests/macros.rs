@@ -0,0 +1,40 @@
+use rustsemantic::model::Node;
+use rustsemantic::parse::parse_source;
+
+#[test]
+fn macro_rules_is_a_container_of_its_arms() {
+    let src = "macro_rules! max {\n    ($x:expr) => { $x };\n    ($x:expr, $($rest:expr),+) => {\n        std::cmp::max($x, max!($($rest),+))\n    };\n}\n";
+    let file = parse_source("macros.rs", src).unwrap();
+    let Node::Container(max) = &file.children[0] else {
+        panic!("macro_rules! should be a container");
+    };
+    assert_eq!(max.name, "max");
+    let arms: Vec<_> = max
+        .children
+        .iter()
+        .map(|arm| match arm {
+            Node::Terminal(t) => (t.item_type.as_str(), t.name.as_str()),
+            Node::Container(_) => panic!("rule arms should be terminals"),
+        })
+        .collect();
+    assert_eq!(
+        arms,
+        [
+            ("macro_rule", "($x:expr)"),
+            ("macro_rule", "($x:expr, $($rest:expr),+)")
+        ]
+    );
+    assert_eq!(
+        &src[max.header_span.span[0] as usize..max.header_span.span[1] as usize],
+        "macro_rules! max {\n    "
+    );
+}
+
+#[test]
+fn macro_rules_without_rules_is_a_terminal() {
+    let file = parse_source("macros.rs", "macro_rules! nothing {}\n").unwrap();
+    let Node::Terminal(t) = &file.children[0] else {
+        panic!("an empty macro_rules! should be a terminal");
+    };
+    assert_eq!(t.name, "nothing");
+}
@@ -0,0 +1,80 @@
+//! `merge` pairs declarations across the three sides by kind and name,
+//! telling apart siblings that share both, and writes conflicts out on
+//! lines of their own.
+
+use rustsemantic::merge::{merge_sources, Labels};
+
+fn merge(base: &str, local: &str, remote: &str) -> (String, usize) {
+    let (merged, summary) =
+        merge_sources(base, local, remote, &Labels::default()).unwrap();
+    (merged, summary.conflicts)
+}
+
+#[test]
+fn impls_of_the_same_type_are_paired_by_content() {
+    let base = "impl A {\n    fn a(&self) {}\n}\n\nimpl B {\n    fn b(&self) {}\n}\n";
+    let local = format!("impl B {{\n    fn c(&self) {{}}\n}}\n\n{}", base);
+    let remote = base.replace("fn b(&self) {}", "fn b(&self) -> u8 { 1 }");
+
+    let (merged, conflicts) = merge(base, &local, &remote);
+    assert_eq!(conflicts, 0);
+    assert_eq!(
+        merged,
+        "impl B {\n    fn c(&self) {}\n}\n\n\
+         impl A {\n    fn a(&self) {}\n}\n\n\
+         impl B {\n    fn b(&self) -> u8 { 1 }\n}\n"
+    );
+}
+
+#[test]
+fn impls_of_a_trait_are_kept_apart_from_inherent_ones() {
+    let base =
+        "impl A {\n    fn f(&self) {}\n}\n\nimpl T for A {\n    fn f(&self) {}\n}\n";
+    let local = base.replacen("fn f(&self) {}", "fn f(&self) -> u8 { 1 }", 1);
+    let remote = base.replace(
+        "impl T for A {\n    fn f(&self) {}",
+        "impl T for A {\n    fn f(&self) -> u8 { 2 }",
+    );
+
+    let (merged, conflicts) = merge(base, &local, &remote);
+    assert_eq!(conflicts, 0);
+    assert_eq!(
+        merged,
+        "impl A {\n    fn f(&self) -> u8 { 1 }\n}\n\n\
+         impl T for A {\n    fn f(&self) -> u8 { 2 }\n}\n"
+    );
+}
+
+#[test]
+fn conflicts_sit_on_lines_of_their_own() {
+    let base = "impl A {\n    fn a(&self) {}\n    fn z(&self) {}\n}\n\nfn tail() {}\n";
+    let local = base.replace("fn a(&self) {}", "fn a(&self) -> u8 { 1 }");
+    let remote = base.replace("fn a(&self) {}", "fn a(&self) -> u8 { 2 }");
+
+    let (merged, conflicts) = merge(base, &local, &remote);
+    assert_eq!(conflicts, 1);
+    assert_eq!(
+        merged,
+        "impl A {\n\
+         <<<<<<< local\n    fn a(&self) -> u8 { 1 }\n\
+         =======\n    fn a(&self) -> u8 { 2 }\n\
+         >>>>>>> remote\n    fn z(&self) {}\n}\n\nfn tail() {}\n"
+    );
+}
+
+#[test]
+fn a_conflict_last_in_its_container_ends_its_line() {
+    let base = "impl A {\n    fn y(&self) {}\n    fn z(&self) {}\n}\n";
+    let local = base.replace("fn z(&self) {}", "fn z(&self) -> u8 { 1 }");
+    let remote = base.replace("fn z(&self) {}", "fn z(&self) -> u8 { 2 }");
+
+    let (merged, conflicts) = merge(base, &local, &remote);
+    assert_eq!(conflicts, 1);
+    assert_eq!(
+        merged,
+        "impl A {\n    fn y(&self) {}\n\
+         <<<<<<< local\n    fn z(&self) -> u8 { 1 }\n\
+         =======\n    fn z(&self) -> u8 { 2 }\n\
+         >>>>>>> remote\n}\n"
+    );
+}
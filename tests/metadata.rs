@@ -0,0 +1,232 @@
+use rustsemantic::metadata::normalize;
+use rustsemantic::model::{Metadata, Node};
+use rustsemantic::parse::{parse_source_with, ParseOptions};
+
+/// The metadata of every declaration in `src`, by name.
+fn declarations(src: &str, options: &ParseOptions) -> Vec<(String, Metadata)> {
+    fn visit(nodes: Vec<Node>, out: &mut Vec<(String, Metadata)>) {
+        for node in nodes {
+            let (name, metadata, children) = match node {
+                Node::Container(c) => (c.name, c.metadata, c.children),
+                Node::Terminal(t) => (t.name, t.metadata, vec![]),
+            };
+            if let Some(metadata) = metadata {
+                out.push((name, metadata));
+            }
+            visit(children, out);
+        }
+    }
+    let file = parse_source_with("metadata.rs", src, options).unwrap();
+    let mut out = vec![];
+    visit(file.children, &mut out);
+    out
+}
+
+#[test]
+fn normalizes_whitespace_in_types() {
+    assert_eq!(normalize("Vec< &'a   mut T >"), "Vec<&'a mut T>");
+    assert_eq!(normalize("HashMap<K ,\n    V>"), "HashMap<K, V>");
+    assert_eq!(normalize("impl Fn( u8 ) ->  u8"), "impl Fn(u8) -> u8");
+    assert_eq!(normalize("[u8 ; 4]"), "[u8 ; 4]");
+}
+
+#[test]
+fn signatures_list_parameter_and_return_types() {
+    let options = ParseOptions {
+        signatures: true,
+        ..ParseOptions::default()
+    };
+    let src = "impl S {\n    fn get(&self, key: &str,\n           default: Option< u8 >) -> u8 { 0 }\n}\n\nfn unit(_: ()) {}\n";
+    let found = declarations(src, &options);
+    assert_eq!(found.len(), 2);
+
+    let get = found[0].1.signature.as_ref().unwrap();
+    assert_eq!(get.parameters, ["&self", "&str", "Option<u8>"]);
+    assert_eq!(get.return_type.as_deref(), Some("u8"));
+
+    let unit = found[1].1.signature.as_ref().unwrap();
+    assert_eq!(unit.parameters, ["()"]);
+    assert_eq!(unit.return_type, None);
+
+    assert!(declarations(src, &ParseOptions::default()).is_empty());
+}
+
+#[test]
+fn qualifiers_record_visibility_and_modifiers() {
+    let options = ParseOptions {
+        qualifiers: true,
+        ..ParseOptions::default()
+    };
+    let src = "pub(crate) async unsafe fn f() {}\nunsafe impl Send for X {}\npub const fn g() {}\nextern \"system\" fn h() {}\nstruct S {\n    pub(super) a: u8,\n}\nfn plain() {}\n";
+    let found = declarations(src, &options);
+    assert_eq!(found.len(), 5);
+
+    let f = &found[0].1;
+    assert_eq!(f.visibility.as_deref(), Some("pub(crate)"));
+    assert!(f.is_async && f.is_unsafe && !f.is_const);
+
+    assert!(found[1].1.is_unsafe);
+    assert!(found[2].1.is_const);
+    assert_eq!(found[2].1.visibility.as_deref(), Some("pub"));
+    assert_eq!(found[3].1.abi.as_deref(), Some("system"));
+    assert_eq!(found[4].1.visibility.as_deref(), Some("pub(super)"));
+
+    let json = serde_json::to_value(f).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({ "visibility": "pub(crate)", "async": true, "unsafe": true })
+    );
+}
+
+#[test]
+fn docs_collect_outer_doc_comments_across_attributes() {
+    let options = ParseOptions {
+        docs: true,
+        ..ParseOptions::default()
+    };
+    let src = r#"/// Adds one.
+///
+/// Panics on overflow.
+#[inline]
+#[doc = "Really."]
+fn add_one() {}
+
+// Not a doc comment.
+fn plain() {}
+
+//// Not one either.
+fn banner() {}
+
+/**
+ * Block docs.
+ */
+struct Block;
+"#;
+    let found = declarations(src, &options);
+    let docs: Vec<&str> = found
+        .iter()
+        .map(|(_, m)| m.doc.as_deref().unwrap())
+        .collect();
+    assert_eq!(
+        docs,
+        ["Adds one.\n\nPanics on overflow.\nReally.", "Block docs."]
+    );
+}
+
+#[test]
+fn excerpts_keep_the_first_line_of_declarations() {
+    let options = ParseOptions {
+        excerpts: true,
+        ..ParseOptions::default()
+    };
+    let long = format!("const LONG: &str = \"{}\";", "x".repeat(100));
+    let src = format!(
+        "/// Docs stay out.\n#[inline]\npub fn f(\n    x: u8,\n) -> u8 {{\n    x\n}}\n\n{}\n",
+        long
+    );
+    let found = declarations(&src, &options);
+    let excerpts: Vec<&str> = found
+        .iter()
+        .map(|(_, m)| m.excerpt.as_deref().unwrap())
+        .collect();
+    assert_eq!(excerpts[0], "pub fn f(");
+    assert_eq!(excerpts[1].chars().count(), 80);
+    assert!(excerpts[1].starts_with("const LONG: &str = \"xxx"));
+    assert!(excerpts[1].ends_with("x…"));
+    assert_eq!(excerpts.len(), 2);
+}
+
+#[test]
+fn tests_flags_test_code_and_what_is_inside_it() {
+    let options = ParseOptions {
+        tests: true,
+        ..ParseOptions::default()
+    };
+    let src = r#"pub fn api() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helper() {}
+
+    #[test]
+    fn works() {}
+}
+
+#[tokio::test]
+fn tokio_works() {}
+
+/// Not a test.
+#[cfg(not(test))]
+fn production() {}
+"#;
+    let flagged: Vec<String> = declarations(src, &options)
+        .into_iter()
+        .filter(|(_, m)| m.is_test)
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        flagged,
+        [
+            "tests #[cfg(test)]",
+            "super::*",
+            "helper",
+            "works",
+            "tokio_works"
+        ]
+    );
+}
+
+#[test]
+fn complexity_counts_decisions_and_nesting_per_function() {
+    let options = ParseOptions {
+        complexity: true,
+        ..ParseOptions::default()
+    };
+    let src = r#"fn straight() -> u32 {
+    1
+}
+
+fn branchy(x: u32, ready: bool) -> u32 {
+    if x > 10 && ready {
+        for i in 0..x {
+            while i > 0 {
+                break;
+            }
+        }
+        0
+    } else if x > 5 || !ready {
+        match x {
+            6 => 1,
+            7 => 2,
+            _ => 3,
+        }
+    } else {
+        fn nested(y: u32) -> u32 {
+            if y > 0 { y } else { 0 }
+        }
+        nested(x)
+    }
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+}
+"#;
+    let measured: Vec<(String, u32, u32)> = declarations(src, &options)
+        .into_iter()
+        .filter_map(|(name, m)| m.complexity.map(|c| (name, c.cyclomatic, c.nesting)))
+        .collect();
+    let measure =
+        |name: &str, cyclomatic, nesting| (name.to_string(), cyclomatic, nesting);
+    assert_eq!(
+        measured,
+        [
+            measure("straight", 1, 0),
+            // if, &&, for, while, else if, ||, and a match of three arms.
+            measure("branchy", 9, 3),
+            measure("nested", 2, 1),
+        ]
+    );
+}
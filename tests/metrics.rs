@@ -0,0 +1,39 @@
+//! The server counts what it does, for `GET /metrics`.
+
+use rustsemantic::server::Server;
+use serde_json::json;
+
+#[test]
+fn requests_parses_and_cache_hits_are_counted() {
+    let mut server = Server::default();
+    let document = json!({ "name": "a.rs", "text": "fn f() {}\n" });
+    server.call("parse", &document).unwrap();
+    server.call("outline", &document).unwrap();
+    server
+        .call("parse", &json!({ "name": "b.rs", "text": "fn (\n" }))
+        .unwrap();
+    assert!(server.call("parse", &json!({})).is_err());
+    assert!(server.call("made-up", &json!({})).is_err());
+
+    let metrics = &server.metrics;
+    assert_eq!(metrics.requests["parse"], 3);
+    assert_eq!(metrics.requests["outline"], 1);
+    assert_eq!(metrics.requests["unknown"], 1);
+    assert_eq!(metrics.errors["parse"], 1);
+    assert_eq!(metrics.cache_hits, 1);
+    assert_eq!(metrics.cache_misses, 2);
+    assert_eq!(metrics.parse_durations["rust"].count(), 2);
+    assert_eq!(metrics.syntax_errors["rust"], 1);
+
+    let text = metrics.render();
+    for line in [
+        "# TYPE rustsemantic_requests_total counter",
+        "rustsemantic_requests_total{method=\"parse\"} 3",
+        "rustsemantic_request_errors_total{method=\"unknown\"} 1",
+        "rustsemantic_parse_duration_seconds_bucket{language=\"rust\",le=\"+Inf\"} 2",
+        "rustsemantic_parse_duration_seconds_count{language=\"rust\"} 2",
+        "rustsemantic_cache_hits_total 1",
+    ] {
+        assert!(text.lines().any(|l| l == line), "{} in\n{}", line, text);
+    }
+}
@@ -0,0 +1,183 @@
+//! `mod foo;` declarations resolve to the files rustc would load, `#[path]`
+//! and all, for outline and diff to inline.
+
+mod common;
+
+use std::path::Path;
+
+use rustsemantic::diff::{diff_file_pair, ChangeKind};
+use rustsemantic::outline::{crate_outline, inline_modules, outline, OutlineItem};
+use rustsemantic::parse::{parse_source, read_file};
+
+use common::scratch;
+
+/// A crate with a module in `name.rs`, one in `name/mod.rs`, a module
+/// declared inside an inline one, and one whose file is missing.
+fn write_crate(root: &Path, answer: u32) {
+    let files = [
+        (
+            "lib.rs",
+            "mod a;\nmod b;\nmod inline {\n    mod c;\n}\nmod missing;\n".to_string(),
+        ),
+        ("a.rs", "pub fn in_a() {}\nmod nested;\n".to_string()),
+        (
+            "a/nested.rs",
+            format!("pub fn answer() -> u32 {{ {} }}\n", answer),
+        ),
+        ("b/mod.rs", "pub struct InB {}\n".to_string()),
+        ("inline/c.rs", "pub fn in_c() {}\n".to_string()),
+    ];
+    for (name, contents) in files {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+fn names(items: &[OutlineItem], prefix: &str, out: &mut Vec<String>) {
+    for item in items {
+        let name = format!("{}{}", prefix, item.name);
+        out.push(name.clone());
+        names(&item.children, &format!("{}::", name), out);
+    }
+}
+
+#[test]
+fn outline_inlines_module_files() {
+    let root = scratch("modules-outline");
+    write_crate(&root, 42);
+    let lib = root.join("lib.rs");
+    let contents = read_file(&lib.to_string_lossy()).unwrap();
+    let mut items = outline(&parse_source("lib.rs", &contents).unwrap(), &contents);
+    inline_modules(&mut items, &contents, &lib).unwrap();
+
+    let mut all = vec![];
+    names(&items, "", &mut all);
+    assert_eq!(
+        all,
+        [
+            "a",
+            "a::in_a",
+            "a::nested",
+            "a::nested::answer",
+            "b",
+            "b::InB",
+            "inline",
+            "inline::c",
+            "inline::c::in_c",
+            "missing",
+        ]
+    );
+    assert!(items[0].file.as_deref().unwrap().ends_with("/a.rs"));
+    assert!(items[1].file.as_deref().unwrap().ends_with("/b/mod.rs"));
+    assert_eq!(items[3].file, None);
+}
+
+#[test]
+fn diff_reports_changes_inside_module_files() {
+    let (old, new) = (scratch("modules-diff-old"), scratch("modules-diff-new"));
+    write_crate(&old, 41);
+    write_crate(&new, 42);
+
+    let diff = diff_file_pair(&old.join("lib.rs"), &new.join("lib.rs"), true).unwrap();
+    assert_eq!(diff.changes.len(), 1);
+    let change = &diff.changes[0];
+    assert_eq!(change.change, ChangeKind::Modified);
+    assert_eq!(change.name, "a::nested::answer");
+    assert!(change.file.as_deref().unwrap().ends_with("/a/nested.rs"));
+
+    let plain =
+        diff_file_pair(&old.join("lib.rs"), &new.join("lib.rs"), false).unwrap();
+    assert!(plain.changes.is_empty());
+}
+
+#[test]
+fn crates_outline_as_one_tree_following_path_attributes() {
+    let root = scratch("modules-crate");
+    let files = [
+        ("Cargo.toml", "[package]\nname = \"shapes\"\nversion = \"0.1.0\"\n"),
+        (
+            "src/lib.rs",
+            "#[path = \"gen/generated.rs\"]\npub mod generated;\nmod plain;\nmod inline {\n    #[path = \"moved.rs\"]\n    mod c;\n}\n",
+        ),
+        ("src/gen/generated.rs", "pub struct Generated {}\nmod beside;\n"),
+        ("src/gen/beside.rs", "pub fn beside() {}\n"),
+        ("src/plain.rs", "#[path = \"other.rs\"]\nmod elsewhere;\n"),
+        ("src/other.rs", "pub fn other() {}\n"),
+        ("src/inline/moved.rs", "pub fn moved() {}\n"),
+    ];
+    for (name, contents) in files {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    let krate = crate_outline(&root).unwrap();
+    assert_eq!(krate.item_type, "crate");
+    assert_eq!(krate.name, "shapes");
+    assert!(krate.file.as_deref().unwrap().ends_with("src/lib.rs"));
+    let mut all = vec![];
+    names(&krate.children, "", &mut all);
+    assert_eq!(
+        all,
+        [
+            "generated",
+            "generated::Generated",
+            "generated::beside",
+            "generated::beside::beside",
+            "plain",
+            "plain::elsewhere",
+            "plain::elsewhere::other",
+            "inline",
+            "inline::c",
+            "inline::c::moved",
+        ]
+    );
+    assert_eq!(
+        crate_outline(&root.join("src/lib.rs")).unwrap().name,
+        "shapes"
+    );
+    assert!(crate_outline(&root.join("src")).is_err());
+}
+
+#[test]
+fn lsp_symbols_select_the_identifier() {
+    let dir = scratch("modules-lsp");
+    let file = dir.join("lib.rs");
+    std::fs::write(
+        &file,
+        "pub(crate) struct Foo;\nfn n() {}\npub trait Shape {}\nimpl Shape for Foo {}\n",
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .args(["outline", "--format", "lsp"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let symbols: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let found: Vec<String> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            let selection = &symbol["selectionRange"];
+            format!(
+                "{} {}:{}-{}",
+                symbol["name"].as_str().unwrap(),
+                selection["start"]["line"],
+                selection["start"]["character"],
+                selection["end"]["character"]
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "Foo 0:18-21",
+            "n 1:3-4",
+            "Shape 2:10-15",
+            "impl Shape for Foo 3:15-18"
+        ]
+    );
+}
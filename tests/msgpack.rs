@@ -0,0 +1,40 @@
+//! Encodings checked against the examples in the MessagePack spec.
+
+use serde_json::json;
+
+use rustsemantic::msgpack::to_vec;
+
+#[test]
+fn encodes_scalars_in_their_smallest_form() {
+    assert_eq!(to_vec(&json!(null)), [0xc0]);
+    assert_eq!(to_vec(&json!(true)), [0xc3]);
+    assert_eq!(to_vec(&json!(false)), [0xc2]);
+    assert_eq!(to_vec(&json!(7)), [0x07]);
+    assert_eq!(to_vec(&json!(200)), [0xcc, 200]);
+    assert_eq!(to_vec(&json!(1000)), [0xcd, 0x03, 0xe8]);
+    assert_eq!(to_vec(&json!(70000)), [0xce, 0x00, 0x01, 0x11, 0x70]);
+    assert_eq!(to_vec(&json!(-1)), [0xff]);
+    assert_eq!(to_vec(&json!(-33)), [0xd0, 0xdf]);
+    assert_eq!(to_vec(&json!(-1000)), [0xd1, 0xfc, 0x18]);
+    assert_eq!(to_vec(&json!(1.5)), [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn encodes_strings_arrays_and_maps_with_length_headers() {
+    assert_eq!(to_vec(&json!("fn")), [0xa2, b'f', b'n']);
+    let long = "x".repeat(40);
+    let mut expected = vec![0xd9, 40];
+    expected.extend_from_slice(long.as_bytes());
+    assert_eq!(to_vec(&json!(long)), expected);
+
+    assert_eq!(to_vec(&json!([1, -1])), [0x92, 0x01, 0xff]);
+    let sixteen: Vec<u8> = (0..16).collect();
+    let mut expected = vec![0xdc, 0x00, 0x10];
+    expected.extend(&sixteen);
+    assert_eq!(to_vec(&json!(sixteen)), expected);
+
+    assert_eq!(
+        to_vec(&json!({ "span": [0, -1] })),
+        [0x81, 0xa4, b's', b'p', b'a', b'n', 0x92, 0x00, 0xff]
+    );
+}
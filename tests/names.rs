@@ -0,0 +1,258 @@
+use rustsemantic::parse::{parse_source, parse_source_with, Nested, ParseOptions};
+use rustsemantic::templates::NameTemplates;
+
+fn names(node: &rustsemantic::model::Node, out: &mut Vec<String>) {
+    out.push(format!("{} {}", node.kind(), node.name()));
+    for child in node.children() {
+        names(child, out);
+    }
+}
+
+#[test]
+fn extern_blocks_are_named_by_abi_and_foreign_items_by_identifier() {
+    let src = "extern \"system\" {\n    fn f(x: i32);\n    pub static mut count: u32;\n}\n\nextern {\n    static errno: i32;\n}\n";
+    let file = parse_source("ffi.rs", src).unwrap();
+    let mut all = vec![];
+    for child in &file.children {
+        names(child, &mut all);
+    }
+    let named: Vec<_> = all
+        .iter()
+        .filter(|n| n.starts_with("foreign_mod_item") || n.contains("_item "))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        named,
+        [
+            "foreign_mod_item extern \"system\"",
+            "function_signature_item f",
+            "static_item count",
+            "foreign_mod_item extern \"C\"",
+            "static_item errno",
+        ]
+    );
+}
+
+#[test]
+fn methods_are_qualified_with_their_impl_when_asked() {
+    let src = "impl<T> Display for Wrapper< T > {\n    fn fmt(&self) {}\n}\n\nimpl Foo {\n    pub(crate) fn new() {}\n}\n\nfn free() {}\n";
+    let options = ParseOptions {
+        qualify_methods: true,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("impls.rs", src, &options).unwrap();
+    let mut all = vec![];
+    for child in &file.children {
+        names(child, &mut all);
+    }
+    let functions: Vec<_> = all
+        .iter()
+        .filter(|n| n.starts_with("function_item"))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        functions,
+        [
+            "function_item <Wrapper<T> as Display>::fmt",
+            "function_item Foo::new",
+            "function_item free",
+        ]
+    );
+}
+
+#[test]
+fn fields_and_variants_are_named_terminals() {
+    let src = "struct Config {\n    #[serde(default)]\n    pub name: String,\n    retries: u32,\n}\n\nenum Shape {\n    Circle { radius: f64 },\n    Square(f64),\n    Empty,\n}\n";
+    let file = parse_source("types.rs", src).unwrap();
+    let mut all = vec![];
+    for child in &file.children {
+        names(child, &mut all);
+    }
+    let members: Vec<_> = all
+        .iter()
+        .filter(|n| {
+            n.starts_with("field_declaration ") || n.starts_with("enum_variant ")
+        })
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        members,
+        [
+            "field_declaration name",
+            "field_declaration retries",
+            "enum_variant Circle",
+            "enum_variant Square",
+            "enum_variant Empty",
+        ]
+    );
+    let fields = file.children[0].children().last().unwrap();
+    assert_eq!(fields.kind(), "field_declaration_list");
+    assert!(fields.children().iter().all(|f| f.children().is_empty()));
+}
+
+#[test]
+fn constants_statics_and_aliases_are_named_terminals() {
+    let src = "const MAX: usize = 10;\npub static mut COUNT: u32 = 0;\npub(crate) type Result<T> = std::result::Result<T, Error>;\n\ntrait Shape {\n    const SIDES: u32;\n    type Output: Clone;\n}\n";
+    let file = parse_source("items.rs", src).unwrap();
+    let mut all = vec![];
+    for child in &file.children {
+        names(child, &mut all);
+    }
+    let items: Vec<_> = all
+        .iter()
+        .filter(|n| !n.starts_with("trait_item") && !n.starts_with("declaration_list"))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        items,
+        [
+            "const_item MAX",
+            "static_item COUNT",
+            "type_item Result",
+            "type_identifier Shape",
+            "const_item SIDES",
+            "associated_type Output",
+        ]
+    );
+}
+
+#[test]
+fn nested_functions_are_folded_or_nested_by_path() {
+    let src = "fn outer() {\n    fn inner() {\n        let f = |x: u8| {\n            fn deepest() {}\n        };\n    }\n}\n";
+    let functions = |nested| {
+        let options = ParseOptions {
+            nested,
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("nested.rs", src, &options).unwrap();
+        let mut all = vec![];
+        names(&file.children[0], &mut all);
+        all.into_iter()
+            .filter(|n| {
+                n.starts_with("function_item") || n.starts_with("closure_expression")
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        functions(Nested::Fold),
+        ["function_item outer", "function_item inner"]
+    );
+    assert_eq!(
+        functions(Nested::Nest),
+        [
+            "function_item outer",
+            "function_item outer::inner",
+            "closure_expression outer::inner::{closure}",
+            "function_item outer::inner::{closure}::deepest",
+        ]
+    );
+}
+
+#[test]
+fn duplicate_declarations_are_numbered_in_order_when_asked() {
+    let src = "use a;\n\nimpl Foo {\n    fn a() {}\n}\n\nimpl Foo {\n    fn a() {}\n    fn a() {}\n}\n\nuse b;\n\nimpl Foo {}\n";
+    let options = ParseOptions {
+        ordinals: true,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("duplicates.rs", src, &options).unwrap();
+    let mut all = vec![];
+    for child in &file.children {
+        names(child, &mut all);
+    }
+    let numbered: Vec<_> = all
+        .iter()
+        .filter(|n| {
+            n.starts_with("impl_item")
+                || n.starts_with("function_item")
+                || n.starts_with("uses")
+        })
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        numbered,
+        [
+            "uses uses",
+            "impl_item impl Foo",
+            "function_item a",
+            "impl_item impl Foo (2)",
+            "function_item a",
+            "function_item a (2)",
+            "uses uses (2)",
+            "impl_item impl Foo (3)",
+        ]
+    );
+
+    let plain = parse_source("duplicates.rs", src).unwrap();
+    assert!(plain
+        .children
+        .iter()
+        .all(|child| !child.name().contains('(')));
+}
+
+#[test]
+fn impls_are_named_after_their_type_and_trait() {
+    let src = "impl A {\n    fn a() {}\n}\n\nimpl B {\n    fn b() {}\n}\n\nimpl<T> Display for Wrapper< T > {}\n";
+    let options = ParseOptions {
+        ordinals: true,
+        ..ParseOptions::default()
+    };
+    let impls = |src: &str| -> Vec<String> {
+        let file = parse_source_with("impls.rs", src, &options).unwrap();
+        file.children
+            .iter()
+            .filter(|child| child.kind() == "impl_item")
+            .map(|child| child.name().to_string())
+            .collect()
+    };
+    assert_eq!(
+        impls(src),
+        ["impl A", "impl B", "impl Display for Wrapper<T>"]
+    );
+    // Another impl of B above them renumbers only the impls of B.
+    assert_eq!(
+        impls(&format!("impl B {{\n    fn c() {{}}\n}}\n\n{}", src)),
+        [
+            "impl B",
+            "impl A",
+            "impl B (2)",
+            "impl Display for Wrapper<T>"
+        ]
+    );
+}
+
+#[test]
+fn templates_name_declarations_of_their_kind() {
+    let templates = NameTemplates::parse(
+        "# labels for the merge tool\n\
+         function_item = \"{vis} fn {name}{generics}\"\n\
+         impl_item = \"impl {trait} for {type}\"\n\
+         struct_item = \"{{{kind}}} {name}\"\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        name_templates: templates,
+        ..ParseOptions::default()
+    };
+    let src = "pub(crate) fn map<T,  U>(x: T) -> U {}\n\nfn private() {}\n\nimpl Clone for Point {}\n\nstruct Point {}\n";
+    let file = parse_source_with("a.rs", src, &options).unwrap();
+    let named: Vec<_> = file.children.iter().map(|child| child.name()).collect();
+    assert_eq!(
+        named,
+        [
+            "pub(crate) fn map<T, U>",
+            "fn private",
+            "impl Clone for Point",
+            "{struct_item} Point"
+        ]
+    );
+
+    for bad in [
+        "function_item = {name}",
+        "function_item = \"{nmae}\"",
+        "function_item = \"{name\"",
+        "function_item",
+    ] {
+        assert!(NameTemplates::parse(bad).is_err(), "{}", bad);
+    }
+}
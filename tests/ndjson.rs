@@ -0,0 +1,38 @@
+use serde_json::Value;
+
+use rustsemantic::model::Node;
+use rustsemantic::ndjson::write_nodes;
+use rustsemantic::parse::parse_source;
+
+fn count(nodes: &[Node]) -> usize {
+    nodes.iter().map(|node| 1 + count(node.children())).sum()
+}
+
+#[test]
+fn flattens_every_node_after_its_parent() {
+    let src = "mod m {\n    fn f() {}\n}\n\nstruct S;\n";
+    let file = parse_source("m.rs", src).unwrap();
+    let mut out = vec![];
+    write_nodes(&mut out, &file).unwrap();
+
+    let lines: Vec<Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1 + count(&file.children));
+    assert_eq!(lines[0]["type"], "file");
+    assert_eq!(lines[0]["parent"], Value::Null);
+    for (id, line) in lines.iter().enumerate().skip(1) {
+        assert_eq!(line["id"], id);
+        let parent = line["parent"].as_u64().unwrap() as usize;
+        assert!(parent < id);
+        assert_eq!(line["depth"], lines[parent]["depth"].as_u64().unwrap() + 1);
+    }
+    let function = lines.iter().find(|l| l["type"] == "function_item").unwrap();
+    let parent = |line: &Value| &lines[line["parent"].as_u64().unwrap() as usize];
+    let body = parent(function);
+    assert_eq!(body["type"], "declaration_list");
+    assert_eq!(parent(body)["type"], "mod_item");
+    assert_eq!(parent(parent(body))["id"], 0);
+}
@@ -0,0 +1,130 @@
+//! Paths as SemanticMerge sends them, quirks and all, resolve to the files
+//! meant.
+
+mod common;
+
+use std::io::{Error, ErrorKind};
+
+use rustsemantic::files::{
+    clean_path, resolve_path, verbatim_path, DisplayNames, FileError, FileFailure,
+    PathEncoding,
+};
+
+use common::scratch;
+
+#[test]
+fn quotes_and_trailing_separators_are_dropped() {
+    assert_eq!(clean_path(r#""C:\Temp\a b.rs""#), r"C:\Temp\a b.rs");
+    assert_eq!(clean_path(r"C:\Temp\a.rs\"), r"C:\Temp\a.rs");
+    assert_eq!(clean_path("/tmp/a.rs//"), "/tmp/a.rs");
+    assert_eq!(clean_path(r"C:\"), r"C:\");
+    assert_eq!(clean_path("/"), "/");
+    assert_eq!(clean_path("a.rs"), "a.rs");
+}
+
+#[test]
+fn absolute_windows_paths_take_the_long_path_form() {
+    assert_eq!(verbatim_path(r"C:\Temp\a.rs"), r"\\?\C:\Temp\a.rs");
+    assert_eq!(verbatim_path("C:/Temp/./x/../a.rs"), r"\\?\C:\Temp\a.rs");
+    assert_eq!(verbatim_path(r"C:\..\a.rs"), r"\\?\C:\a.rs");
+    assert_eq!(verbatim_path(r"C:\"), r"\\?\C:\");
+    assert_eq!(
+        verbatim_path(r"\\server\share\dir\a.rs"),
+        r"\\?\UNC\server\share\dir\a.rs"
+    );
+    assert_eq!(
+        verbatim_path(r"\\server\share\..\..\a.rs"),
+        r"\\?\UNC\server\share\a.rs"
+    );
+    assert_eq!(verbatim_path(r"\\?\C:\Temp\a.rs"), r"\\?\C:\Temp\a.rs");
+    assert_eq!(verbatim_path(r"src\a.rs"), r"src\a.rs");
+}
+
+#[cfg(unix)]
+#[test]
+fn directories_are_canonicalized() {
+    let dir = scratch("paths");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let raw = format!("\"{}/sub/../missing.rs/\"", dir.display());
+    assert_eq!(
+        resolve_path(&raw),
+        std::fs::canonicalize(&dir).unwrap().join("missing.rs")
+    );
+}
+
+#[test]
+fn path_lines_are_decoded_in_the_encoding_given() {
+    let latin1 = b"C:\\\xe9t\xe9 \x80.rs";
+    assert_eq!(
+        PathEncoding::Latin1.decode(latin1).unwrap(),
+        "C:\\été \u{80}.rs"
+    );
+    assert_eq!(
+        PathEncoding::Windows1252.decode(latin1).unwrap(),
+        "C:\\été €.rs"
+    );
+    assert!(PathEncoding::Utf8.decode(latin1).is_err());
+    assert!(PathEncoding::Auto
+        .decode(latin1)
+        .unwrap()
+        .starts_with("C:\\été"));
+    assert_eq!(
+        PathEncoding::Auto
+            .decode("\u{feff}/tmp/été.rs".as_bytes())
+            .unwrap(),
+        "/tmp/été.rs"
+    );
+    assert_eq!(
+        PathEncoding::parse("CP1252").unwrap(),
+        PathEncoding::Windows1252
+    );
+    assert!(PathEncoding::parse("ebcdic").is_err());
+}
+
+#[test]
+fn io_errors_are_classified() {
+    let of = |kind| FileFailure::of(&Error::from(kind));
+    assert_eq!(of(ErrorKind::NotFound), FileFailure::Missing);
+    assert_eq!(
+        of(ErrorKind::PermissionDenied),
+        FileFailure::PermissionDenied
+    );
+    assert_eq!(of(ErrorKind::IsADirectory), FileFailure::IsADirectory);
+    assert_eq!(of(ErrorKind::InvalidData), FileFailure::NotText);
+    assert_eq!(of(ErrorKind::Interrupted), FileFailure::Other);
+    if cfg!(windows) {
+        assert_eq!(
+            FileFailure::of(&Error::from_raw_os_error(32)),
+            FileFailure::Locked
+        );
+    }
+
+    let error = FileError::wrap(
+        std::path::Path::new("a.rs"),
+        false,
+        Error::from(ErrorKind::NotFound).into(),
+    );
+    let file = error.downcast_ref::<FileError>().unwrap();
+    assert_eq!(file.failure.as_str(), "missing");
+    assert!(error
+        .to_string()
+        .starts_with("failed to read a.rs: it doesn't exist"));
+    let other =
+        FileError::wrap(std::path::Path::new("a.rs"), true, anyhow::anyhow!("no"));
+    assert!(other.downcast_ref::<FileError>().is_none());
+}
+
+#[test]
+fn display_names_match_paths_then_file_names() {
+    let names = DisplayNames::parse(
+        "# merge of src/lib.rs\n\n\"/tmp/a/tmp1.rs/\" = src/lib.rs\ntmp2.rs = src/old.rs\ntmp2.rs = src/new.rs\n",
+    )
+    .unwrap();
+    assert_eq!(names.name("/tmp/a/tmp1.rs"), "src/lib.rs");
+    assert_eq!(names.name("/tmp/b/tmp1.rs"), "tmp1.rs");
+    assert_eq!(names.name("/tmp/b/tmp2.rs"), "src/new.rs");
+    assert_eq!(DisplayNames::default().name("\"/tmp/c/x.rs\""), "x.rs");
+
+    let error = DisplayNames::parse("tmp1.rs src/lib.rs\n").unwrap_err();
+    assert_eq!(error.to_string(), "line 1: expected source = name");
+}
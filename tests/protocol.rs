@@ -0,0 +1,811 @@
+//! Replays SemanticMerge external parser sessions against the binary:
+//! `shell <flag file>`, then `source / encoding / output` triples on stdin
+//! answered with `OK` or `KO`, until the `end` sentinel.
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use common::scratch;
+
+/// A scratch directory the session runs in, to check it leaves nothing
+/// behind in its working directory.
+/// Runs one session with `stdin` as the whole input, returning the response
+/// lines and whether the process exited successfully.
+fn session(dir: &Path, stdin: &str) -> (Vec<String>, bool) {
+    session_with_env(dir, stdin, &[])
+}
+
+fn session_with_env(
+    dir: &Path,
+    stdin: impl AsRef<[u8]>,
+    env: &[(&str, &str)],
+) -> (Vec<String>, bool) {
+    let (responses, status) = session_status(dir, stdin, env);
+    (responses, status.success())
+}
+
+/// Like [`session_with_env`], with the exit status itself.
+fn session_status(
+    dir: &Path,
+    stdin: impl AsRef<[u8]>,
+    env: &[(&str, &str)],
+) -> (Vec<String>, std::process::ExitStatus) {
+    let mut child = spawn_shell(dir, env);
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_ref())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("flag.txt")).unwrap(),
+        "hello"
+    );
+    let responses = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    (responses, output.status)
+}
+
+/// Starts `shell <dir>/flag.txt` in `dir` with its stdio piped, and
+/// without the caller's logging, recording and comparing settings.
+fn spawn_shell(dir: &Path, env: &[(&str, &str)]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .current_dir(dir)
+        .env_remove("SEMANTIC_RUST_LOG")
+        .env_remove("SEMANTIC_RUST_LOG_FILTER")
+        .env_remove("SEMANTIC_RUST_RECORD")
+        .env_remove("SEMANTIC_RUST_COMPARE")
+        .env_remove("SEMANTIC_RUST_CLEANUP")
+        .env_remove("SEMANTIC_RUST_BINARY")
+        .env_remove("SEMANTIC_RUST_NAMES")
+        .env("SEMANTIC_RUST_REPORT", dir.with_extension("failures.jsonl"))
+        .envs(env.iter().copied())
+        .arg("shell")
+        .arg(dir.join("flag.txt"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn rustsemantic shell")
+}
+
+fn tree(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+const SOURCE: &str =
+    "struct Point {\n    x: i32,\n}\n\nfn origin() -> Point {\n    Point { x: 0 }\n}\n";
+
+#[test]
+fn answers_each_request_and_stops_at_end() {
+    let dir = scratch("protocol-basic");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let first = dir.join("a.json");
+    let second = dir.join("b.json");
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        first.display(),
+        source.display(),
+        second.display()
+    );
+
+    let (responses, success) = session(&dir, &input);
+    assert!(success);
+    assert_eq!(responses, ["OK", "OK"]);
+    let written = tree(&first);
+    assert_eq!(written["type"], "file");
+    assert_eq!(written["parsingErrorsDetected"], false);
+    assert_eq!(written["children"].as_array().unwrap().len(), 2);
+    assert_eq!(written, tree(&second));
+}
+
+#[test]
+fn tolerates_crlf_and_blank_lines() {
+    let dir = scratch("protocol-crlf");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let output = dir.join("a.json");
+    let input = format!(
+        "\r\n{}\r\nUTF-8\r\n\r\n{}\r\n\n\nend\r\n",
+        source.display(),
+        output.display()
+    );
+
+    let (responses, success) = session(&dir, &input);
+    assert!(success);
+    assert_eq!(responses, ["OK"]);
+    assert_eq!(tree(&output)["children"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn keeps_spaces_in_paths() {
+    let dir = scratch("protocol-spaces");
+    let nested = dir.join("my project");
+    std::fs::create_dir_all(&nested).unwrap();
+    let source = nested.join("the end.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let output = nested.join("tree output.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+
+    let (responses, success) = session(&dir, &input);
+    assert!(success);
+    assert_eq!(responses, ["OK"]);
+    assert_eq!(tree(&output)["name"], "the end.rs");
+}
+
+#[test]
+fn answers_ko_for_unreadable_sources_and_carries_on() {
+    let dir = scratch("protocol-missing");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let missing = dir.join("missing.rs");
+    let output = dir.join("a.json");
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        missing.display(),
+        dir.join("missing.json").display(),
+        source.display(),
+        output.display()
+    );
+
+    let (responses, success) = session(&dir, &input);
+    assert!(success);
+    assert_eq!(responses, ["KO", "OK"]);
+    assert!(output.exists());
+}
+
+#[test]
+fn reports_syntax_errors_in_the_tree() {
+    let dir = scratch("protocol-errors");
+    let source = dir.join("broken.rs");
+    std::fs::write(&source, "fn broken( {\n}\n\nstruct Fine;\n").unwrap();
+    let output = dir.join("broken.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+
+    let (responses, success) = session(&dir, &input);
+    assert!(success);
+    assert_eq!(responses, ["OK"]);
+    assert_eq!(tree(&output)["parsingErrorsDetected"], true);
+}
+
+#[test]
+fn ends_with_a_distinct_code_when_stdin_closes() {
+    let dir = scratch("protocol-eof");
+    let (responses, status) = session_status(&dir, "", &[]);
+    assert_eq!(status.code(), Some(3));
+    assert!(responses.is_empty());
+
+    // Also in the middle of a request.
+    let (responses, status) = session_status(&dir, "a.rs\nUTF-8\n", &[]);
+    assert_eq!(status.code(), Some(3));
+    assert!(responses.is_empty());
+}
+
+#[test]
+fn identical_sources_give_identical_output() {
+    let outputs: Vec<Vec<u8>> = ["determinism-a", "determinism-b"]
+        .iter()
+        .map(|test| {
+            let dir = scratch(&format!("protocol-{}", test));
+            let source = dir.join("lib.rs");
+            std::fs::write(&source, SOURCE).unwrap();
+            let output = dir.join("tree.json");
+            let input =
+                format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+            assert_eq!(session(&dir, &input).0, ["OK"]);
+            std::fs::read(&output).unwrap()
+        })
+        .collect();
+    assert_eq!(outputs[0], outputs[1]);
+}
+
+#[test]
+fn counts_spans_in_utf16_code_units() {
+    let dir = scratch("protocol-utf16");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, "const C: &str = \"🦀é\";\nstruct S;\n").unwrap();
+    let output = dir.join("a.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+
+    assert_eq!(session(&dir, &input).0, ["OK"]);
+    let written = tree(&output);
+    // The crab is two code units and the accent one, against four and two
+    // bytes, so the struct starts at 22 rather than 25.
+    assert_eq!(written["children"][1]["headerSpan"][0], 22);
+    // Span ends are inclusive: the footer is the one newline at the end.
+    assert_eq!(written["footerSpan"], serde_json::json!([32, 32]));
+}
+
+#[test]
+fn logs_only_where_asked() {
+    let dir = scratch("protocol-logging");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display(),
+        dir.join("missing.rs").display(),
+        dir.join("b.json").display()
+    );
+
+    assert_eq!(session(&dir, &input).0, ["OK", "KO"]);
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["a.json", "a.rs", "flag.txt"]);
+
+    let log = dir.join("logs").join("shell.log");
+    std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+    let env = [("SEMANTIC_RUST_LOG", log.to_str().unwrap())];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "KO"]);
+    let text = std::fs::read_to_string(&log).unwrap();
+    assert!(text.contains("INFO") && text.contains("a.rs"), "{}", text);
+    assert!(
+        text.contains("WARN") && text.contains("missing.rs"),
+        "{}",
+        text
+    );
+    assert!(!text.contains("DEBUG"), "{}", text);
+}
+
+#[test]
+fn logs_sizes_and_timings_at_debug_level() {
+    let dir = scratch("protocol-metrics");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display()
+    );
+    let log = dir.join("shell.log");
+    let env = [
+        ("SEMANTIC_RUST_LOG", log.to_str().unwrap()),
+        ("SEMANTIC_RUST_LOG_FILTER", "debug"),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    let text = std::fs::read_to_string(&log).unwrap();
+    let line = text.lines().find(|l| l.contains("DEBUG")).unwrap();
+    for field in [
+        format!("bytes={}", SOURCE.len()),
+        "nodes=".to_string(),
+        "parse_micros=".to_string(),
+        "serialize_micros=".to_string(),
+        "output_bytes=".to_string(),
+    ] {
+        assert!(line.contains(&field), "{} in {}", field, line);
+    }
+}
+
+#[test]
+fn binary_files_log_their_size_in_bytes() {
+    let dir = scratch("protocol-binary-size");
+    let source = dir.join("blob.rs");
+    std::fs::write(&source, b"\xff\xfe\x00\x01\n\xff\n").unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("blob.json").display()
+    );
+    let log = dir.join("shell.log");
+    let env = [
+        ("SEMANTIC_RUST_LOG", log.to_str().unwrap()),
+        ("SEMANTIC_RUST_LOG_FILTER", "debug"),
+        ("SEMANTIC_RUST_BINARY", "opaque"),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    let text = std::fs::read_to_string(&log).unwrap();
+    let line = text.lines().find(|l| l.contains("DEBUG")).unwrap();
+    assert!(line.contains("bytes=7"), "{}", line);
+}
+
+#[test]
+fn panics_become_errors() {
+    let result: anyhow::Result<()> =
+        rustsemantic::shell::contain_panics(|| panic!("walker blew up on {}", "a.rs"));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "panicked: walker blew up on a.rs"
+    );
+    assert_eq!(rustsemantic::shell::contain_panics(|| Ok(1)).unwrap(), 1);
+}
+
+#[test]
+fn recorded_sessions_replay_the_same() {
+    let dir = scratch("protocol-record");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display(),
+        dir.join("missing.rs").display(),
+        dir.join("b.json").display()
+    );
+    let recording = dir.join("session.jsonl");
+    let env = [("SEMANTIC_RUST_RECORD", recording.to_str().unwrap())];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "KO"]);
+    // The sources go into the recording, so it replays without them.
+    std::fs::remove_file(&source).unwrap();
+
+    let replay = |recording: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+            .arg("replay")
+            .arg(recording)
+            .output()
+            .unwrap()
+    };
+    let output = replay(&recording);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1: a.rs OK\n2: missing.rs KO\n"
+    );
+
+    let tampered = dir.join("tampered.jsonl");
+    let text = std::fs::read_to_string(&recording).unwrap();
+    std::fs::write(&tampered, text.replace("\"KO\"", "\"OK\"")).unwrap();
+    let output = replay(&tampered);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("2: missing.rs KO (differs from the recording)"));
+}
+
+#[test]
+fn failures_are_reported_in_detail() {
+    let dir = scratch("protocol-report");
+    let missing = dir.join("missing.rs");
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        missing.display(),
+        dir.join("a.json").display(),
+        missing.display(),
+        dir.join("b.json").display()
+    );
+    let report = dir.join("failures.jsonl");
+    let env = [("SEMANTIC_RUST_REPORT", report.to_str().unwrap())];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["KO", "KO"]);
+    let text = std::fs::read_to_string(&report).unwrap();
+    let reports: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["input"], missing.to_str().unwrap());
+    assert_eq!(reports[0]["language"], "rust");
+    assert_eq!(reports[0]["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!reports[0]["errors"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn failure_reports_locate_syntax_errors() {
+    let dir = scratch("protocol-report-syntax");
+    let source = dir.join("broken.rs");
+    std::fs::write(&source, "fn ok() {}\nfn broken( {}\n").unwrap();
+    let error = anyhow::anyhow!("positions overflow").context("writing the tree");
+    let report = rustsemantic::report::failure_report(source.to_str().unwrap(), &error);
+    assert_eq!(report.errors, ["writing the tree", "positions overflow"]);
+    assert_eq!(report.syntax_errors.len(), 1);
+    assert_eq!(report.syntax_errors[0].start[0], 2);
+}
+
+#[test]
+fn consecutive_trees_for_one_file_are_compared_when_asked() {
+    let dir = scratch("protocol-compare");
+    let (base, local) = (dir.join("base"), dir.join("local"));
+    std::fs::create_dir_all(&base).unwrap();
+    std::fs::create_dir_all(&local).unwrap();
+    std::fs::write(base.join("a.rs"), "fn a() {}\n").unwrap();
+    std::fs::write(local.join("a.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        base.join("a.rs").display(),
+        dir.join("base.json").display(),
+        local.join("a.rs").display(),
+        dir.join("local.json").display()
+    );
+    let log = dir.join("shell.log");
+    let env = [
+        ("SEMANTIC_RUST_LOG", log.to_str().unwrap()),
+        ("SEMANTIC_RUST_COMPARE", "1"),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "OK"]);
+    let text = std::fs::read_to_string(&log).unwrap();
+    assert!(text.contains("changes=4"), "{}", text);
+    assert!(text.contains("+ function_item b"), "{}", text);
+}
+
+#[test]
+fn options_can_be_set_in_the_environment() {
+    let dir = scratch("protocol-environment");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, "fn outer() {\n    fn inner() {}\n}\n").unwrap();
+    let output = dir.join("a.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+    let env = [
+        ("SEMANTIC_RUST_HASHES", "1"),
+        ("SEMANTIC_RUST_NESTED", "nest"),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    let outer = &tree(&output)["children"][0];
+    assert!(outer["hash"].is_string(), "{}", outer);
+    assert!(outer.to_string().contains(r#""name":"outer::inner""#));
+
+    let env = [("SEMANTIC_RUST_HASHES", "maybe")];
+    assert!(!session_with_env(&dir, &input, &env).1);
+}
+
+#[test]
+fn quoted_paths_with_trailing_separators_are_answered() {
+    let dir = scratch("protocol-quirks");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let output = dir.join("a.json");
+    let input = format!(
+        "\"{}\"\nUTF-8\n{}/\nend\n",
+        source.display(),
+        output.display()
+    );
+    assert_eq!(session(&dir, &input).0, ["OK"]);
+    assert_eq!(tree(&output)["name"], "a.rs");
+}
+
+#[test]
+fn non_ascii_paths_are_decoded() {
+    let dir = scratch("protocol-unicode").join("répertoire 🦀");
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("été.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let output = dir.join("sortie.json");
+    let input = format!(
+        "\u{feff}{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        output.display()
+    );
+    assert_eq!(session(&dir, &input).0, ["OK"]);
+    assert_eq!(tree(&output)["name"], "été.rs");
+
+    // The same request from a host that writes Latin-1.
+    let ascii = scratch("protocol-latin1");
+    std::fs::write(ascii.join("été.rs"), SOURCE).unwrap();
+    let output = ascii.join("out.json");
+    let mut input = ascii.display().to_string().into_bytes();
+    input.extend(b"/\xe9t\xe9.rs\nUTF-8\n");
+    input.extend(format!("{}\nend\n", output.display()).bytes());
+    let env = [("SEMANTIC_RUST_PATH_ENCODING", "latin1")];
+    assert_eq!(session_with_env(&ascii, &input, &env).0, ["OK"]);
+    assert_eq!(tree(&output)["name"], "été.rs");
+}
+
+#[test]
+fn ends_with_a_distinct_code_when_nobody_reads_the_responses() {
+    let dir = scratch("protocol-broken-pipe");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display()
+    );
+    let mut child = spawn_shell(&dir, &[]);
+    drop(child.stdout.take());
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(4));
+}
+
+#[cfg(unix)]
+#[test]
+fn ends_with_a_distinct_code_when_terminated() {
+    let dir = scratch("protocol-sigterm");
+    let mut child = spawn_shell(&dir, &[]);
+    let flag = dir.join("flag.txt");
+    while !flag.exists() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    // The shell handles termination before it writes the flag file.
+    let killed = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    assert_eq!(child.wait().unwrap().code(), Some(5));
+}
+
+#[test]
+fn trees_are_removed_at_the_end_when_asked() {
+    let dir = scratch("protocol-cleanup");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let output = dir.join("a.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+    assert_eq!(session(&dir, &input).0, ["OK"]);
+    assert!(output.exists());
+
+    let env = [("SEMANTIC_RUST_CLEANUP", "1")];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    assert!(!output.exists());
+    assert!(source.exists());
+}
+
+#[test]
+fn io_failures_are_answered_ko_with_their_reason() {
+    let dir = scratch("protocol-io-failures");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let latin1 = dir.join("latin1.rs");
+    std::fs::write(&latin1, b"// caf\xe9\nfn f() {}\n").unwrap();
+    let requests = [
+        (dir.join("missing.rs"), dir.join("1.json")),
+        (dir.to_path_buf(), dir.join("2.json")),
+        (latin1, dir.join("3.json")),
+        (source.clone(), dir.join("no-such-dir").join("4.json")),
+        (source, dir.join("5.json")),
+    ];
+    let mut input = String::new();
+    for (source, output) in &requests {
+        input += &format!("{}\nUTF-8\n{}\n", source.display(), output.display());
+    }
+    input += "end\n";
+    let report = dir.join("failures.jsonl");
+    let env = [("SEMANTIC_RUST_REPORT", report.to_str().unwrap())];
+
+    let (responses, status) = session_status(&dir, &input, &env);
+    assert_eq!(responses, ["KO", "KO", "KO", "KO", "OK"]);
+    assert_eq!(status.code(), Some(0));
+    let reasons: Vec<serde_json::Value> = std::fs::read_to_string(&report)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["reason"].clone()
+        })
+        .collect();
+    assert_eq!(
+        reasons,
+        ["missing", "is-a-directory", "not-text", "missing"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_sources_are_answered_ko() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch("protocol-permissions");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    std::fs::set_permissions(&source, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // Root reads it anyway.
+    if std::fs::read(&source).is_ok() {
+        return;
+    }
+    let input = format!(
+        "{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display()
+    );
+    let report = dir.join("failures.jsonl");
+    let env = [("SEMANTIC_RUST_REPORT", report.to_str().unwrap())];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["KO"]);
+    let text = std::fs::read_to_string(&report).unwrap();
+    assert!(
+        text.contains("\"reason\":\"permission-denied\""),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn sources_and_trees_can_be_sent_inline() {
+    let dir = scratch("protocol-inline");
+    let output = dir.join("a.json");
+    let mut input = format!("inline:{}:a.rs\n", SOURCE.len()).into_bytes();
+    input.extend(SOURCE.as_bytes());
+    input.extend(format!("UTF-8\n{}\n", output.display()).as_bytes());
+    input.extend(format!("inline:{}:a.rs\n", SOURCE.len()).as_bytes());
+    input.extend(SOURCE.as_bytes());
+    input.extend(b"UTF-8\n-\n");
+    input.extend(b"inline:2:bad.rs\n\xff\xfe\nUTF-8\n-\nend\n");
+
+    let mut child = spawn_shell(&dir, &[]);
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let result = child.wait_with_output().unwrap();
+    assert!(result.status.success());
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    let (first, rest) = stdout.split_once('\n').unwrap();
+    assert_eq!(first, "OK");
+    let (header, rest) = rest.split_once('\n').unwrap();
+    let length: usize = header.strip_prefix("OK ").unwrap().parse().unwrap();
+    let (tree, rest) = rest.split_at(length);
+    assert_eq!(rest, "\nKO\n");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(tree).unwrap(),
+        self::tree(&output)
+    );
+    assert_eq!(self::tree(&output)["name"], "a.rs");
+}
+
+#[test]
+fn binary_files_are_answered_ko_unless_asked_otherwise() {
+    let dir = scratch("protocol-binary");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, b"fn f() {}\0\0\0\n").unwrap();
+    let output = dir.join("a.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+    assert_eq!(session(&dir, &input).0, ["KO"]);
+    assert!(!output.exists());
+
+    let env = [("SEMANTIC_RUST_BINARY", "opaque")];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    assert_eq!(tree(&output)["children"][0]["type"], "binary");
+}
+
+#[test]
+fn binary_files_that_are_not_utf8_are_answered_opaque() {
+    let dir = scratch("protocol-binary-bytes");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, b"\xff\xfe\x00\x01").unwrap();
+    let output = dir.join("a.json");
+    let input = format!("{}\nUTF-8\n{}\nend\n", source.display(), output.display());
+    assert_eq!(session(&dir, &input).0, ["KO"]);
+
+    let env = [("SEMANTIC_RUST_BINARY", "opaque")];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK"]);
+    let tree = tree(&output);
+    assert_eq!(tree["children"][0]["type"], "binary");
+    assert_eq!(tree["children"][0]["span"], serde_json::json!([0, 3]));
+}
+
+#[test]
+fn temp_copies_are_named_as_mapped() {
+    let dir = scratch("protocol-names");
+    let sources = ["tmp1234.rs", "tmp5678.rs", "tmp9.rs"].map(|name| {
+        let source = dir.join(name);
+        std::fs::write(&source, SOURCE).unwrap();
+        source
+    });
+    let names = dir.join("names.txt");
+    std::fs::write(
+        &names,
+        format!(
+            "# copies of this merge\n{} = src/geometry.rs\ntmp5678.rs = src/shapes.rs\n",
+            sources[0].display()
+        ),
+    )
+    .unwrap();
+    let input: String = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            format!(
+                "{}\nUTF-8\n{}\n",
+                source.display(),
+                dir.join(format!("{}.json", i)).display()
+            )
+        })
+        .collect::<String>()
+        + "end\n";
+    let recording = dir.join("session.jsonl");
+    let env = [
+        ("SEMANTIC_RUST_NAMES", names.to_str().unwrap()),
+        ("SEMANTIC_RUST_RECORD", recording.to_str().unwrap()),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "OK", "OK"]);
+    let named = |i: usize| tree(&dir.join(format!("{}.json", i)))["name"].clone();
+    assert_eq!(named(0), "src/geometry.rs");
+    assert_eq!(named(1), "src/shapes.rs");
+    assert_eq!(named(2), "tmp9.rs");
+    // The names go into the recording too.
+    std::fs::remove_file(&names).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("replay")
+        .arg(&recording)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1: src/geometry.rs OK\n2: src/shapes.rs OK\n3: tmp9.rs OK\n"
+    );
+}
+
+#[test]
+fn recordings_carry_name_templates_and_binary_sources() {
+    let dir = scratch("protocol-record-files");
+    let source = dir.join("a.rs");
+    std::fs::write(&source, SOURCE).unwrap();
+    let blob = dir.join("blob.rs");
+    std::fs::write(&blob, b"\xff\xfe\x00\x01").unwrap();
+    let templates = dir.join("templates.txt");
+    std::fs::write(&templates, "function_item = \"fn {name}\"\n").unwrap();
+    let input = format!(
+        "{}\nUTF-8\n{}\n{}\nUTF-8\n{}\nend\n",
+        source.display(),
+        dir.join("a.json").display(),
+        blob.display(),
+        dir.join("blob.json").display()
+    );
+    let recording = dir.join("session.jsonl");
+    let env = [
+        ("SEMANTIC_RUST_NAME_TEMPLATES", templates.to_str().unwrap()),
+        ("SEMANTIC_RUST_BINARY", "opaque"),
+        ("SEMANTIC_RUST_RECORD", recording.to_str().unwrap()),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "OK"]);
+    for path in [&source, &blob, &templates] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .arg("replay")
+        .arg(&recording)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1: a.rs OK\n2: blob.rs OK\n"
+    );
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn sessions_end_with_a_summary_in_the_log() {
+    let dir = scratch("protocol-summary");
+    for name in ["base.rs", "local.rs"] {
+        std::fs::write(dir.join(name), SOURCE).unwrap();
+    }
+    let input: String = ["base.rs", "local.rs", "missing.rs"]
+        .iter()
+        .map(|name| {
+            format!(
+                "{}\nUTF-8\n{}\n",
+                dir.join(name).display(),
+                dir.join(name).with_extension("json").display()
+            )
+        })
+        .collect::<String>()
+        + "end\n";
+    let names = dir.join("names.txt");
+    std::fs::write(&names, "base.rs = a.rs\nlocal.rs = a.rs\n").unwrap();
+    let log = dir.join("shell.log");
+    let env = [
+        ("SEMANTIC_RUST_LOG", log.to_str().unwrap()),
+        ("SEMANTIC_RUST_NAMES", names.to_str().unwrap()),
+    ];
+    assert_eq!(session_with_env(&dir, &input, &env).0, ["OK", "OK", "KO"]);
+    let text = std::fs::read_to_string(&log).unwrap();
+    let line = text
+        .lines()
+        .find(|l| l.contains("session summary"))
+        .unwrap_or_else(|| panic!("no summary in {}", text));
+    for field in [
+        "ending=End",
+        "requests=3",
+        "parsed=2",
+        "failed=1",
+        "repeated=1",
+        "languages=rust=2",
+        "busy_ms=",
+        "total_ms=",
+    ] {
+        assert!(line.contains(field), "{} in {}", field, line);
+    }
+}
@@ -0,0 +1,53 @@
+//! Functions are matched by name whatever their qualifiers, and diffs say
+//! which qualifiers changed.
+
+use rustsemantic::diff::{declarations, diff_declarations, diff_options, ChangeKind};
+use rustsemantic::parse::{parse_source, parse_source_with};
+
+const OLD: &str = r#"fn fetch() {}
+unsafe fn raw() {}
+pub(crate) extern "C" fn callback() {}
+fn constant() {}
+"#;
+
+const NEW: &str = r#"async fn fetch() {}
+fn raw() {}
+pub(crate) const unsafe extern "C" fn callback() {}
+fn constant() {}
+"#;
+
+#[test]
+fn qualifiers_stay_out_of_names() {
+    for src in [OLD, NEW] {
+        let file = parse_source("qualifiers.rs", src).unwrap();
+        let names: Vec<&str> = file.children.iter().map(|node| node.name()).collect();
+        assert_eq!(names, ["fetch", "raw", "callback", "constant"]);
+    }
+}
+
+#[test]
+fn diffs_report_qualifier_changes() {
+    let decls = |src| {
+        let file = parse_source_with("qualifiers.rs", src, &diff_options()).unwrap();
+        declarations(&file, src)
+    };
+    let changes = diff_declarations(&decls(OLD), &decls(NEW));
+    let found: Vec<(&str, ChangeKind, Vec<&str>)> = changes
+        .iter()
+        .map(|c| {
+            (
+                c.name.as_str(),
+                c.change,
+                c.qualifiers.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("fetch", ChangeKind::Modified, vec!["+async"]),
+            ("raw", ChangeKind::Modified, vec!["-unsafe"]),
+            ("callback", ChangeKind::Modified, vec!["+const", "+unsafe"]),
+        ]
+    );
+}
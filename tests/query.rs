@@ -0,0 +1,104 @@
+//! `query` selects declarations by kind and name glob, step by step down
+//! the tree.
+
+mod common;
+
+use std::process::Command;
+
+use rustsemantic::query::query;
+
+use common::scratch;
+
+const SRC: &str = "mod net {
+    pub struct Conn;
+    impl Conn {
+        pub fn connect() {}
+        pub fn connect_tls() {}
+        fn close() {}
+    }
+    impl Drop for Conn {
+        fn drop(&mut self) {}
+    }
+    mod inner {
+        fn connect() {}
+    }
+}
+pub trait Draw {
+    fn draw(&self);
+}
+fn connect() {}
+";
+
+fn names(selector: &str) -> Vec<String> {
+    query("lib.rs", SRC.to_string(), selector, false)
+        .unwrap()
+        .into_iter()
+        .map(|m| m.qualified_name)
+        .collect()
+}
+
+#[test]
+fn steps_narrow_by_kind_name_and_nesting() {
+    assert_eq!(
+        names("mod net > impl * > fn connect*"),
+        ["net::impl Conn::connect", "net::impl Conn::connect_tls"]
+    );
+    assert_eq!(
+        names("fn connect"),
+        ["net::impl Conn::connect", "net::inner::connect", "connect"]
+    );
+    assert_eq!(names("> fn connect"), ["connect"]);
+    assert_eq!(
+        names("mod net >> fn connect"),
+        ["net::impl Conn::connect", "net::inner::connect"]
+    );
+    assert_eq!(names("impl Drop* > *"), ["net::impl Drop for Conn::drop"]);
+    assert_eq!(names("trait Draw > fn"), ["Draw::draw"]);
+    assert_eq!(names("struct_item"), ["net::Conn"]);
+}
+
+#[test]
+fn matches_carry_the_names_parse_gives() {
+    let src = "struct Buffer;\npub trait Draw {}\nimpl<T> Draw for Wrapper<T> {}\n";
+    let found = |selector| -> Vec<String> {
+        query("a.rs", src.to_string(), selector, false)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect()
+    };
+    assert_eq!(found("struct Buffer"), ["Buffer"]);
+    assert_eq!(found("trait"), ["Draw"]);
+    assert_eq!(found("impl Wrapper<T>"), ["impl Draw for Wrapper<T>"]);
+}
+
+#[test]
+fn bad_selectors_are_errors() {
+    for selector in ["", "impl Conn >", "mod > > fn", "bogus x"] {
+        assert!(
+            query("lib.rs", SRC.to_string(), selector, false).is_err(),
+            "{:?}",
+            selector
+        );
+    }
+}
+
+#[test]
+fn results_are_json_with_optional_text() {
+    let dir = scratch("query");
+    let file = dir.join("lib.rs");
+    std::fs::write(&file, SRC).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .args(["query", "--text"])
+        .arg(&file)
+        .arg("> fn connect")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 1);
+    assert_eq!(json[0]["type"], "function_item");
+    assert_eq!(json[0]["qualifiedName"], "connect");
+    assert_eq!(json[0]["start"][0], 18);
+    assert_eq!(json[0]["text"], "fn connect() {}");
+}
@@ -0,0 +1,174 @@
+//! Validates the trees emitted for the fixtures against the shipped JSON
+//! Schema, using a validator for just the keywords the schema uses.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use rustsemantic::inject::Injections;
+use rustsemantic::model::SCHEMA_VERSION;
+use rustsemantic::parse::{parse_source_with, Binary, Comments, Nested, ParseOptions};
+use rustsemantic::schema::SCHEMA;
+use rustsemantic::templates::NameTemplates;
+use rustsemantic::units::{Base, Ends, Unit};
+
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(pointer) => resolve(root.pointer(&pointer[1..]).unwrap(), root),
+        None => schema,
+    }
+}
+
+fn validate(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+) -> Result<(), String> {
+    let schema = resolve(schema, root);
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return Err(format!("{}: expected {}, found {}", path, expected, value));
+        }
+    }
+    if let Some(options) = schema["oneOf"].as_array() {
+        let matches = options
+            .iter()
+            .filter(|option| validate(value, option, root, path).is_ok())
+            .count();
+        if matches != 1 {
+            return Err(format!(
+                "{}: matches {} of the oneOf schemas",
+                path, matches
+            ));
+        }
+    }
+    if let Some(kinds) = schema["type"].as_array() {
+        if !kinds.iter().any(|kind| {
+            let single = serde_json::json!({ "type": kind });
+            validate(value, &single, root, path).is_ok()
+        }) {
+            return Err(format!(
+                "{}: expected one of {:?}, found {}",
+                path, kinds, value
+            ));
+        }
+    }
+    if let Some(kind) = schema["type"].as_str() {
+        let ok = match kind {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => return Err(format!("{}: unsupported type {}", path, kind)),
+        };
+        if !ok {
+            return Err(format!("{}: expected {}, found {}", path, kind, value));
+        }
+    }
+    if let Some(minimum) = schema["minimum"].as_i64() {
+        if value.as_i64().is_some_and(|v| v < minimum) {
+            return Err(format!("{}: {} is below {}", path, value, minimum));
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for key in schema["required"].as_array().into_iter().flatten() {
+            if !object.contains_key(key.as_str().unwrap()) {
+                return Err(format!("{}: missing {}", path, key));
+            }
+        }
+        for (key, field) in object {
+            match schema["properties"].get(key) {
+                Some(property) => {
+                    validate(field, property, root, &format!("{}.{}", path, key))?
+                }
+                None if schema["additionalProperties"] == false => {
+                    return Err(format!("{}: unexpected property {}", path, key));
+                }
+                None => {}
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        let len = items.len() as u64;
+        if schema["minItems"].as_u64().is_some_and(|min| len < min)
+            || schema["maxItems"].as_u64().is_some_and(|max| len > max)
+        {
+            return Err(format!("{}: wrong number of items ({})", path, len));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(item, item_schema, root, &format!("{}[{}]", path, i))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn fixtures_match_the_schema() {
+    let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+    assert_eq!(
+        schema["properties"]["schemaVersion"]["const"],
+        SCHEMA_VERSION
+    );
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut sources: Vec<(String, String)> = std::fs::read_dir(fixtures)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.to_string_lossy().into_owned();
+            (name, std::fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    sources.push(("empty.rs".to_string(), String::new()));
+    sources.push(("broken.rs".to_string(), "fn broken( {\n".to_string()));
+
+    let all = ParseOptions {
+        hashes: true,
+        signatures: true,
+        qualifiers: true,
+        docs: true,
+        excerpts: true,
+        tests: true,
+        complexity: true,
+        offsets: Unit::Utf16,
+        columns: Unit::Utf16,
+        tab_width: Some(4),
+        base: Base { line: 0, column: 1 },
+        ends: Ends::Inclusive,
+        qualify_methods: true,
+        nested: Nested::Nest,
+        injections: Injections::parse(if cfg!(feature = "inject-html") {
+            "html=html"
+        } else {
+            ""
+        })
+        .unwrap(),
+        doctests: true,
+        ordinals: true,
+        max_depth: None,
+        name_templates: NameTemplates::default(),
+        skip_kinds: vec!["token_tree".to_string()],
+        min_bytes: 4,
+        min_lines: 0,
+        flatten: true,
+        comments: Comments::Named,
+        binary: Binary::Opaque,
+    };
+    for (name, src) in sources {
+        for options in [ParseOptions::default(), all.clone()] {
+            let file = parse_source_with(&name, &src, &options).unwrap();
+            let tree = serde_json::to_value(file).unwrap();
+            if let Err(e) = validate(&tree, &schema, &schema, "$") {
+                panic!(
+                    "{} with {:?} does not match the schema: {}",
+                    name, options, e
+                );
+            }
+        }
+    }
+}
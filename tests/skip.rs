@@ -0,0 +1,99 @@
+//! `--skip-kinds`, `--min-bytes` and `--min-lines` keep nodes out of the
+//! tree, leaving their text to the nodes around them.
+
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_kinds, parse_source_with, ParseOptions};
+
+const SOURCE: &str = "fn area(w: u32, h: u32) -> u32 {\n    let a = w * h;\n    println!(\"{}\", a);\n    a\n}\n\nstruct Point {\n    x: i32,\n}\n";
+
+fn kinds(nodes: &[Node], out: &mut Vec<String>) {
+    for node in nodes {
+        out.push(node.kind().to_string());
+        kinds(node.children(), out);
+    }
+}
+
+#[test]
+fn skipped_kinds_never_become_nodes() {
+    let all = parse_source_with("a.rs", SOURCE, &ParseOptions::default()).unwrap();
+    let mut before = vec![];
+    kinds(&all.children, &mut before);
+    assert!(before.iter().any(|kind| kind == "block"));
+
+    let options = ParseOptions {
+        skip_kinds: parse_kinds(" block, token_tree ,,"),
+        ..ParseOptions::default()
+    };
+    assert_eq!(options.skip_kinds, ["block", "token_tree"]);
+    let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+    let mut after = vec![];
+    kinds(&file.children, &mut after);
+    assert!(!after
+        .iter()
+        .any(|kind| kind == "block" || kind == "token_tree"));
+    assert!(after.len() < before.len());
+    common::check_tiling(&file, SOURCE).unwrap();
+    common::check_locations(&file, SOURCE).unwrap();
+
+    // The function's body, its last child, is in its footer now.
+    let Node::Container(function) = &file.children[0] else {
+        panic!("expected a container, got {:?}", file.children[0]);
+    };
+    let [start, end] = function.footer_span.span;
+    assert!(SOURCE[start as usize..end as usize].starts_with(" {\n    let a"));
+}
+
+#[test]
+fn containers_left_without_children_become_terminals() {
+    let options = ParseOptions {
+        skip_kinds: parse_kinds("type_identifier,field_declaration_list"),
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("a.rs", SOURCE, &options).unwrap();
+    let point = file
+        .children
+        .iter()
+        .find(|node| node.kind() == "struct_item")
+        .unwrap();
+    assert!(matches!(point, Node::Terminal(_)), "{:?}", point);
+    common::check_tiling(&file, SOURCE).unwrap();
+}
+
+#[test]
+fn small_nodes_are_left_out_but_declarations_stay() {
+    let count = |options: &ParseOptions| {
+        let file = parse_source_with("a.rs", SOURCE, options).unwrap();
+        common::check_tiling(&file, SOURCE).unwrap();
+        common::check_locations(&file, SOURCE).unwrap();
+        let mut out = vec![];
+        kinds(&file.children, &mut out);
+        out
+    };
+    let all = count(&ParseOptions::default());
+    assert!(all.iter().any(|kind| kind == "identifier"));
+
+    let by_bytes = count(&ParseOptions {
+        min_bytes: 8,
+        ..ParseOptions::default()
+    });
+    assert!(!by_bytes.iter().any(|kind| kind == "identifier"));
+    for kind in ["function_item", "struct_item", "field_declaration"] {
+        assert!(
+            by_bytes.iter().any(|k| k == kind),
+            "{} in {:?}",
+            kind,
+            by_bytes
+        );
+    }
+
+    let by_lines = count(&ParseOptions {
+        min_lines: 2,
+        ..ParseOptions::default()
+    });
+    assert!(by_lines.len() < by_bytes.len());
+    assert!(by_lines.iter().any(|kind| kind == "block"));
+    assert!(!by_lines.iter().any(|kind| kind == "let_declaration"));
+    assert!(by_lines.iter().any(|kind| kind == "field_declaration"));
+}
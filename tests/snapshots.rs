@@ -0,0 +1,93 @@
+//! Snapshot tests over the JSON tree emitted for each file in
+//! `tests/fixtures`. Run with `UPDATE_SNAPSHOTS=1` to accept new output; a
+//! mismatch leaves the actual output next to the snapshot as `<name>.json.new`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixtures(dir: &Path, out: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("read fixtures directory")
+        .map(|entry| entry.expect("read fixture entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            fixtures(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+#[test]
+fn fixture_snapshots() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let fixture_dir = root.join("tests/fixtures");
+    // Each grammar parses the fixtures into its own node kinds.
+    let snapshot_dir = if cfg!(feature = "grammar-0-19") {
+        root.join("tests/snapshots/grammar-0-19")
+    } else {
+        root.join("tests/snapshots")
+    };
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    let mut paths = vec![];
+    fixtures(&fixture_dir, &mut paths);
+    assert!(
+        !paths.is_empty(),
+        "no fixtures in {}",
+        fixture_dir.display()
+    );
+
+    let mut failures = vec![];
+    for path in paths {
+        // Parse by relative path so the snapshot doesn't embed the checkout.
+        let relative = path.strip_prefix(root).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+            .current_dir(root)
+            .arg("parse")
+            .arg(relative)
+            .output()
+            .expect("run rustsemantic parse");
+        assert!(
+            output.status.success(),
+            "parse {} failed: {}",
+            relative.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let actual = String::from_utf8(output.stdout).unwrap();
+
+        let name = path
+            .strip_prefix(&fixture_dir)
+            .unwrap()
+            .with_extension("json");
+        let snapshot = snapshot_dir.join(&name);
+        let pending = snapshot.with_extension("json.new");
+        if update {
+            std::fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+            std::fs::write(&snapshot, &actual).unwrap();
+            let _ = std::fs::remove_file(&pending);
+            continue;
+        }
+        match std::fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == actual => {
+                let _ = std::fs::remove_file(&pending);
+            }
+            Ok(_) => {
+                std::fs::write(&pending, &actual).unwrap();
+                failures.push(format!("{} changed", name.display()));
+            }
+            Err(_) => {
+                std::fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+                std::fs::write(&pending, &actual).unwrap();
+                failures.push(format!("{} has no snapshot", name.display()));
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "snapshot mismatches (rerun with UPDATE_SNAPSHOTS=1 to accept):\n{}",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,35 @@
+use rustsemantic::stats::Stats;
+
+#[test]
+fn counts_kinds_depth_and_largest_functions() {
+    let mut stats = Stats::new(2);
+    stats
+        .add("a.rs", "fn short() {}\n\nfn long() {\n    let x = 1;\n}\n")
+        .unwrap();
+    stats
+        .add("b.rs", "mod m {\n    fn longest() {\n\n\n    }\n}\n")
+        .unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.nodes_by_kind["function_item"], 3);
+    assert_eq!(stats.nodes_by_kind["mod_item"], 1);
+    assert_eq!(stats.nodes, stats.nodes_by_kind.values().sum::<usize>());
+    // mod_item > declaration_list > function_item > block
+    assert_eq!(
+        stats
+            .slowest_files
+            .iter()
+            .find(|f| f.file == "b.rs")
+            .unwrap()
+            .max_depth,
+        4
+    );
+    assert_eq!(stats.max_depth, 4);
+
+    let largest: Vec<_> = stats
+        .largest_functions
+        .iter()
+        .map(|f| (f.file.as_str(), f.line, f.lines))
+        .collect();
+    assert_eq!(largest, [("b.rs", 2, 4), ("a.rs", 3, 3)]);
+}
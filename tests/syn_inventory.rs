@@ -0,0 +1,175 @@
+//! Differential check of the top-level item inventory against `syn`: every
+//! file both parsers accept should yield the same items, of the same kinds,
+//! in the same order and with the same names. Runs over `tests/fixtures`, and
+//! over `RUSTSEMANTIC_CORPUS` (see `tests/corpus.rs`) when it is set.
+//!
+//! Items the walk misses or adds always fail the test. Misnamed items are
+//! reported, and fail it too with `RUSTSEMANTIC_SYN_STRICT=1`.
+
+use std::path::{Path, PathBuf};
+
+use rustsemantic::model::{base_name, is_declaration_kind};
+use rustsemantic::parse::parse_source;
+
+/// The tree-sitter kind and, where the item has one, the identifier `syn`
+/// sees for a top-level item. Items without a tree-sitter declaration
+/// counterpart (macro invocations, verbatim tokens) are skipped.
+fn syn_item(item: &syn::Item) -> Option<(&'static str, Option<String>)> {
+    use syn::Item;
+    let name = |ident: &syn::Ident| Some(ident.to_string());
+    Some(match item {
+        Item::Const(i) => ("const_item", name(&i.ident)),
+        Item::Enum(i) => ("enum_item", name(&i.ident)),
+        Item::ExternCrate(i) => ("extern_crate_declaration", name(&i.ident)),
+        Item::Fn(i) => ("function_item", name(&i.sig.ident)),
+        Item::ForeignMod(_) => ("foreign_mod_item", None),
+        Item::Impl(_) => ("impl_item", None),
+        Item::Macro(i) => ("macro_definition", name(i.ident.as_ref()?)),
+        Item::Mod(i) => ("mod_item", name(&i.ident)),
+        Item::Static(i) => ("static_item", name(&i.ident)),
+        Item::Struct(i) => ("struct_item", name(&i.ident)),
+        Item::Trait(i) => ("trait_item", name(&i.ident)),
+        Item::Type(i) => ("type_item", name(&i.ident)),
+        Item::Union(i) => ("union_item", name(&i.ident)),
+        Item::Use(_) => ("use_declaration", None),
+        _ => return None,
+    })
+}
+
+#[derive(Default)]
+struct Report {
+    compared: usize,
+    skipped: Vec<String>,
+    mismatched: Vec<String>,
+    misnamed: Vec<String>,
+}
+
+fn compare(path: &Path, report: &mut Report) {
+    let Ok(src) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let name = path.to_string_lossy();
+    let Ok(syn_file) = syn::parse_file(&src) else {
+        return;
+    };
+    let file = parse_source(&name, &src).unwrap();
+    if file.parsing_errors_detected {
+        report
+            .skipped
+            .push(format!("{}: tree-sitter reports syntax errors", name));
+        return;
+    }
+    report.compared += 1;
+
+    let expected: Vec<_> = syn_file.items.iter().filter_map(syn_item).collect();
+    let kinds: Vec<&str> = expected.iter().map(|(kind, _)| *kind).collect();
+    // Runs of `use` declarations are grouped under a `uses` container.
+    let actual: Vec<_> = file
+        .children
+        .iter()
+        .flat_map(|node| match node.kind() {
+            "uses" => node.children().iter().collect(),
+            _ => vec![node],
+        })
+        .filter(|node| is_declaration_kind(node.kind()))
+        .filter(|node| kinds.contains(&node.kind()) || syn_kind_exists(node.kind()))
+        .collect();
+
+    let actual_kinds: Vec<&str> = actual.iter().map(|node| node.kind()).collect();
+    if actual_kinds != kinds {
+        report.mismatched.push(format!(
+            "{}: syn sees {:?}\n    tree-sitter sees {:?}",
+            name, kinds, actual_kinds
+        ));
+        return;
+    }
+    for ((kind, expected_name), node) in expected.iter().zip(&actual) {
+        if let Some(expected_name) = expected_name {
+            if base_name(node.name()) != expected_name {
+                report.misnamed.push(format!(
+                    "{}:{}: {} `{}` is named `{}`",
+                    name,
+                    node.location_span().start[0],
+                    kind,
+                    expected_name,
+                    node.name()
+                ));
+            }
+        }
+    }
+}
+
+/// Whether `kind` is one `syn_item` can produce, so that tree-sitter items
+/// of other kinds don't count as extras.
+fn syn_kind_exists(kind: &str) -> bool {
+    matches!(
+        kind,
+        "const_item"
+            | "enum_item"
+            | "extern_crate_declaration"
+            | "function_item"
+            | "foreign_mod_item"
+            | "impl_item"
+            | "macro_definition"
+            | "mod_item"
+            | "static_item"
+            | "struct_item"
+            | "trait_item"
+            | "type_item"
+            | "union_item"
+            | "use_declaration"
+    )
+}
+
+fn rust_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_files(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+#[test]
+fn item_inventory_matches_syn() {
+    let mut paths = vec![];
+    rust_files(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+        &mut paths,
+    );
+    if let Some(roots) = std::env::var_os("RUSTSEMANTIC_CORPUS") {
+        for root in std::env::split_paths(&roots) {
+            rust_files(&root, &mut paths);
+        }
+    }
+    paths.sort();
+
+    let mut report = Report::default();
+    for path in &paths {
+        compare(path, &mut report);
+    }
+    eprintln!(
+        "compared {} files with syn ({} skipped): {} inventory mismatches, {} misnamed items",
+        report.compared,
+        report.skipped.len(),
+        report.mismatched.len(),
+        report.misnamed.len()
+    );
+    for line in report.skipped.iter().chain(&report.misnamed) {
+        eprintln!("  {}", line);
+    }
+    assert!(report.compared > 0);
+    assert!(
+        report.mismatched.is_empty(),
+        "{}",
+        report.mismatched.join("\n")
+    );
+    if std::env::var_os("RUSTSEMANTIC_SYN_STRICT").is_some() {
+        assert!(report.misnamed.is_empty(), "{}", report.misnamed.join("\n"));
+    }
+}
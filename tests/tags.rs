@@ -0,0 +1,49 @@
+//! `tags` writes ctags entries under the identifiers editors jump to.
+
+mod common;
+
+use std::process::Command;
+
+use common::scratch;
+
+#[test]
+fn tags_are_named_by_identifier() {
+    let dir = scratch("tags");
+    std::fs::write(
+        dir.join("lib.rs"),
+        "struct Buffer;\npub(crate) struct Foo;\npub trait Shape {\n    fn area(&self);\n}\nunion U {\n    a: u32,\n}\nmod geometry {\n    impl<T> crate::Shape for Point<T> {\n        fn area(&self) {}\n    }\n}\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .current_dir(&dir)
+        .args(["tags", "-f", "-", "lib.rs"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tags: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with("!_"))
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let mut tag = format!("{} {}", fields[0], fields[3]);
+            if let Some(scope) = fields.get(5) {
+                tag = format!("{} {}", tag, scope);
+            }
+            tag
+        })
+        .collect();
+    assert_eq!(
+        tags,
+        [
+            "Buffer s",
+            "Foo s",
+            "Point c module:geometry",
+            "Shape i",
+            "U s",
+            "area P interface:Shape",
+            "area P implementation:geometry::Point",
+            "geometry n",
+        ]
+    );
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use rustsemantic::model::Node;
+use rustsemantic::parse::{parse_source, parse_source_with, ParseOptions};
+use rustsemantic::units::{Base, Ends, Offsets, Unit};
+
+const SOURCE: &str =
+    "/// Crab 🦀.\nfn été() -> &'static str {\n    \"ünïcödé\"\n}\n\nstruct S;\n";
+
+#[test]
+fn utf16_offsets_count_code_units() {
+    let offsets = Offsets::new("aé🦀b", Unit::Utf16);
+    let converted: Vec<usize> = [0, 1, 3, 7, 8]
+        .iter()
+        .map(|&b| offsets.convert(b))
+        .collect();
+    assert_eq!(converted, [0, 1, 2, 4, 5]);
+    assert_eq!(Offsets::new("aé", Unit::Bytes).convert(3), 3);
+}
+
+#[test]
+fn utf16_spans_tile_the_utf16_text() {
+    let options = ParseOptions {
+        offsets: Unit::Utf16,
+        ..ParseOptions::default()
+    };
+    let file = parse_source_with("units.rs", SOURCE, &options).unwrap();
+    let units: Vec<u16> = SOURCE.encode_utf16().collect();
+    let rebuilt: Vec<u16> = common::spans(&file)
+        .iter()
+        .flat_map(|[start, end]| units[*start as usize..*end as usize].to_vec())
+        .collect();
+    assert_eq!(String::from_utf16(&rebuilt).unwrap(), SOURCE);
+
+    // Byte spans of the same file are longer wherever there is non-ASCII.
+    let bytes = parse_source("units.rs", SOURCE).unwrap();
+    let last = |spans: Vec<[i64; 2]>| spans.last().unwrap()[1];
+    assert_eq!(last(common::spans(&file)) as usize, units.len());
+    assert_eq!(last(common::spans(&bytes)) as usize, SOURCE.len());
+}
+
+#[test]
+fn columns_count_the_requested_unit() {
+    let src = "const C: &str = \"🦀é\"; struct S;\n";
+    let struct_columns = |columns: Unit| {
+        let options = ParseOptions {
+            columns,
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("columns.rs", src, &options).unwrap();
+        let location = file.children[1].location_span();
+        (location.start, location.end)
+    };
+    assert_eq!(struct_columns(Unit::Bytes), ([1, 26], [1, 35]));
+    assert_eq!(struct_columns(Unit::Chars), ([1, 22], [1, 31]));
+    assert_eq!(struct_columns(Unit::Utf16), ([1, 23], [1, 32]));
+}
+
+#[test]
+fn positions_past_32_bits_are_kept_and_flagged() {
+    let mut file = parse_source("small.rs", "struct S;\n").unwrap();
+    file.check_fits_i32().unwrap();
+
+    let huge = i64::from(i32::MAX) + 10;
+    file.footer_span.span = [huge - 1, huge];
+    let json = serde_json::to_string(&file).unwrap();
+    assert!(json.contains(&huge.to_string()));
+    assert!(file.check_fits_i32().is_err());
+}
+
+#[test]
+fn tabs_advance_to_the_next_tab_stop() {
+    let src = "mod m {\n\tfn f() {}\n \tfn g() {}\n}\n";
+    let function_columns = |tab_width: Option<usize>| {
+        let options = ParseOptions {
+            tab_width,
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("tabs.rs", src, &options).unwrap();
+        let body = &file.children[0].children()[1];
+        body.children()
+            .iter()
+            .map(|f| (f.location_span().start[1], f.location_span().end[1]))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(function_columns(None), [(1, 10), (2, 11)]);
+    assert_eq!(function_columns(Some(4)), [(4, 13), (4, 13)]);
+    assert_eq!(function_columns(Some(8)), [(8, 17), (8, 17)]);
+}
+
+#[test]
+fn lines_and_columns_count_from_the_requested_base() {
+    let src = "struct S;\nfn f() {}\n";
+    let located = |base: Base| {
+        let options = ParseOptions {
+            base,
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("base.rs", src, &options).unwrap();
+        let f = file.children[1].location_span();
+        (f.start, f.end, file.location_span.start)
+    };
+    assert_eq!(located(Base::default()), ([2, 0], [2, 9], [1, 0]));
+    assert_eq!(
+        located(Base { line: 0, column: 0 }),
+        ([1, 0], [1, 9], [0, 0])
+    );
+    assert_eq!(
+        located(Base { line: 1, column: 1 }),
+        ([2, 1], [2, 10], [1, 1])
+    );
+    assert!(Base::parse_one("--line-base", "2").is_err());
+}
+
+#[test]
+fn span_ends_follow_the_requested_convention() {
+    fn find<'a>(nodes: &'a [Node], kind: &str) -> Option<&'a Node> {
+        nodes.iter().find_map(|node| {
+            if node.kind() == kind {
+                Some(node)
+            } else {
+                find(node.children(), kind)
+            }
+        })
+    }
+    let src = "fn f() { (x+1); }\n";
+    let spans = |ends: Ends| {
+        let options = ParseOptions {
+            ends,
+            ..ParseOptions::default()
+        };
+        let file = parse_source_with("ends.rs", src, &options).unwrap();
+        let Node::Container(sum) = find(&file.children, "binary_expression").unwrap()
+        else {
+            panic!("binary_expression should have children");
+        };
+        let Node::Terminal(x) = &sum.children[0] else {
+            panic!("x should be a terminal");
+        };
+        (x.span.span, sum.header_span.span, file.footer_span.span)
+    };
+    // `x` is one byte and the sum's header, which starts right at `x`, none.
+    assert_eq!(spans(Ends::Exclusive), ([10, 11], [10, 10], [17, 18]));
+    assert_eq!(spans(Ends::Inclusive), ([10, 10], [0, -1], [17, 17]));
+}
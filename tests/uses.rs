@@ -0,0 +1,30 @@
+use rustsemantic::model::Node;
+use rustsemantic::parse::parse_source;
+
+#[test]
+fn adjacent_uses_are_grouped_with_one_terminal_per_import() {
+    let src = "use std::fmt;\nuse std::io::{\n    self,\n    Write,\n};\n\npub use crate::model::Node;\n// Parsing.\nuse crate::parse;\n\nfn main() {}\n";
+    let file = parse_source("uses.rs", src).unwrap();
+    let kinds: Vec<_> = file.children.iter().map(|c| c.kind()).collect();
+    assert_eq!(kinds, ["uses", "line_comment", "uses", "function_item"]);
+
+    let Node::Container(uses) = &file.children[0] else {
+        panic!("uses should be a container");
+    };
+    let imports: Vec<_> = uses
+        .children
+        .iter()
+        .map(|import| match import {
+            Node::Terminal(t) => t.name.as_str(),
+            Node::Container(_) => panic!("imports should be terminals"),
+        })
+        .collect();
+    assert_eq!(
+        imports,
+        ["std::fmt", "std::io::{self, Write}", "crate::model::Node"]
+    );
+    assert_eq!(uses.location_span.start, [1, 0]);
+    assert_eq!(uses.location_span.end, [7, 27]);
+    assert_eq!(uses.header_span.span, [0, 0]);
+    assert_eq!(file.children[2].children().len(), 1);
+}
@@ -0,0 +1,85 @@
+//! `workspace` models every crate in a Cargo workspace, following each
+//! target's modules into their files.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::scratch;
+
+fn write(root: &Path, files: &[(&str, &str)]) {
+    for (name, contents) in files {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+#[test]
+fn crates_are_modeled_with_their_modules() {
+    let root = scratch("workspace-model");
+    write(
+        &root,
+        &[
+            (
+                "Cargo.toml",
+                "[workspace]\nmembers = [\"core\", \"app\"]\nresolver = \"2\"\n",
+            ),
+            (
+                "core/Cargo.toml",
+                "[package]\nname = \"core\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+            ),
+            ("core/src/lib.rs", "pub mod shapes;\nmod missing;\n"),
+            ("core/src/shapes.rs", "pub struct Circle {\n    r: f64,\n}\nmod area;\n"),
+            ("core/src/shapes/area.rs", "pub fn area() -> f64 { 0.0 }\n"),
+            (
+                "app/Cargo.toml",
+                "[package]\nname = \"app\"\nversion = \"0.2.0\"\nedition = \"2021\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+            ),
+            ("app/src/main.rs", "fn main() {}\n"),
+        ],
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_rustsemantic"))
+        .args(["workspace", "--format", "json", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let model: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(!text.contains(root.to_str().unwrap()), "{}", text);
+
+    let package = |name: &str| {
+        model["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|package| package["name"] == name)
+            .unwrap()
+            .clone()
+    };
+    let core = package("core");
+    assert_eq!(core["manifest"], "core/Cargo.toml");
+    let lib = &core["targets"][0];
+    assert_eq!(lib["kinds"], serde_json::json!(["lib"]));
+    assert_eq!(
+        lib["files"],
+        serde_json::json!([
+            "core/src/lib.rs",
+            "core/src/shapes.rs",
+            "core/src/shapes/area.rs"
+        ])
+    );
+    let shapes = &lib["items"][0];
+    assert_eq!(shapes["file"], "core/src/shapes.rs");
+    assert_eq!(shapes["children"][0]["name"], "Circle");
+    assert_eq!(shapes["children"][1]["children"][0]["name"], "area");
+    assert_eq!(lib["items"][1]["children"], serde_json::json!([]));
+
+    let app = package("app");
+    assert_eq!(app["version"], "0.2.0");
+    assert_eq!(app["dependencies"], serde_json::json!(["core"]));
+    assert_eq!(app["targets"][0]["kinds"], serde_json::json!(["bin"]));
+    assert_eq!(app["targets"][0]["items"][0]["name"], "main");
+}